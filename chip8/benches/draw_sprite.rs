@@ -0,0 +1,56 @@
+//! Times drawing sprites and clearing the screen, the hottest paths when
+//! running programs. Run with `cargo bench -p chip8`.
+//!
+//! This uses a plain timer rather than a benchmarking framework so it runs on
+//! stable Rust without any dependencies. It needs Rust 1.66 for
+//! `std::hint::black_box`, which is newer than the library's minimum.
+#![allow(clippy::incompatible_msrv)]
+
+use std::{hint::black_box, time::Instant};
+
+use chip8::screen::Chip8Screen;
+
+const ITERATIONS: u32 = 200_000;
+
+/// A 15 row sprite, the tallest `DXYN` can draw
+const SPRITE: [u8; 15] = [
+    0xff, 0x81, 0xbd, 0xa5, 0xa5, 0xbd, 0x81, 0xff, 0x81, 0xbd, 0xa5, 0xa5, 0xbd, 0x81, 0xff,
+];
+
+/// Run `f` `ITERATIONS` times and print how long each took on average
+fn bench(name: &str, mut f: impl FnMut(u32)) {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    let nanos = start.elapsed().as_nanos() / ITERATIONS as u128;
+    println!("{name:<32} {nanos:>6} ns/iter");
+}
+
+fn bench_screen<const W: usize, const H: usize>(size: &str) {
+    let mut screen = Chip8Screen::<W, H>::default();
+    bench(&format!("draw_sprite {size}"), |i| {
+        // every position, including ones clipped at the edges
+        let (x, y) = (i as u8, (i >> 8) as u8);
+        black_box(screen.draw_sprite(x, y, black_box(&SPRITE)));
+    });
+
+    let mut screen = Chip8Screen::<W, H>::default();
+    screen.select_planes(0b11);
+    let sprites = [SPRITE, SPRITE].concat();
+    bench(&format!("draw_sprite {size} two planes"), |i| {
+        let (x, y) = (i as u8, (i >> 8) as u8);
+        black_box(screen.draw_sprite(x, y, black_box(&sprites)));
+    });
+
+    let mut screen = Chip8Screen::<W, H>::default();
+    bench(&format!("clear {size}"), |_| {
+        screen.clear();
+        black_box(&screen);
+    });
+}
+
+fn main() {
+    bench_screen::<64, 32>("64x32");
+    bench_screen::<128, 64>("128x64");
+}
@@ -0,0 +1,486 @@
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use crate::{
+    instruction::{encode, Inst},
+    memory::PROGRAM_OFFSET_BYTES,
+};
+
+/// Assemble a program from source with one instruction per line, using the
+/// mnemonics `Inst` is displayed with, e.g. `LD V0, 0x12` or `DRW V0, V1, 5`.
+///
+/// - Numbers can be decimal, or hexadecimal or binary with a `0x` or `0b`
+///   prefix
+/// - A line can start with a label such as `loop:`, which can be used in
+///   place of a number. Labels are addresses from where the program is loaded.
+/// - `DB` followed by comma separated bytes inserts data, e.g. for sprites
+/// - Everything after a `;` is a comment
+///
+/// The result can be passed straight to `Chip8::new()`.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let lines: Vec<Line> = source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| Line::parse(i + 1, text))
+        .collect();
+
+    // find the address of every label first, so they can be used before
+    // they're defined
+    let mut labels = BTreeMap::new();
+    let mut address = PROGRAM_OFFSET_BYTES;
+    for line in &lines {
+        if let Some(label) = line.label {
+            if labels.insert(label, address as u16).is_some() {
+                return Err(line.error(AssembleErrorKind::DuplicateLabel {
+                    label: label.to_string(),
+                }));
+            }
+        }
+        address += line.len();
+    }
+
+    let mut bytes = Vec::new();
+    for line in &lines {
+        if let Some(mnemonic) = line.mnemonic {
+            let assembler = LineAssembler {
+                labels: &labels,
+                mnemonic,
+                operands: &line.operands,
+            };
+            assembler
+                .assemble(&mut bytes)
+                .map_err(|kind| line.error(kind))?;
+        }
+    }
+    Ok(bytes)
+}
+
+/// A line of source split into its parts
+struct Line<'a> {
+    number: usize,
+    label: Option<&'a str>,
+    mnemonic: Option<&'a str>,
+    operands: Vec<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    fn parse(number: usize, text: &'a str) -> Line<'a> {
+        let mut text = text.split(';').next().unwrap_or_default().trim();
+        let mut label = None;
+        if let Some((before, after)) = text.split_once(':') {
+            label = Some(before.trim());
+            text = after.trim();
+        }
+        let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+            Some((mnemonic, operands)) => (mnemonic, operands.split(',').map(str::trim).collect()),
+            None => (text, Vec::new()),
+        };
+        Line {
+            number,
+            label,
+            mnemonic: if mnemonic.is_empty() {
+                None
+            } else {
+                Some(mnemonic)
+            },
+            operands,
+        }
+    }
+
+    /// The number of bytes this line assembles to
+    fn len(&self) -> usize {
+        match self.mnemonic {
+            None => 0,
+            Some(mnemonic) if mnemonic.eq_ignore_ascii_case("DB") => self.operands.len(),
+            Some(_)
+                if self
+                    .operands
+                    .iter()
+                    .any(|operand| long_operand(operand).is_some()) =>
+            {
+                4
+            }
+            Some(_) => 2,
+        }
+    }
+
+    fn error(&self, kind: AssembleErrorKind) -> AssembleError {
+        AssembleError {
+            line: self.number,
+            kind,
+        }
+    }
+}
+
+/// The operand of `LD I, LONG NNNN`, if this is one
+fn long_operand(operand: &str) -> Option<&str> {
+    let (keyword, value) = operand.split_once(char::is_whitespace)?;
+    keyword.eq_ignore_ascii_case("LONG").then(|| value.trim())
+}
+
+enum Operand<'a> {
+    /// `VX`
+    Register(u8),
+    /// `I`
+    I,
+    /// `[I]`
+    IndirectI,
+    /// `DT`
+    DelayTimer,
+    /// `ST`
+    SoundTimer,
+    /// `K`
+    Key,
+    /// `F`
+    Font,
+    /// `B`
+    Bcd,
+    /// `LONG NNNN`
+    Long(&'a str),
+    /// A number or label
+    Value(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(operand: &'a str) -> Operand<'a> {
+        if let Some(value) = long_operand(operand) {
+            return Operand::Long(value);
+        }
+        match operand.to_ascii_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::DelayTimer,
+            "ST" => Operand::SoundTimer,
+            "K" => Operand::Key,
+            "F" => Operand::Font,
+            "B" => Operand::Bcd,
+            upper => match upper.strip_prefix('V').map(|x| u8::from_str_radix(x, 16)) {
+                Some(Ok(x)) if upper.len() == 2 => Operand::Register(x),
+                _ => Operand::Value(operand),
+            },
+        }
+    }
+}
+
+struct LineAssembler<'a> {
+    labels: &'a BTreeMap<&'a str, u16>,
+    mnemonic: &'a str,
+    operands: &'a [&'a str],
+}
+
+impl LineAssembler<'_> {
+    fn assemble(&self, bytes: &mut Vec<u8>) -> Result<(), AssembleErrorKind> {
+        let mnemonic = self.mnemonic.to_ascii_uppercase();
+        if mnemonic == "DB" {
+            for operand in self.operands {
+                bytes.push(self.value(operand, 0xff)? as u8);
+            }
+            return Ok(());
+        }
+
+        let inst = self.instruction(&mnemonic)?;
+        bytes.extend_from_slice(&encode(&inst).to_be_bytes());
+        if let Inst::LoadLongIntoI { nnnn } = inst {
+            bytes.extend_from_slice(&nnnn.to_be_bytes());
+        }
+        Ok(())
+    }
+
+    fn instruction(&self, mnemonic: &str) -> Result<Inst, AssembleErrorKind> {
+        use Operand::*;
+
+        let operands: Vec<Operand> = self.operands.iter().map(|o| Operand::parse(o)).collect();
+        let address = |value: &str| self.value(value, 0xfff);
+        let byte = |value: &str| self.value(value, 0xff).map(|nn| nn as u8);
+        let nibble = |value: &str| self.value(value, 0xf).map(|n| n as u8);
+
+        let inst = match (mnemonic, operands.as_slice()) {
+            ("SYS", [Value(nnn)]) => Inst::Exe { nnn: address(nnn)? },
+            ("CLS", []) => Inst::Clear,
+            ("RET", []) => Inst::Return,
+            ("EXIT", []) => Inst::Exit,
+            ("JP", [Value(nnn)]) => Inst::Jump { nnn: address(nnn)? },
+            ("JP", [Register(0), Value(nnn)]) => Inst::JumpAdd { nnn: address(nnn)? },
+            ("CALL", [Value(nnn)]) => Inst::Call { nnn: address(nnn)? },
+            ("SE", [Register(vx), Value(nn)]) => Inst::SkipEqualValue {
+                vx: *vx,
+                nn: byte(nn)?,
+            },
+            ("SE", [Register(vx), Register(vy)]) => Inst::SkipEqualRegister { vx: *vx, vy: *vy },
+            ("SNE", [Register(vx), Value(nn)]) => Inst::SkipNotEqualValue {
+                vx: *vx,
+                nn: byte(nn)?,
+            },
+            ("SNE", [Register(vx), Register(vy)]) => {
+                Inst::SkipNotEqualRegister { vx: *vx, vy: *vy }
+            }
+            ("LD", [Register(vx), Value(nn)]) => Inst::LoadValue {
+                vx: *vx,
+                nn: byte(nn)?,
+            },
+            ("LD", [Register(vx), Register(vy)]) => Inst::LoadRegister { vx: *vx, vy: *vy },
+            ("LD", [I, Value(nnn)]) => Inst::LoadIntoI { nnn: address(nnn)? },
+            ("LD", [I, Long(nnnn)]) => Inst::LoadLongIntoI {
+                nnnn: self.value(nnnn, 0xffff)?,
+            },
+            ("LD", [Register(vx), DelayTimer]) => Inst::LoadDelay { vx: *vx },
+            ("LD", [Register(vx), Key]) => Inst::WaitForKey { vx: *vx },
+            ("LD", [DelayTimer, Register(vx)]) => Inst::SetDelay { vx: *vx },
+            ("LD", [SoundTimer, Register(vx)]) => Inst::SetSound { vx: *vx },
+            ("LD", [Font, Register(vx)]) => Inst::LoadDigitSpriteAddrIntoI { vx: *vx },
+            ("LD", [Bcd, Register(vx)]) => Inst::StoreBCD { vx: *vx },
+            ("LD", [IndirectI, Register(vx)]) => Inst::StoreRegisters { vx: *vx },
+            ("LD", [Register(vx), IndirectI]) => Inst::LoadRegisters { vx: *vx },
+            ("ADD", [Register(vx), Value(nn)]) => Inst::AddValue {
+                vx: *vx,
+                nn: byte(nn)?,
+            },
+            ("ADD", [Register(vx), Register(vy)]) => Inst::AddRegister { vx: *vx, vy: *vy },
+            ("ADD", [I, Register(vx)]) => Inst::AddToI { vx: *vx },
+            ("OR", [Register(vx), Register(vy)]) => Inst::Or { vx: *vx, vy: *vy },
+            ("AND", [Register(vx), Register(vy)]) => Inst::And { vx: *vx, vy: *vy },
+            ("XOR", [Register(vx), Register(vy)]) => Inst::Xor { vx: *vx, vy: *vy },
+            ("SUB", [Register(vx), Register(vy)]) => Inst::SubRegisterXY { vx: *vx, vy: *vy },
+            ("SHR", [Register(vx), Register(vy)]) => Inst::ShiftRight { vx: *vx, vy: *vy },
+            ("SUBN", [Register(vx), Register(vy)]) => Inst::SubRegisterYX { vx: *vx, vy: *vy },
+            ("SHL", [Register(vx), Register(vy)]) => Inst::ShiftLeft { vx: *vx, vy: *vy },
+            ("RND", [Register(vx), Value(nn)]) => Inst::LoadRandom {
+                vx: *vx,
+                nn: byte(nn)?,
+            },
+            ("DRW", [Register(vx), Register(vy), Value(n)]) => Inst::DrawSprite {
+                vx: *vx,
+                vy: *vy,
+                n: nibble(n)?,
+            },
+            ("SKP", [Register(vx)]) => Inst::SkipIfKey { vx: *vx },
+            ("SKNP", [Register(vx)]) => Inst::SkipIfNotKey { vx: *vx },
+            ("PLANE", [Value(n)]) => Inst::SetPlane { n: nibble(n)? },
+            ("AUDIO", []) => Inst::LoadAudioPattern,
+            ("PITCH", [Register(vx)]) => Inst::SetPitch { vx: *vx },
+            (
+                "SYS" | "CLS" | "RET" | "JP" | "CALL" | "SE" | "SNE" | "LD" | "ADD" | "OR" | "AND"
+                | "XOR" | "SUB" | "SHR" | "SUBN" | "SHL" | "RND" | "DRW" | "SKP" | "SKNP" | "PLANE"
+                | "AUDIO" | "PITCH",
+                _,
+            ) => {
+                return Err(AssembleErrorKind::InvalidOperands {
+                    mnemonic: self.mnemonic.to_string(),
+                })
+            }
+            _ => {
+                return Err(AssembleErrorKind::UnknownMnemonic {
+                    mnemonic: self.mnemonic.to_string(),
+                })
+            }
+        };
+        Ok(inst)
+    }
+
+    /// Parse a number or label which must not be greater than `max`
+    fn value(&self, value: &str, max: u16) -> Result<u16, AssembleErrorKind> {
+        let parsed = if let Some(hex) = value.strip_prefix("0x") {
+            u32::from_str_radix(hex, 16).ok()
+        } else if let Some(binary) = value.strip_prefix("0b") {
+            u32::from_str_radix(binary, 2).ok()
+        } else if value.starts_with(|c: char| c.is_ascii_digit()) {
+            value.parse().ok()
+        } else {
+            match self.labels.get(value) {
+                Some(address) => Some(*address as u32),
+                None => {
+                    return Err(AssembleErrorKind::UnknownLabel {
+                        label: value.to_string(),
+                    })
+                }
+            }
+        };
+        match parsed {
+            Some(parsed) if parsed <= max as u32 => Ok(parsed as u16),
+            Some(_) => Err(AssembleErrorKind::OutOfRange {
+                value: value.to_string(),
+                max,
+            }),
+            None => Err(AssembleErrorKind::InvalidNumber {
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// Error type for `assemble()`.
+///
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
+#[derive(Debug)]
+pub struct AssembleError {
+    /// The line the error is on, starting from 1
+    pub line: usize,
+    pub kind: AssembleErrorKind,
+}
+
+#[derive(Debug)]
+pub enum AssembleErrorKind {
+    UnknownMnemonic {
+        mnemonic: String,
+    },
+    /// The mnemonic is known, but not with these operands
+    InvalidOperands {
+        mnemonic: String,
+    },
+    InvalidNumber {
+        value: String,
+    },
+    /// The number or label's address is too large for the operand
+    OutOfRange {
+        value: String,
+        max: u16,
+    },
+    UnknownLabel {
+        label: String,
+    },
+    DuplicateLabel {
+        label: String,
+    },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AssembleError {}
+
+impl fmt::Display for AssembleErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleErrorKind::UnknownMnemonic { mnemonic } => {
+                write!(f, "unknown mnemonic: {mnemonic}")
+            }
+            AssembleErrorKind::InvalidOperands { mnemonic } => {
+                write!(f, "invalid operands for {mnemonic}")
+            }
+            AssembleErrorKind::InvalidNumber { value } => write!(f, "invalid number: {value}"),
+            AssembleErrorKind::OutOfRange { value, max } => {
+                write!(f, "{value} is greater than the maximum of 0x{max:x}")
+            }
+            AssembleErrorKind::UnknownLabel { label } => write!(f, "unknown label: {label}"),
+            AssembleErrorKind::DuplicateLabel { label } => {
+                write!(f, "label defined more than once: {label}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::String, vec, vec::Vec};
+    use core::fmt::Write;
+
+    use super::{assemble, AssembleErrorKind};
+    use crate::instruction::{decode, decode_with_immediate, has_immediate};
+
+    /// Disassemble a program with one instruction per line
+    fn disassemble(bytes: &[u8]) -> String {
+        let words: Vec<u16> = bytes
+            .chunks(2)
+            .map(|word| u16::from_be_bytes([word[0], word[1]]))
+            .collect();
+        let mut source = String::new();
+        let mut words = words.into_iter();
+        while let Some(word) = words.next() {
+            let inst = if has_immediate(word) {
+                decode_with_immediate(word, words.next().unwrap())
+            } else {
+                decode(word)
+            };
+            writeln!(source, "{}", inst.unwrap()).unwrap();
+        }
+        source
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; draw the digit in V0 forever
+            start:
+                LD V0, 0x0a
+                LD F, V0
+                DRW V1, V2, 5 ; a comment
+            loop: JP loop
+        ";
+
+        let bytes = assemble(source).unwrap();
+
+        assert_eq!(bytes, vec![0x60, 0x0a, 0xf0, 0x29, 0xd1, 0x25, 0x12, 0x06]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "
+            CLS
+            LD I, LONG 0x1234
+            CALL sub
+            SE V1, 0b101
+            SNE VA, VB
+            ld v3, 200
+            ADD I, V4
+            LD [I], VF
+            LD V2, [I]
+            PLANE 3
+            AUDIO
+            PITCH V5
+        sub:
+            SHL V1, V2
+            RND VE, 0xff
+            RET
+        ";
+
+        let bytes = assemble(source).unwrap();
+        let disassembled = disassemble(&bytes);
+
+        assert_eq!(assemble(&disassembled).unwrap(), bytes);
+        assert!(disassembled.contains("CALL 0x21a"));
+    }
+
+    #[test]
+    fn test_data() {
+        let bytes = assemble("LD I, sprite\nsprite: DB 0xff, 0b10000001").unwrap();
+
+        assert_eq!(bytes, vec![0xa2, 0x02, 0xff, 0x81]);
+    }
+
+    #[test]
+    fn test_errors_have_line_numbers() {
+        let err = assemble("CLS\nFOO V0").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(matches!(
+            err.kind,
+            AssembleErrorKind::UnknownMnemonic { .. }
+        ));
+
+        let err = assemble("CLS\nCLS\nLD V0, 0x100").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(matches!(err.kind, AssembleErrorKind::OutOfRange { .. }));
+
+        let err = assemble("DRW V0, 5").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            AssembleErrorKind::InvalidOperands { .. }
+        ));
+
+        let err = assemble("JP nowhere").unwrap_err();
+        assert!(matches!(err.kind, AssembleErrorKind::UnknownLabel { .. }));
+
+        let err = assemble("a: CLS\na: CLS").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(matches!(err.kind, AssembleErrorKind::DuplicateLabel { .. }));
+    }
+}
@@ -1,15 +1,50 @@
+#[cfg(feature = "alloc")]
+use crate::snapshot::{Snapshot, SnapshotError};
+#[cfg(feature = "trace")]
+use crate::trace::TraceBuffer;
 use crate::{
-    instruction::{self, decode, Inst},
-    memory::{self, Chip8Memory, CHIP8_MEMORY_SIZE_BYTES, PROGRAM_OFFSET_BYTES},
-    screen::Chip8Screen,
+    instruction::{
+        self, decode, decode_with_immediate, has_immediate, DecodeError, Inst, INST_KIND_COUNT,
+    },
+    keyboard::{just_pressed, just_released},
+    memory::{
+        self, Chip8Memory, LoadError, MemoryRegion, CHIP8_MEMORY_SIZE_BYTES,
+        DIGIT_SPRITE_SIZE_BYTES, DISPLAY_OFFSET_BYTES, FONT_SIZE_BYTES, PROGRAM_OFFSET_BYTES,
+        XO_CHIP_MEMORY_SIZE_BYTES,
+    },
+    quirks::Quirks,
+    rng::Rng,
+    screen::Screen,
+    timing::CycleCosts,
 };
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use core::fmt;
 
-const STACK_SIZE: usize = 12;
+pub const STACK_SIZE: usize = 12;
+/// The size of the XO-CHIP audio pattern buffer
+pub const AUDIO_PATTERN_SIZE_BYTES: usize = 16;
+/// The initial audio pitch, at which the audio pattern plays at 4000 bits per
+/// second
+pub const DEFAULT_PITCH: u8 = 64;
+/// The maximum number of breakpoints which can be set at once
+pub const MAX_BREAKPOINTS: usize = 16;
+/// The maximum number of strict mode warnings kept until they're cleared
+pub const MAX_WARNINGS: usize = 16;
+/// In strict mode, calls which leave this few free stack slots or fewer are
+/// warned about
+const STACK_WARNING_FREE_SLOTS: usize = 2;
 
+/// A CHIP-8 machine.
+///
+/// Cloning duplicates the full machine state, including memory, the screen,
+/// breakpoints and quirks, so the clone continues exactly as the original
+/// would. Two machines are equal when every part of their state is, which
+/// makes them easy to compare in tests.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Chip8 {
     memory: Chip8Memory,
-    screen: Chip8Screen,
+    screen: Screen,
     /// general purpose registers
     v_reg: [u8; 16],
     /// memory address register
@@ -23,14 +58,150 @@ pub struct Chip8 {
 
     delay_timer: u8,
     sound_timer: u8,
+    /// The XO-CHIP audio pattern, played one bit at a time while the sound
+    /// timer is active
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE_BYTES],
+    /// The XO-CHIP audio pitch, see `pitch()`
+    pitch: u8,
+    /// The number of 60Hz timer ticks so far, see `frame_count()`
+    frame_count: u64,
+    /// Whether the last cycle was `FX0A` waiting for a key
+    waiting_for_key: bool,
+    /// The keys which were held when `FX0A` started waiting and haven't been
+    /// released since, which can't complete the wait
+    waiting_initial_state: [bool; 16],
+    /// Whether `waiting_initial_state` is used, see
+    /// `set_ignore_keys_held_before_wait()`. Unlike the other options, this
+    /// starts enabled.
+    ignore_keys_held_before_wait: bool,
+    /// Whether `00FD` (`Exit`) has stopped the program, see `is_halted()`
+    halted: bool,
+    /// Whether a sprite has been drawn since the timers last ticked, which
+    /// makes the next `DXYN` wait with the `display_wait` quirk
+    drew_since_vblank: bool,
+    /// The source of `CXNN`'s random numbers
+    rng: Rng,
+    /// Used instead of `rng` if set, see `set_rng_override()`
+    rng_override: Option<u8>,
+
+    quirks: Quirks,
+    /// How long each instruction takes, see `last_cycle_cost()`
+    cycle_costs: CycleCosts,
+    /// The cost of the instruction run by the last cycle
+    last_cycle_cost: u32,
+
+    /// The length of the loaded program, for detecting writes into it
+    program_len: usize,
+    /// What the memory and registers hold when a program is loaded
+    init: InitState,
+    /// Copy the screen into memory whenever it changes, see
+    /// `set_mirror_display()`
+    mirror_display: bool,
+    /// Count writes into the loaded program, see `program_write_count()`
+    detect_program_writes: bool,
+    program_write_count: u32,
+    /// Draws which set VF to 1, see `collision_count()`
+    collision_count: u64,
+    /// Instructions run of each kind, or `None` if they aren't being counted,
+    /// see `instruction_histogram()`
+    instruction_histogram: Option<[u64; INST_KIND_COUNT]>,
+
+    /// Report questionable behaviour as warnings, see `set_strict()`
+    strict: bool,
+    /// Skip unknown instructions instead of failing, see
+    /// `set_tolerate_unknown_instructions()`
+    tolerate_unknown_instructions: bool,
+    /// Skip `0NNN` instead of failing, see `set_skip_machine_code_calls()`
+    skip_machine_code_calls: bool,
+    /// Warn about fetching instructions from odd addresses, see
+    /// `set_warn_on_misaligned_pc()`
+    warn_on_misaligned_pc: bool,
+    /// Warn about `7XNN` wrapping past 255, see `set_warn_on_add_overflow()`
+    warn_on_add_overflow: bool,
+    /// Warnings raised in strict mode since they were last cleared
+    warnings: [Option<Warning>; MAX_WARNINGS],
+
+    /// Addresses which `cycle()` reports `CycleOutcome::BreakpointHit` for
+    breakpoints: [Option<u16>; MAX_BREAKPOINTS],
+    /// Called with every instruction, see `set_trace_hook()`
+    trace_hook: TraceHookSlot,
+    /// The last instructions run, see `recent_trace()`
+    #[cfg(feature = "trace")]
+    trace: TraceBuffer,
+}
+
+/// A function which `cycle()` calls with the address, the opcode and the
+/// decoded instruction of each instruction just before running it
+pub type TraceHook = fn(pc: u16, opcode: u16, inst: &Inst);
+
+/// Holds the trace hook, which isn't machine state, so machines are equal
+/// whatever their hooks are
+#[derive(Clone, Copy, Default)]
+struct TraceHookSlot(Option<TraceHook>);
+
+impl PartialEq for TraceHookSlot {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for TraceHookSlot {}
+
+/// What the registers and the memory outside the font and program hold when a
+/// program is loaded, see `Chip8::new_with_init()`.
+///
+/// Real hardware doesn't clear them at power-on, so a program which reads
+/// them before writing them may only work by luck on some machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitState {
+    /// Everything is zero, like most emulators
+    #[default]
+    Zeroed,
+    /// Every byte is the given value
+    Fill(u8),
+    /// Random bytes which are the same every time for the same seed
+    Random { seed: u64 },
 }
 
 impl Chip8 {
+    /// Create a CHIP-8 with the given program loaded.
+    ///
+    /// Panics if the program is too long to fit in memory, use `try_new()`
+    /// for programs which haven't been checked.
     #[must_use]
     pub fn new(program: &[u8]) -> Chip8 {
+        Self::new_with_quirks(program, Quirks::default())
+    }
+
+    /// Like `new()`, but returns an error if the program is too long to fit in
+    /// memory instead of panicking
+    pub fn try_new(program: &[u8]) -> Result<Chip8, LoadError> {
+        Self::try_new_with_quirks(program, Quirks::default())
+    }
+
+    /// Like `new_with_quirks()`, but returns an error if the program is too
+    /// long to fit in memory instead of panicking
+    pub fn try_new_with_quirks(program: &[u8], quirks: Quirks) -> Result<Chip8, LoadError> {
+        let max_len = memory_size(&quirks) - PROGRAM_OFFSET_BYTES;
+        if program.len() > max_len {
+            return Err(LoadError::ProgramTooLong {
+                len: program.len(),
+                max_len,
+            });
+        }
+        Ok(Self::new_with_quirks(program, quirks))
+    }
+
+    /// Create a CHIP-8 with the given program loaded, using the given quirks
+    /// instead of the defaults.
+    ///
+    /// Panics if the program is too long to fit in memory, see
+    /// `try_new_with_quirks()`.
+    #[must_use]
+    pub fn new_with_quirks(program: &[u8], quirks: Quirks) -> Chip8 {
         Chip8 {
-            memory: Chip8Memory::new(program),
-            screen: Chip8Screen::new(),
+            memory: Chip8Memory::new_with_size(program, memory_size(&quirks)),
+            screen: Screen::default(),
             v_reg: [0; 16],
             i_reg: 0,
             stack: [0; STACK_SIZE],
@@ -38,90 +209,964 @@ impl Chip8 {
             pc: PROGRAM_OFFSET_BYTES as u16,
             delay_timer: 0,
             sound_timer: 0,
+            audio_pattern: [0; AUDIO_PATTERN_SIZE_BYTES],
+            pitch: DEFAULT_PITCH,
+            frame_count: 0,
+            waiting_for_key: false,
+            waiting_initial_state: [false; 16],
+            ignore_keys_held_before_wait: true,
+            halted: false,
+            drew_since_vblank: false,
+            rng: Rng::default(),
+            rng_override: None,
+            quirks,
+            cycle_costs: CycleCosts::default(),
+            last_cycle_cost: 0,
+            program_len: program.len(),
+            init: InitState::Zeroed,
+            mirror_display: false,
+            detect_program_writes: false,
+            program_write_count: 0,
+            collision_count: 0,
+            instruction_histogram: None,
+            strict: false,
+            tolerate_unknown_instructions: false,
+            skip_machine_code_calls: false,
+            warn_on_misaligned_pc: false,
+            warn_on_add_overflow: false,
+            warnings: [None; MAX_WARNINGS],
+            breakpoints: [None; MAX_BREAKPOINTS],
+            trace_hook: TraceHookSlot::default(),
+            #[cfg(feature = "trace")]
+            trace: TraceBuffer::default(),
         }
     }
 
-    /// Advance the CHIP-8 by one cycle using the inputs given.
+    /// Create a CHIP-8 with the given program loaded, where the V registers,
+    /// I and the memory outside the font and program start as `init`
+    /// describes instead of zeroed. `load_program()` uses `init` again.
+    ///
+    /// Panics if the program is too long to fit in memory.
+    #[must_use]
+    pub fn new_with_init(program: &[u8], init: InitState) -> Chip8 {
+        let mut chip8 = Self::new(program);
+        chip8.init = init;
+        chip8.apply_init_state();
+        chip8
+    }
+
+    /// Create a CHIP-8 with the given program loaded, using `font` for the
+    /// hex digit sprites instead of the default ones. See `set_font()`.
+    #[must_use]
+    pub fn with_font(program: &[u8], font: &[u8; FONT_SIZE_BYTES]) -> Chip8 {
+        let mut chip8 = Self::new(program);
+        chip8.set_font(font);
+        chip8
+    }
+
+    /// Replace the hex digit sprites which `FX29` points to with `font`,
+    /// which holds the 5 byte sprites for 0 to F in order.
+    ///
+    /// This overwrites memory, so it should only be done before the program
+    /// starts running. `load_program()` restores the default sprites.
+    pub fn set_font(&mut self, font: &[u8; FONT_SIZE_BYTES]) {
+        self.memory.set_font(font);
+    }
+
+    /// Replace the loaded program and reset the CHIP-8 to its initial state,
+    /// reusing this instance. The quirks, `InitState` and random number
+    /// generator are kept. Nothing is changed if the program is too long to
+    /// fit in memory.
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        self.memory.load_program(program)?;
+        self.screen = Screen::default();
+        self.v_reg = [0; 16];
+        self.i_reg = 0;
+        self.stack = [0; STACK_SIZE];
+        self.stack_ptr = 0;
+        self.pc = PROGRAM_OFFSET_BYTES as u16;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.audio_pattern = [0; AUDIO_PATTERN_SIZE_BYTES];
+        self.pitch = DEFAULT_PITCH;
+        self.frame_count = 0;
+        self.waiting_for_key = false;
+        self.waiting_initial_state = [false; 16];
+        self.halted = false;
+        self.drew_since_vblank = false;
+        self.last_cycle_cost = 0;
+        self.program_len = program.len();
+        self.program_write_count = 0;
+        self.collision_count = 0;
+        self.reset_instruction_histogram();
+        self.warnings = [None; MAX_WARNINGS];
+        #[cfg(feature = "trace")]
+        self.trace.clear();
+        self.apply_init_state();
+        Ok(())
+    }
+
+    /// Advance the CHIP-8 by one cycle using the inputs given, and return
+    /// anything notable which happened.
     ///
     /// Don't forget to call `update_timers()` 60 times per realtime second.
     pub fn cycle(
         &mut self,
         keyboard_state: &[bool; 16],
         previous_keyboard_state: &[bool; 16],
-    ) -> Result<(), CycleError> {
+    ) -> Result<CycleOutcome, CycleError> {
+        if self.halted {
+            self.last_cycle_cost = 0;
+            return Ok(CycleOutcome::Halted);
+        }
+        if self.warn_on_misaligned_pc && self.pc & 1 != 0 {
+            self.warn(Warning::MisalignedPc { pc: self.pc });
+        }
+        if self.pc as usize + 1 >= self.memory.size() {
+            self.last_cycle_cost = 0;
+            return Err(CycleError::ExecuteError(ExecuteError::PcOutOfBounds {
+                pc: self.pc,
+            }));
+        }
         // Get instruction at program counter
         let instruction_bytes = self.get_instruction();
-        let instruction = match decode(instruction_bytes) {
-            Ok(inst) => inst,
+        let instruction = self.peek_instruction();
+        let outcome = match instruction {
+            Ok(instruction) => {
+                if let Some(hook) = self.trace_hook.0 {
+                    hook(self.pc, instruction_bytes, &instruction);
+                }
+                #[cfg(feature = "trace")]
+                self.trace.push(self.pc, instruction_bytes);
+                self.last_cycle_cost = self.cycle_costs.cost(&instruction);
+                match self.execute_instruction(instruction, keyboard_state, previous_keyboard_state)
+                {
+                    Ok(outcome) => {
+                        if let Some(histogram) = &mut self.instruction_histogram {
+                            histogram[instruction.kind()] += 1;
+                        }
+                        outcome
+                    }
+                    Err(err) => return Err(CycleError::ExecuteError(err)),
+                }
+            }
+            Err(DecodeError::UnknownInstruction { inst }) if self.tolerate_unknown_instructions => {
+                self.warn(Warning::UnknownInstruction { pc: self.pc, inst });
+                self.last_cycle_cost = self.cycle_costs.other;
+                self.advance_pc(2).map_err(CycleError::ExecuteError)?;
+                CycleOutcome::Normal
+            }
             Err(err) => return Err(CycleError::DecodeError(err)),
         };
-        if let Err(err) =
-            self.execute_instruction(instruction, keyboard_state, previous_keyboard_state)
-        {
-            Err(CycleError::ExecuteError(err))
+        self.waiting_for_key = outcome == CycleOutcome::WaitingForKey;
+        if self.breakpoints.contains(&Some(self.pc)) {
+            Ok(CycleOutcome::BreakpointHit)
         } else {
-            Ok(())
+            Ok(outcome)
+        }
+    }
+
+    /// Run `frames` frames without a GUI, where each frame is
+    /// `cycles_per_frame` cycles followed by a timer update. The keyboard is
+    /// held in `keyboard_state` for the whole run.
+    pub fn run_frames(
+        &mut self,
+        frames: usize,
+        cycles_per_frame: usize,
+        keyboard_state: &[bool; 16],
+    ) -> Result<(), CycleError> {
+        self.run_frames_with(frames, cycles_per_frame, keyboard_state, |_| {})
+    }
+
+    /// Like `run_frames()`, but `on_frame` is called at the end of each frame,
+    /// once its cycles have run and the timers have ticked. This is the point
+    /// to produce a frame's worth of audio and video, so they stay in step
+    /// with the CHIP-8 however often the front-end draws.
+    pub fn run_frames_with(
+        &mut self,
+        frames: usize,
+        cycles_per_frame: usize,
+        keyboard_state: &[bool; 16],
+        mut on_frame: impl FnMut(&Chip8),
+    ) -> Result<(), CycleError> {
+        for _ in 0..frames {
+            for _ in 0..cycles_per_frame {
+                self.cycle(keyboard_state, keyboard_state)?;
+            }
+            self.update_timers();
+            on_frame(self);
+        }
+        Ok(())
+    }
+
+    /// Advance the CHIP-8 past the instruction at the program counter. If it's
+    /// a `Call`, cycles are run until the matching `Return` has executed, so
+    /// the whole subroutine is stepped over. Otherwise this is the same as a
+    /// single `cycle()`.
+    ///
+    /// Stepping stops early if a breakpoint is hit or the program exits inside
+    /// the subroutine. If the subroutine hasn't returned after `max_cycles`
+    /// cycles, `StepOverError::CycleLimitReached` is returned and the CHIP-8 is
+    /// left wherever it got to. While running a subroutine the timers are
+    /// updated after every `cycles_per_frame` cycles, so delay timer waits
+    /// finish.
+    pub fn step_over(
+        &mut self,
+        keyboard_state: &[bool; 16],
+        previous_keyboard_state: &[bool; 16],
+        cycles_per_frame: usize,
+        max_cycles: usize,
+    ) -> Result<CycleOutcome, StepOverError> {
+        let is_call = matches!(decode(self.get_instruction()), Ok(Inst::Call { .. }));
+        let depth = self.stack_ptr;
+        let mut outcome = self
+            .cycle(keyboard_state, previous_keyboard_state)
+            .map_err(StepOverError::CycleError)?;
+        if !is_call {
+            return Ok(outcome);
+        }
+        let cycles_per_frame = cycles_per_frame.max(1);
+        let mut cycles = 1;
+        while self.stack_ptr > depth
+            && !matches!(outcome, CycleOutcome::BreakpointHit | CycleOutcome::Halted)
+        {
+            if cycles >= max_cycles {
+                return Err(StepOverError::CycleLimitReached { max_cycles });
+            }
+            if cycles % cycles_per_frame == 0 {
+                self.update_timers();
+            }
+            outcome = self
+                .cycle(keyboard_state, keyboard_state)
+                .map_err(StepOverError::CycleError)?;
+            cycles += 1;
+        }
+        Ok(outcome)
+    }
+
+    /// Run up to `n` cycles, stopping early at a breakpoint or once the
+    /// program has exited, and return how many ran along with the outcome of
+    /// the last one, or the error which stopped them. The cycle which failed
+    /// isn't counted. Timers aren't updated.
+    ///
+    /// `previous_keyboard_state` is only used for the first cycle, after which
+    /// the keyboard is held in `keyboard_state`.
+    pub fn cycle_n(
+        &mut self,
+        n: usize,
+        keyboard_state: &[bool; 16],
+        previous_keyboard_state: &[bool; 16],
+    ) -> (usize, Result<CycleOutcome, CycleError>) {
+        let mut previous_keyboard_state = previous_keyboard_state;
+        let mut outcome = CycleOutcome::Normal;
+        for cycles in 0..n {
+            outcome = match self.cycle(keyboard_state, previous_keyboard_state) {
+                Ok(outcome) => outcome,
+                Err(err) => return (cycles, Err(err)),
+            };
+            previous_keyboard_state = keyboard_state;
+            if matches!(outcome, CycleOutcome::BreakpointHit | CycleOutcome::Halted) {
+                return (cycles + 1, Ok(outcome));
+            }
+        }
+        (n, Ok(outcome))
+    }
+
+    /// Run cycles until one draws a sprite or clears the screen, so the
+    /// screen may have changed, and return whether it did. Also stops early
+    /// at a breakpoint, after `max_cycles` cycles, once the program has
+    /// exited, or while `FX0A` (`WaitForKey`) is waiting for a key, returning
+    /// `false`.
+    ///
+    /// The timers are updated after every `cycles_per_frame` cycles, so
+    /// programs which wait on the delay timer still reach their next draw.
+    /// `previous_keyboard_state` is only used for the first cycle, after which
+    /// the keyboard is held in `keyboard_state`.
+    pub fn run_to_next_draw(
+        &mut self,
+        keyboard_state: &[bool; 16],
+        previous_keyboard_state: &[bool; 16],
+        cycles_per_frame: usize,
+        max_cycles: usize,
+    ) -> Result<bool, CycleError> {
+        let cycles_per_frame = cycles_per_frame.max(1);
+        let mut previous_keyboard_state = previous_keyboard_state;
+        for cycles in 1..=max_cycles {
+            // checked before the cycle since a breakpoint hit replaces
+            // `CycleOutcome::DrewSprite`
+            let draws = matches!(
+                decode(self.get_instruction()),
+                Ok(Inst::DrawSprite { .. } | Inst::Clear)
+            );
+            let outcome = self.cycle(keyboard_state, previous_keyboard_state)?;
+            previous_keyboard_state = keyboard_state;
+            if cycles % cycles_per_frame == 0 {
+                self.update_timers();
+            }
+            if draws {
+                return Ok(true);
+            }
+            if matches!(
+                outcome,
+                CycleOutcome::BreakpointHit | CycleOutcome::WaitingForKey | CycleOutcome::Halted
+            ) {
+                return Ok(false);
+            }
         }
+        Ok(false)
     }
 
     /// Update the delay timer and sound timer. This should be called 60 times
     /// per realtime second
     pub fn update_timers(&mut self) {
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
+        self.update_timers_by(1);
+    }
+
+    /// Decrement the delay timer and sound timer by `ticks`, stopping at 0.
+    /// This is the same as calling `update_timers()` `ticks` times, for when
+    /// several 60Hz ticks have passed at once or the timers should run at a
+    /// different rate.
+    pub fn update_timers_by(&mut self, ticks: u8) {
+        self.delay_timer = self.delay_timer.saturating_sub(ticks);
+        self.sound_timer = self.sound_timer.saturating_sub(ticks);
+        self.frame_count += ticks as u64;
+        if ticks > 0 {
+            self.drew_since_vblank = false;
         }
     }
 
+    /// The number of 60Hz frames completed since the program was loaded,
+    /// where a frame ends each time the timers tick. A front-end running
+    /// `cycles_per_frame` cycles between timer updates can compare this
+    /// before and after running to find how many frame boundaries passed, for
+    /// timing things like audio independently of its own frame rate.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The XO-CHIP audio pattern loaded by `F002`, which is played from the
+    /// most significant bit of the first byte while the speaker is playing,
+    /// where each bit is a 1 or 0 sample
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE_BYTES] {
+        &self.audio_pattern
+    }
+
+    /// The XO-CHIP audio pitch set by `FX3A`. The audio pattern plays at
+    /// `4000 * 2^((pitch - 64) / 48)` bits per second.
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Whether the CHIP-8 is stalled on `FX0A` until a key is pressed (or
+    /// released, depending on the quirks), so a front-end can prompt the user
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key
+    }
+
+    /// Whether the program has exited with `00FD` (`Exit`). `cycle()` does
+    /// nothing but return `CycleOutcome::Halted` until `load_program()` is
+    /// called.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
     /// Whether the CHIP-8 speaker is playing
     pub fn is_sound_playing(&self) -> bool {
         self.sound_timer >= 2
     }
 
-    pub fn get_screen(&self) -> &Chip8Screen {
+    /// How long the instruction run by the last cycle takes on the original
+    /// hardware, in microseconds, according to `cycle_costs()`. This is 0
+    /// before the first cycle.
+    ///
+    /// Summing these lets a frame be budgeted by machine time instead of by
+    /// instruction count, see `timing::FrameBudget`.
+    pub fn last_cycle_cost(&self) -> u32 {
+        self.last_cycle_cost
+    }
+
+    /// The table `last_cycle_cost()` is looked up in
+    pub fn cycle_costs(&self) -> &CycleCosts {
+        &self.cycle_costs
+    }
+
+    /// Replace the table `last_cycle_cost()` is looked up in. The default is
+    /// `CycleCosts::vip()`.
+    pub fn set_cycle_costs(&mut self, cycle_costs: CycleCosts) {
+        self.cycle_costs = cycle_costs;
+    }
+
+    /// The delay timer, which counts down to 0 at 60Hz
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    /// The sound timer, which counts down to 0 at 60Hz and plays the beep
+    /// while it's running, see `is_sound_playing()`
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn get_screen(&self) -> &Screen {
         &self.screen
     }
+
+    /// The screen, for debuggers which edit pixels directly. Drawing with
+    /// this doesn't set VF.
+    pub fn get_screen_mut(&mut self) -> &mut Screen {
+        &mut self.screen
+    }
+
+    /// Switch between the 64x32 screen and the SUPER-CHIP 128x64 high-res
+    /// screen, which clears it but keeps the selected planes. Loading a
+    /// program switches back to low-res.
+    pub fn set_high_res(&mut self, high_res: bool) {
+        let selected_planes = self.screen.selected_planes();
+        self.screen = Screen::new(high_res);
+        self.screen.select_planes(selected_planes);
+        self.update_display_mirror();
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// Change the quirks while running. Memory is resized if
+    /// `extended_memory` changes, keeping as many bytes as fit. When it
+    /// shrinks, `I` and the program counter wrap around into the smaller
+    /// memory, as the addresses would on a 12-bit interpreter.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        if quirks.extended_memory != self.quirks.extended_memory {
+            let size = memory_size(&quirks);
+            let mut memory = Chip8Memory::new_with_size(&[], size);
+            let kept = size.min(self.memory.size());
+            memory.set_bytes(0, self.memory.get_bytes(0, kept));
+            self.memory = memory;
+            let mask = (size - 1) as u16;
+            self.i_reg &= mask;
+            self.pc &= mask;
+        }
+        self.quirks = quirks;
+    }
+
+    /// The program counter
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// The big-endian word at the program counter, which is the opcode of the
+    /// instruction the next cycle will execute
+    pub fn peek_opcode(&self) -> u16 {
+        self.get_instruction()
+    }
+
+    /// Decode the instruction the next cycle will execute, including the
+    /// address word after `F000`, without running it. `F000` is only an
+    /// instruction with the `extended_memory` quirk, and one without room for
+    /// its address word before the end of memory is
+    /// `DecodeError::MissingImmediate`.
+    pub fn peek_instruction(&self) -> Result<Inst, DecodeError> {
+        let opcode = self.get_instruction();
+        if has_immediate(opcode) && !self.quirks.extended_memory {
+            return Err(DecodeError::UnknownInstruction { inst: opcode });
+        }
+        let instruction = if has_immediate(opcode) && self.pc as usize + 3 < self.memory.size() {
+            decode_with_immediate(opcode, self.get_word(self.pc as usize + 2))
+        } else {
+            decode(opcode)
+        }?;
+        match instruction {
+            Inst::LoadAudioPattern | Inst::SetPitch { .. } if !self.quirks.extended_memory => {
+                Err(DecodeError::UnknownInstruction { inst: opcode })
+            }
+            _ => Ok(instruction),
+        }
+    }
+
+    /// The memory address register
+    pub fn i_register(&self) -> u16 {
+        self.i_reg
+    }
+
+    /// The general purpose registers, `V0` to `VF`
+    pub fn v_registers(&self) -> &[u8; 16] {
+        &self.v_reg
+    }
+
+    /// The layout of memory with the loaded program, see `memory::memory_map()`
+    pub fn memory_map(&self) -> [MemoryRegion; 4] {
+        memory::memory_map(self.memory.size(), self.program_len)
+    }
+
+    /// All of the addressable memory, for debugging
+    pub fn dump_memory(&self) -> &[u8] {
+        self.memory.get_bytes(0, self.memory.size())
+    }
+
+    /// Overwrite a byte of memory, for debugging. Panics if `index` is beyond
+    /// the end of memory.
+    pub fn write_memory(&mut self, index: usize, value: u8) {
+        self.memory.set(index, value);
+    }
+
+    /// Report `CycleOutcome::BreakpointHit` from `cycle()` when the program
+    /// counter reaches `address`, before the instruction there is executed.
+    /// Returns false if `MAX_BREAKPOINTS` breakpoints are already set.
+    pub fn add_breakpoint(&mut self, address: u16) -> bool {
+        if self.breakpoints.contains(&Some(address)) {
+            return true;
+        }
+        if let Some(slot) = self.breakpoints.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(address);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        for slot in &mut self.breakpoints {
+            if *slot == Some(address) {
+                *slot = None;
+            }
+        }
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints = [None; MAX_BREAKPOINTS];
+    }
+
+    /// The addresses breakpoints are set at, in no particular order
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().flatten().copied()
+    }
+
+    /// Restart the random number generator used by `CXNN` (`LoadRandom`)
+    /// from `seed`. Running a program again with the same seed and inputs
+    /// gives the same results.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Make `CXNN` (`LoadRandom`) use `value & NN` instead of a random number,
+    /// or go back to random numbers if `value` is `None`. This is intended for
+    /// tests, where a fixed value is clearer than a seed. The random number
+    /// generator doesn't advance while it's overridden.
+    pub fn set_rng_override(&mut self, value: Option<u8>) {
+        self.rng_override = value;
+    }
+
+    /// Start or stop copying the screen into memory at `DISPLAY_OFFSET_BYTES`
+    /// in the format of `Chip8Screen::to_packed_bytes()` whenever `00E0` or
+    /// `DXYN` changes it, while the screen is low-res. Otherwise the screen is
+    /// kept separate from memory, so programs can use all of it.
+    ///
+    /// The COSMAC VIP drew the screen from that memory, and a few programs
+    /// read the screen back from it. Writing to it doesn't change the screen,
+    /// and the program loses whatever it kept there.
+    pub fn set_mirror_display(&mut self, enabled: bool) {
+        self.mirror_display = enabled;
+        self.update_display_mirror();
+    }
+
+    pub fn mirrors_display(&self) -> bool {
+        self.mirror_display
+    }
+
+    /// Start or stop counting writes into the memory the program was loaded
+    /// into, which usually means it's accidentally corrupting itself
+    pub fn set_detect_program_writes(&mut self, enabled: bool) {
+        self.detect_program_writes = enabled;
+    }
+
+    /// The number of bytes written into the loaded program by `FX33`
+    /// (`StoreBCD`) or `FX55` (`StoreRegisters`) since it was loaded, while
+    /// detection was enabled with `set_detect_program_writes()`
+    pub fn program_write_count(&self) -> u32 {
+        self.program_write_count
+    }
+
+    /// The number of `DXYN` (`DrawSprite`) instructions which have set VF to 1
+    /// because of a collision since the program was loaded or
+    /// `reset_collision_count()` was called. Restoring a snapshot doesn't
+    /// change it.
+    pub fn collision_count(&self) -> u64 {
+        self.collision_count
+    }
+
+    pub fn reset_collision_count(&mut self) {
+        self.collision_count = 0;
+    }
+
+    /// Start or stop counting the instructions run of each kind, for profiling
+    /// programs, which costs nothing while it's stopped. Stopping discards the
+    /// counts.
+    pub fn set_count_instructions(&mut self, enabled: bool) {
+        match (enabled, self.instruction_histogram.is_some()) {
+            (true, false) => self.instruction_histogram = Some([0; INST_KIND_COUNT]),
+            (false, _) => self.instruction_histogram = None,
+            (true, true) => {}
+        }
+    }
+
+    /// The number of instructions of each kind, indexed by `Inst::kind()`,
+    /// which have run without an error since counting was started with
+    /// `set_count_instructions()`, the program was loaded or
+    /// `reset_instruction_histogram()` was called. `None` if they aren't being
+    /// counted.
+    pub fn instruction_histogram(&self) -> Option<&[u64; INST_KIND_COUNT]> {
+        self.instruction_histogram.as_ref()
+    }
+
+    pub fn reset_instruction_histogram(&mut self) {
+        if let Some(histogram) = &mut self.instruction_histogram {
+            *histogram = [0; INST_KIND_COUNT];
+        }
+    }
+
+    /// Start or stop strict mode, where behaviour which is allowed but is
+    /// usually a bug is reported through `warnings()` without stopping the
+    /// program
+    pub fn set_strict(&mut self, enabled: bool) {
+        self.strict = enabled;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Start or stop skipping instructions which fail to decode with
+    /// `DecodeError::UnknownInstruction`, as if they were 2 byte no-ops,
+    /// instead of returning the error from `cycle()`. Each one skipped is
+    /// reported through `warnings()` as `Warning::UnknownInstruction`.
+    ///
+    /// Some ROMs contain opcodes which only did something on particular
+    /// hardware, and otherwise run fine without them.
+    pub fn set_tolerate_unknown_instructions(&mut self, enabled: bool) {
+        self.tolerate_unknown_instructions = enabled;
+    }
+
+    pub fn tolerates_unknown_instructions(&self) -> bool {
+        self.tolerate_unknown_instructions
+    }
+
+    /// Start or stop treating `0NNN` (`Exe`), which calls a machine code
+    /// subroutine on the original hardware, as a no-op instead of failing
+    /// with `ExecuteError::UnknownMachineSubroutine`. The machine code itself
+    /// is never run, so this only helps ROMs whose machine code doesn't matter
+    /// to the rest of the program.
+    pub fn set_skip_machine_code_calls(&mut self, enabled: bool) {
+        self.skip_machine_code_calls = enabled;
+    }
+
+    pub fn skips_machine_code_calls(&self) -> bool {
+        self.skip_machine_code_calls
+    }
+
+    /// Start or stop ignoring keys which were already held when `FX0A`
+    /// (`WaitForKey`) started waiting, until they've been released. Otherwise
+    /// letting go of a key held from before completes the wait with the
+    /// `wait_key_on_release` quirk, and keyboards which repeat key presses
+    /// can complete it without it.
+    pub fn set_ignore_keys_held_before_wait(&mut self, enabled: bool) {
+        self.ignore_keys_held_before_wait = enabled;
+    }
+
+    pub fn ignores_keys_held_before_wait(&self) -> bool {
+        self.ignore_keys_held_before_wait
+    }
+
+    /// Call `hook` with every instruction `cycle()` runs, or stop calling it
+    /// if `hook` is `None`, for logging execution. Snapshots don't include it
+    /// and it doesn't affect equality.
+    pub fn set_trace_hook(&mut self, hook: Option<TraceHook>) {
+        self.trace_hook = TraceHookSlot(hook);
+    }
+
+    /// The address and opcode of each of the last `TRACE_CAPACITY`
+    /// instructions `cycle()` has run since the program was loaded, oldest
+    /// first. Like the trace hook, snapshots don't include them and they don't
+    /// affect equality.
+    #[cfg(feature = "trace")]
+    pub fn recent_trace(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.trace.iter()
+    }
+
+    /// Start or stop warning through `warnings()` whenever an instruction is
+    /// fetched from an odd address, independently of strict mode. See
+    /// `Warning::MisalignedPc`.
+    pub fn set_warn_on_misaligned_pc(&mut self, enabled: bool) {
+        self.warn_on_misaligned_pc = enabled;
+    }
+
+    /// Start or stop warning through `warnings()` whenever `7XNN` wraps past
+    /// 255, independently of strict mode, for learning about register width.
+    /// The result still wraps, as on real hardware. See
+    /// `Warning::AddOverflow`.
+    pub fn set_warn_on_add_overflow(&mut self, enabled: bool) {
+        self.warn_on_add_overflow = enabled;
+    }
+
+    /// The warnings raised in strict mode, by `set_warn_on_misaligned_pc()`,
+    /// `set_warn_on_add_overflow()` or `set_tolerate_unknown_instructions()`
+    /// since the program was loaded or `clear_warnings()` was called, oldest
+    /// first. Only the first `MAX_WARNINGS` are kept.
+    pub fn warnings(&self) -> impl Iterator<Item = Warning> + '_ {
+        self.warnings.iter().flatten().copied()
+    }
+
+    pub fn clear_warnings(&mut self) {
+        self.warnings = [None; MAX_WARNINGS];
+    }
+
+    /// Copy the complete state of the CHIP-8, so it can be restored later with
+    /// `restore_snapshot()`
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.memory.get_bytes(0, self.memory.size()).to_vec(),
+            high_res: self.screen.is_high_res(),
+            screen_planes: core::array::from_fn(|plane| self.screen.plane_to_packed_vec(plane)),
+            selected_planes: self.screen.selected_planes(),
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            stack: self.stack,
+            stack_ptr: self.stack_ptr,
+            pc: self.pc,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            rng_state: self.rng.state(),
+            quirks: self.quirks,
+            frame_count: self.frame_count,
+            waiting_for_key: self.waiting_for_key,
+            waiting_initial_keys: self
+                .waiting_initial_state
+                .iter()
+                .enumerate()
+                .fold(0, |keys, (key, &held)| keys | (held as u16) << key),
+            halted: self.halted,
+            drew_since_vblank: self.drew_since_vblank,
+        }
+    }
+
+    /// Encode the complete state of the CHIP-8 in a compact binary format,
+    /// see `Snapshot::to_bytes()`
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_snapshot().to_bytes()
+    }
+
+    /// Create a CHIP-8 from the state encoded by `to_bytes()`
+    #[cfg(feature = "alloc")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Chip8, SnapshotError> {
+        let snapshot = Snapshot::from_bytes(bytes)?;
+        let mut chip8 = Self::new_with_quirks(&[], snapshot.quirks);
+        chip8.restore_snapshot(&snapshot)?;
+        Ok(chip8)
+    }
+
+    /// Replace the state of the CHIP-8, including the quirks, with one from
+    /// `to_snapshot()`. Nothing is changed if the snapshot is invalid.
+    #[cfg(feature = "alloc")]
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        let expected_len = memory_size(&snapshot.quirks);
+        if snapshot.memory.len() != expected_len {
+            return Err(SnapshotError::InvalidMemorySize {
+                len: snapshot.memory.len(),
+                expected_len,
+            });
+        }
+        if snapshot.stack_ptr as usize > STACK_SIZE {
+            return Err(SnapshotError::InvalidStackPointer {
+                stack_ptr: snapshot.stack_ptr,
+            });
+        }
+        let mut screen = Screen::new(snapshot.high_res);
+        let plane_len = screen.packed_size_bytes();
+        for (plane, bytes) in snapshot.screen_planes.iter().enumerate() {
+            if bytes.len() != plane_len {
+                return Err(SnapshotError::InvalidScreenSize {
+                    len: bytes.len(),
+                    expected_len: plane_len,
+                });
+            }
+            screen.set_plane_from_packed_slice(plane, bytes);
+        }
+        screen.select_planes(snapshot.selected_planes);
+
+        self.memory = Chip8Memory::new_with_size(&[], expected_len);
+        self.memory.set_bytes(0, &snapshot.memory);
+        self.screen = screen;
+        self.v_reg = snapshot.v_reg;
+        self.i_reg = snapshot.i_reg;
+        self.stack = snapshot.stack;
+        self.stack_ptr = snapshot.stack_ptr;
+        self.pc = snapshot.pc;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        self.rng = Rng::from_state(snapshot.rng_state);
+        self.quirks = snapshot.quirks;
+        self.frame_count = snapshot.frame_count;
+        self.waiting_for_key = snapshot.waiting_for_key;
+        self.waiting_initial_state =
+            core::array::from_fn(|key| snapshot.waiting_initial_keys & 1 << key != 0);
+        self.halted = snapshot.halted;
+        self.drew_since_vblank = snapshot.drew_since_vblank;
+        Ok(())
+    }
 }
 
 impl Chip8 {
+    /// Read the instruction at the program counter. Instructions are usually
+    /// at even addresses, but the program counter can be odd after a jump or
+    /// call to an odd address, in which case the instruction is still the
+    /// big-endian word at `pc` and `pc + 1`.
     #[must_use]
     fn get_instruction(&self) -> u16 {
-        let pc = self.pc as usize;
-        ((self.memory.get(pc) as u16) << 8) | (self.memory.get(pc + 1) as u16)
+        self.get_word(self.pc as usize)
     }
 
-    fn execute_instruction(
-        &mut self,
-        instruction: Inst,
-        keyboard_state: &[bool; 16],
-        previous_keyboard_state: &[bool; 16],
-    ) -> Result<(), ExecuteError> {
-        let mut increment_pc = true;
-        let mut skip_next_instruction = false;
-        match instruction {
-            Inst::Exe { nnn } => return Err(ExecuteError::UnknownMachineSubroutine { nnn }),
-            Inst::Clear => self.screen.clear(),
-            Inst::Return => {
-                if self.stack_ptr == 0 {
-                    return Err(ExecuteError::EmptyStackReturn);
-                }
+    /// Read the big-endian word at `index`, where bytes past the end of memory
+    /// read as 0 so that peeking at a runaway program counter can't panic
+    #[must_use]
+    fn get_word(&self, index: usize) -> u16 {
+        let byte = |index| match index < self.memory.size() {
+            true => self.memory.get(index),
+            false => 0,
+        };
+        ((byte(index) as u16) << 8) | (byte(index + 1) as u16)
+    }
 
-                self.pc = self.stack[self.stack_ptr as usize - 1];
-                self.stack_ptr -= 1;
-            }
-            Inst::Jump { nnn } => {
-                self.pc = nnn;
-                increment_pc = false;
-            }
-            Inst::Call { nnn } => {
-                increment_pc = false;
-                // TODO stack overflow
-                self.stack[self.stack_ptr as usize] = self.pc;
-                self.stack_ptr += 1;
-                self.pc = nnn;
-            }
+    /// With the `protect_reserved_memory` quirk, return an error if writing
+    /// upwards from `start` would write below the program. Nothing should be
+    /// written if this fails.
+    fn check_writable(&self, start: u16) -> Result<(), ExecuteError> {
+        if self.quirks.protect_reserved_memory && (start as usize) < PROGRAM_OFFSET_BYTES {
+            return Err(ExecuteError::ProtectedMemoryWrite { addr: start });
+        }
+        Ok(())
+    }
+
+    /// The register `8XY6` and `8XYE` shift, which depends on the
+    /// `shift_uses_vy` quirk
+    fn shift_source(&self, vx: u8, vy: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            vy
+        } else {
+            vx
+        }
+    }
+
+    /// With the `logic_resets_vf` quirk, clear `VF` after `8XY1`, `8XY2` or
+    /// `8XY3`
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.v_reg[0xf] = 0;
+        }
+    }
+
+    /// Return an error if the `len` bytes from `I` aren't all in memory.
+    /// Nothing should be accessed if this fails.
+    fn check_i_range(&self, len: u8) -> Result<(), ExecuteError> {
+        if self.i_reg as usize + len as usize > self.memory.size() {
+            return Err(ExecuteError::RegisterMemoryOverflow {
+                index: self.i_reg,
+                len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Record `warning` if there's room for it
+    fn warn(&mut self, warning: Warning) {
+        if let Some(slot) = self.warnings.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(warning);
+        }
+    }
+
+    /// In strict mode, warn if the program counter was just set to an odd
+    /// address by a jump from `from`
+    fn check_jump_target(&mut self, from: u16) {
+        if self.strict && self.pc & 1 != 0 {
+            self.warn(Warning::OddJump { from, to: self.pc });
+        }
+    }
+
+    /// Write to memory, counting writes into the loaded program if enabled
+    fn store_byte(&mut self, index: usize, value: u8) {
+        if self.strict
+            && (memory::SPRITES_OFFSET_BYTES..memory::SPRITES_OFFSET_BYTES + FONT_SIZE_BYTES)
+                .contains(&index)
+        {
+            self.warn(Warning::FontWrite { addr: index as u16 });
+        }
+        if self.detect_program_writes
+            && (PROGRAM_OFFSET_BYTES..PROGRAM_OFFSET_BYTES + self.program_len).contains(&index)
+        {
+            self.program_write_count += 1;
+        }
+        self.memory.set(index, value);
+    }
+
+    fn execute_instruction(
+        &mut self,
+        instruction: Inst,
+        keyboard_state: &[bool; 16],
+        previous_keyboard_state: &[bool; 16],
+    ) -> Result<CycleOutcome, ExecuteError> {
+        let mut outcome = CycleOutcome::Normal;
+        let mut increment_pc = true;
+        let mut skip_next_instruction = false;
+        match instruction {
+            Inst::Exe { .. } if self.skip_machine_code_calls => {}
+            Inst::Exe { nnn } => return Err(ExecuteError::UnknownMachineSubroutine { nnn }),
+            Inst::Clear => {
+                self.screen.clear();
+                self.update_display_mirror();
+            }
+            Inst::Exit => {
+                // the program counter stays on `00FD`
+                increment_pc = false;
+                self.halted = true;
+                outcome = CycleOutcome::Halted;
+            }
+            Inst::Return => {
+                if self.stack_ptr == 0 {
+                    return Err(ExecuteError::EmptyStackReturn);
+                }
+
+                self.pc = self.stack[self.stack_ptr as usize - 1];
+                self.stack_ptr -= 1;
+            }
+            Inst::Jump { nnn } => {
+                let from = self.pc;
+                self.pc = nnn;
+                increment_pc = false;
+                self.check_jump_target(from);
+            }
+            Inst::Call { nnn } => {
+                if self.stack_ptr as usize >= STACK_SIZE {
+                    return Err(ExecuteError::StackOverflow { pc: self.pc });
+                }
+
+                increment_pc = false;
+                self.stack[self.stack_ptr as usize] = self.pc;
+                self.stack_ptr += 1;
+                let from = self.pc;
+                self.pc = nnn;
+                if self.strict && STACK_SIZE - self.stack_ptr as usize <= STACK_WARNING_FREE_SLOTS {
+                    self.warn(Warning::StackNearlyFull {
+                        depth: self.stack_ptr,
+                    });
+                }
+                self.check_jump_target(from);
+            }
             Inst::SkipEqualValue { vx, nn } => {
                 skip_next_instruction = self.v_reg[vx as usize] == nn
             }
@@ -133,12 +1178,29 @@ impl Chip8 {
             }
             Inst::LoadValue { vx, nn } => self.v_reg[vx as usize] = nn,
             Inst::AddValue { vx, nn } => {
-                self.v_reg[vx as usize] = self.v_reg[vx as usize].wrapping_add(nn)
+                let (sum, overflowed) = self.v_reg[vx as usize].overflowing_add(nn);
+                if overflowed && self.warn_on_add_overflow {
+                    self.warn(Warning::AddOverflow {
+                        pc: self.pc,
+                        vx,
+                        nn,
+                    });
+                }
+                self.v_reg[vx as usize] = sum
             }
             Inst::LoadRegister { vx, vy } => self.v_reg[vx as usize] = self.v_reg[vy as usize],
-            Inst::Or { vx, vy } => self.v_reg[vx as usize] |= self.v_reg[vy as usize],
-            Inst::And { vx, vy } => self.v_reg[vx as usize] &= self.v_reg[vy as usize],
-            Inst::Xor { vx, vy } => self.v_reg[vx as usize] ^= self.v_reg[vy as usize],
+            Inst::Or { vx, vy } => {
+                self.v_reg[vx as usize] |= self.v_reg[vy as usize];
+                self.reset_vf_after_logic();
+            }
+            Inst::And { vx, vy } => {
+                self.v_reg[vx as usize] &= self.v_reg[vy as usize];
+                self.reset_vf_after_logic();
+            }
+            Inst::Xor { vx, vy } => {
+                self.v_reg[vx as usize] ^= self.v_reg[vy as usize];
+                self.reset_vf_after_logic();
+            }
             Inst::AddRegister { vx, vy } => {
                 let vx = vx as usize;
                 let vy = vy as usize;
@@ -164,8 +1226,9 @@ impl Chip8 {
                 };
             }
             Inst::ShiftRight { vx, vy } => {
-                let flag = self.v_reg[vy as usize] & 0b00000001;
-                self.v_reg[vx as usize] = self.v_reg[vy as usize] >> 1;
+                let value = self.v_reg[self.shift_source(vx, vy) as usize];
+                let flag = value & 0b00000001;
+                self.v_reg[vx as usize] = value >> 1;
                 self.v_reg[0xf] = flag;
             }
             Inst::SubRegisterYX { vx, vy } => {
@@ -181,39 +1244,68 @@ impl Chip8 {
                 };
             }
             Inst::ShiftLeft { vx, vy } => {
-                let flag = (self.v_reg[vy as usize] & 0b10000000) >> 7;
-                self.v_reg[vx as usize] = self.v_reg[vy as usize] << 1;
+                let value = self.v_reg[self.shift_source(vx, vy) as usize];
+                let flag = (value & 0b10000000) >> 7;
+                self.v_reg[vx as usize] = value << 1;
                 self.v_reg[0xf] = flag;
             }
             Inst::SkipNotEqualRegister { vx, vy } => {
                 skip_next_instruction = self.v_reg[vx as usize] != self.v_reg[vy as usize]
             }
             Inst::LoadIntoI { nnn } => self.i_reg = nnn,
+            Inst::LoadLongIntoI { nnnn } => {
+                self.i_reg = nnnn;
+                // skip over the immediate word
+                self.advance_pc(2)?;
+            }
             Inst::JumpAdd { nnn } => {
                 // TODO: bounds check
-                self.pc = nnn + (self.v_reg[0] as u16);
+                let from = self.pc;
+                // `BXNN` with the `jump_with_vx` quirk
+                let offset_reg = if self.quirks.jump_with_vx {
+                    (nnn >> 8) as usize
+                } else {
+                    0
+                };
+                self.pc = nnn + (self.v_reg[offset_reg] as u16);
                 increment_pc = false;
+                self.check_jump_target(from);
             }
             Inst::LoadRandom { vx, nn } => {
-                // return Err(ExecuteError::UnimplementedInstruction { inst: instruction })
-                // TODO implement this
-                self.v_reg[vx as usize] = 123 & nn;
+                let random = match self.rng_override {
+                    Some(value) => value,
+                    None => self.rng.next_u8(),
+                };
+                self.v_reg[vx as usize] = random & nn
             }
             Inst::DrawSprite { vx, vy, n } => {
+                if self.quirks.display_wait && self.drew_since_vblank {
+                    // try again once the timers have ticked
+                    return Ok(CycleOutcome::WaitingForVblank);
+                }
+                // each selected plane has its own sprite data
+                let len = n * self.screen.selected_plane_count() as u8;
                 // TODO: find out what the correct behavior is here
-                if self.i_reg as usize + n as usize > CHIP8_MEMORY_SIZE_BYTES {
+                if self.i_reg as usize + len as usize > self.memory.size() {
                     return Err(ExecuteError::SpriteMemoryOverflow {
                         index: self.i_reg,
-                        len: n,
+                        len,
                     });
                 }
 
-                let sprite = self.memory.get_bytes(self.i_reg as usize, n as usize);
-                self.v_reg[0xf] = self.screen.draw_sprite(
+                let sprite = self.memory.get_bytes(self.i_reg as usize, len as usize);
+                let result = self.screen.draw_sprite_detailed(
                     self.v_reg[vx as usize],
                     self.v_reg[vy as usize],
                     sprite,
-                ) as u8
+                );
+                self.v_reg[0xf] = result.vf(self.screen.is_high_res());
+                if result.collision {
+                    self.collision_count += 1;
+                }
+                self.update_display_mirror();
+                self.drew_since_vblank = true;
+                outcome = CycleOutcome::DrewSprite;
             }
             Inst::SkipIfKey { vx } => {
                 skip_next_instruction = keyboard_state[self.v_reg[vx as usize] as usize]
@@ -221,64 +1313,243 @@ impl Chip8 {
             Inst::SkipIfNotKey { vx } => {
                 skip_next_instruction = !keyboard_state[self.v_reg[vx as usize] as usize]
             }
+            Inst::SetPlane { n } => self.screen.select_planes(n),
             Inst::LoadDelay { vx } => self.v_reg[vx as usize] = self.delay_timer,
             Inst::WaitForKey { vx } => {
                 increment_pc = false;
+                outcome = CycleOutcome::WaitingForKey;
+                if !self.waiting_for_key {
+                    self.waiting_initial_state = *previous_keyboard_state;
+                }
+                let completed = if self.quirks.wait_key_on_release {
+                    just_released(keyboard_state, previous_keyboard_state)
+                } else {
+                    just_pressed(keyboard_state, previous_keyboard_state)
+                };
                 for i in 0..16 {
-                    if previous_keyboard_state[i] && !keyboard_state[i] {
+                    if self.ignore_keys_held_before_wait && self.waiting_initial_state[i] {
+                        // keys held from before have to be released first
+                        self.waiting_initial_state[i] = keyboard_state[i];
+                        continue;
+                    }
+                    if completed[i] {
                         self.v_reg[vx as usize] = i as u8;
                         increment_pc = true;
+                        outcome = CycleOutcome::Normal;
                         break;
                     }
                 }
             }
             Inst::SetDelay { vx } => self.delay_timer = self.v_reg[vx as usize],
             Inst::SetSound { vx } => self.sound_timer = self.v_reg[vx as usize],
+            Inst::LoadAudioPattern => {
+                let index = self.i_reg as usize;
+                if index + AUDIO_PATTERN_SIZE_BYTES > self.memory.size() {
+                    return Err(ExecuteError::AudioPatternMemoryOverflow { index: self.i_reg });
+                }
+                self.audio_pattern
+                    .copy_from_slice(self.memory.get_bytes(index, AUDIO_PATTERN_SIZE_BYTES));
+            }
+            Inst::SetPitch { vx } => self.pitch = self.v_reg[vx as usize],
             Inst::AddToI { vx } => {
-                // TODO: bounds check/wrapping?
-                self.i_reg += self.v_reg[vx as usize] as u16
+                let old = self.i_reg;
+                self.i_reg = self.i_reg.wrapping_add(self.v_reg[vx as usize] as u16);
+                if self.quirks.add_to_i_sets_vf {
+                    // only crossing out of the 4KB range counts
+                    self.v_reg[0xf] = (old <= 0x0fff && self.i_reg > 0x0fff) as u8;
+                }
             }
             Inst::LoadDigitSpriteAddrIntoI { vx } => {
-                self.i_reg = memory::SPRITES_OFFSET_BYTES as u16 + vx as u16;
+                let digit = (self.v_reg[vx as usize] & 0xf) as usize;
+                self.i_reg =
+                    (memory::SPRITES_OFFSET_BYTES + digit * DIGIT_SPRITE_SIZE_BYTES) as u16;
             }
             Inst::StoreBCD { vx } => {
                 let value = self.v_reg[vx as usize];
                 let ones = value % 10;
                 let tens = (value / 10) % 10;
                 let hundreds = (value / 100) % 10;
-                self.memory.set(self.i_reg as usize, hundreds);
-                self.memory.set(self.i_reg as usize + 1, tens);
-                self.memory.set(self.i_reg as usize + 2, ones);
+                self.check_i_range(3)?;
+                self.check_writable(self.i_reg)?;
+                self.store_byte(self.i_reg as usize, hundreds);
+                self.store_byte(self.i_reg as usize + 1, tens);
+                self.store_byte(self.i_reg as usize + 2, ones);
             }
             Inst::StoreRegisters { vx } => {
+                self.check_i_range(vx + 1)?;
+                self.check_writable(self.i_reg)?;
                 for i in 0..=vx {
-                    self.memory
-                        .set(self.i_reg as usize + i as usize, self.v_reg[i as usize]);
+                    self.store_byte(self.i_reg as usize + i as usize, self.v_reg[i as usize]);
+                }
+                if self.quirks.load_store_increments_i {
+                    self.i_reg = self.i_reg.wrapping_add(vx as u16 + 1);
                 }
-                self.i_reg += vx as u16 + 1;
             }
             Inst::LoadRegisters { vx } => {
+                self.check_i_range(vx + 1)?;
                 for i in 0..=vx {
                     self.v_reg[i as usize] = self.memory.get(self.i_reg as usize + i as usize);
                 }
-                self.i_reg += vx as u16 + 1;
+                if self.quirks.load_store_increments_i {
+                    self.i_reg = self.i_reg.wrapping_add(vx as u16 + 1);
+                }
             }
         };
         if increment_pc {
-            self.pc += 2;
+            self.advance_pc(2)?;
         }
         if skip_next_instruction {
-            self.pc += 2;
+            // instructions with an immediate are skipped entirely
+            if self.quirks.extended_memory && has_immediate(self.get_instruction()) {
+                self.advance_pc(2)?;
+            }
+            self.advance_pc(2)?;
         }
+        Ok(outcome)
+    }
+
+    /// Move the program counter forward by `bytes`, failing instead of
+    /// overflowing past the end of the 64KB XO-CHIP memory
+    fn advance_pc(&mut self, bytes: u16) -> Result<(), ExecuteError> {
+        self.pc = self
+            .pc
+            .checked_add(bytes)
+            .ok_or(ExecuteError::PcOutOfBounds { pc: self.pc })?;
         Ok(())
     }
 }
 
+impl Chip8 {
+    /// Copy the screen into memory if `set_mirror_display()` is enabled
+    fn update_display_mirror(&mut self) {
+        if let (true, Screen::LowRes(screen)) = (self.mirror_display, &self.screen) {
+            self.memory
+                .set_bytes(DISPLAY_OFFSET_BYTES, &screen.to_packed_bytes());
+        }
+    }
+
+    /// Overwrite the zeroed registers and unused memory of a freshly loaded
+    /// program as `init` describes
+    fn apply_init_state(&mut self) {
+        let mut next_byte = match self.init {
+            InitState::Zeroed => return,
+            InitState::Fill(value) => InitBytes::Fill(value),
+            InitState::Random { seed } => InitBytes::Random(Rng::new(seed)),
+        };
+        for v in &mut self.v_reg {
+            *v = next_byte.next();
+        }
+        let i_reg = u16::from_be_bytes([next_byte.next(), next_byte.next()]);
+        // memory sizes are powers of two, so this keeps I addressable
+        self.i_reg = i_reg & (self.memory.size() - 1) as u16;
+        let program_end = PROGRAM_OFFSET_BYTES + self.program_len;
+        let unused = (FONT_SIZE_BYTES..PROGRAM_OFFSET_BYTES).chain(program_end..self.memory.size());
+        for address in unused {
+            self.memory.set(address, next_byte.next());
+        }
+    }
+}
+
+/// The bytes `InitState` fills with
+enum InitBytes {
+    Fill(u8),
+    Random(Rng),
+}
+
+impl InitBytes {
+    fn next(&mut self) -> u8 {
+        match self {
+            InitBytes::Fill(value) => *value,
+            InitBytes::Random(rng) => rng.next_u8(),
+        }
+    }
+}
+
+/// The size of memory with the given quirks
+fn memory_size(quirks: &Quirks) -> usize {
+    if quirks.extended_memory {
+        XO_CHIP_MEMORY_SIZE_BYTES
+    } else {
+        CHIP8_MEMORY_SIZE_BYTES
+    }
+}
+
+/// Something notable which happened during a successful `cycle()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleOutcome {
+    /// Nothing worth reporting
+    Normal,
+    /// A sprite was drawn, so the screen may have changed
+    DrewSprite,
+    /// `FX0A` (`WaitForKey`) is waiting for a key, so the program counter
+    /// didn't advance
+    WaitingForKey,
+    /// `DXYN` (`DrawSprite`) is waiting for the timers to tick with the
+    /// `display_wait` quirk, so the program counter didn't advance
+    WaitingForVblank,
+    /// The program counter reached a breakpoint. The instruction there hasn't
+    /// been executed yet.
+    BreakpointHit,
+    /// The program has exited with `00FD` (`Exit`), so nothing was run and
+    /// nothing will be until the program is loaded again
+    Halted,
+}
+
+/// Something questionable but allowed which a program did, reported in strict
+/// mode, see `Chip8::set_strict()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// A subroutine call left the stack `depth` deep, close to overflowing
+    StackNearlyFull { depth: u8 },
+    /// `FX33` or `FX55` wrote into the hex digit sprites at `addr`
+    FontWrite { addr: u16 },
+    /// A jump or call at `from` went to the odd address `to`, which is
+    /// between instructions
+    OddJump { from: u16, to: u16 },
+    /// An instruction was fetched from the odd address `pc`. Some programs do
+    /// this deliberately, but it's usually a bug.
+    MisalignedPc { pc: u16 },
+    /// The unknown instruction `inst` at `pc` was skipped, see
+    /// `Chip8::set_tolerate_unknown_instructions()`
+    UnknownInstruction { pc: u16, inst: u16 },
+    /// `7XNN` at `pc` wrapped `VX` past 255 when adding `nn`, see
+    /// `Chip8::set_warn_on_add_overflow()`
+    AddOverflow { pc: u16, vx: u8, nn: u8 },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::StackNearlyFull { depth } => {
+                write!(f, "the stack is {depth} of {STACK_SIZE} deep")
+            }
+            Warning::FontWrite { addr } => {
+                write!(f, "wrote into the font at 0x{addr:03x}")
+            }
+            Warning::OddJump { from, to } => {
+                write!(f, "jumped from 0x{from:03x} to the odd address 0x{to:03x}")
+            }
+            Warning::MisalignedPc { pc } => {
+                write!(f, "fetched an instruction from the odd address 0x{pc:03x}")
+            }
+            Warning::UnknownInstruction { pc, inst } => {
+                write!(
+                    f,
+                    "skipped the unknown instruction 0x{inst:04x} at 0x{pc:03x}"
+                )
+            }
+            Warning::AddOverflow { pc, vx, nn } => {
+                write!(f, "adding {nn} to V{vx:X} at 0x{pc:03x} wrapped past 255")
+            }
+        }
+    }
+}
+
 /// Error type for `execute_instruction()`.
 ///
-/// Note that this doesn't implement the `Error` trait, for reasons specified
-/// in the readme section titled "Why not implement the `Error` trait on error
-/// types?".
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
 #[derive(Debug)]
 pub enum ExecuteError {
     /// This instruction is valid but not implemented
@@ -288,9 +1559,24 @@ pub enum ExecuteError {
     UnknownMachineSubroutine { nnn: u16 },
     /// Attempted to `Return` when the stack was empty
     EmptyStackReturn,
+    /// Attempted to `Call` at `pc` when the stack was already full
+    StackOverflow { pc: u16 },
 
     /// A `DrawSprite` instruction attempted to read bytes beyond the end of memory
     SpriteMemoryOverflow { index: u16, len: u8 },
+    /// `FX33` or `FX55` attempted to write below the program with the
+    /// `protect_reserved_memory` quirk
+    ProtectedMemoryWrite { addr: u16 },
+    /// `F002` attempted to read the audio pattern from beyond the end of
+    /// memory
+    AudioPatternMemoryOverflow { index: u16 },
+    /// `FX33`, `FX55` or `FX65` attempted to access `len` bytes from `index`,
+    /// past the end of memory
+    RegisterMemoryOverflow { index: u16, len: u8 },
+    /// The program counter reached the end of memory, where there's no room
+    /// for a whole instruction, or the instruction at `pc` would have moved it
+    /// past the end of the 64KB XO-CHIP memory
+    PcOutOfBounds { pc: u16 },
 }
 
 impl fmt::Display for ExecuteError {
@@ -308,21 +1594,51 @@ impl fmt::Display for ExecuteError {
                     "attempted to return from a subroutine when the stack is empty"
                 )
             }
+            ExecuteError::StackOverflow { pc } => {
+                write!(
+                    f,
+                    "attempted to call a subroutine at 0x{pc:03x} when the stack is full"
+                )
+            }
             ExecuteError::SpriteMemoryOverflow { index, len } => {
                 write!(
                     f,
                     "a draw sprite instruction attempted to read data beyond the end of memory at index {index} with length {len}"
                 )
             }
+            ExecuteError::ProtectedMemoryWrite { addr } => {
+                write!(
+                    f,
+                    "attempted to write to reserved memory below the program at 0x{addr:03x}"
+                )
+            }
+            ExecuteError::AudioPatternMemoryOverflow { index } => {
+                write!(
+                    f,
+                    "attempted to load the audio pattern from beyond the end of memory at index {index}"
+                )
+            }
+            ExecuteError::RegisterMemoryOverflow { index, len } => {
+                write!(
+                    f,
+                    "attempted to access data beyond the end of memory at index {index} with length {len}"
+                )
+            }
+            ExecuteError::PcOutOfBounds { pc } => {
+                write!(
+                    f,
+                    "the program counter ran off the end of memory at 0x{pc:03x}"
+                )
+            }
         }
     }
 }
 
 /// Error type for `cycle()`.
 ///
-/// Note that this doesn't implement the `Error` trait, for reasons specified
-/// in the readme section titled "Why not implement the `Error` trait on error
-/// types?".
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
 #[derive(Debug)]
 pub enum CycleError {
     DecodeError(instruction::DecodeError),
@@ -337,3 +1653,1857 @@ impl fmt::Display for CycleError {
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExecuteError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CycleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CycleError::DecodeError(inner) => Some(inner),
+            CycleError::ExecuteError(inner) => Some(inner),
+        }
+    }
+}
+
+/// Error type for `step_over()`.
+///
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
+#[derive(Debug)]
+pub enum StepOverError {
+    CycleError(CycleError),
+    /// The subroutine didn't return within `max_cycles` cycles
+    CycleLimitReached {
+        max_cycles: usize,
+    },
+}
+
+impl fmt::Display for StepOverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepOverError::CycleError(inner) => inner.fmt(f),
+            StepOverError::CycleLimitReached { max_cycles } => {
+                write!(f, "the subroutine didn't return within {max_cycles} cycles")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StepOverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StepOverError::CycleError(inner) => Some(inner),
+            StepOverError::CycleLimitReached { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Chip8, CycleError, CycleOutcome, ExecuteError, InitState, StepOverError, Warning,
+        DEFAULT_PITCH, STACK_SIZE,
+    };
+    use crate::{
+        instruction::{DecodeError, Inst, INST_KIND_COUNT},
+        memory::{
+            LoadError, RegionKind, CHIP8_MEMORY_SIZE_BYTES, DISPLAY_OFFSET_BYTES, FONT_SIZE_BYTES,
+            PROGRAM_OFFSET_BYTES,
+        },
+        quirks::Quirks,
+        screen::{Screen, PACKED_SCREEN_SIZE_BYTES},
+        timing::CycleCosts,
+    };
+
+    const NO_KEYS: [bool; 16] = [false; 16];
+
+    fn key_state(key: usize) -> [bool; 16] {
+        let mut state = NO_KEYS;
+        state[key] = true;
+        state
+    }
+
+    #[test]
+    fn test_wait_for_key_completes_on_release() {
+        // F30A: wait for a key and store it in V3
+        let mut chip8 = Chip8::new(&[0xf3, 0x0a]);
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        // pressing the key isn't enough
+        chip8.cycle(&key_state(0x5), &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        // holding the key isn't enough either
+        chip8.cycle(&key_state(0x5), &key_state(0x5)).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        chip8.cycle(&NO_KEYS, &key_state(0x5)).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v_reg[3], 0x5);
+    }
+
+    #[test]
+    fn test_wait_for_key_ignores_key_held_before() {
+        // F30A: wait for a key and store it in V3
+        let mut chip8 = Chip8::new(&[0xf3, 0x0a]);
+
+        // the key is already held when the wait starts
+        chip8.cycle(&key_state(0x5), &key_state(0x5)).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        // so releasing it doesn't complete the wait
+        chip8.cycle(&NO_KEYS, &key_state(0x5)).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+        assert!(chip8.is_waiting_for_key());
+
+        // but pressing and releasing it again does
+        chip8.cycle(&key_state(0x5), &NO_KEYS).unwrap();
+        chip8.cycle(&NO_KEYS, &key_state(0x5)).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v_reg[3], 0x5);
+
+        // without ignoring held keys, releasing it completes the wait
+        let mut chip8 = Chip8::new(&[0xf3, 0x0a]);
+        chip8.set_ignore_keys_held_before_wait(false);
+        chip8.cycle(&key_state(0x5), &key_state(0x5)).unwrap();
+        chip8.cycle(&NO_KEYS, &key_state(0x5)).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn test_wait_for_key_ignores_repeated_press() {
+        let quirks = Quirks {
+            wait_key_on_release: false,
+            ..Default::default()
+        };
+        // F30A: wait for a key and store it in V3
+        let mut chip8 = Chip8::new_with_quirks(&[0xf3, 0x0a], quirks);
+
+        chip8.cycle(&key_state(0x5), &key_state(0x5)).unwrap();
+        // a keyboard repeating the held key looks like another press
+        chip8.cycle(&key_state(0x5), &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        // other keys still work
+        chip8.cycle(&key_state(0x6), &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v_reg[3], 0x6);
+    }
+
+    #[test]
+    fn test_wait_for_key_completes_on_press() {
+        let quirks = Quirks {
+            wait_key_on_release: false,
+            ..Default::default()
+        };
+        let mut chip8 = Chip8::new_with_quirks(&[0xf3, 0x0a], quirks);
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        // releasing a key that was held before waiting doesn't count
+        chip8.cycle(&NO_KEYS, &key_state(0xa)).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        chip8.cycle(&key_state(0xb), &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v_reg[3], 0xb);
+    }
+
+    #[test]
+    fn test_set_plane_and_draw_two_planes() {
+        // F301: select both planes
+        // A206: I = 0x206
+        // D011: draw a 1 row sprite on each plane at (V0, V1)
+        // sprite data: first plane X., second plane .X
+        let mut chip8 = Chip8::new(&[0xf3, 0x01, 0xa2, 0x06, 0xd0, 0x11, 0x80, 0x40]);
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        let screen = chip8.get_screen();
+        assert_eq!(screen.selected_planes(), 0b11);
+        assert_eq!(screen.get_pixel_planes(0, 0), 0b01);
+        assert_eq!(screen.get_pixel_planes(1, 0), 0b10);
+    }
+
+    #[test]
+    fn test_peek_instruction() {
+        // the start of the IBM logo ROM: 00E0 A22A 600C
+        let mut chip8 = Chip8::new(&[0x00, 0xe0, 0xa2, 0x2a, 0x60, 0x0c]);
+
+        assert_eq!(chip8.peek_opcode(), 0x00e0);
+        assert_eq!(chip8.peek_instruction().unwrap(), Inst::Clear);
+        assert_eq!(chip8.pc, 0x200);
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.peek_opcode(), 0xa22a);
+        assert_eq!(
+            chip8.peek_instruction().unwrap(),
+            Inst::LoadIntoI { nnn: 0x22a }
+        );
+
+        // F000 NNNN includes its address word
+        let chip8 = Chip8::new_with_quirks(&[0xf0, 0x00, 0xab, 0xcd], Quirks::xochip());
+        assert_eq!(chip8.peek_opcode(), 0xf000);
+        assert_eq!(
+            chip8.peek_instruction().unwrap(),
+            Inst::LoadLongIntoI { nnnn: 0xabcd }
+        );
+    }
+
+    #[test]
+    fn test_load_long_into_i() {
+        let quirks = Quirks {
+            extended_memory: true,
+            ..Default::default()
+        };
+        // F000 ABCD: I = 0xABCD
+        let mut chip8 = Chip8::new_with_quirks(&[0xf0, 0x00, 0xab, 0xcd], quirks);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        assert_eq!(chip8.i_reg, 0xabcd);
+        assert_eq!(chip8.pc, 0x204);
+        assert_eq!(chip8.memory.size(), 65536);
+    }
+
+    #[test]
+    fn test_load_long_needs_extended_memory() {
+        // F000 ABCD: I = 0xABCD on XO-CHIP only
+        let mut chip8 = Chip8::new(&[0xf0, 0x00, 0xab, 0xcd]);
+
+        assert!(matches!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::DecodeError(DecodeError::UnknownInstruction {
+                inst: 0xf000
+            }))
+        ));
+        assert_eq!(chip8.i_reg, 0);
+    }
+
+    #[test]
+    fn test_audio_needs_extended_memory() {
+        // F002: load the audio pattern, F33A: pitch = V3, both XO-CHIP only
+        for opcode in [[0xf0, 0x02], [0xf3, 0x3a]] {
+            let mut chip8 = Chip8::new(&opcode);
+
+            assert!(matches!(
+                chip8.cycle(&NO_KEYS, &NO_KEYS),
+                Err(CycleError::DecodeError(
+                    DecodeError::UnknownInstruction { .. }
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_register_memory_overflow() {
+        // FF55: store V0 to VF from I
+        // FF65: load V0 to VF from I
+        // F333: store the BCD of V3 at I
+        for (opcode, len) in [([0xff, 0x55], 16), ([0xff, 0x65], 16), ([0xf3, 0x33], 3)] {
+            for (quirks, size) in [(Quirks::vip(), 0x1000), (Quirks::xochip(), 0x10000)] {
+                let mut chip8 = Chip8::new_with_quirks(&opcode, quirks);
+                // one byte short of room
+                chip8.i_reg = (size - len as usize + 1) as u16;
+                chip8.v_reg = [0x99; 16];
+
+                let result = chip8.cycle(&NO_KEYS, &NO_KEYS);
+
+                assert!(
+                    matches!(
+                        result,
+                        Err(CycleError::ExecuteError(ExecuteError::RegisterMemoryOverflow {
+                            index,
+                            len: error_len,
+                        })) if index == chip8.i_reg && error_len == len
+                    ),
+                    "{opcode:02x?} at {:#x}",
+                    chip8.i_reg
+                );
+                // nothing was stored or loaded
+                let end = &chip8.dump_memory()[size - 16..];
+                assert!(end.iter().all(|&byte| byte == 0));
+                assert_eq!(chip8.v_reg, [0x99; 16]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_to_i_wraps() {
+        // 6002: V0 = 2
+        // F01E: I += V0
+        let mut chip8 = Chip8::new_with_quirks(&[0x60, 0x02, 0xf0, 0x1e], Quirks::xochip());
+        chip8.i_reg = 0xffff;
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.i_reg, 0x0001);
+    }
+
+    #[test]
+    fn test_skip_over_load_long() {
+        // 3000: skip next instruction as V0 == 0
+        // F000 1234: skipped
+        // 6001: V0 = 1
+        let program = [0x30, 0x00, 0xf0, 0x00, 0x12, 0x34, 0x60, 0x01];
+        let mut chip8 = Chip8::new_with_quirks(&program, Quirks::xochip());
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc, 0x206);
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.i_reg, 0);
+        assert_eq!(chip8.v_reg[0], 1);
+    }
+
+    #[test]
+    fn test_load_program_resets_state() {
+        // 6342: V3 = 0x42
+        // F315: delay timer = V3
+        // 2300: call 0x300
+        let mut chip8 = Chip8::new(&[0x63, 0x42, 0xf3, 0x15, 0x23, 0x00]);
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        chip8.load_program(&[0x00, 0xe0]).unwrap();
+
+        assert_eq!(chip8.pc, 0x200);
+        assert_eq!(chip8.v_reg, [0; 16]);
+        assert_eq!(chip8.stack_ptr, 0);
+        assert_eq!(chip8.delay_timer, 0);
+        assert_eq!(chip8.memory.get_bytes(0x200, 4), [0x00, 0xe0, 0, 0]);
+    }
+
+    #[test]
+    fn test_init_state_fill() {
+        let program = [0x63, 0x42];
+        let mut chip8 = Chip8::new_with_init(&program, InitState::Fill(0xa5));
+
+        assert_eq!(chip8.v_reg, [0xa5; 16]);
+        assert_eq!(chip8.i_reg, 0xa5a5 & 0xfff);
+        // the font and program aren't overwritten
+        assert_eq!(chip8.memory.get_bytes(0, 5), [0xf0, 0x90, 0x90, 0x90, 0xf0]);
+        assert_eq!(chip8.memory.get(FONT_SIZE_BYTES), 0xa5);
+        assert_eq!(chip8.memory.get(0x1ff), 0xa5);
+        assert_eq!(chip8.memory.get_bytes(0x200, 3), [0x63, 0x42, 0xa5]);
+        assert_eq!(chip8.memory.get(0xfff), 0xa5);
+        assert_eq!(chip8.pc, 0x200);
+
+        // loading a program fills again
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        chip8.load_program(&program).unwrap();
+        assert_eq!(chip8.v_reg, [0xa5; 16]);
+
+        assert!(Chip8::new_with_init(&program, InitState::Zeroed) == Chip8::new(&program));
+    }
+
+    #[test]
+    fn test_init_state_random() {
+        let program = [0x63, 0x42];
+        let a = Chip8::new_with_init(&program, InitState::Random { seed: 7 });
+        let b = Chip8::new_with_init(&program, InitState::Random { seed: 7 });
+        let c = Chip8::new_with_init(&program, InitState::Random { seed: 8 });
+
+        assert!(a == b);
+        assert!(a.v_reg != c.v_reg);
+        assert!(a.v_reg.iter().any(|v| *v != a.v_reg[0]));
+        assert!(a.i_reg < 0x1000);
+        assert_eq!(a.memory.get_bytes(0x200, 2), program);
+        assert_eq!(
+            a.memory.get_bytes(0, FONT_SIZE_BYTES),
+            c.memory.get_bytes(0, FONT_SIZE_BYTES)
+        );
+        assert!(a.memory.get_bytes(0x300, 16) != c.memory.get_bytes(0x300, 16));
+    }
+
+    #[test]
+    fn test_load_program_too_long() {
+        let mut chip8 = Chip8::new(&[0x63, 0x42]);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        let result = chip8.load_program(&[0; 4096]);
+
+        assert!(matches!(result, Err(LoadError::ProgramTooLong { .. })));
+        // the running program is left alone
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v_reg[3], 0x42);
+    }
+
+    #[test]
+    fn test_timers_decrement_while_waiting_for_key() {
+        // 6010: V0 = 0x10
+        // F015: delay timer = V0
+        // F018: sound timer = V0
+        // F10A: wait for a key and store it in V1
+        let mut chip8 = Chip8::new(&[0x60, 0x10, 0xf0, 0x15, 0xf0, 0x18, 0xf1, 0x0a]);
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        for _ in 0..5 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            chip8.update_timers();
+        }
+
+        assert_eq!(chip8.pc, 0x206);
+        assert_eq!(chip8.delay_timer, 0x10 - 5);
+        assert_eq!(chip8.sound_timer, 0x10 - 5);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_restore_snapshot() {
+        // 7001: V0 += 1
+        // D001: draw the 1 byte sprite at I (0x000) at V0, V0
+        // 1200: jump back to the start
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0xd0, 0x01, 0x12, 0x00]);
+        chip8.run_frames(1, 2, &NO_KEYS).unwrap();
+        let snapshot = chip8.to_snapshot();
+
+        chip8.run_frames(2, 30, &NO_KEYS).unwrap();
+        assert_ne!(chip8.to_snapshot(), snapshot);
+        chip8.restore_snapshot(&snapshot).unwrap();
+
+        assert_eq!(chip8.to_snapshot(), snapshot);
+        assert_eq!(chip8.pc, 0x204);
+        assert_eq!(chip8.v_reg[0], 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_restore_invalid_snapshot() {
+        let mut chip8 = Chip8::new(&[0x70, 0x01]);
+        let mut snapshot = chip8.to_snapshot();
+        snapshot.memory.truncate(100);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        let result = chip8.restore_snapshot(&snapshot);
+
+        assert!(matches!(
+            result,
+            Err(crate::snapshot::SnapshotError::InvalidMemorySize { len: 100, .. })
+        ));
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn test_detect_program_writes() {
+        // A200: I = 0x200, the start of the program
+        // F155: store V0 and V1 at I
+        // A300: I = 0x300, beyond the end of the program
+        // F155: store V0 and V1 at I
+        let program = [0xa2, 0x00, 0xf1, 0x55, 0xa3, 0x00, 0xf1, 0x55];
+        let mut chip8 = Chip8::new(&program);
+        chip8.set_detect_program_writes(true);
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.program_write_count(), 2);
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.program_write_count(), 2);
+    }
+
+    #[test]
+    fn test_program_writes_not_detected_by_default() {
+        let mut chip8 = Chip8::new(&[0xa2, 0x00, 0xf1, 0x55]);
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.program_write_count(), 0);
+    }
+
+    #[test]
+    fn test_mirror_display() {
+        // A20C: I = 0x20C, the sprite
+        // D011: draw it at (V0, V1)
+        // AF00: I = 0xF00, the display memory
+        // F065: read the screen's first byte back into V0
+        // 00E0: clear the screen
+        // 1206: loop back to reading it
+        // sprite data: X.X.....
+        let program = [
+            0xa2, 0x0c, 0xd0, 0x11, 0xaf, 0x00, 0xf0, 0x65, 0x00, 0xe0, 0x12, 0x06, 0xa0,
+        ];
+        let mut chip8 = Chip8::new(&program);
+        chip8.set_mirror_display(true);
+
+        for _ in 0..4 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.v_reg[0], 0xa0);
+        let Screen::LowRes(screen) = &chip8.screen else {
+            panic!("the screen should be low-res");
+        };
+        assert_eq!(
+            chip8
+                .memory
+                .get_bytes(DISPLAY_OFFSET_BYTES, PACKED_SCREEN_SIZE_BYTES),
+            screen.to_packed_bytes()
+        );
+
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.v_reg[0], 0x00);
+    }
+
+    #[test]
+    fn test_display_not_mirrored_by_default() {
+        // A206: I = 0x206, the sprite
+        // D011: draw it at (V0, V1)
+        // 1204: loop forever
+        // sprite data: X.X.....
+        let mut chip8 = Chip8::new(&[0xa2, 0x06, 0xd0, 0x11, 0x12, 0x04, 0xa0]);
+        chip8.write_memory(DISPLAY_OFFSET_BYTES, 0x42);
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.memory.get(DISPLAY_OFFSET_BYTES), 0x42);
+
+        // enabling it copies the screen straight away
+        chip8.set_mirror_display(true);
+        assert_eq!(chip8.memory.get(DISPLAY_OFFSET_BYTES), 0xa0);
+    }
+
+    #[test]
+    fn test_set_high_res() {
+        // F029: I = the sprite for the digit in V0
+        // D005: draw it at (V0, V0)
+        // D005: draw it again, erasing it
+        // 1206: loop forever
+        let program = [0xf0, 0x29, 0xd0, 0x05, 0xd0, 0x05, 0x12, 0x06];
+        let mut chip8 = Chip8::new(&program);
+        chip8.screen.select_planes(0b10);
+        chip8.screen.set_pixel(0, 0, true);
+
+        chip8.set_high_res(true);
+        assert!(chip8.screen.is_high_res());
+        assert_eq!((chip8.screen.width(), chip8.screen.height()), (128, 64));
+        assert!(!chip8.screen.get_pixel(0, 0));
+        assert_eq!(chip8.screen.selected_planes(), 0b10);
+
+        // in high-res mode VF is the number of rows which collided
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.v_reg[0xf], 5);
+
+        chip8.load_program(&program).unwrap();
+        assert!(!chip8.screen.is_high_res());
+    }
+
+    #[test]
+    fn test_instruction_histogram() {
+        // 6005: V0 = 5
+        // 70FF: V0 += 0xFF, decrementing it
+        // 3000: skip if V0 == 0
+        // 1202: jump to the decrement
+        // 00FD: exit
+        let program = [0x60, 0x05, 0x70, 0xff, 0x30, 0x00, 0x12, 0x02, 0x00, 0xfd];
+        let mut chip8 = Chip8::new_with_quirks(&program, Quirks::schip());
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert!(chip8.instruction_histogram().is_none());
+
+        chip8.set_count_instructions(true);
+        while !chip8.is_halted() {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        let histogram = chip8.instruction_histogram().unwrap();
+        let count = |inst: Inst| histogram[inst.kind()];
+        // the first load was run before counting started
+        assert_eq!(count(Inst::LoadValue { vx: 0, nn: 0 }), 0);
+        assert_eq!(count(Inst::AddValue { vx: 0, nn: 0 }), 5);
+        assert_eq!(count(Inst::SkipEqualValue { vx: 0, nn: 0 }), 5);
+        assert_eq!(count(Inst::Jump { nnn: 0 }), 4);
+        assert_eq!(count(Inst::Exit), 1);
+        assert_eq!(histogram.iter().sum::<u64>(), 15);
+
+        chip8.reset_instruction_histogram();
+        assert_eq!(chip8.instruction_histogram(), Some(&[0; INST_KIND_COUNT]));
+        chip8.set_count_instructions(false);
+        assert!(chip8.instruction_histogram().is_none());
+    }
+
+    #[test]
+    fn test_collision_count() {
+        // F029: I = the sprite for the digit in V0, which is 0
+        // D005: draw it at 0, 0
+        // D005: draw it again, colliding
+        // D005: draw it a third time on a blank screen
+        // D005: draw it again, colliding
+        let program = [0xf0, 0x29, 0xd0, 0x05, 0xd0, 0x05, 0xd0, 0x05, 0xd0, 0x05];
+        let mut chip8 = Chip8::new(&program);
+
+        chip8.cycle_n(3, &NO_KEYS, &NO_KEYS).1.unwrap();
+        assert_eq!(chip8.collision_count(), 1);
+        chip8.cycle_n(2, &NO_KEYS, &NO_KEYS).1.unwrap();
+        assert_eq!(chip8.collision_count(), 2);
+
+        chip8.reset_collision_count();
+        assert_eq!(chip8.collision_count(), 0);
+    }
+
+    #[test]
+    fn test_strict_stack_nearly_full() {
+        // 2200: call 0x200, recursing forever
+        let mut chip8 = Chip8::new(&[0x22, 0x00]);
+        chip8.set_strict(true);
+
+        for _ in 0..STACK_SIZE - 3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.warnings().count(), 0);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        assert!(chip8.warnings().eq([Warning::StackNearlyFull {
+            depth: STACK_SIZE as u8 - 2
+        }]));
+    }
+
+    #[test]
+    fn test_strict_font_write() {
+        // A04E: I = 0x04e, the end of the font
+        // F133: store the BCD of V1 at I
+        let mut chip8 = Chip8::new(&[0xa0, 0x4e, 0xf1, 0x33]);
+        chip8.set_strict(true);
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        // the last byte is after the font
+        assert!(chip8.warnings().eq([
+            Warning::FontWrite { addr: 0x04e },
+            Warning::FontWrite { addr: 0x04f },
+        ]));
+    }
+
+    #[test]
+    fn test_strict_odd_jumps() {
+        // 1203: jump to 0x203
+        // 00: padding
+        // 2207: call 0x207
+        // 0000: padding
+        // 6001: V0 = 1
+        // B20A: jump to 0x20a + V0
+        let program = [
+            0x12, 0x03, 0x00, 0x22, 0x07, 0x00, 0x00, 0x60, 0x01, 0xb2, 0x0a,
+        ];
+        let mut chip8 = Chip8::new(&program);
+        chip8.set_strict(true);
+
+        for _ in 0..4 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert!(chip8.warnings().eq([
+            Warning::OddJump {
+                from: 0x200,
+                to: 0x203
+            },
+            Warning::OddJump {
+                from: 0x203,
+                to: 0x207
+            },
+            Warning::OddJump {
+                from: 0x209,
+                to: 0x20b
+            },
+        ]));
+        assert_eq!(chip8.pc(), 0x20b);
+    }
+
+    #[test]
+    fn test_no_warnings_by_default() {
+        // A000: I = 0, the start of the font
+        // F055: store V0 at I
+        // 1203: jump to 0x203
+        let mut chip8 = Chip8::new(&[0xa0, 0x00, 0xf0, 0x55, 0x12, 0x03]);
+
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert!(!chip8.is_strict());
+        assert_eq!(chip8.warnings().count(), 0);
+    }
+
+    #[test]
+    fn test_fetch_at_odd_pc() {
+        // 1203: jump to 0x203
+        // 00: padding
+        // 6A42: VA = 0x42
+        let mut chip8 = Chip8::new(&[0x12, 0x03, 0x00, 0x6a, 0x42]);
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc(), 0x203);
+        assert_eq!(chip8.get_instruction(), 0x6a42);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        assert_eq!(chip8.v_reg[0xa], 0x42);
+        assert_eq!(chip8.pc(), 0x205);
+        // only reported when enabled
+        assert_eq!(chip8.warnings().count(), 0);
+    }
+
+    #[test]
+    fn test_warn_on_misaligned_pc() {
+        let mut chip8 = Chip8::new(&[0x12, 0x03, 0x00, 0x6a, 0x42]);
+        chip8.set_warn_on_misaligned_pc(true);
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        // without strict mode the jump itself isn't reported
+        assert!(chip8.warnings().eq([Warning::MisalignedPc { pc: 0x203 }]));
+    }
+
+    #[test]
+    fn test_warn_on_add_overflow() {
+        // 6AF0: VA = 0xF0
+        // 7A0F: VA += 0x0F, which doesn't wrap
+        // 7AFF: VA += 0xFF, which wraps
+        let program = [0x6a, 0xf0, 0x7a, 0x0f, 0x7a, 0xff];
+        let mut quiet = Chip8::new(&program);
+        let mut chip8 = quiet.clone();
+        chip8.set_warn_on_add_overflow(true);
+
+        for _ in 0..3 {
+            quiet.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.v_reg[0xa], 0xfe);
+        assert_eq!(chip8.v_reg[0xf], 0);
+        assert!(chip8.warnings().eq([Warning::AddOverflow {
+            pc: 0x204,
+            vx: 0xa,
+            nn: 0xff
+        }]));
+        // only reported when enabled
+        assert_eq!(quiet.v_reg, chip8.v_reg);
+        assert_eq!(quiet.warnings().count(), 0);
+    }
+
+    #[test]
+    fn test_run_to_next_draw() {
+        // 6007: V0 = 7
+        // 6107: V1 = 7
+        // D015: draw 5 rows at V0, V1
+        // 00E0: clear the screen
+        // 1208: loop forever
+        let program = [0x60, 0x07, 0x61, 0x07, 0xd0, 0x15, 0x00, 0xe0, 0x12, 0x08];
+        let mut chip8 = Chip8::new(&program);
+
+        assert!(chip8.run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 100).unwrap());
+        // stopped just after the draw
+        assert_eq!(chip8.pc(), 0x206);
+        assert!(chip8.screen.get_pixel(7, 7));
+
+        assert!(chip8.run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 100).unwrap());
+        assert_eq!(chip8.pc(), 0x208);
+        assert!(!chip8.screen.get_pixel(7, 7));
+
+        assert!(!chip8.run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 100).unwrap());
+        assert_eq!(chip8.pc(), 0x208);
+    }
+
+    #[test]
+    fn test_run_to_next_draw_ticks_timers() {
+        // 00E0: clear the screen
+        // 6A05: VA = 5
+        // FA15: DT = VA
+        // FA07: VA = DT
+        // 3A00: skip if VA == 0
+        // 1206: jump to FA07
+        // 1200: jump to the start
+        let program = [
+            0x00, 0xe0, 0x6a, 0x05, 0xfa, 0x15, 0xfa, 0x07, 0x3a, 0x00, 0x12, 0x06, 0x12, 0x00,
+        ];
+        let mut chip8 = Chip8::new(&program);
+
+        assert!(chip8
+            .run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 1000)
+            .unwrap());
+        // the delay timer wait finishes and the screen is cleared again
+        assert!(chip8
+            .run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 1000)
+            .unwrap());
+        assert_eq!(chip8.pc(), 0x202);
+    }
+
+    #[test]
+    fn test_run_to_next_draw_stops_at_breakpoint() {
+        // 6007: V0 = 7
+        // 6107: V1 = 7
+        // D015: draw 5 rows at V0, V1
+        let mut chip8 = Chip8::new(&[0x60, 0x07, 0x61, 0x07, 0xd0, 0x15]);
+        assert!(chip8.add_breakpoint(0x202));
+
+        assert!(!chip8.run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 100).unwrap());
+        assert_eq!(chip8.pc(), 0x202);
+
+        // a draw which lands on a breakpoint still counts
+        chip8.add_breakpoint(0x206);
+        assert!(chip8.run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 100).unwrap());
+        assert_eq!(chip8.pc(), 0x206);
+    }
+
+    #[test]
+    fn test_timer_accessors() {
+        // 6A3C: VA = 60
+        // FA15: delay timer = VA
+        // FA18: sound timer = VA
+        let mut chip8 = Chip8::new(&[0x6a, 0x3c, 0xfa, 0x15, 0xfa, 0x18]);
+        for _ in 0..3 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.delay_timer(), 60);
+        assert_eq!(chip8.sound_timer(), 60);
+
+        chip8.update_timers_by(10);
+
+        assert_eq!(chip8.delay_timer(), 50);
+        assert_eq!(chip8.sound_timer(), 50);
+    }
+
+    #[test]
+    fn test_last_cycle_cost() {
+        // 00E0: clear
+        // 6A05: VA = 5
+        // 8AB4: VA += VB
+        // D005: draw
+        // FA33: BCD
+        // 120A: loop
+        let program = [
+            0x00, 0xe0, 0x6a, 0x05, 0x8a, 0xb4, 0xd0, 0x05, 0xfa, 0x33, 0x12, 0x0a,
+        ];
+        let costs = CycleCosts::vip();
+        let mut chip8 = Chip8::new(&program);
+        assert_eq!(chip8.last_cycle_cost(), 0);
+
+        let mut cycle_costs = [0; 6];
+        for cost in &mut cycle_costs {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            *cost = chip8.last_cycle_cost();
+        }
+
+        assert_eq!(
+            cycle_costs,
+            [
+                costs.clear,
+                costs.load_value,
+                costs.arithmetic,
+                costs.draw_sprite,
+                costs.bcd,
+                costs.jump
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_cycle_costs() {
+        let costs = CycleCosts {
+            load_value: 1000,
+            ..CycleCosts::vip()
+        };
+        let mut chip8 = Chip8::new(&[0x6a, 0x05]);
+        chip8.set_cycle_costs(costs);
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        assert_eq!(chip8.cycle_costs(), &costs);
+        assert_eq!(chip8.last_cycle_cost(), 1000);
+    }
+
+    #[test]
+    fn test_seeded_random() {
+        // C0FF: V0 = random
+        // C10F: V1 = random & 0x0f
+        // 1200: loop
+        let program = [0xc0, 0xff, 0xc1, 0x0f, 0x12, 0x00];
+        let run = |seed| {
+            let mut chip8 = Chip8::new(&program);
+            chip8.seed_rng(seed);
+            let mut values = [0; 16];
+            for pair in values.chunks_mut(2) {
+                for _ in 0..3 {
+                    chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+                }
+                pair.copy_from_slice(&chip8.v_reg[..2]);
+            }
+            values
+        };
+
+        let values = run(1);
+
+        assert_eq!(values, run(1));
+        assert_ne!(values, run(2));
+        assert!(values.chunks(2).all(|pair| pair[1] <= 0x0f));
+        assert!(values.chunks(2).any(|pair| pair[0] != values[0]));
+    }
+
+    #[test]
+    fn test_rng_override() {
+        // C0FF: V0 = random
+        // C10F: V1 = random & 0x0f
+        // 1200: loop
+        let mut chip8 = Chip8::new(&[0xc0, 0xff, 0xc1, 0x0f, 0x12, 0x00]);
+        chip8.set_rng_override(Some(0xa5));
+        let unaffected = chip8.clone();
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(chip8.v_reg[..2], [0xa5, 0x05]);
+        assert_eq!(chip8.rng, unaffected.rng);
+
+        chip8.set_rng_override(None);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_ne!(chip8.rng, unaffected.rng);
+    }
+
+    #[test]
+    fn test_call_with_full_stack() {
+        // 2200: call 0x200, which calls itself until the stack is full
+        let mut chip8 = Chip8::new(&[0x22, 0x00]);
+        for _ in 0..STACK_SIZE {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        // the 13th nested call has nowhere to store its return address
+        let result = chip8.cycle(&NO_KEYS, &NO_KEYS);
+
+        assert!(matches!(
+            result,
+            Err(CycleError::ExecuteError(ExecuteError::StackOverflow {
+                pc: 0x200
+            }))
+        ));
+        assert_eq!(chip8.stack_ptr as usize, STACK_SIZE);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cycle_error_source() {
+        use std::{boxed::Box, error::Error, string::ToString};
+
+        // 00EE: return, which fails as the stack is empty
+        let mut chip8 = Chip8::new(&[0x00, 0xee]);
+        let err: Box<dyn Error> = Box::new(chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap_err());
+
+        let source = err.source().unwrap();
+        assert!(source.is::<ExecuteError>());
+        assert_eq!(source.to_string(), err.to_string());
+        assert!(source.source().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_step_over_error_source() {
+        use std::{boxed::Box, error::Error};
+
+        let mut chip8 = Chip8::new(&[0x00, 0xee]);
+        let err: Box<dyn Error> =
+            Box::new(chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 100).unwrap_err());
+
+        assert!(err.source().unwrap().is::<CycleError>());
+
+        let limit: Box<dyn Error> = Box::new(StepOverError::CycleLimitReached { max_cycles: 1 });
+        assert!(limit.source().is_none());
+    }
+
+    #[test]
+    fn test_clear_warnings() {
+        let mut chip8 = Chip8::new(&[0x12, 0x01]);
+        chip8.set_strict(true);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.warnings().count(), 1);
+
+        chip8.clear_warnings();
+
+        assert_eq!(chip8.warnings().count(), 0);
+    }
+
+    #[test]
+    fn test_draw_outcome() {
+        // D001: draw the 1 byte sprite at I (0x000) at V0, V0
+        let mut chip8 = Chip8::new(&[0xd0, 0x01]);
+
+        let outcome = chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        assert_eq!(outcome, CycleOutcome::DrewSprite);
+    }
+
+    #[test]
+    fn test_wait_for_key_outcome() {
+        // F30A: wait for a key and store it in V3
+        let mut chip8 = Chip8::new(&[0xf3, 0x0a]);
+
+        let outcome = chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(outcome, CycleOutcome::WaitingForKey);
+
+        let outcome = chip8.cycle(&NO_KEYS, &key_state(0x4)).unwrap();
+        assert_eq!(outcome, CycleOutcome::Normal);
+    }
+
+    #[test]
+    fn test_breakpoint_outcome() {
+        // 6001: V0 = 1
+        // 6102: V1 = 2
+        let mut chip8 = Chip8::new(&[0x60, 0x01, 0x61, 0x02]);
+        assert!(chip8.add_breakpoint(0x202));
+
+        let outcome = chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(outcome, CycleOutcome::BreakpointHit);
+        // the instruction at the breakpoint hasn't run yet
+        assert_eq!(chip8.v_reg[1], 0);
+
+        chip8.remove_breakpoint(0x202);
+        assert_eq!(chip8.breakpoints().count(), 0);
+    }
+
+    #[test]
+    fn test_write_memory() {
+        let mut chip8 = Chip8::new(&[0x00, 0xe0]);
+
+        chip8.write_memory(0x201, 0xee);
+
+        assert_eq!(chip8.dump_memory().len(), 4096);
+        assert_eq!(chip8.dump_memory()[0x200..0x202], [0x00, 0xee]);
+    }
+
+    #[test]
+    fn test_add_to_i_sets_vf() {
+        let quirks = Quirks {
+            add_to_i_sets_vf: true,
+            ..Default::default()
+        };
+        // 6001: V0 = 1
+        // F01E: I += V0
+        for (i, vf) in [(0x0ffe, 0), (0x0fff, 1), (0x1000, 0)] {
+            let mut chip8 = Chip8::new_with_quirks(&[0x60, 0x01, 0xf0, 0x1e], quirks);
+            chip8.i_reg = i;
+            chip8.v_reg[0xf] = 0xaa;
+            for _ in 0..2 {
+                chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            }
+            assert_eq!(chip8.i_reg, i + 1);
+            assert_eq!(chip8.v_reg[0xf], vf, "I = {:#x}", i + 1);
+        }
+    }
+
+    #[test]
+    fn test_add_to_i_above_4kb_clears_vf() {
+        let quirks = Quirks {
+            add_to_i_sets_vf: true,
+            ..Quirks::xochip()
+        };
+        // 6010: V0 = 0x10
+        // F01E: I += V0
+        let mut chip8 = Chip8::new_with_quirks(&[0x60, 0x10, 0xf0, 0x1e], quirks);
+        chip8.i_reg = 0x2000;
+        chip8.v_reg[0xf] = 0xaa;
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.i_reg, 0x2010);
+        assert_eq!(chip8.v_reg[0xf], 0);
+    }
+
+    #[test]
+    fn test_add_to_i_leaves_vf_by_default() {
+        let mut chip8 = Chip8::new(&[0x60, 0x01, 0xf0, 0x1e]);
+        chip8.i_reg = 0x0fff;
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.v_reg[0xf], 0);
+    }
+
+    #[test]
+    fn test_shift_uses_vy() {
+        // 8016: V0 = V1 >> 1
+        // 802E: V0 = V2 << 1
+        for (shift_uses_vy, shifted) in [(true, [0x08, 0x40]), (false, [0x01, 0x02])] {
+            let quirks = Quirks {
+                shift_uses_vy,
+                ..Default::default()
+            };
+            let mut chip8 = Chip8::new_with_quirks(&[0x80, 0x16, 0x80, 0x2e], quirks);
+            chip8.v_reg[0] = 0x02;
+            chip8.v_reg[1] = 0x11;
+            chip8.v_reg[2] = 0x20;
+
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            assert_eq!(chip8.v_reg[0], shifted[0]);
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            assert_eq!(chip8.v_reg[0], shifted[1]);
+        }
+    }
+
+    #[test]
+    fn test_load_store_increments_i() {
+        // F255: store V0 to V2 at I
+        // F165: load V0 to V1 from I
+        for (load_store_increments_i, i) in [(true, 0x305), (false, 0x300)] {
+            let quirks = Quirks {
+                load_store_increments_i,
+                ..Default::default()
+            };
+            let mut chip8 = Chip8::new_with_quirks(&[0xf2, 0x55, 0xf1, 0x65], quirks);
+            chip8.i_reg = 0x300;
+            for _ in 0..2 {
+                chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            }
+
+            assert_eq!(chip8.i_reg, i);
+        }
+    }
+
+    #[test]
+    fn test_jump_with_vx() {
+        // B310: jump to 0x310 plus V0, or V3 with the quirk
+        for (jump_with_vx, pc) in [(false, 0x311), (true, 0x313)] {
+            let quirks = Quirks {
+                jump_with_vx,
+                ..Default::default()
+            };
+            let mut chip8 = Chip8::new_with_quirks(&[0xb3, 0x10], quirks);
+            chip8.v_reg[0] = 0x01;
+            chip8.v_reg[3] = 0x03;
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+            assert_eq!(chip8.pc, pc);
+        }
+    }
+
+    #[test]
+    fn test_logic_resets_vf() {
+        // 8011: V0 |= V1
+        // 8012: V0 &= V1
+        // 8013: V0 ^= V1
+        for opcode in [0x11, 0x12, 0x13] {
+            for (logic_resets_vf, vf) in [(true, 0), (false, 0xaa)] {
+                let quirks = Quirks {
+                    logic_resets_vf,
+                    ..Default::default()
+                };
+                let mut chip8 = Chip8::new_with_quirks(&[0x80, opcode], quirks);
+                chip8.v_reg[0xf] = 0xaa;
+                chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+                assert_eq!(chip8.v_reg[0xf], vf, "80{opcode:02x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_wait() {
+        let quirks = Quirks {
+            display_wait: true,
+            ..Default::default()
+        };
+        // D005: draw a sprite
+        // D005: draw it again, which waits for the timers to tick
+        let mut chip8 = Chip8::new_with_quirks(&[0xd0, 0x05, 0xd0, 0x05], quirks);
+        let outcome = chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(outcome, CycleOutcome::DrewSprite);
+
+        for _ in 0..3 {
+            let outcome = chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+            assert_eq!(outcome, CycleOutcome::WaitingForVblank);
+            assert_eq!(chip8.pc, 0x202);
+        }
+
+        chip8.update_timers();
+        let outcome = chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(outcome, CycleOutcome::DrewSprite);
+        assert_eq!(chip8.pc, 0x204);
+    }
+
+    #[test]
+    fn test_set_quirks_resizes_memory() {
+        let mut chip8 = Chip8::new(&[0x12, 0x34]);
+
+        chip8.set_quirks(Quirks {
+            extended_memory: true,
+            ..Default::default()
+        });
+        assert!(chip8.quirks().extended_memory);
+        assert_eq!(chip8.dump_memory().len(), 0x10000);
+        assert_eq!(chip8.dump_memory()[0x200..0x202], [0x12, 0x34]);
+
+        chip8.set_quirks(Quirks::default());
+        assert_eq!(chip8.dump_memory().len(), 0x1000);
+        assert_eq!(chip8.dump_memory()[0x200..0x202], [0x12, 0x34]);
+    }
+
+    #[test]
+    fn test_set_quirks_shrinking_wraps_addresses() {
+        let mut chip8 = Chip8::new_with_quirks(&[], Quirks::xochip());
+        chip8.i_reg = 0xabcd;
+        chip8.pc = 0x1200;
+
+        chip8.set_quirks(Quirks::vip());
+
+        assert_eq!(chip8.i_reg, 0xbcd);
+        assert_eq!(chip8.pc, 0x200);
+    }
+
+    #[test]
+    fn test_update_timers_by() {
+        let mut chip8 = Chip8::new(&[]);
+        chip8.delay_timer = 10;
+        chip8.sound_timer = 3;
+
+        chip8.update_timers_by(4);
+        assert_eq!(chip8.delay_timer, 6);
+        assert_eq!(chip8.sound_timer, 0);
+
+        chip8.update_timers_by(u8::MAX);
+        assert_eq!(chip8.delay_timer, 0);
+        assert_eq!(chip8.sound_timer, 0);
+
+        chip8.update_timers_by(1);
+        assert_eq!(chip8.delay_timer, 0);
+    }
+
+    #[test]
+    fn test_protected_memory_write() {
+        let quirks = Quirks {
+            protect_reserved_memory: true,
+            ..Default::default()
+        };
+        // A1FF: I = 0x1FF, just below the program
+        // F155: store V0 and V1 at I
+        let mut chip8 = Chip8::new_with_quirks(&[0xa1, 0xff, 0xf1, 0x55], quirks);
+        chip8.v_reg[0] = 0xaa;
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        let result = chip8.cycle(&NO_KEYS, &NO_KEYS);
+
+        assert!(matches!(
+            result,
+            Err(CycleError::ExecuteError(
+                ExecuteError::ProtectedMemoryWrite { addr: 0x1ff }
+            ))
+        ));
+        assert_eq!(chip8.memory.get(0x1ff), 0);
+        assert_eq!(chip8.i_reg, 0x1ff);
+    }
+
+    #[test]
+    fn test_unprotected_memory_write() {
+        let mut chip8 = Chip8::new(&[0xa1, 0xff, 0xf1, 0x55]);
+        chip8.v_reg[0] = 0xaa;
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.memory.get(0x1ff), 0xaa);
+    }
+
+    #[test]
+    fn test_clone_steps_identically() {
+        // 7001: V0 += 1
+        // D001: draw the 1 byte sprite at I (0x000) at V0, V0
+        // F055: store V0 at I
+        // 1200: jump back to the start
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0xd0, 0x01, 0xf0, 0x55, 0x12, 0x00]);
+        chip8.run_frames(1, 7, &NO_KEYS).unwrap();
+
+        let mut clone = chip8.clone();
+        chip8.run_frames(3, 10, &NO_KEYS).unwrap();
+        clone.run_frames(3, 10, &NO_KEYS).unwrap();
+
+        assert_eq!(clone.pc, chip8.pc);
+        assert_eq!(clone.i_reg, chip8.i_reg);
+        assert_eq!(clone.v_reg, chip8.v_reg);
+        assert_eq!(clone.dump_memory(), chip8.dump_memory());
+        assert!(clone.screen == chip8.screen);
+    }
+
+    #[test]
+    fn test_identical_runs_are_equal() {
+        // 7001: V0 += 1
+        // D001: draw the 1 byte sprite at I (0x000) at V0, V0
+        // 2200: call the start
+        let program = [0x70, 0x01, 0xd0, 0x01, 0x22, 0x00];
+        let mut a = Chip8::new(&program);
+        let mut b = Chip8::new(&program);
+        a.run_frames(2, 6, &NO_KEYS).unwrap();
+        b.run_frames(2, 6, &NO_KEYS).unwrap();
+
+        assert!(a == b);
+
+        b.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert!(a != b);
+    }
+
+    #[test]
+    fn test_step_over_call() {
+        // 2206: call the subroutine
+        // 6101: V1 = 1
+        // 1204: loop forever
+        // 6005: V0 = 5
+        // 7001: V0 += 1
+        // 00EE: return
+        let mut chip8 = Chip8::new(&[
+            0x22, 0x06, 0x61, 0x01, 0x12, 0x04, 0x60, 0x05, 0x70, 0x01, 0x00, 0xee,
+        ]);
+
+        chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 100).unwrap();
+
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.stack_ptr, 0);
+        assert_eq!(chip8.v_reg[0], 6);
+        assert_eq!(chip8.v_reg[1], 0);
+    }
+
+    #[test]
+    fn test_step_over_not_call() {
+        // 6005: V0 = 5
+        // 6101: V1 = 1
+        let mut chip8 = Chip8::new(&[0x60, 0x05, 0x61, 0x01]);
+
+        chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 100).unwrap();
+
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v_reg[0], 5);
+        assert_eq!(chip8.v_reg[1], 0);
+    }
+
+    #[test]
+    fn test_step_over_cycle_limit() {
+        // 2202: call the subroutine
+        // 1202: loop forever without returning
+        let mut chip8 = Chip8::new(&[0x22, 0x02, 0x12, 0x02]);
+
+        let result = chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 10);
+
+        assert!(matches!(
+            result,
+            Err(StepOverError::CycleLimitReached { max_cycles: 10 })
+        ));
+    }
+
+    #[test]
+    fn test_step_over_ticks_timers() {
+        // 2204: call the subroutine
+        // 1202: loop forever
+        // 6A05: VA = 5
+        // FA15: DT = VA
+        // FA07: VA = DT
+        // 3A00: skip if VA == 0
+        // 1208: jump to FA07
+        // 00EE: return
+        let program = [
+            0x22, 0x04, 0x12, 0x02, 0x6a, 0x05, 0xfa, 0x15, 0xfa, 0x07, 0x3a, 0x00, 0x12, 0x08,
+            0x00, 0xee,
+        ];
+        let mut chip8 = Chip8::new(&program);
+
+        chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 1000).unwrap();
+
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.delay_timer, 0);
+    }
+
+    #[test]
+    fn test_step_over_stops_at_breakpoint() {
+        // 2204: call the subroutine
+        // 1202: loop forever
+        // 6005: V0 = 5
+        // 00EE: return
+        let mut chip8 = Chip8::new(&[0x22, 0x04, 0x12, 0x02, 0x60, 0x05, 0x00, 0xee]);
+        chip8.add_breakpoint(0x206);
+
+        let outcome = chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 100).unwrap();
+
+        assert_eq!(outcome, CycleOutcome::BreakpointHit);
+        assert_eq!(chip8.pc, 0x206);
+    }
+
+    #[test]
+    fn test_load_digit_sprite_addr() {
+        // 630b: V3 = 0xb
+        // f329: I = address of the sprite for V3
+        let mut chip8 = Chip8::new(&[0x63, 0x0b, 0xf3, 0x29]);
+
+        chip8.run_frames(1, 2, &NO_KEYS).unwrap();
+
+        assert_eq!(chip8.i_reg, 0xb * 5);
+        assert_eq!(
+            chip8.memory.get_bytes(chip8.i_reg as usize, 5),
+            [0xe0, 0x90, 0xe0, 0x90, 0xe0]
+        );
+    }
+
+    #[test]
+    fn test_custom_font() {
+        let mut font = [0; FONT_SIZE_BYTES];
+        for (i, byte) in font.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        // 6002: V0 = 2
+        // f029: I = address of the sprite for V0
+        let mut chip8 = Chip8::with_font(&[0x60, 0x02, 0xf0, 0x29], &font);
+
+        chip8.run_frames(1, 2, &NO_KEYS).unwrap();
+
+        assert_eq!(
+            chip8.memory.get_bytes(chip8.i_reg as usize, 5),
+            [10, 11, 12, 13, 14]
+        );
+    }
+
+    #[test]
+    fn test_try_new_exactly_fitting_program() {
+        let program = [0xab; CHIP8_MEMORY_SIZE_BYTES - PROGRAM_OFFSET_BYTES];
+
+        let chip8 = Chip8::try_new(&program).unwrap();
+
+        assert_eq!(chip8.dump_memory()[CHIP8_MEMORY_SIZE_BYTES - 1], 0xab);
+    }
+
+    #[test]
+    fn test_try_new_program_too_long() {
+        let program = [0xab; CHIP8_MEMORY_SIZE_BYTES - PROGRAM_OFFSET_BYTES + 1];
+
+        let result = Chip8::try_new(&program);
+
+        assert!(matches!(
+            result,
+            Err(LoadError::ProgramTooLong {
+                len: 3585,
+                max_len: 3584
+            })
+        ));
+
+        // there's room for it in XO-CHIP memory
+        let quirks = Quirks::xochip();
+        assert!(Chip8::try_new_with_quirks(&program, quirks).is_ok());
+    }
+
+    #[test]
+    fn test_frame_signal_once_per_tick() {
+        // 6005: V0 = 5
+        // 1202: loop forever
+        let mut chip8 = Chip8::new(&[0x60, 0x05, 0x12, 0x02]);
+        let mut frames = [0; 4];
+        let mut i = 0;
+
+        chip8
+            .run_frames_with(4, 10, &NO_KEYS, |chip8| {
+                frames[i] = chip8.frame_count();
+                i += 1;
+            })
+            .unwrap();
+
+        assert_eq!(frames, [1, 2, 3, 4]);
+        chip8.update_timers_by(3);
+        assert_eq!(chip8.frame_count(), 7);
+    }
+
+    #[test]
+    fn test_memory_map() {
+        let chip8 = Chip8::new_with_quirks(&[0x12, 0x00], Quirks::xochip());
+
+        let map = chip8.memory_map();
+
+        assert_eq!(
+            (map[2].kind, map[2].start, map[2].end),
+            (RegionKind::Program, 0x200, 0x202)
+        );
+        assert_eq!(map[3].end, chip8.dump_memory().len());
+    }
+
+    #[test]
+    fn test_tolerate_unknown_instructions() {
+        // 6005: V0 = 5
+        // 5121: unknown
+        // 7001: V0 += 1
+        // 00FD: exit
+        let program = [0x60, 0x05, 0x51, 0x21, 0x70, 0x01, 0x00, 0xfd];
+
+        let mut strict = Chip8::new(&program);
+        assert!(!strict.tolerates_unknown_instructions());
+        strict.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert!(matches!(
+            strict.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::DecodeError(DecodeError::UnknownInstruction {
+                inst: 0x5121
+            }))
+        ));
+
+        let mut tolerant = Chip8::new(&program);
+        tolerant.set_tolerate_unknown_instructions(true);
+        tolerant.run_frames(1, 10, &NO_KEYS).unwrap();
+        assert!(tolerant.is_halted());
+        assert_eq!(tolerant.v_reg[0], 6);
+        assert!(tolerant.warnings().eq([Warning::UnknownInstruction {
+            pc: 0x202,
+            inst: 0x5121
+        }]));
+    }
+
+    #[test]
+    fn test_cycle_n() {
+        // 7001: V0 += 1
+        // 7101: V1 += 1
+        // 5121: unknown
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x71, 0x01, 0x51, 0x21]);
+
+        assert!(matches!(
+            chip8.cycle_n(1, &NO_KEYS, &NO_KEYS),
+            (1, Ok(CycleOutcome::Normal))
+        ));
+        assert_eq!(chip8.pc, 0x202);
+        assert!(matches!(chip8.cycle_n(0, &NO_KEYS, &NO_KEYS), (0, Ok(_))));
+
+        // stops at the error without counting it
+        assert!(matches!(
+            chip8.cycle_n(10, &NO_KEYS, &NO_KEYS),
+            (
+                1,
+                Err(CycleError::DecodeError(DecodeError::UnknownInstruction {
+                    inst: 0x5121
+                }))
+            )
+        ));
+        assert_eq!(chip8.pc, 0x204);
+
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x12, 0x00]);
+        assert!(chip8.add_breakpoint(0x202));
+        assert!(matches!(
+            chip8.cycle_n(10, &NO_KEYS, &NO_KEYS),
+            (1, Ok(CycleOutcome::BreakpointHit))
+        ));
+    }
+
+    #[test]
+    fn test_skip_machine_code_calls() {
+        // 6005: V0 = 5
+        // 0123: machine code subroutine at 0x123
+        // 7001: V0 += 1
+        let program = [0x60, 0x05, 0x01, 0x23, 0x70, 0x01];
+
+        let mut chip8 = Chip8::new(&program);
+        assert!(!chip8.skips_machine_code_calls());
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert!(matches!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::ExecuteError(
+                ExecuteError::UnknownMachineSubroutine { nnn: 0x123 }
+            ))
+        ));
+        assert_eq!(chip8.pc, 0x202);
+
+        let mut skipping = Chip8::new(&program);
+        skipping.set_skip_machine_code_calls(true);
+        for _ in 0..3 {
+            skipping.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(skipping.pc, 0x206);
+        assert_eq!(skipping.v_reg[0], 6);
+        assert_eq!(skipping.stack_ptr, 0);
+    }
+
+    #[test]
+    fn test_exit_halts() {
+        // 6005: V0 = 5
+        // 00FD: exit
+        // 6006: V0 = 6, never run
+        let mut chip8 = Chip8::new(&[0x60, 0x05, 0x00, 0xfd, 0x60, 0x06]);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert!(!chip8.is_halted());
+
+        assert_eq!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap(),
+            CycleOutcome::Halted
+        );
+        for _ in 0..10 {
+            assert_eq!(
+                chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap(),
+                CycleOutcome::Halted
+            );
+        }
+
+        assert!(chip8.is_halted());
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(chip8.v_reg[0], 5);
+        assert!(!chip8.run_to_next_draw(&NO_KEYS, &NO_KEYS, 10, 100).unwrap());
+
+        chip8.load_program(&[0x60, 0x06]).unwrap();
+        assert!(!chip8.is_halted());
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.v_reg[0], 6);
+    }
+
+    #[test]
+    fn test_step_over_stops_on_exit() {
+        // 2204: call 0x204
+        // 1202: loop
+        // 00FD: exit
+        let mut chip8 = Chip8::new(&[0x22, 0x04, 0x12, 0x02, 0x00, 0xfd]);
+
+        let outcome = chip8.step_over(&NO_KEYS, &NO_KEYS, 10, 100).unwrap();
+
+        assert_eq!(outcome, CycleOutcome::Halted);
+        assert!(chip8.is_halted());
+    }
+
+    #[test]
+    fn test_is_waiting_for_key() {
+        // F30A: wait for a key and store it in V3
+        let mut chip8 = Chip8::new(&[0xf3, 0x0a]);
+        assert!(!chip8.is_waiting_for_key());
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert!(chip8.is_waiting_for_key());
+
+        // press and release the key
+        chip8.cycle(&key_state(0x5), &NO_KEYS).unwrap();
+        assert!(chip8.is_waiting_for_key());
+        chip8.cycle(&NO_KEYS, &key_state(0x5)).unwrap();
+        assert!(!chip8.is_waiting_for_key());
+        assert_eq!(chip8.v_reg[3], 0x5);
+    }
+
+    #[test]
+    fn test_load_audio_pattern() {
+        // A206: I = the pattern below
+        // F002: load the audio pattern from I
+        // 1204: loop forever
+        // 0x206: 16 bytes of pattern
+        let pattern: [u8; 16] = core::array::from_fn(|i| i as u8);
+        let mut program = [0; 22];
+        program[..6].copy_from_slice(&[0xa2, 0x06, 0xf0, 0x02, 0x12, 0x04]);
+        program[6..].copy_from_slice(&pattern);
+        let mut chip8 = Chip8::new_with_quirks(&program, Quirks::xochip());
+        assert_eq!(chip8.audio_pattern(), &[0; 16]);
+
+        chip8.run_frames(1, 2, &NO_KEYS).unwrap();
+
+        assert_eq!(chip8.audio_pattern(), &pattern);
+    }
+
+    #[test]
+    fn test_load_audio_pattern_overflow() {
+        // F000 FFF8: I = 0xfff8, only 8 bytes before the end of memory
+        // F002: load the audio pattern from I
+        let mut chip8 =
+            Chip8::new_with_quirks(&[0xf0, 0x00, 0xff, 0xf8, 0xf0, 0x02], Quirks::xochip());
+
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        let result = chip8.cycle(&NO_KEYS, &NO_KEYS);
+
+        assert!(matches!(
+            result,
+            Err(CycleError::ExecuteError(
+                ExecuteError::AudioPatternMemoryOverflow { index: 0xfff8 }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_pc_at_end_of_memory() {
+        // 1FFF: jump to the last byte of memory
+        let mut chip8 = Chip8::new(&[0x1f, 0xff]);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(chip8.pc(), 0xfff);
+
+        // peeking reads past the end as 0 instead of panicking
+        assert_eq!(chip8.peek_opcode(), 0x0000);
+        let result = chip8.cycle(&NO_KEYS, &NO_KEYS);
+
+        assert!(matches!(
+            result,
+            Err(CycleError::ExecuteError(ExecuteError::PcOutOfBounds {
+                pc: 0xfff
+            }))
+        ));
+        // nothing ran, so it fails the same way again
+        assert_eq!(chip8.pc(), 0xfff);
+        assert!(chip8.cycle(&NO_KEYS, &NO_KEYS).is_err());
+    }
+
+    #[test]
+    fn test_pc_at_end_of_extended_memory() {
+        // 6001 in the last word of memory: V0 = 1, with nowhere for the PC to
+        // go after it
+        let mut chip8 = Chip8::new_with_quirks(&[], Quirks::xochip());
+        chip8.write_memory(0xfffe, 0x60);
+        chip8.write_memory(0xffff, 0x01);
+        chip8.pc = 0xfffe;
+
+        assert!(matches!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::ExecuteError(ExecuteError::PcOutOfBounds {
+                pc: 0xfffe
+            }))
+        ));
+        assert_eq!(chip8.pc(), 0xfffe);
+
+        // 3000 in the second to last word: skip past the end as V0 == 0
+        let mut chip8 = Chip8::new_with_quirks(&[], Quirks::xochip());
+        chip8.write_memory(0xfffc, 0x30);
+        chip8.pc = 0xfffc;
+
+        assert!(matches!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::ExecuteError(ExecuteError::PcOutOfBounds {
+                pc: 0xfffe
+            }))
+        ));
+
+        // an unknown instruction in the last word, which is skipped
+        let mut chip8 = Chip8::new_with_quirks(&[], Quirks::xochip());
+        chip8.set_tolerate_unknown_instructions(true);
+        chip8.write_memory(0xfffe, 0x51);
+        chip8.write_memory(0xffff, 0x21);
+        chip8.pc = 0xfffe;
+
+        assert!(matches!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::ExecuteError(ExecuteError::PcOutOfBounds {
+                pc: 0xfffe
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_long_load_at_end_of_memory() {
+        // F000 in the last word of memory, with no room for its address word
+        let mut chip8 = Chip8::new_with_quirks(&[], Quirks::xochip());
+        chip8.write_memory(0xfffe, 0xf0);
+        chip8.pc = 0xfffe;
+
+        assert!(matches!(
+            chip8.peek_instruction(),
+            Err(DecodeError::MissingImmediate { inst: 0xf000 })
+        ));
+        assert!(matches!(
+            chip8.cycle(&NO_KEYS, &NO_KEYS),
+            Err(CycleError::DecodeError(DecodeError::MissingImmediate {
+                inst: 0xf000
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_set_pitch() {
+        // 6370: V3 = 112
+        // F33A: pitch = V3
+        let mut chip8 = Chip8::new_with_quirks(&[0x63, 0x70, 0xf3, 0x3a], Quirks::xochip());
+        assert_eq!(chip8.pitch(), DEFAULT_PITCH);
+
+        chip8.run_frames(1, 2, &NO_KEYS).unwrap();
+
+        assert_eq!(chip8.pitch(), 112);
+    }
+
+    #[test]
+    fn test_draw_15_row_sprite() {
+        // A206: I = the sprite below
+        // D01F: draw the 15 row sprite at V0, V0
+        // 1204: loop forever
+        // 0x206: a 15 row sprite of solid rows
+        let mut program = [0xff; 21];
+        program[..6].copy_from_slice(&[0xa2, 0x06, 0xd0, 0x1f, 0x12, 0x04]);
+        let mut chip8 = Chip8::new(&program);
+
+        chip8.run_frames(1, 3, &NO_KEYS).unwrap();
+
+        let screen = chip8.get_screen();
+        for y in 0..15 {
+            assert!(screen.get_pixel(0, y), "row {y}");
+            assert!(screen.get_pixel(7, y), "row {y}");
+        }
+        assert!(!screen.get_pixel(0, 15));
+        assert_eq!(chip8.v_reg[0xf], 0);
+    }
+
+    #[test]
+    fn test_trace_hook() {
+        use crate::instruction::Inst;
+        use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+
+        static CALLS: AtomicU32 = AtomicU32::new(0);
+        static LAST_PC: AtomicU16 = AtomicU16::new(0);
+        fn hook(pc: u16, opcode: u16, inst: &Inst) {
+            assert_eq!(crate::instruction::decode(opcode).ok().as_ref(), Some(inst));
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            LAST_PC.store(pc, Ordering::Relaxed);
+        }
+
+        // 3 instructions then a jump back to the start
+        let mut chip8 = Chip8::new(&[0x60, 0x01, 0x70, 0x01, 0x00, 0xe0, 0x12, 0x00]);
+        chip8.set_trace_hook(Some(hook));
+        for _ in 0..10 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 10);
+        assert_eq!(LAST_PC.load(Ordering::Relaxed), 0x202);
+
+        chip8.set_trace_hook(None);
+        chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        assert_eq!(CALLS.load(Ordering::Relaxed), 10);
+        // the hook isn't machine state
+        let mut traced = chip8.clone();
+        traced.set_trace_hook(Some(hook));
+        assert!(traced == chip8);
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_recent_trace() {
+        use crate::trace::TRACE_CAPACITY;
+
+        // 3 instructions then a jump back to the start
+        let program = [0x60, 0x01, 0x70, 0x01, 0x00, 0xe0, 0x12, 0x00];
+        let mut chip8 = Chip8::new(&program);
+        assert_eq!(chip8.recent_trace().count(), 0);
+        let cycles = TRACE_CAPACITY + 6;
+        for _ in 0..cycles {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.recent_trace().count(), TRACE_CAPACITY);
+        // the oldest entries were dropped, leaving the most recent in order
+        let expected = [
+            (0x200, 0x6001),
+            (0x202, 0x7001),
+            (0x204, 0x00e0),
+            (0x206, 0x1200),
+        ]
+        .into_iter()
+        .cycle()
+        .skip(cycles - TRACE_CAPACITY)
+        .take(TRACE_CAPACITY);
+        assert!(chip8.recent_trace().eq(expected));
+
+        chip8.load_program(&program).unwrap();
+        assert_eq!(chip8.recent_trace().count(), 0);
+    }
+}
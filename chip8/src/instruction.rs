@@ -1,12 +1,21 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use core::fmt;
 
+#[cfg(feature = "alloc")]
+use crate::memory::PROGRAM_OFFSET_BYTES;
+
 /// Represents valid CHIP-8 instructions.
 ///
 /// The documentation comments for fields of this enum are excerpts from
 /// [CHIP‐8 Instruction Set](https://github.com/mattmikolay/chip-8/wiki/CHIP%E2%80%908-Instruction-Set)
 /// by Matthew Mikolay which is licensed under
 /// [Creative Commons Attribution Share Alike 4.0](https://creativecommons.org/licenses/by-sa/4.0/).
-#[derive(Debug)]
+///
+/// The `Display` implementation formats instructions using the mnemonics from
+/// [Cowgod's Chip-8 Technical Reference](http://devernay.free.fr/hacks/chip8/C8TECH10.HTM),
+/// which `assembler::assemble()` accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Inst {
     /// `0NNN`: Execute machine language subroutine at address `NNN`
     Exe { nnn: u16 },
@@ -14,6 +23,8 @@ pub enum Inst {
     Clear,
     /// `00EE`: Return from a subroutine
     Return,
+    /// `00FD`: Stop running the program (SUPER-CHIP)
+    Exit,
     /// `1NNN`: Jump to a subroutine
     Jump { nnn: u16 },
     /// `2NNN`: Execute subroutine starting at address `NNN`
@@ -66,6 +77,9 @@ pub enum Inst {
     SkipNotEqualRegister { vx: u8, vy: u8 },
     /// `ANNN`: Store memory address `NNN` in register `I`
     LoadIntoI { nnn: u16 },
+    /// `F000 NNNN`: Store the 16-bit memory address `NNNN` from the following
+    /// word in register `I` (XO-CHIP)
+    LoadLongIntoI { nnnn: u16 },
     /// `BNNN`: Jump to address `NNN + V0`
     JumpAdd { nnn: u16 },
     /// `CXNN`: Set `VX` to a random number with a mask of `NN`
@@ -80,6 +94,12 @@ pub enum Inst {
     /// `EXA1`: Skip the following instruction if the key corresponding to the hex
     /// value currently stored in register `VX` is not pressed.
     SkipIfNotKey { vx: u8 },
+    /// `FN01`: Select the bit-planes `N` which drawing and clearing operate on
+    /// (XO-CHIP)
+    SetPlane { n: u8 },
+    /// `F002`: Load the 16 byte audio pattern buffer from memory starting at
+    /// the address stored in `I` (XO-CHIP)
+    LoadAudioPattern,
     /// `FX07`: Store the current value of the delay timer in register `VX`
     LoadDelay { vx: u8 },
     /// `FX0A`: Wait for a keypress and store the result in register `VX`
@@ -93,6 +113,9 @@ pub enum Inst {
     /// `FX29`: Set `I` to the memory address of the sprite data corresponding to
     /// the hexadecimal digit stored in register `VX`
     LoadDigitSpriteAddrIntoI { vx: u8 },
+    /// `FX3A`: Set the playback pitch of the audio pattern buffer to the value
+    /// of register `VX` (XO-CHIP)
+    SetPitch { vx: u8 },
     /// `FX33`: Store the [binary-coded decimal](https://en.wikipedia.org/wiki/Binary-coded_decimal)
     /// equivalent of the value stored in register VX at addresses `I`, `I + 1`,
     /// and `I + 2`
@@ -107,8 +130,186 @@ pub enum Inst {
     LoadRegisters { vx: u8 },
 }
 
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Inst::Exe { nnn } => write!(f, "SYS 0x{nnn:03x}"),
+            Inst::Clear => write!(f, "CLS"),
+            Inst::Return => write!(f, "RET"),
+            Inst::Exit => write!(f, "EXIT"),
+            Inst::Jump { nnn } => write!(f, "JP 0x{nnn:03x}"),
+            Inst::Call { nnn } => write!(f, "CALL 0x{nnn:03x}"),
+            Inst::SkipEqualValue { vx, nn } => write!(f, "SE V{vx:X}, 0x{nn:02x}"),
+            Inst::SkipNotEqualValue { vx, nn } => write!(f, "SNE V{vx:X}, 0x{nn:02x}"),
+            Inst::SkipEqualRegister { vx, vy } => write!(f, "SE V{vx:X}, V{vy:X}"),
+            Inst::LoadValue { vx, nn } => write!(f, "LD V{vx:X}, 0x{nn:02x}"),
+            Inst::AddValue { vx, nn } => write!(f, "ADD V{vx:X}, 0x{nn:02x}"),
+            Inst::LoadRegister { vx, vy } => write!(f, "LD V{vx:X}, V{vy:X}"),
+            Inst::Or { vx, vy } => write!(f, "OR V{vx:X}, V{vy:X}"),
+            Inst::And { vx, vy } => write!(f, "AND V{vx:X}, V{vy:X}"),
+            Inst::Xor { vx, vy } => write!(f, "XOR V{vx:X}, V{vy:X}"),
+            Inst::AddRegister { vx, vy } => write!(f, "ADD V{vx:X}, V{vy:X}"),
+            Inst::SubRegisterXY { vx, vy } => write!(f, "SUB V{vx:X}, V{vy:X}"),
+            Inst::ShiftRight { vx, vy } => write!(f, "SHR V{vx:X}, V{vy:X}"),
+            Inst::SubRegisterYX { vx, vy } => write!(f, "SUBN V{vx:X}, V{vy:X}"),
+            Inst::ShiftLeft { vx, vy } => write!(f, "SHL V{vx:X}, V{vy:X}"),
+            Inst::SkipNotEqualRegister { vx, vy } => write!(f, "SNE V{vx:X}, V{vy:X}"),
+            Inst::LoadIntoI { nnn } => write!(f, "LD I, 0x{nnn:03x}"),
+            Inst::LoadLongIntoI { nnnn } => write!(f, "LD I, LONG 0x{nnnn:04x}"),
+            Inst::JumpAdd { nnn } => write!(f, "JP V0, 0x{nnn:03x}"),
+            Inst::LoadRandom { vx, nn } => write!(f, "RND V{vx:X}, 0x{nn:02x}"),
+            Inst::DrawSprite { vx, vy, n } => write!(f, "DRW V{vx:X}, V{vy:X}, {n}"),
+            Inst::SkipIfKey { vx } => write!(f, "SKP V{vx:X}"),
+            Inst::SkipIfNotKey { vx } => write!(f, "SKNP V{vx:X}"),
+            Inst::SetPlane { n } => write!(f, "PLANE {n}"),
+            Inst::LoadAudioPattern => write!(f, "AUDIO"),
+            Inst::LoadDelay { vx } => write!(f, "LD V{vx:X}, DT"),
+            Inst::WaitForKey { vx } => write!(f, "LD V{vx:X}, K"),
+            Inst::SetDelay { vx } => write!(f, "LD DT, V{vx:X}"),
+            Inst::SetSound { vx } => write!(f, "LD ST, V{vx:X}"),
+            Inst::AddToI { vx } => write!(f, "ADD I, V{vx:X}"),
+            Inst::LoadDigitSpriteAddrIntoI { vx } => write!(f, "LD F, V{vx:X}"),
+            Inst::SetPitch { vx } => write!(f, "PITCH V{vx:X}"),
+            Inst::StoreBCD { vx } => write!(f, "LD B, V{vx:X}"),
+            Inst::StoreRegisters { vx } => write!(f, "LD [I], V{vx:X}"),
+            Inst::LoadRegisters { vx } => write!(f, "LD V{vx:X}, [I]"),
+        }
+    }
+}
+
+/// Encode an instruction into its first word, which `decode()` turns back into
+/// the same instruction. Instructions with an immediate word (see `has_immediate()`) must be
+/// followed by it, which for `LoadLongIntoI` is `nnnn`.
+#[must_use]
+pub fn encode(inst: &Inst) -> u16 {
+    let xy = |x: u8, y: u8| ((x as u16) << 8) | ((y as u16) << 4);
+    let xnn = |x: u8, nn: u8| ((x as u16) << 8) | nn as u16;
+    let x = |x: u8| (x as u16) << 8;
+    match *inst {
+        Inst::Exe { nnn } => nnn,
+        Inst::Clear => 0x00e0,
+        Inst::Return => 0x00ee,
+        Inst::Exit => 0x00fd,
+        Inst::Jump { nnn } => 0x1000 | nnn,
+        Inst::Call { nnn } => 0x2000 | nnn,
+        Inst::SkipEqualValue { vx, nn } => 0x3000 | xnn(vx, nn),
+        Inst::SkipNotEqualValue { vx, nn } => 0x4000 | xnn(vx, nn),
+        Inst::SkipEqualRegister { vx, vy } => 0x5000 | xy(vx, vy),
+        Inst::LoadValue { vx, nn } => 0x6000 | xnn(vx, nn),
+        Inst::AddValue { vx, nn } => 0x7000 | xnn(vx, nn),
+        Inst::LoadRegister { vx, vy } => 0x8000 | xy(vx, vy),
+        Inst::Or { vx, vy } => 0x8001 | xy(vx, vy),
+        Inst::And { vx, vy } => 0x8002 | xy(vx, vy),
+        Inst::Xor { vx, vy } => 0x8003 | xy(vx, vy),
+        Inst::AddRegister { vx, vy } => 0x8004 | xy(vx, vy),
+        Inst::SubRegisterXY { vx, vy } => 0x8005 | xy(vx, vy),
+        Inst::ShiftRight { vx, vy } => 0x8006 | xy(vx, vy),
+        Inst::SubRegisterYX { vx, vy } => 0x8007 | xy(vx, vy),
+        Inst::ShiftLeft { vx, vy } => 0x800e | xy(vx, vy),
+        Inst::SkipNotEqualRegister { vx, vy } => 0x9000 | xy(vx, vy),
+        Inst::LoadIntoI { nnn } => 0xa000 | nnn,
+        Inst::LoadLongIntoI { .. } => 0xf000,
+        Inst::JumpAdd { nnn } => 0xb000 | nnn,
+        Inst::LoadRandom { vx, nn } => 0xc000 | xnn(vx, nn),
+        Inst::DrawSprite { vx, vy, n } => 0xd000 | xy(vx, vy) | n as u16,
+        Inst::SkipIfKey { vx } => 0xe09e | x(vx),
+        Inst::SkipIfNotKey { vx } => 0xe0a1 | x(vx),
+        Inst::SetPlane { n } => 0xf001 | x(n),
+        Inst::LoadAudioPattern => 0xf002,
+        Inst::LoadDelay { vx } => 0xf007 | x(vx),
+        Inst::WaitForKey { vx } => 0xf00a | x(vx),
+        Inst::SetDelay { vx } => 0xf015 | x(vx),
+        Inst::SetSound { vx } => 0xf018 | x(vx),
+        Inst::AddToI { vx } => 0xf01e | x(vx),
+        Inst::LoadDigitSpriteAddrIntoI { vx } => 0xf029 | x(vx),
+        Inst::SetPitch { vx } => 0xf03a | x(vx),
+        Inst::StoreBCD { vx } => 0xf033 | x(vx),
+        Inst::StoreRegisters { vx } => 0xf055 | x(vx),
+        Inst::LoadRegisters { vx } => 0xf065 | x(vx),
+    }
+}
+
+/// The number of kinds of instruction, see `Inst::kind()`
+pub const INST_KIND_COUNT: usize = 40;
+
+/// The opcode pattern of each kind of instruction, indexed by `Inst::kind()`,
+/// where `X`, `Y` and `N` stand for operands
+pub const INST_KIND_PATTERNS: [&str; INST_KIND_COUNT] = [
+    "0NNN", "00E0", "00EE", "00FD", "1NNN", "2NNN", "3XNN", "4XNN", "5XY0", "6XNN", "7XNN", "8XY0",
+    "8XY1", "8XY2", "8XY3", "8XY4", "8XY5", "8XY6", "8XY7", "8XYE", "9XY0", "ANNN", "F000", "BNNN",
+    "CXNN", "DXYN", "EX9E", "EXA1", "FN01", "F002", "FX07", "FX0A", "FX15", "FX18", "FX1E", "FX29",
+    "FX3A", "FX33", "FX55", "FX65",
+];
+
+impl Inst {
+    /// Which kind of instruction this is regardless of its operands, from 0 to
+    /// `INST_KIND_COUNT`, for counting instructions by kind
+    #[must_use]
+    pub fn kind(&self) -> usize {
+        match self {
+            Inst::Exe { .. } => 0,
+            Inst::Clear => 1,
+            Inst::Return => 2,
+            Inst::Exit => 3,
+            Inst::Jump { .. } => 4,
+            Inst::Call { .. } => 5,
+            Inst::SkipEqualValue { .. } => 6,
+            Inst::SkipNotEqualValue { .. } => 7,
+            Inst::SkipEqualRegister { .. } => 8,
+            Inst::LoadValue { .. } => 9,
+            Inst::AddValue { .. } => 10,
+            Inst::LoadRegister { .. } => 11,
+            Inst::Or { .. } => 12,
+            Inst::And { .. } => 13,
+            Inst::Xor { .. } => 14,
+            Inst::AddRegister { .. } => 15,
+            Inst::SubRegisterXY { .. } => 16,
+            Inst::ShiftRight { .. } => 17,
+            Inst::SubRegisterYX { .. } => 18,
+            Inst::ShiftLeft { .. } => 19,
+            Inst::SkipNotEqualRegister { .. } => 20,
+            Inst::LoadIntoI { .. } => 21,
+            Inst::LoadLongIntoI { .. } => 22,
+            Inst::JumpAdd { .. } => 23,
+            Inst::LoadRandom { .. } => 24,
+            Inst::DrawSprite { .. } => 25,
+            Inst::SkipIfKey { .. } => 26,
+            Inst::SkipIfNotKey { .. } => 27,
+            Inst::SetPlane { .. } => 28,
+            Inst::LoadAudioPattern => 29,
+            Inst::LoadDelay { .. } => 30,
+            Inst::WaitForKey { .. } => 31,
+            Inst::SetDelay { .. } => 32,
+            Inst::SetSound { .. } => 33,
+            Inst::AddToI { .. } => 34,
+            Inst::LoadDigitSpriteAddrIntoI { .. } => 35,
+            Inst::SetPitch { .. } => 36,
+            Inst::StoreBCD { .. } => 37,
+            Inst::StoreRegisters { .. } => 38,
+            Inst::LoadRegisters { .. } => 39,
+        }
+    }
+}
+
+/// Whether `inst` is the first word of an instruction which is followed by a
+/// 16-bit immediate word, and must be decoded with `decode_with_immediate()`
+#[must_use]
+pub fn has_immediate(inst: u16) -> bool {
+    inst == 0xf000
+}
+
+/// Decode an instruction which is followed by a 16-bit immediate word, see
+/// `has_immediate()`. Other instructions are decoded as with `decode()`.
+pub fn decode_with_immediate(inst: u16, immediate: u16) -> Result<Inst, DecodeError> {
+    match inst {
+        0xf000 => Ok(Inst::LoadLongIntoI { nnnn: immediate }),
+        _ => decode(inst),
+    }
+}
+
 /// Decode a u16 into an Instruction. Returns an error when attempting to
-/// decode an invalid instruction.
+/// decode an invalid instruction, or an instruction which is followed by an
+/// immediate word (see `has_immediate()`).
 pub fn decode(inst: u16) -> Result<Inst, DecodeError> {
     // common values decoded from instructions
     let vx = ((inst & 0x0f00) >> 8) as u8;
@@ -121,6 +322,7 @@ pub fn decode(inst: u16) -> Result<Inst, DecodeError> {
         0x0000 => match inst {
             0x00e0 => Ok(Inst::Clear),
             0x00ee => Ok(Inst::Return),
+            0x00fd => Ok(Inst::Exit),
             _ => Ok(Inst::Exe { nnn }),
         },
         0x1000 => Ok(Inst::Jump { nnn }),
@@ -159,6 +361,9 @@ pub fn decode(inst: u16) -> Result<Inst, DecodeError> {
             _ => Err(DecodeError::UnknownInstruction { inst }),
         },
         0xf000 => match inst & 0x00ff {
+            0x0000 if has_immediate(inst) => Err(DecodeError::MissingImmediate { inst }),
+            0x0001 => Ok(Inst::SetPlane { n: vx }),
+            0x0002 if vx == 0 => Ok(Inst::LoadAudioPattern),
             0x0007 => Ok(Inst::LoadDelay { vx }),
             0x000A => Ok(Inst::WaitForKey { vx }),
             0x0015 => Ok(Inst::SetDelay { vx }),
@@ -166,6 +371,7 @@ pub fn decode(inst: u16) -> Result<Inst, DecodeError> {
             0x001E => Ok(Inst::AddToI { vx }),
             0x0029 => Ok(Inst::LoadDigitSpriteAddrIntoI { vx }),
             0x0033 => Ok(Inst::StoreBCD { vx }),
+            0x003A => Ok(Inst::SetPitch { vx }),
             0x0055 => Ok(Inst::StoreRegisters { vx }),
             0x0065 => Ok(Inst::LoadRegisters { vx }),
             _ => Err(DecodeError::UnknownInstruction { inst }),
@@ -174,14 +380,68 @@ pub fn decode(inst: u16) -> Result<Inst, DecodeError> {
     }
 }
 
+/// Decode every 2-byte word of `program` without running it, and return the
+/// address, as loaded at `PROGRAM_OFFSET_BYTES`, and error of each one which
+/// fails to decode. An instruction with an immediate word (see
+/// `has_immediate()`) is decoded with the word after it, which is skipped. A
+/// trailing odd byte isn't a whole word, so it isn't checked.
+///
+/// Control flow isn't followed, so sprites and other data mixed in with the
+/// code are decoded too and may be reported even though they're never run.
+/// This is a quick check of whether a ROM is likely to run, not a proof.
+#[cfg(feature = "alloc")]
+#[must_use]
+pub fn validate_rom(program: &[u8]) -> Vec<(u16, DecodeError)> {
+    let words: Vec<u16> = program
+        .chunks_exact(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+        .collect();
+    let mut errors = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let addr = (PROGRAM_OFFSET_BYTES + i * 2) as u16;
+        let result = match words.get(i + 1) {
+            Some(&immediate) if has_immediate(words[i]) => {
+                i += 1;
+                decode_with_immediate(words[i - 1], immediate)
+            }
+            _ => decode(words[i]),
+        };
+        if let Err(err) = result {
+            errors.push((addr, err));
+        }
+        i += 1;
+    }
+    errors
+}
+
+/// The addresses of the instructions in `memory` which match `opcode` in the
+/// bits set in `mask`, e.g. `0xd000` with the mask `0xf000` finds every
+/// `DXYN`. Only even addresses are checked, since that's where instructions
+/// are in almost every program. Like `validate_rom()`, this doesn't know code
+/// from data, so data which happens to match is found too.
+#[cfg(feature = "alloc")]
+#[must_use]
+pub fn find_opcode(memory: &[u8], opcode: u16, mask: u16) -> Vec<usize> {
+    memory
+        .chunks_exact(2)
+        .enumerate()
+        .filter(|(_, word)| u16::from_be_bytes([word[0], word[1]]) & mask == opcode & mask)
+        .map(|(i, _)| i * 2)
+        .collect()
+}
+
 /// Error type for `decode()`.
 ///
-/// Note that this doesn't implement the `Error` trait, for reasons specified
-/// in the readme section titled "Why not implement the `Error` trait on error
-/// types?".
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
 #[derive(Debug)]
 pub enum DecodeError {
     UnknownInstruction { inst: u16 },
+    /// The instruction is followed by an immediate word, so it must be
+    /// decoded with `decode_with_immediate()`
+    MissingImmediate { inst: u16 },
 }
 
 impl fmt::Display for DecodeError {
@@ -190,6 +450,143 @@ impl fmt::Display for DecodeError {
             DecodeError::UnknownInstruction { inst: inst_bytes } => {
                 write!(f, "unknown instruction: 0x{:04x}", inst_bytes)
             }
+            DecodeError::MissingImmediate { inst } => {
+                write!(
+                    f,
+                    "instruction 0x{:04x} must be decoded with its immediate word",
+                    inst
+                )
+            }
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        decode, decode_with_immediate, encode, has_immediate, DecodeError, Inst, INST_KIND_COUNT,
+        INST_KIND_PATTERNS,
+    };
+    #[cfg(feature = "alloc")]
+    use super::{find_opcode, validate_rom};
+
+    #[test]
+    fn test_inst_kind_patterns() {
+        let mut seen = [false; INST_KIND_COUNT];
+        for word in 0..=u16::MAX {
+            let inst = match has_immediate(word) {
+                true => decode_with_immediate(word, 0x1234),
+                false => decode(word),
+            };
+            let Ok(inst) = inst else {
+                continue;
+            };
+            let kind = inst.kind();
+            seen[kind] = true;
+            let pattern = INST_KIND_PATTERNS[kind];
+            // the decoder accepts some variations, e.g. `9XYN`
+            let word = encode(&inst);
+            for (i, expected) in pattern.chars().enumerate() {
+                let nibble = u32::from((word >> (12 - 4 * i)) & 0xf);
+                assert!(
+                    matches!(expected, 'X' | 'Y' | 'N') || expected.to_digit(16) == Some(nibble),
+                    "{word:04X} doesn't match {pattern}"
+                );
+            }
+        }
+        assert_eq!(seen, [true; INST_KIND_COUNT]);
+    }
+
+    #[test]
+    fn test_decode_load_long() {
+        assert!(has_immediate(0xf000));
+        assert!(!has_immediate(0xf001));
+        assert!(matches!(
+            decode_with_immediate(0xf000, 0x1234),
+            Ok(Inst::LoadLongIntoI { nnnn: 0x1234 })
+        ));
+        assert!(matches!(
+            decode(0xf000),
+            Err(DecodeError::MissingImmediate { inst: 0xf000 })
+        ));
+    }
+
+    #[test]
+    fn test_decode_with_immediate_single_word() {
+        assert!(matches!(
+            decode_with_immediate(0xa123, 0xffff),
+            Ok(Inst::LoadIntoI { nnn: 0x123 })
+        ));
+    }
+
+    #[test]
+    fn test_decode_audio() {
+        assert!(matches!(decode(0xf002), Ok(Inst::LoadAudioPattern)));
+        assert!(matches!(
+            decode(0xf102),
+            Err(DecodeError::UnknownInstruction { inst: 0xf102 })
+        ));
+        assert!(matches!(decode(0xf53a), Ok(Inst::SetPitch { vx: 5 })));
+    }
+
+    #[test]
+    fn test_decode_exit() {
+        assert!(matches!(decode(0x00fd), Ok(Inst::Exit)));
+        assert_eq!(encode(&Inst::Exit), 0x00fd);
+        // other 00NN instructions are still machine language subroutines
+        assert!(matches!(decode(0x00fc), Ok(Inst::Exe { nnn: 0x0fc })));
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        for inst in 0..=u16::MAX {
+            if let Ok(decoded) = decode(inst) {
+                assert_eq!(decode(encode(&decoded)).unwrap(), decoded);
+            }
+        }
+        assert_eq!(encode(&Inst::LoadLongIntoI { nnnn: 0x1234 }), 0xf000);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_validate_rom() {
+        // 00E0: clear
+        // F000 5FFF: I = 0x5FFF, whose immediate word would be bad on its own
+        // 5121: unknown
+        // D125: draw
+        // F000: missing its immediate word
+        let program = [
+            0x00, 0xe0, 0xf0, 0x00, 0x5f, 0xff, 0x51, 0x21, 0xd1, 0x25, 0xf0, 0x00,
+        ];
+
+        let errors = validate_rom(&program);
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            (0x206, DecodeError::UnknownInstruction { inst: 0x5121 })
+        ));
+        assert!(matches!(
+            errors[1],
+            (0x20a, DecodeError::MissingImmediate { inst: 0xf000 })
+        ));
+        assert!(validate_rom(&[0x00, 0xe0, 0x12]).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_find_opcode() {
+        // D125 and DAB1 are draws, 1D00 has a D which isn't the opcode, and
+        // 0D 12 has DXYN's bytes at an odd address
+        let memory = [0xd1, 0x25, 0x1d, 0x00, 0x0d, 0x12, 0xda, 0xb1, 0x00, 0xe0];
+
+        assert_eq!(find_opcode(&memory, 0xd000, 0xf000), [0, 6]);
+        assert_eq!(find_opcode(&memory, 0xd125, 0xffff), [0]);
+        assert_eq!(find_opcode(&memory, 0xd001, 0xf00f), [6]);
+        assert!(find_opcode(&memory, 0xf000, 0xf000).is_empty());
+        assert!(find_opcode(&[0xd1], 0xd000, 0xf000).is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+/// Which of the 16 CHIP-8 keys are held, for building the `[bool; 16]` which
+/// `Chip8::cycle()` takes without indexing arrays by hand, e.g. in tests and
+/// scripted playback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyboardState {
+    keys: [bool; 16],
+}
+
+impl KeyboardState {
+    /// No keys held
+    #[must_use]
+    pub fn new() -> KeyboardState {
+        KeyboardState::default()
+    }
+
+    /// Only the keys in `keys` held.
+    ///
+    /// Panics if a key is greater than `0xF`.
+    #[must_use]
+    pub fn with_keys(keys: &[u8]) -> KeyboardState {
+        let mut state = KeyboardState::new();
+        for &key in keys {
+            state.press(key);
+        }
+        state
+    }
+
+    /// Hold `key`, from `0x0` to `0xF`.
+    ///
+    /// Panics if `key` is greater than `0xF`.
+    pub fn press(&mut self, key: u8) {
+        self.keys[Self::index(key)] = true;
+    }
+
+    /// Stop holding `key`, from `0x0` to `0xF`.
+    ///
+    /// Panics if `key` is greater than `0xF`.
+    pub fn release(&mut self, key: u8) {
+        self.keys[Self::index(key)] = false;
+    }
+
+    /// Stop holding every key
+    pub fn release_all(&mut self) {
+        self.keys = [false; 16];
+    }
+
+    /// Whether `key` is held.
+    ///
+    /// Panics if `key` is greater than `0xF`.
+    #[must_use]
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.keys[Self::index(key)]
+    }
+
+    /// The state in the form `Chip8::cycle()` takes, indexed by key
+    #[must_use]
+    pub fn as_array(&self) -> &[bool; 16] {
+        &self.keys
+    }
+
+    fn index(key: u8) -> usize {
+        if key > 0xf {
+            panic!("key is greater than 0xF")
+        }
+        key as usize
+    }
+}
+
+impl From<[bool; 16]> for KeyboardState {
+    fn from(keys: [bool; 16]) -> Self {
+        KeyboardState { keys }
+    }
+}
+
+impl From<KeyboardState> for [bool; 16] {
+    fn from(state: KeyboardState) -> Self {
+        state.keys
+    }
+}
+
+/// The keys which are down in `current` but weren't in `previous`
+#[must_use]
+pub fn just_pressed(current: &[bool; 16], previous: &[bool; 16]) -> [bool; 16] {
+    let mut state = [false; 16];
+    for i in 0..16 {
+        state[i] = current[i] && !previous[i];
+    }
+    state
+}
+
+/// The keys which were down in `previous` but aren't in `current`
+#[must_use]
+pub fn just_released(current: &[bool; 16], previous: &[bool; 16]) -> [bool; 16] {
+    just_pressed(previous, current)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{just_pressed, just_released, KeyboardState};
+    use crate::cpu::Chip8;
+
+    #[test]
+    fn test_press_and_release() {
+        let mut state = KeyboardState::new();
+        assert_eq!(state.as_array(), &[false; 16]);
+
+        state.press(0x5);
+        state.press(0xf);
+        assert!(state.is_pressed(0x5) && state.is_pressed(0xf));
+        assert!(!state.is_pressed(0x0));
+        assert_eq!(state, KeyboardState::with_keys(&[0xf, 0x5]));
+
+        state.release(0x5);
+        assert!(!state.is_pressed(0x5));
+        // releasing a key which isn't held does nothing
+        state.release(0x5);
+        assert_eq!(state, KeyboardState::with_keys(&[0xf]));
+
+        state.release_all();
+        assert_eq!(state, KeyboardState::new());
+    }
+
+    #[test]
+    fn test_array_conversion() {
+        let state = KeyboardState::with_keys(&[0x0, 0xa]);
+        let mut expected = [false; 16];
+        expected[0x0] = true;
+        expected[0xa] = true;
+
+        assert_eq!(state.as_array(), &expected);
+        assert_eq!(<[bool; 16]>::from(state), expected);
+        assert_eq!(KeyboardState::from(expected), state);
+    }
+
+    #[test]
+    fn test_just_pressed() {
+        let mut previous = [false; 16];
+        let mut current = [false; 16];
+        // held in both
+        previous[0x1] = true;
+        current[0x1] = true;
+        // released
+        previous[0x2] = true;
+        // pressed
+        current[0xa] = true;
+
+        let mut expected = [false; 16];
+        expected[0xa] = true;
+        assert_eq!(just_pressed(&current, &previous), expected);
+        assert_eq!(just_pressed(&current, &current), [false; 16]);
+    }
+
+    #[test]
+    fn test_just_released() {
+        let mut previous = [false; 16];
+        let mut current = [false; 16];
+        previous[0x1] = true;
+        current[0x1] = true;
+        previous[0x2] = true;
+        current[0xa] = true;
+
+        let mut expected = [false; 16];
+        expected[0x2] = true;
+        assert_eq!(just_released(&current, &previous), expected);
+        assert_eq!(just_released(&[false; 16], &[true; 16]), [true; 16]);
+    }
+
+    #[test]
+    fn test_cycle_with_keyboard_state() {
+        // EA9E: skip the next instruction if the key in VA is held
+        // 6001: V0 = 1
+        let program = [0xea, 0x9e, 0x60, 0x01];
+        let held = KeyboardState::with_keys(&[0x0]);
+
+        let mut chip8 = Chip8::new(&program);
+        chip8.cycle(held.as_array(), held.as_array()).unwrap();
+        assert_eq!(chip8.pc(), 0x204);
+
+        let mut chip8 = Chip8::new(&program);
+        let released = KeyboardState::new();
+        chip8
+            .cycle(released.as_array(), released.as_array())
+            .unwrap();
+        assert_eq!(chip8.pc(), 0x202);
+    }
+
+    #[test]
+    #[should_panic(expected = "key is greater than 0xF")]
+    fn test_press_invalid_key() {
+        KeyboardState::new().press(0x10);
+    }
+}
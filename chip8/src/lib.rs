@@ -1,11 +1,28 @@
 #![no_std]
-#[forbid(unsafe_code)]
-#[deny(clippy::all)]
+#![forbid(unsafe_code)]
+#![deny(clippy::all)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+pub mod assembler;
 pub mod instruction;
 pub mod cpu;
+pub mod keyboard;
 pub mod memory;
+pub mod quirks;
+#[cfg(feature = "alloc")]
+pub mod recording;
+pub mod rng;
 pub mod screen;
+#[cfg(feature = "alloc")]
+pub mod snapshot;
+pub mod timing;
+#[cfg(feature = "trace")]
+pub mod trace;
 
 pub fn add(left: f32, right: f32) -> f32 {
     left + right
@@ -1,13 +1,24 @@
+use core::fmt;
+
 /// The amount of memory available to the CHIP-8
 pub const CHIP8_MEMORY_SIZE_BYTES: usize = 4096;
 
+/// The amount of memory available to XO-CHIP programs
+pub const XO_CHIP_MEMORY_SIZE_BYTES: usize = 65536;
+
 /// The offset from the start of memory of the start of the default hex digit
 /// sprites
 pub const SPRITES_OFFSET_BYTES: usize = 0x0;
 
+/// The size of each hex digit sprite
+pub const DIGIT_SPRITE_SIZE_BYTES: usize = 5;
+
+/// The size of a font of all 16 hex digit sprites
+pub const FONT_SIZE_BYTES: usize = DIGIT_SPRITE_SIZE_BYTES * 16;
+
 // See here for more info:
 // devernay.free.fr/hacks/chip8/C8TECH10.HTM#2.4
-const DEFAULT_SPRITES: [u8; 5 * 16] = [
+const DEFAULT_SPRITES: [u8; FONT_SIZE_BYTES] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -29,29 +40,170 @@ const DEFAULT_SPRITES: [u8; 5 * 16] = [
 /// The offset from the start of memory that the program bytes should be loaded
 pub const PROGRAM_OFFSET_BYTES: usize = 0x200;
 
+/// Where the COSMAC VIP kept the 256 bytes of display memory it drew the
+/// screen from, see `Chip8::set_mirror_display()`
+pub const DISPLAY_OFFSET_BYTES: usize = 0xf00;
+
+/// What a `MemoryRegion` is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// The hex digit sprites
+    Font,
+    /// The rest of the memory before the program, which the original
+    /// interpreter used
+    Reserved,
+    /// The loaded program
+    Program,
+    /// The unused memory after the program
+    Free,
+}
+
+impl RegionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionKind::Font => "Font",
+            RegionKind::Reserved => "Reserved",
+            RegionKind::Program => "Program",
+            RegionKind::Free => "Free",
+        }
+    }
+}
+
+/// A labelled range of memory, see `memory_map()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub kind: RegionKind,
+    /// The address of the first byte
+    pub start: usize,
+    /// The address after the last byte, so the region is empty if this is
+    /// `start`
+    pub end: usize,
+}
+
+impl MemoryRegion {
+    /// Whether `address` is in the region
+    pub fn contains(&self, address: usize) -> bool {
+        (self.start..self.end).contains(&address)
+    }
+}
+
+/// Describe the layout of a memory of `size` bytes with a program of
+/// `program_len` bytes loaded, as one region of each `RegionKind` in the order
+/// they're declared. The regions cover the whole memory without overlapping.
+///
+/// The program may use `Free` memory for its own data, and may change what's
+/// in the other regions while it runs.
+#[must_use]
+pub fn memory_map(size: usize, program_len: usize) -> [MemoryRegion; 4] {
+    let font_end = SPRITES_OFFSET_BYTES + FONT_SIZE_BYTES;
+    let program_end = (PROGRAM_OFFSET_BYTES + program_len).min(size);
+    [
+        MemoryRegion {
+            kind: RegionKind::Font,
+            start: SPRITES_OFFSET_BYTES,
+            end: font_end,
+        },
+        MemoryRegion {
+            kind: RegionKind::Reserved,
+            start: font_end,
+            end: PROGRAM_OFFSET_BYTES,
+        },
+        MemoryRegion {
+            kind: RegionKind::Program,
+            start: PROGRAM_OFFSET_BYTES,
+            end: program_end,
+        },
+        MemoryRegion {
+            kind: RegionKind::Free,
+            start: program_end,
+            end: size,
+        },
+    ]
+}
+
 /// Represents the memory (RAM) of the CHIP-8
-pub struct Chip8Memory([u8; CHIP8_MEMORY_SIZE_BYTES]);
+#[derive(Clone, PartialEq, Eq)]
+pub struct Chip8Memory {
+    /// Exactly `size` bytes with the `alloc` feature, so that a 4KB machine
+    /// is cheap to clone
+    #[cfg(feature = "alloc")]
+    bytes: alloc::vec::Vec<u8>,
+    /// Large enough for the extended XO-CHIP memory without an allocator, only
+    /// the first `size` bytes are addressable
+    #[cfg(not(feature = "alloc"))]
+    bytes: [u8; XO_CHIP_MEMORY_SIZE_BYTES],
+    size: usize,
+}
 
 impl Chip8Memory {
     /// Create a CHIP-8 memory loaded with the default hex digit sprites and
     /// the given program
     pub fn new(program: &[u8]) -> Chip8Memory {
-        let mut memory = Self([0; CHIP8_MEMORY_SIZE_BYTES]);
+        Self::new_with_size(program, CHIP8_MEMORY_SIZE_BYTES)
+    }
+
+    /// Create a memory of `size` bytes loaded with the default hex digit
+    /// sprites and the given program. `size` must not be greater than
+    /// `XO_CHIP_MEMORY_SIZE_BYTES`.
+    pub fn new_with_size(program: &[u8], size: usize) -> Chip8Memory {
+        if size > XO_CHIP_MEMORY_SIZE_BYTES {
+            panic!("memory size is greater than the maximum")
+        }
+
+        let mut memory = Self {
+            #[cfg(feature = "alloc")]
+            bytes: alloc::vec![0; size],
+            #[cfg(not(feature = "alloc"))]
+            bytes: [0; XO_CHIP_MEMORY_SIZE_BYTES],
+            size,
+        };
         memory.load_bytes(SPRITES_OFFSET_BYTES, &DEFAULT_SPRITES);
         memory.load_bytes(PROGRAM_OFFSET_BYTES, program);
         memory
     }
-    
+
+    /// Reset the memory to contain only the default hex digit sprites and the
+    /// given program. Memory is left unchanged if the program doesn't fit.
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        let max_len = self.size - PROGRAM_OFFSET_BYTES;
+        if program.len() > max_len {
+            return Err(LoadError::ProgramTooLong {
+                len: program.len(),
+                max_len,
+            });
+        }
+
+        self.bytes.fill(0);
+        self.load_bytes(SPRITES_OFFSET_BYTES, &DEFAULT_SPRITES);
+        self.load_bytes(PROGRAM_OFFSET_BYTES, program);
+        Ok(())
+    }
+
+    /// The number of addressable bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
     pub fn get(&self, index: usize) -> u8 {
-        *self.0.get(index).unwrap()
+        *self.bytes[..self.size].get(index).unwrap()
     }
-    
+
     pub fn set(&mut self, index: usize, value: u8) {
-        *self.0.get_mut(index).unwrap() = value;
+        *self.bytes[..self.size].get_mut(index).unwrap() = value;
+    }
+
+    pub fn get_bytes(&self, index: usize, len: usize) -> &[u8] {
+        &self.bytes[..self.size][index..index + len]
+    }
+
+    pub fn set_bytes(&mut self, index: usize, bytes: &[u8]) {
+        self.bytes[..self.size][index..index + bytes.len()].copy_from_slice(bytes);
     }
-    
-    pub fn get_bytes(&self, index: usize, len: usize) -> &[u8] { 
-        &self.0[index..index + len]
+
+    /// Replace the hex digit sprites with `font`, which holds the sprites for
+    /// 0 to F in order. `load_program()` restores the default sprites.
+    pub fn set_font(&mut self, font: &[u8; FONT_SIZE_BYTES]) {
+        self.load_bytes(SPRITES_OFFSET_BYTES, font);
     }
 }
 
@@ -61,21 +213,133 @@ impl Chip8Memory {
         offset: usize,
         bytes: &[u8],
     ) {
-        if offset >= CHIP8_MEMORY_SIZE_BYTES {
+        if offset >= self.size {
             panic!("offset is greater than memory size")
         }
 
-        if bytes.len() > CHIP8_MEMORY_SIZE_BYTES - offset {
+        if bytes.len() > self.size - offset {
             panic!("program is too long");
         }
 
-        for (i, byte) in bytes.iter().enumerate() {
-            self.0[i + 0x200] = *byte;
+        self.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+/// Error type for loading a program into memory.
+///
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
+#[derive(Debug)]
+pub enum LoadError {
+    /// The program is longer than the memory available for it
+    ProgramTooLong { len: usize, max_len: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::ProgramTooLong { len, max_len } => {
+                write!(
+                    f,
+                    "program is {len} bytes long but only {max_len} bytes are available"
+                )
+            }
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for LoadError {}
+
 #[cfg(test)]
 mod test {
-    // TODO: tests for Chip8Memory::load_bytes_into_memory
-}
\ No newline at end of file
+    use super::{
+        memory_map, Chip8Memory, LoadError, RegionKind, CHIP8_MEMORY_SIZE_BYTES, DEFAULT_SPRITES,
+        PROGRAM_OFFSET_BYTES, SPRITES_OFFSET_BYTES, XO_CHIP_MEMORY_SIZE_BYTES,
+    };
+
+    #[test]
+    fn test_new_loads_sprites_and_program() {
+        let memory = Chip8Memory::new(&[0x12, 0x34]);
+
+        assert_eq!(
+            memory.get_bytes(SPRITES_OFFSET_BYTES, DEFAULT_SPRITES.len()),
+            DEFAULT_SPRITES
+        );
+        assert_eq!(memory.get_bytes(PROGRAM_OFFSET_BYTES, 3), [0x12, 0x34, 0]);
+    }
+
+    #[test]
+    fn test_load_program_replaces_program() {
+        let mut memory = Chip8Memory::new(&[0x12, 0x34, 0x56]);
+        memory.set(0x300, 0xff);
+
+        memory.load_program(&[0xab]).unwrap();
+
+        assert_eq!(memory.get_bytes(PROGRAM_OFFSET_BYTES, 3), [0xab, 0, 0]);
+        assert_eq!(memory.get(0x300), 0);
+        assert_eq!(
+            memory.get_bytes(SPRITES_OFFSET_BYTES, DEFAULT_SPRITES.len()),
+            DEFAULT_SPRITES
+        );
+    }
+
+    #[test]
+    fn test_load_program_too_long() {
+        let mut memory = Chip8Memory::new(&[0x12]);
+        let program = [0; CHIP8_MEMORY_SIZE_BYTES - PROGRAM_OFFSET_BYTES + 1];
+
+        let result = memory.load_program(&program);
+
+        assert!(matches!(
+            result,
+            Err(LoadError::ProgramTooLong {
+                len: 3585,
+                max_len: 3584
+            })
+        ));
+        // memory is unchanged
+        assert_eq!(memory.get(PROGRAM_OFFSET_BYTES), 0x12);
+    }
+
+    #[test]
+    fn test_memory_map_covers_memory() {
+        for (size, program_len) in [
+            (CHIP8_MEMORY_SIZE_BYTES, 0),
+            (CHIP8_MEMORY_SIZE_BYTES, 0x123),
+            (
+                CHIP8_MEMORY_SIZE_BYTES,
+                CHIP8_MEMORY_SIZE_BYTES - PROGRAM_OFFSET_BYTES,
+            ),
+            (XO_CHIP_MEMORY_SIZE_BYTES, 0x123),
+        ] {
+            let map = memory_map(size, program_len);
+
+            // each region starts where the last ended, so there are no gaps
+            // or overlaps
+            let mut end = 0;
+            for region in map {
+                assert_eq!(region.start, end, "{region:?}");
+                assert!(region.end >= region.start, "{region:?}");
+                end = region.end;
+            }
+            assert_eq!(end, size);
+        }
+
+        let map = memory_map(CHIP8_MEMORY_SIZE_BYTES, 0x10);
+        assert_eq!(
+            map.map(|region| region.kind),
+            [
+                RegionKind::Font,
+                RegionKind::Reserved,
+                RegionKind::Program,
+                RegionKind::Free
+            ]
+        );
+        assert_eq!(map[0].end, 0x50);
+        assert!(map[2].contains(0x20f));
+        assert!(!map[2].contains(0x210));
+        assert!(map[3].contains(0x210));
+    }
+}
@@ -0,0 +1,230 @@
+/// Behaviors which differ between CHIP-8 interpreters.
+///
+/// Programs are sometimes written against a particular interpreter and rely
+/// on its behavior, so these can be toggled to run them correctly. The
+/// defaults match the original behavior of this emulator, which is
+/// `Quirks::vip()` except that the logical instructions leave `VF` alone and
+/// sprites are drawn without waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// `FX0A` (`WaitForKey`) completes when a key is released rather than when
+    /// it is pressed. The COSMAC VIP waits for the key to be released.
+    pub wait_key_on_release: bool,
+    /// XO-CHIP: memory is extended to 64KB, which can be addressed by loading
+    /// a 16-bit address into `I` with `F000 NNNN`, and the audio instructions
+    /// `F002` and `FX3A` are available. Without it, these are unknown
+    /// instructions.
+    pub extended_memory: bool,
+    /// `FX1E` (`AddToI`) sets `VF` to `01` when the add takes `I` from
+    /// `0x0FFF` or below to past it, and `00` otherwise. This is undocumented behavior of the Amiga interpreter
+    /// which Spacefight 2091! relies on.
+    pub add_to_i_sets_vf: bool,
+    /// `FX33` (`StoreBCD`) and `FX55` (`StoreRegisters`) fail with
+    /// `ExecuteError::ProtectedMemoryWrite` instead of writing below
+    /// `PROGRAM_OFFSET_BYTES`, where the font is. No interpreter does this, but
+    /// it helps catch bugs in programs.
+    pub protect_reserved_memory: bool,
+    /// `8XY6` and `8XYE` (`ShiftRight` and `ShiftLeft`) shift `VY` and store
+    /// the result in `VX`, like the COSMAC VIP. Without it, `VX` is shifted in
+    /// place and `VY` is ignored, like CHIP-48 and SUPER-CHIP.
+    pub shift_uses_vy: bool,
+    /// `FX55` and `FX65` (`StoreRegisters` and `LoadRegisters`) leave `I`
+    /// pointing after the last register they accessed, like the COSMAC VIP.
+    /// Without it, `I` is unchanged, like SUPER-CHIP 1.1.
+    pub load_store_increments_i: bool,
+    /// `BNNN` (`JumpAdd`) jumps to `XNN` plus `VX`, where `X` is the highest
+    /// nibble of `NNN`, like CHIP-48 and SUPER-CHIP. Without it, it jumps to
+    /// `NNN` plus `V0`, like the COSMAC VIP.
+    pub jump_with_vx: bool,
+    /// `8XY1`, `8XY2` and `8XY3` (`Or`, `And` and `Xor`) set `VF` to `00`, as
+    /// a side effect of how the COSMAC VIP implemented them.
+    pub logic_resets_vf: bool,
+    /// `DXYN` (`DrawSprite`) draws at most one sprite per 60Hz frame. Once a
+    /// sprite has been drawn, the next one waits until the timers tick, like
+    /// the COSMAC VIP waiting for the vertical blank interrupt.
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            wait_key_on_release: true,
+            extended_memory: false,
+            add_to_i_sets_vf: false,
+            protect_reserved_memory: false,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_with_vx: false,
+            logic_resets_vf: false,
+            display_wait: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// The COSMAC VIP's original CHIP-8 interpreter:
+    /// - `wait_key_on_release`: `true`
+    /// - `extended_memory`: `false`
+    /// - `add_to_i_sets_vf`: `false`
+    /// - `protect_reserved_memory`: `false`
+    /// - `shift_uses_vy`: `true`
+    /// - `load_store_increments_i`: `true`
+    /// - `jump_with_vx`: `false`
+    /// - `logic_resets_vf`: `true`
+    /// - `display_wait`: `true`
+    #[must_use]
+    pub fn vip() -> Quirks {
+        Quirks {
+            wait_key_on_release: true,
+            extended_memory: false,
+            add_to_i_sets_vf: false,
+            protect_reserved_memory: false,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_with_vx: false,
+            logic_resets_vf: true,
+            display_wait: true,
+        }
+    }
+
+    /// CHIP-48 on the HP 48 calculators. It increments `I` by `X` rather than
+    /// `X + 1` in `FX55` and `FX65`, which `load_store_increments_i` is the
+    /// closest match for:
+    /// - `wait_key_on_release`: `false`
+    /// - `extended_memory`: `false`
+    /// - `add_to_i_sets_vf`: `false`
+    /// - `protect_reserved_memory`: `false`
+    /// - `shift_uses_vy`: `false`
+    /// - `load_store_increments_i`: `true`
+    /// - `jump_with_vx`: `true`
+    /// - `logic_resets_vf`: `false`
+    /// - `display_wait`: `false`
+    #[must_use]
+    pub fn chip48() -> Quirks {
+        Quirks {
+            wait_key_on_release: false,
+            extended_memory: false,
+            add_to_i_sets_vf: false,
+            protect_reserved_memory: false,
+            shift_uses_vy: false,
+            load_store_increments_i: true,
+            jump_with_vx: true,
+            logic_resets_vf: false,
+            display_wait: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP 48 calculators:
+    /// - `wait_key_on_release`: `false`
+    /// - `extended_memory`: `false`
+    /// - `add_to_i_sets_vf`: `false`
+    /// - `protect_reserved_memory`: `false`
+    /// - `shift_uses_vy`: `false`
+    /// - `load_store_increments_i`: `false`
+    /// - `jump_with_vx`: `true`
+    /// - `logic_resets_vf`: `false`
+    /// - `display_wait`: `false`
+    #[must_use]
+    pub fn schip() -> Quirks {
+        Quirks {
+            wait_key_on_release: false,
+            extended_memory: false,
+            add_to_i_sets_vf: false,
+            protect_reserved_memory: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_with_vx: true,
+            logic_resets_vf: false,
+            display_wait: false,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo:
+    /// - `wait_key_on_release`: `true`
+    /// - `extended_memory`: `true`
+    /// - `add_to_i_sets_vf`: `false`
+    /// - `protect_reserved_memory`: `false`
+    /// - `shift_uses_vy`: `true`
+    /// - `load_store_increments_i`: `true`
+    /// - `jump_with_vx`: `false`
+    /// - `logic_resets_vf`: `false`
+    /// - `display_wait`: `false`
+    #[must_use]
+    pub fn xochip() -> Quirks {
+        Quirks {
+            wait_key_on_release: true,
+            extended_memory: true,
+            add_to_i_sets_vf: false,
+            protect_reserved_memory: false,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_with_vx: false,
+            logic_resets_vf: false,
+            display_wait: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Quirks;
+
+    #[test]
+    fn test_default_is_vip_without_logic_vf_or_display_wait() {
+        assert_eq!(
+            Quirks::default(),
+            Quirks {
+                logic_resets_vf: false,
+                display_wait: false,
+                ..Quirks::vip()
+            }
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let vip = Quirks::vip();
+        assert!(vip.wait_key_on_release);
+        assert!(!vip.extended_memory);
+        assert!(!vip.add_to_i_sets_vf);
+        assert!(vip.shift_uses_vy);
+        assert!(vip.load_store_increments_i);
+        assert!(!vip.jump_with_vx);
+        assert!(vip.logic_resets_vf);
+        assert!(vip.display_wait);
+
+        let chip48 = Quirks::chip48();
+        assert!(!chip48.wait_key_on_release);
+        assert!(!chip48.extended_memory);
+        assert!(!chip48.add_to_i_sets_vf);
+        assert!(!chip48.shift_uses_vy);
+        assert!(chip48.load_store_increments_i);
+        assert!(chip48.jump_with_vx);
+        assert!(!chip48.logic_resets_vf);
+        assert!(!chip48.display_wait);
+
+        let schip = Quirks::schip();
+        assert!(!schip.wait_key_on_release);
+        assert!(!schip.extended_memory);
+        assert!(!schip.add_to_i_sets_vf);
+        assert!(!schip.shift_uses_vy);
+        assert!(!schip.load_store_increments_i);
+        assert!(schip.jump_with_vx);
+        assert!(!schip.logic_resets_vf);
+        assert!(!schip.display_wait);
+
+        let xochip = Quirks::xochip();
+        assert!(xochip.wait_key_on_release);
+        assert!(xochip.extended_memory);
+        assert!(!xochip.add_to_i_sets_vf);
+        assert!(xochip.shift_uses_vy);
+        assert!(xochip.load_store_increments_i);
+        assert!(!xochip.jump_with_vx);
+        assert!(!xochip.logic_resets_vf);
+        assert!(!xochip.display_wait);
+
+        for quirks in [vip, chip48, schip, xochip] {
+            assert!(!quirks.protect_reserved_memory);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use alloc::vec::Vec;
+
+use crate::cpu::{Chip8, CycleError};
+
+/// The keyboard state of every frame of a run of a program, along with
+/// everything else needed to repeat the run exactly with `replay()`.
+///
+/// Each frame is `cycles_per_frame` cycles followed by a timer update, as in
+/// `Chip8::run_frames()`. Every cycle in a frame sees the keyboard change from
+/// the previous frame's state to this frame's, and the state before the first
+/// frame is no keys held.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    /// A hash of the program which was recorded, to check the right program
+    /// is loaded before replaying. How it's calculated is up to the caller.
+    pub rom_hash: u64,
+    /// The seed of the random number generator, see `Chip8::seed_rng()`
+    pub seed: u64,
+    pub cycles_per_frame: u32,
+    /// The keys held during each frame
+    pub frames: Vec<[bool; 16]>,
+}
+
+impl Recording {
+    /// Start a recording with no frames. `chip8` should be seeded with `seed`
+    /// and have its program freshly loaded.
+    #[must_use]
+    pub fn new(rom_hash: u64, seed: u64, cycles_per_frame: u32) -> Recording {
+        Recording {
+            rom_hash,
+            seed,
+            cycles_per_frame,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push_frame(&mut self, keyboard_state: [bool; 16]) {
+        self.frames.push(keyboard_state);
+    }
+
+    /// Run every recorded frame on `chip8`, which should have the recorded
+    /// program freshly loaded with the same quirks it was recorded with. The
+    /// random number generator is seeded with `seed` first.
+    ///
+    /// Breakpoints don't stop the replay.
+    pub fn replay(&self, chip8: &mut Chip8) -> Result<(), CycleError> {
+        chip8.seed_rng(self.seed);
+        let mut previous_keyboard_state = [false; 16];
+        for keyboard_state in &self.frames {
+            for _ in 0..self.cycles_per_frame {
+                chip8.cycle(keyboard_state, &previous_keyboard_state)?;
+            }
+            chip8.update_timers();
+            previous_keyboard_state = *keyboard_state;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec;
+
+    use super::Recording;
+    use crate::cpu::Chip8;
+
+    /// Draws the digit of each key pressed at a random position
+    // F00A: wait for a key and store it in V0
+    // F029: I = the sprite for V0
+    // C13F: V1 = random & 0x3f
+    // C21F: V2 = random & 0x1f
+    // D125: draw 5 rows at V1, V2
+    // 1200: loop
+    const PROGRAM: [u8; 12] = [
+        0xf0, 0x0a, 0xf0, 0x29, 0xc1, 0x3f, 0xc2, 0x1f, 0xd1, 0x25, 0x12, 0x00,
+    ];
+
+    fn key_state(key: usize) -> [bool; 16] {
+        let mut state = [false; 16];
+        state[key] = true;
+        state
+    }
+
+    /// Play `keys` on a CHIP-8 in the same way as a front-end would, recording
+    /// it as it goes
+    fn record(seed: u64, keys: &[[bool; 16]]) -> (Chip8, Recording) {
+        let mut chip8 = Chip8::new(&PROGRAM);
+        chip8.seed_rng(seed);
+        let mut recording = Recording::new(1, seed, 10);
+        let mut previous_keyboard_state = [false; 16];
+        for keyboard_state in keys {
+            for _ in 0..recording.cycles_per_frame {
+                chip8
+                    .cycle(keyboard_state, &previous_keyboard_state)
+                    .unwrap();
+            }
+            chip8.update_timers();
+            recording.push_frame(*keyboard_state);
+            previous_keyboard_state = *keyboard_state;
+        }
+        (chip8, recording)
+    }
+
+    #[test]
+    fn test_replay_matches_recording() {
+        let mut keys = vec![[false; 16]; 3];
+        for key in [0x1, 0x7, 0xa] {
+            keys.extend([key_state(key), key_state(key), [false; 16]]);
+        }
+        let (recorded, recording) = record(1234, &keys);
+
+        let mut replayed = Chip8::new(&PROGRAM);
+        recording.replay(&mut replayed).unwrap();
+
+        assert_eq!(recording.frames.len(), 12);
+        assert!(replayed.get_screen() == recorded.get_screen());
+        assert!(replayed == recorded);
+    }
+
+    #[test]
+    fn test_replay_uses_seed() {
+        let keys = [key_state(0x3), [false; 16]];
+        let (_, recording) = record(1, &keys);
+        let (other_seed, _) = record(2, &keys);
+
+        let mut replayed = Chip8::new(&PROGRAM);
+        recording.replay(&mut replayed).unwrap();
+
+        assert!(replayed.get_screen() != other_seed.get_screen());
+    }
+}
@@ -0,0 +1,98 @@
+/// The random number generator used by `CXNN` (`LoadRandom`).
+///
+/// This is xorshift64*, which is fast and small enough for `no_std`, and is
+/// seedable so that runs of a program can be repeated exactly. It isn't
+/// suitable for anything needing unpredictable numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// The seed used when none is given
+    pub const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+    /// Create a generator which will produce the same numbers every time it's
+    /// given the same `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Rng {
+        // xorshift gets stuck at 0
+        let state = if seed == 0 { Self::DEFAULT_SEED } else { seed };
+        Rng { state }
+    }
+
+    /// Recreate a generator from its `state()`, so it continues from where it
+    /// was
+    #[must_use]
+    pub fn from_state(state: u64) -> Rng {
+        Self::new(state)
+    }
+
+    /// The current state, see `from_state()`
+    #[must_use]
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        // the high bits are the most random
+        (self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 56) as u8
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SEED)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rng;
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+
+        let a: [u8; 16] = core::array::from_fn(|_| a.next_u8());
+        let b: [u8; 16] = core::array::from_fn(|_| b.next_u8());
+        let c: [u8; 16] = core::array::from_fn(|_| c.next_u8());
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_zero_seed() {
+        let mut rng = Rng::new(0);
+
+        assert_ne!(rng.state(), 0);
+        let numbers: [u8; 16] = core::array::from_fn(|_| rng.next_u8());
+        assert!(numbers.iter().any(|&n| n != numbers[0]));
+    }
+
+    #[test]
+    fn test_from_state_continues() {
+        let mut rng = Rng::new(7);
+        rng.next_u8();
+        let mut restored = Rng::from_state(rng.state());
+
+        assert_eq!(restored.next_u8(), rng.next_u8());
+    }
+
+    #[test]
+    fn test_covers_all_values() {
+        let mut rng = Rng::default();
+        let mut seen = [false; 256];
+        for _ in 0..10_000 {
+            seen[rng.next_u8() as usize] = true;
+        }
+
+        assert!(seen.iter().all(|&seen| seen));
+    }
+}
@@ -1,29 +1,207 @@
+use core::fmt;
+
 /// The width of the CHIP-8 screen
 pub const SCREEN_WIDTH_PIXELS: usize = 64;
 /// The height of the CHIP-8 screen
 pub const SCREEN_HEIGHT_PIXELS: usize = 32;
+/// The width of the SUPER-CHIP high-res screen
+pub const HIGH_RES_SCREEN_WIDTH_PIXELS: usize = 128;
+/// The height of the SUPER-CHIP high-res screen
+pub const HIGH_RES_SCREEN_HEIGHT_PIXELS: usize = 64;
+/// The number of bytes needed to store one plane with one bit per pixel
+pub const PACKED_SCREEN_SIZE_BYTES: usize = SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS / 8;
+/// The number of bytes needed to store the screen with one byte per pixel
+pub const GRAYSCALE_SCREEN_SIZE_BYTES: usize = SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS;
+/// The number of bit-planes of the screen. The original CHIP-8 only has one,
+/// XO-CHIP adds a second for four colors.
+pub const PLANE_COUNT: usize = 2;
+
+/// The result of drawing a sprite
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrawResult {
+    /// Whether any on pixel was turned off
+    pub collision: bool,
+    /// The number of sprite rows in which an on pixel was turned off
+    pub collided_rows: u8,
+    /// The number of sprite rows which weren't drawn because they were past
+    /// the bottom of the screen
+    pub clipped_rows: u8,
+}
+
+impl DrawResult {
+    /// The value `VF` is set to after drawing. In SUPER-CHIP high-res mode
+    /// this is the number of rows which collided or were clipped, otherwise
+    /// it's `1` if there was a collision and `0` if not.
+    #[must_use]
+    pub fn vf(&self, high_res: bool) -> u8 {
+        if high_res {
+            self.collided_rows + self.clipped_rows
+        } else {
+            self.collision as u8
+        }
+    }
+}
 
-/// Represents the state of a CHIP-8 screen.
-pub struct Chip8Screen {
-    screen: [bool; SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS],
+/// Represents the state of a CHIP-8 screen which is `W` pixels wide and `H`
+/// pixels high. The size defaults to the original 64x32, see `LowResScreen`
+/// and `HighResScreen`.
+///
+/// Screens of every size share the same drawing code. `W` must be at most 128
+/// so each row fits in a `u128`, and `H` at most 256 so coordinates fit in a
+/// `u8`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Chip8Screen<const W: usize = SCREEN_WIDTH_PIXELS, const H: usize = SCREEN_HEIGHT_PIXELS>
+{
+    /// Each row of each plane as a bitmask, where bit N is the pixel N from
+    /// the left, so sprites are drawn a whole row at a time
+    planes: [[u128; H]; PLANE_COUNT],
+    /// Bitmask of the planes which drawing and clearing operate on, where the
+    /// least significant bit is the first plane
+    selected_planes: u8,
 }
 
-impl Default for Chip8Screen {
+/// The original 64x32 CHIP-8 screen
+pub type LowResScreen = Chip8Screen<SCREEN_WIDTH_PIXELS, SCREEN_HEIGHT_PIXELS>;
+/// The SUPER-CHIP 128x64 high-res screen
+pub type HighResScreen = Chip8Screen<HIGH_RES_SCREEN_WIDTH_PIXELS, HIGH_RES_SCREEN_HEIGHT_PIXELS>;
+
+impl<const W: usize, const H: usize> Default for Chip8Screen<W, H> {
     fn default() -> Self {
+        let () = Self::SIZE_IS_VALID;
         Self {
-            screen: [false; SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS],
+            planes: [[0; H]; PLANE_COUNT],
+            selected_planes: 0b01,
         }
     }
 }
 
 impl Chip8Screen {
+    /// Create a low-res screen. Screens of other sizes are created with
+    /// `default()`, e.g. `HighResScreen::default()`.
     #[must_use]
     pub fn new() -> Chip8Screen {
         Self::default()
     }
 
+    /// The first plane packed into one bit per pixel, row by row from the top,
+    /// with the leftmost pixel of each byte in the most significant bit
+    #[must_use]
+    pub fn to_packed_bytes(&self) -> [u8; PACKED_SCREEN_SIZE_BYTES] {
+        self.plane_to_packed_bytes(0)
+    }
+
+    /// The given plane packed in the same format as `to_packed_bytes()`
+    #[must_use]
+    pub fn plane_to_packed_bytes(&self, plane: usize) -> [u8; PACKED_SCREEN_SIZE_BYTES] {
+        let mut bytes = [0; PACKED_SCREEN_SIZE_BYTES];
+        self.pack_plane(plane, &mut bytes);
+        bytes
+    }
+
+    /// One byte per pixel, row by row from the top, which is `on` for pixels
+    /// which are on in any plane and `off` for the rest
+    #[must_use]
+    pub fn to_grayscale(&self, on: u8, off: u8) -> [u8; GRAYSCALE_SCREEN_SIZE_BYTES] {
+        let mut bytes = [off; GRAYSCALE_SCREEN_SIZE_BYTES];
+        for (y, row) in bytes.chunks_exact_mut(SCREEN_WIDTH_PIXELS).enumerate() {
+            for (x, byte) in row.iter_mut().enumerate() {
+                if self.get_pixel_planes(x as u8, y as u8) != 0 {
+                    *byte = on;
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Replace the given plane with one packed by `plane_to_packed_bytes()`
+    pub fn set_plane_from_packed_bytes(
+        &mut self,
+        plane: usize,
+        bytes: &[u8; PACKED_SCREEN_SIZE_BYTES],
+    ) {
+        self.set_plane_from_packed_slice(plane, bytes);
+    }
+
+    /// Replace the first plane with one packed by `to_packed_bytes()`, such
+    /// as a pre-rendered frame. The other planes are left as they are.
+    pub fn set_from_packed_bytes(&mut self, bytes: &[u8]) -> Result<(), PackedScreenError> {
+        let bytes: &[u8; PACKED_SCREEN_SIZE_BYTES] = bytes
+            .try_into()
+            .map_err(|_| PackedScreenError::InvalidLength { len: bytes.len() })?;
+        self.set_plane_from_packed_bytes(0, bytes);
+        Ok(())
+    }
+}
+
+impl<const W: usize, const H: usize> Chip8Screen<W, H> {
+    /// Fails to compile if the screen is too big, see `Chip8Screen`
+    const SIZE_IS_VALID: () = assert!(W <= 128 && H <= 256, "the screen is too big");
+
+    /// The bits of a row which are on the screen
+    const ROW_MASK: u128 = if W == 128 { u128::MAX } else { (1 << W) - 1 };
+
+    /// The size of a plane packed by `plane_to_packed_vec()`
+    pub const PACKED_SIZE_BYTES: usize = W * H / 8;
+
+    /// The width of the screen in pixels
+    #[must_use]
+    pub fn width(&self) -> usize {
+        W
+    }
+
+    /// The height of the screen in pixels
+    #[must_use]
+    pub fn height(&self) -> usize {
+        H
+    }
+
+    /// Clear the selected planes
     pub fn clear(&mut self) {
-        self.screen = [false; SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS];
+        for plane in 0..PLANE_COUNT {
+            if self.is_plane_selected(plane) {
+                self.planes[plane] = [0; H];
+            }
+        }
+    }
+
+    /// Turn every pixel of the selected planes on if `value` is true, or off
+    /// like `clear()` if it's false
+    pub fn fill(&mut self, value: bool) {
+        let row = if value { Self::ROW_MASK } else { 0 };
+        for plane in 0..PLANE_COUNT {
+            if self.is_plane_selected(plane) {
+                self.planes[plane] = [row; H];
+            }
+        }
+    }
+
+    /// Flip every pixel of the selected planes
+    pub fn invert(&mut self) {
+        for plane in 0..PLANE_COUNT {
+            if self.is_plane_selected(plane) {
+                for row in &mut self.planes[plane] {
+                    *row ^= Self::ROW_MASK;
+                }
+            }
+        }
+    }
+
+    /// Select which planes drawing and clearing operate on, where `planes` is
+    /// a bitmask and the least significant bit is the first plane. Bits for
+    /// planes which don't exist are ignored.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & ((1 << PLANE_COUNT) - 1);
+    }
+
+    #[must_use]
+    pub fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    /// The number of planes which are selected
+    #[must_use]
+    pub fn selected_plane_count(&self) -> usize {
+        self.selected_planes.count_ones() as usize
     }
 
     /// Draw a sprite to the screen, where `sprite` is an array of pixels for an
@@ -34,76 +212,424 @@ impl Chip8Screen {
     /// See [CHIP‐8 Technical Reference](https://github.com/mattmikolay/chip-8/wiki/CHIP%E2%80%908-Technical-Reference#graphics)
     /// by Matthew Mikolay for more info.
     ///
-    /// The length of `sprite` must be less than 8.
+    /// `DXYN` draws sprites of up to 15 rows, but any height up to the height
+    /// of the screen can be drawn.
     ///
     /// `x` and `y` coordinates will be wrapped modulo the size of the screen in
-    /// their respective directions.
+    /// their respective directions. Only the starting coordinates wrap: the
+    /// parts of the sprite past the right and bottom edges are clipped rather
+    /// than drawn on the other side. This is what the COSMAC VIP does, and
+    /// what the quirks test ROMs expect of interpreters which clip sprites, so
+    /// a starting coordinate past the edge from a large `VX` or `VY` never
+    /// clips the whole sprite.
+    ///
+    /// When more than one plane is selected, `sprite` contains the data for
+    /// each selected plane one after the other, starting with the first plane,
+    /// so N is `sprite.len()` divided by the number of selected planes. A
+    /// collision on any plane is reported as a collision.
     #[must_use]
     pub fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
-        if sprite.is_empty() {
-            // no pixels to draw, can't be any collisions
-            return false;
+        self.draw_sprite_detailed(x, y, sprite).collision
+    }
+
+    /// Draw a sprite in the same way as `draw_sprite()`, returning more detail
+    /// about collisions and clipping. A row collides if it collides on any
+    /// plane.
+    #[must_use]
+    pub fn draw_sprite_detailed(&mut self, x: u8, y: u8, sprite: &[u8]) -> DrawResult {
+        let plane_count = self.selected_plane_count();
+        let sprite_height = sprite.len() / plane_count.max(1);
+        if sprite_height == 0 {
+            // no pixels to draw on each plane, can't be any collisions
+            return DrawResult::default();
         }
 
-        // Whether an on pixel (value true) has been turned off (set to false)
-        let mut collision = false;
+        // bit N is set if row N of the sprite collided
+        let mut collided_rows: u64 = 0;
+        let mut clipped_rows = 0;
+        let mut plane_sprites = sprite.chunks(sprite_height);
+        for plane in 0..PLANE_COUNT {
+            if self.is_plane_selected(plane) {
+                if let Some(plane_sprite) = plane_sprites.next() {
+                    let (plane_collided_rows, plane_clipped_rows) =
+                        self.draw_sprite_on_plane(plane, x, y, plane_sprite);
+                    collided_rows |= plane_collided_rows;
+                    clipped_rows = plane_clipped_rows;
+                }
+            }
+        }
 
-        // Wrap coordinate
-        let x = x % (SCREEN_WIDTH_PIXELS as u8);
-        let y = y % (SCREEN_HEIGHT_PIXELS as u8);
-
-        let sprite_width = 8;
-        let sprite_height = sprite.len();
-        // The dimensions of the actual area to draw, stopping at the border of
-        // the screen.
-        let area_width = if x as usize + sprite_width > SCREEN_WIDTH_PIXELS {
-            (sprite_width - ((x as usize + sprite_width) % SCREEN_WIDTH_PIXELS)) as u8
-        } else {
-            sprite_width as u8
-        };
-        let area_height = if y as usize + sprite_height > SCREEN_HEIGHT_PIXELS {
-            (sprite_width - ((y as usize + sprite_height) % SCREEN_HEIGHT_PIXELS)) as u8
+        DrawResult {
+            collision: collided_rows != 0,
+            collided_rows: collided_rows.count_ones() as u8,
+            clipped_rows,
+        }
+    }
+
+    /// Whether the pixel is on in the first plane
+    #[must_use]
+    pub fn get_pixel(&self, x: u8, y: u8) -> bool {
+        self.get_plane_pixel(0, x, y)
+    }
+
+    /// Set the pixel in the first plane
+    pub fn set_pixel(&mut self, x: u8, y: u8, value: bool) {
+        self.set_plane_pixel(0, x, y, value)
+    }
+
+    #[must_use]
+    pub fn get_plane_pixel(&self, plane: usize, x: u8, y: u8) -> bool {
+        Self::check_bounds(x, y);
+        self.planes[plane][y as usize] & (1 << x) != 0
+    }
+
+    pub fn set_plane_pixel(&mut self, plane: usize, x: u8, y: u8, value: bool) {
+        Self::check_bounds(x, y);
+        let row = &mut self.planes[plane][y as usize];
+        if value {
+            *row |= 1 << x;
         } else {
-            sprite_height as u8
-        };
+            *row &= !(1 << x);
+        }
+    }
 
-        for ix in 0..area_width {
-            for iy in 0..area_height {
-                let pixel = self.get_pixel(x + ix, y + iy);
-                let sprite_pixel = (sprite[iy as usize] & (0b1000_0000 >> ix)) != 0;
-                if pixel && sprite_pixel {
-                    collision = true;
+    /// Bitmask of the planes the pixel is on in, where the least significant
+    /// bit is the first plane
+    #[must_use]
+    pub fn get_pixel_planes(&self, x: u8, y: u8) -> u8 {
+        let mut planes = 0;
+        for plane in 0..PLANE_COUNT {
+            if self.get_plane_pixel(plane, x, y) {
+                planes |= 1 << plane;
+            }
+        }
+        planes
+    }
+
+    /// Each row of the screen from the top as a bitmask of the pixels which
+    /// are on in any plane, where bit `x` is the pixel in column `x`. This is
+    /// faster than calling `get_pixel_planes()` for every pixel when drawing
+    /// the screen one row at a time.
+    pub fn rows(&self) -> impl Iterator<Item = u128> + '_ {
+        (0..H).map(|y| self.planes.iter().fold(0, |row, plane| row | plane[y]))
+    }
+
+    /// Call `f` with the coordinates of each pixel which is different to the
+    /// same pixel in `previous`, and whether it is now on in any plane.
+    pub fn for_each_changed(&self, previous: &Self, mut f: impl FnMut(u8, u8, bool)) {
+        for y in 0..H {
+            for x in 0..W {
+                let (x, y) = (x as u8, y as u8);
+                let planes = self.get_pixel_planes(x, y);
+                if planes != previous.get_pixel_planes(x, y) {
+                    f(x, y, planes != 0);
                 }
-                self.set_pixel(x + ix, y + iy, pixel ^ sprite_pixel);
             }
         }
+    }
 
-        collision
+    /// The coordinates of each pixel which is different to the same pixel in
+    /// `previous`, and whether it is now on in any plane. See
+    /// `for_each_changed()` for a version which doesn't allocate.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn diff(&self, previous: &Self) -> alloc::vec::Vec<(u8, u8, bool)> {
+        let mut changed = alloc::vec::Vec::new();
+        self.for_each_changed(previous, |x, y, value| changed.push((x, y, value)));
+        changed
+    }
+
+    /// The given plane packed in the format of `to_packed_bytes()`, for
+    /// screens of any size
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn plane_to_packed_vec(&self, plane: usize) -> alloc::vec::Vec<u8> {
+        let mut bytes = alloc::vec![0; Self::PACKED_SIZE_BYTES];
+        self.pack_plane(plane, &mut bytes);
+        bytes
+    }
+
+    /// Replace the given plane with one packed by `plane_to_packed_vec()`.
+    ///
+    /// Panics if `bytes` isn't `PACKED_SIZE_BYTES` long.
+    pub fn set_plane_from_packed_slice(&mut self, plane: usize, bytes: &[u8]) {
+        if bytes.len() != Self::PACKED_SIZE_BYTES {
+            panic!("packed plane is the wrong size for the screen")
+        }
+        for (row, packed) in self.planes[plane].iter_mut().zip(bytes.chunks_exact(W / 8)) {
+            let mut row_bytes = [0; 16];
+            row_bytes[..W / 8].copy_from_slice(packed);
+            *row = u128::from_be_bytes(row_bytes).reverse_bits();
+        }
+    }
+
+    /// Draw the screen as one line of text per row, where `#` is a pixel
+    /// which is on in any plane and `.` is one which is off
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_ascii(&self) -> alloc::string::String {
+        let mut text = alloc::string::String::with_capacity((W + 1) * H);
+        for y in 0..H as u8 {
+            for x in 0..W as u8 {
+                text.push(if self.get_pixel_planes(x, y) != 0 {
+                    '#'
+                } else {
+                    '.'
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Draw the screen with Unicode block characters, where each character is
+    /// two pixels stacked vertically, so the text is about as tall as it is
+    /// wide like the screen. Pixels which are on in any plane are drawn as
+    /// blocks, and ones which are off as spaces.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_block_art(&self) -> alloc::string::String {
+        let mut text = alloc::string::String::with_capacity((W + 1) * ((H + 1) / 2) * 3);
+        for y in (0..H).step_by(2) {
+            for x in 0..W as u8 {
+                let top = self.get_pixel_planes(x, y as u8) != 0;
+                // an odd height leaves the last row without a pixel below it
+                let bottom = y + 1 < H && self.get_pixel_planes(x, y as u8 + 1) != 0;
+                text.push(match (top, bottom) {
+                    (false, false) => ' ',
+                    (true, false) => '\u{2580}',
+                    (false, true) => '\u{2584}',
+                    (true, true) => '\u{2588}',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl<const W: usize, const H: usize> Chip8Screen<W, H> {
+    fn is_plane_selected(&self, plane: usize) -> bool {
+        self.selected_planes & (1 << plane) != 0
+    }
+
+    /// Pack a plane into `bytes`, which is `PACKED_SIZE_BYTES` long
+    fn pack_plane(&self, plane: usize, bytes: &mut [u8]) {
+        for (row, packed) in self.planes[plane].iter().zip(bytes.chunks_exact_mut(W / 8)) {
+            // bit N is the pixel N from the left, so reversing the row's bits
+            // puts the leftmost pixel in the most significant bit
+            packed.copy_from_slice(&row.reverse_bits().to_be_bytes()[..W / 8]);
+        }
+    }
+
+    /// Draw a sprite on one plane, returning a bitmask of the rows which
+    /// collided and the number of rows which were clipped
+    fn draw_sprite_on_plane(&mut self, plane: usize, x: u8, y: u8, sprite: &[u8]) -> (u64, u8) {
+        // Bit N is set if an on pixel in row N has been turned off
+        let mut collided_rows = 0;
+
+        // Wrap coordinate
+        let x = x as usize % W;
+        let y = y as usize % H;
+
+        // The rows past the bottom of the screen aren't drawn, and the columns
+        // past the right are masked off
+        let area_height = sprite.len().min(H - y);
+        let rows = &mut self.planes[plane][y..y + area_height];
+        for (iy, (row, sprite_row)) in rows.iter_mut().zip(sprite).enumerate() {
+            // the sprite's leftmost pixel is its most significant bit, but
+            // the row's leftmost pixel is its least significant bit
+            let sprite_row = ((sprite_row.reverse_bits() as u128) << x) & Self::ROW_MASK;
+            if *row & sprite_row != 0 {
+                collided_rows |= 1 << iy;
+            }
+            *row ^= sprite_row;
+        }
+
+        (collided_rows, (sprite.len() - area_height) as u8)
+    }
+
+    /// Panic if `x`, `y` is outside the screen
+    fn check_bounds(x: u8, y: u8) {
+        if x as usize >= W || y as usize >= H {
+            panic!("pixel coordinate is outside screen boundary")
+        }
+    }
+}
+
+/// The screen of a `Chip8`, which is low-res unless it's been switched to the
+/// SUPER-CHIP high-res mode with `Chip8::set_high_res()`. The methods are the
+/// same as `Chip8Screen`'s, for whichever size it is.
+// boxing the high-res screen would need `alloc`, and it's only 1KB bigger
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Eq)]
+pub enum Screen {
+    LowRes(LowResScreen),
+    HighRes(HighResScreen),
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen::LowRes(LowResScreen::default())
+    }
+}
+
+/// Evaluate `$body` with `$screen` bound to the `Chip8Screen` in `$self`,
+/// whichever size it is
+macro_rules! with_screen {
+    ($self:expr, $screen:ident => $body:expr) => {
+        match $self {
+            Screen::LowRes($screen) => $body,
+            Screen::HighRes($screen) => $body,
+        }
+    };
+}
+
+impl Screen {
+    /// A blank screen, which is 128x64 if `high_res` is set and 64x32
+    /// otherwise
+    #[must_use]
+    pub fn new(high_res: bool) -> Screen {
+        if high_res {
+            Screen::HighRes(HighResScreen::default())
+        } else {
+            Screen::LowRes(LowResScreen::default())
+        }
+    }
+
+    #[must_use]
+    pub fn is_high_res(&self) -> bool {
+        matches!(self, Screen::HighRes(_))
+    }
+
+    #[must_use]
+    pub fn width(&self) -> usize {
+        with_screen!(self, screen => screen.width())
+    }
+
+    #[must_use]
+    pub fn height(&self) -> usize {
+        with_screen!(self, screen => screen.height())
+    }
+
+    pub fn clear(&mut self) {
+        with_screen!(self, screen => screen.clear())
+    }
+
+    pub fn select_planes(&mut self, planes: u8) {
+        with_screen!(self, screen => screen.select_planes(planes))
+    }
+
+    #[must_use]
+    pub fn selected_planes(&self) -> u8 {
+        with_screen!(self, screen => screen.selected_planes())
+    }
+
+    #[must_use]
+    pub fn selected_plane_count(&self) -> usize {
+        with_screen!(self, screen => screen.selected_plane_count())
+    }
+
+    #[must_use]
+    pub fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        with_screen!(self, screen => screen.draw_sprite(x, y, sprite))
+    }
+
+    #[must_use]
+    pub fn draw_sprite_detailed(&mut self, x: u8, y: u8, sprite: &[u8]) -> DrawResult {
+        with_screen!(self, screen => screen.draw_sprite_detailed(x, y, sprite))
     }
 
     #[must_use]
     pub fn get_pixel(&self, x: u8, y: u8) -> bool {
-        self.screen[calc_index(x, y)]
+        with_screen!(self, screen => screen.get_pixel(x, y))
     }
 
     pub fn set_pixel(&mut self, x: u8, y: u8, value: bool) {
-        self.screen[calc_index(x, y)] = value
+        with_screen!(self, screen => screen.set_pixel(x, y, value))
     }
+
+    #[must_use]
+    pub fn get_pixel_planes(&self, x: u8, y: u8) -> u8 {
+        with_screen!(self, screen => screen.get_pixel_planes(x, y))
+    }
+
+    /// The size of a plane packed by `plane_to_packed_vec()`
+    #[must_use]
+    pub fn packed_size_bytes(&self) -> usize {
+        match self {
+            Screen::LowRes(_) => LowResScreen::PACKED_SIZE_BYTES,
+            Screen::HighRes(_) => HighResScreen::PACKED_SIZE_BYTES,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn plane_to_packed_vec(&self, plane: usize) -> alloc::vec::Vec<u8> {
+        with_screen!(self, screen => screen.plane_to_packed_vec(plane))
+    }
+
+    pub fn set_plane_from_packed_slice(&mut self, plane: usize, bytes: &[u8]) {
+        with_screen!(self, screen => screen.set_plane_from_packed_slice(plane, bytes))
+    }
+
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_ascii(&self) -> alloc::string::String {
+        with_screen!(self, screen => screen.to_ascii())
+    }
+
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn to_block_art(&self) -> alloc::string::String {
+        with_screen!(self, screen => screen.to_block_art())
+    }
+}
+
+/// Error type for `Chip8Screen::set_from_packed_bytes()`.
+///
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackedScreenError {
+    /// There are `len` bytes instead of `PACKED_SCREEN_SIZE_BYTES`
+    InvalidLength { len: usize },
 }
 
-#[must_use]
-fn calc_index(x: u8, y: u8) -> usize {
-    let x = x as usize;
-    let y = y as usize;
-    if x >= SCREEN_WIDTH_PIXELS || y >= SCREEN_HEIGHT_PIXELS {
-        panic!("pixel coordinate is outside screen boundary")
+impl fmt::Display for PackedScreenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackedScreenError::InvalidLength { len } => {
+                write!(
+                    f,
+                    "packed screen is {len} bytes but should be {PACKED_SCREEN_SIZE_BYTES} bytes"
+                )
+            }
+        }
     }
-    y * SCREEN_WIDTH_PIXELS + x
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for PackedScreenError {}
+
 #[cfg(test)]
 mod test {
-    use super::{calc_index, Chip8Screen};
+    use super::{
+        Chip8Screen, DrawResult, HighResScreen, LowResScreen, PackedScreenError, PLANE_COUNT,
+    };
+    use crate::rng::Rng;
+
+    /// The index of the pixel at `x`, `y` in `plane_pixels()`
+    fn calc_index(x: u8, y: u8) -> usize {
+        LowResScreen::check_bounds(x, y);
+        y as usize * 64 + x as usize
+    }
+
+    /// Every pixel of a plane of a low-res screen, row by row from the top
+    fn plane_pixels(screen: &LowResScreen, plane: usize) -> [bool; 64 * 32] {
+        core::array::from_fn(|i| screen.get_plane_pixel(plane, (i % 64) as u8, (i / 64) as u8))
+    }
 
     #[test]
     fn test_calc_index_bounds_checks_doesnt_panic() {
@@ -178,10 +704,32 @@ mod test {
         let mut screen = Chip8Screen::new();
         let collision = screen.draw_sprite(1, 1, &sprite);
 
-        assert_eq!(screen.screen, expected_screen);
+        assert_eq!(plane_pixels(&screen, 0), expected_screen);
         assert_eq!(collision, expected_collision);
     }
 
+    #[test]
+    fn test_draw_sprite_15_rows() {
+        // a diagonal line 15 pixels long
+        let sprite: [u8; 15] = core::array::from_fn(|row| 0b1000_0000 >> (row % 8));
+        let mut screen = Chip8Screen::new();
+
+        let collision = screen.draw_sprite(10, 5, &sprite);
+
+        assert!(!collision);
+        for row in 0..15u8 {
+            for column in 0..8u8 {
+                let expected = column == row % 8;
+                assert_eq!(
+                    screen.get_pixel(10 + column, 5 + row),
+                    expected,
+                    "row {row}, column {column}"
+                );
+            }
+        }
+        assert!(!screen.get_pixel(10, 20));
+    }
+
     #[test]
     fn test_draw_sprite_overlap() {
         let expected_collision1 = false;
@@ -203,7 +751,7 @@ mod test {
         let collision1 = screen.draw_sprite(0, 0, &sprite1);
         let collision2 = screen.draw_sprite(0, 0, &sprite2);
 
-        assert_eq!(screen.screen, expected_screen);
+        assert_eq!(plane_pixels(&screen, 0), expected_screen);
         assert_eq!(collision1, expected_collision1);
         assert_eq!(collision2, expected_collision2);
     }
@@ -232,7 +780,7 @@ mod test {
         let collision1 = screen.draw_sprite(0, 0, &sprite1);
         let collision2 = screen.draw_sprite(0, 0, &sprite2);
 
-        assert_eq!(screen.screen, expected_screen);
+        assert_eq!(plane_pixels(&screen, 0), expected_screen);
         assert_eq!(collision1, expected_collision1);
         assert_eq!(collision2, expected_collision2);
     }
@@ -259,7 +807,498 @@ mod test {
         let mut screen = Chip8Screen::new();
         let collision = screen.draw_sprite(62, 30, &sprite);
 
-        assert_eq!(screen.screen, expected_screen);
+        assert_eq!(plane_pixels(&screen, 0), expected_screen);
         assert_eq!(collision, expected_collision);
     }
+
+    #[test]
+    fn test_draw_sprite_start_wraps_then_clips() {
+        let sprite = [0b1100_0000, 0b1100_0000];
+
+        let mut screen = LowResScreen::new();
+        _ = screen.draw_sprite(64 + 6, 32 + 8, &sprite);
+        assert!(screen.get_pixel(6, 8) && screen.get_pixel(7, 9));
+        assert_eq!(screen.rows().map(u128::count_ones).sum::<u32>(), 4);
+
+        // wraps to the bottom right corner, where the rest is clipped
+        let mut screen = LowResScreen::new();
+        let result = screen.draw_sprite_detailed(255, 255, &sprite);
+        assert!(screen.get_pixel(63, 31));
+        assert_eq!(screen.rows().map(u128::count_ones).sum::<u32>(), 1);
+        assert_eq!(result.clipped_rows, 1);
+
+        let mut screen = HighResScreen::default();
+        _ = screen.draw_sprite(128 + 126, 64 + 63, &sprite);
+        assert!(screen.get_pixel(126, 63) && screen.get_pixel(127, 63));
+        assert_eq!(screen.rows().map(u128::count_ones).sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn test_draw_sprite_detailed_counts_rows() {
+        let mut screen = Chip8Screen::new();
+        _ = screen.draw_sprite(0, 28, &[0b1000_0000, 0b0000_0000, 0b1000_0000]);
+
+        // 6 rows starting 4 from the bottom: 2 clipped, rows 0 and 2 collide
+        let result = screen.draw_sprite_detailed(0, 28, &[0b1000_0000; 6]);
+
+        assert_eq!(
+            result,
+            DrawResult {
+                collision: true,
+                collided_rows: 2,
+                clipped_rows: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_draw_result_vf_low_res() {
+        let result = DrawResult {
+            collision: true,
+            collided_rows: 3,
+            clipped_rows: 2,
+        };
+        assert_eq!(result.vf(false), 1);
+        assert_eq!(DrawResult::default().vf(false), 0);
+    }
+
+    #[test]
+    fn test_draw_result_vf_high_res() {
+        let result = DrawResult {
+            collision: true,
+            collided_rows: 3,
+            clipped_rows: 2,
+        };
+        assert_eq!(result.vf(true), 5);
+
+        // clipped rows count even without a collision
+        let result = DrawResult {
+            collision: false,
+            collided_rows: 0,
+            clipped_rows: 4,
+        };
+        assert_eq!(result.vf(true), 4);
+    }
+
+    #[test]
+    fn test_to_packed_bytes() {
+        let mut screen = Chip8Screen::new();
+        screen.set_pixel(0, 0, true);
+        screen.set_pixel(9, 0, true);
+        screen.set_pixel(63, 31, true);
+        // other planes aren't included
+        screen.set_plane_pixel(1, 1, 0, true);
+
+        let bytes = screen.to_packed_bytes();
+
+        assert_eq!(bytes[0], 0b1000_0000);
+        assert_eq!(bytes[1], 0b0100_0000);
+        assert_eq!(bytes[255], 0b0000_0001);
+        assert_eq!(bytes.iter().filter(|byte| **byte != 0).count(), 3);
+    }
+
+    #[test]
+    fn test_to_grayscale() {
+        let mut screen = Chip8Screen::new();
+        screen.set_pixel(0, 0, true);
+        screen.set_pixel(9, 0, true);
+        screen.set_pixel(63, 31, true);
+        // pixels in other planes are on too
+        screen.set_plane_pixel(1, 1, 1, true);
+
+        let bytes = screen.to_grayscale(0xff, 0x20);
+
+        assert_eq!(
+            bytes[..10],
+            [0xff, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0xff]
+        );
+        assert_eq!(bytes[64 + 1], 0xff);
+        assert_eq!(bytes[64 * 32 - 1], 0xff);
+        assert_eq!(bytes.iter().filter(|byte| **byte == 0xff).count(), 4);
+        assert_eq!(
+            bytes.iter().filter(|byte| **byte == 0x20).count(),
+            64 * 32 - 4
+        );
+    }
+
+    #[test]
+    fn test_packed_plane_round_trip() {
+        let mut screen = Chip8Screen::new();
+        screen.set_plane_pixel(1, 0, 0, true);
+        screen.set_plane_pixel(1, 12, 7, true);
+        screen.set_plane_pixel(1, 63, 31, true);
+        let bytes = screen.plane_to_packed_bytes(1);
+
+        let mut restored = Chip8Screen::new();
+        restored.set_plane_from_packed_bytes(1, &bytes);
+
+        assert_eq!(restored.planes, screen.planes);
+    }
+
+    #[test]
+    fn test_set_from_packed_bytes() {
+        let mut screen = Chip8Screen::new();
+        screen.set_pixel(0, 0, true);
+        screen.set_pixel(9, 3, true);
+        screen.set_pixel(63, 31, true);
+        let bytes = screen.to_packed_bytes();
+
+        let mut restored = Chip8Screen::new();
+        restored.set_plane_pixel(1, 5, 5, true);
+        restored.set_from_packed_bytes(&bytes).unwrap();
+
+        assert_eq!(restored.to_packed_bytes(), bytes);
+        assert_eq!(restored.planes[0], screen.planes[0]);
+        // other planes are kept
+        assert!(restored.get_plane_pixel(1, 5, 5));
+
+        assert_eq!(
+            restored.set_from_packed_bytes(&bytes[1..]),
+            Err(PackedScreenError::InvalidLength { len: 255 })
+        );
+        assert_eq!(
+            restored.set_from_packed_bytes(&[0; 257]),
+            Err(PackedScreenError::InvalidLength { len: 257 })
+        );
+        assert_eq!(restored.to_packed_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_for_each_changed() {
+        let previous = Chip8Screen::new();
+        let mut screen = Chip8Screen::new();
+        screen.set_pixel(0, 0, true);
+        screen.set_pixel(63, 31, true);
+        screen.set_plane_pixel(1, 5, 6, true);
+
+        let mut changed = [(0, 0, false); 3];
+        let mut count = 0;
+        screen.for_each_changed(&previous, |x, y, value| {
+            changed[count] = (x, y, value);
+            count += 1;
+        });
+
+        assert_eq!(count, 3);
+        assert_eq!(changed, [(0, 0, true), (5, 6, true), (63, 31, true)]);
+    }
+
+    #[test]
+    fn test_rows() {
+        let mut screen = HighResScreen::default();
+        let mut rng = Rng::new(7);
+        for plane in 0..PLANE_COUNT {
+            for _ in 0..500 {
+                let (x, y) = (rng.next_u8() % 128, rng.next_u8() % 64);
+                screen.set_plane_pixel(plane, x, y, true);
+            }
+        }
+
+        let mut rows = 0;
+        for (y, row) in screen.rows().enumerate() {
+            for x in 0..128 {
+                let on = screen.get_pixel_planes(x, y as u8) != 0;
+                assert_eq!(row & (1 << x) != 0, on, "pixel {x}, {y}");
+            }
+            rows += 1;
+        }
+        assert_eq!(rows, 64);
+        assert_eq!(LowResScreen::new().rows().count(), 32);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_to_text() {
+        let mut screen = Chip8Screen::new();
+        // a 2x2 square at the top left, and a pixel under its right side
+        screen.set_pixel(0, 0, true);
+        screen.set_pixel(1, 0, true);
+        screen.set_pixel(0, 1, true);
+        screen.set_pixel(1, 1, true);
+        screen.set_pixel(1, 2, true);
+
+        let ascii = screen.to_ascii();
+        let ascii: alloc::vec::Vec<&str> = ascii.lines().collect();
+        assert_eq!(ascii.len(), 32);
+        assert_eq!(&ascii[0][..4], "##..");
+        assert_eq!(&ascii[1][..4], "##..");
+        assert_eq!(&ascii[2][..4], ".#..");
+        assert!(ascii.iter().all(|line| line.len() == 64));
+
+        let blocks = screen.to_block_art();
+        let blocks: alloc::vec::Vec<&str> = blocks.lines().collect();
+        assert_eq!(blocks.len(), 16);
+        assert!(blocks[0].starts_with("\u{2588}\u{2588}  "));
+        assert!(blocks[1].starts_with(" \u{2580}  "));
+        assert!(blocks.iter().all(|line| line.chars().count() == 64));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_block_art_high_res() {
+        let mut screen = HighResScreen::default();
+        screen.set_pixel(127, 63, true);
+
+        let blocks = screen.to_block_art();
+
+        assert_eq!(blocks.lines().count(), 32);
+        assert!(blocks.lines().all(|line| line.chars().count() == 128));
+        assert!(blocks.ends_with("\u{2584}\n"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_diff() {
+        let mut previous = Chip8Screen::new();
+        previous.set_pixel(1, 1, true);
+        previous.set_pixel(2, 2, true);
+        let mut screen = Chip8Screen::new();
+        screen.set_pixel(2, 2, true);
+        screen.set_pixel(3, 3, true);
+
+        assert_eq!(screen.diff(&previous), [(1, 1, false), (3, 3, true)]);
+        assert!(screen.diff(&screen).is_empty());
+    }
+
+    #[test]
+    fn test_select_planes() {
+        let mut screen = Chip8Screen::new();
+        assert_eq!(screen.selected_planes(), 0b01);
+
+        screen.select_planes(0b10);
+        assert_eq!(screen.selected_planes(), 0b10);
+        assert_eq!(screen.selected_plane_count(), 1);
+
+        // planes which don't exist are ignored
+        screen.select_planes(0b1111);
+        assert_eq!(screen.selected_planes(), 0b11);
+        assert_eq!(screen.selected_plane_count(), 2);
+    }
+
+    #[test]
+    fn test_draw_sprite_second_plane() {
+        let sprite = [0b1000_0000];
+        let mut screen = Chip8Screen::new();
+        screen.select_planes(0b10);
+        let collision = screen.draw_sprite(0, 0, &sprite);
+
+        assert!(!collision);
+        assert!(!screen.get_pixel(0, 0));
+        assert!(screen.get_plane_pixel(1, 0, 0));
+        assert_eq!(screen.get_pixel_planes(0, 0), 0b10);
+    }
+
+    #[test]
+    fn test_draw_sprite_two_planes() {
+        // first plane: X. second plane: XX
+        let sprite = [0b1000_0000, 0b1100_0000];
+        let mut screen = Chip8Screen::new();
+        screen.select_planes(0b11);
+        let collision1 = screen.draw_sprite(0, 0, &sprite);
+
+        assert!(!collision1);
+        assert_eq!(screen.get_pixel_planes(0, 0), 0b11);
+        assert_eq!(screen.get_pixel_planes(1, 0), 0b10);
+        assert_eq!(screen.get_pixel_planes(0, 1), 0b00);
+
+        // only collides on the second plane
+        let sprite = [0b0000_0000, 0b0100_0000];
+        let collision2 = screen.draw_sprite(0, 0, &sprite);
+
+        assert!(collision2);
+        assert_eq!(screen.get_pixel_planes(1, 0), 0b00);
+    }
+
+    #[test]
+    fn test_draw_sprite_too_short_for_planes() {
+        // one byte can't be split between two planes
+        let mut screen = Chip8Screen::new();
+        screen.select_planes(0b11);
+
+        let result = screen.draw_sprite_detailed(0, 0, &[0xff]);
+
+        assert_eq!(result, DrawResult::default());
+        assert!(screen.rows().all(|row| row == 0));
+    }
+
+    #[test]
+    fn test_clear_only_selected_planes() {
+        let mut screen = Chip8Screen::new();
+        screen.select_planes(0b11);
+        _ = screen.draw_sprite(0, 0, &[0b1000_0000, 0b1000_0000]);
+
+        screen.select_planes(0b01);
+        screen.clear();
+
+        assert_eq!(screen.get_pixel_planes(0, 0), 0b10);
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut screen = Chip8Screen::new();
+
+        screen.fill(true);
+        for y in 0..32 {
+            for x in 0..64 {
+                assert!(screen.get_pixel(x, y), "{x}, {y}");
+            }
+        }
+        assert!(screen.to_packed_bytes().iter().all(|byte| *byte == 0xff));
+
+        screen.fill(false);
+        assert!(screen == Chip8Screen::new());
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut screen = Chip8Screen::<8, 2>::default();
+        screen.set_pixel(0, 0, true);
+        screen.set_pixel(7, 1, true);
+
+        screen.invert();
+
+        for y in 0..2 {
+            for x in 0..8 {
+                let was_on = (x, y) == (0, 0) || (x, y) == (7, 1);
+                assert_eq!(screen.get_pixel(x, y), !was_on, "{x}, {y}");
+            }
+        }
+        screen.invert();
+        assert!(screen.get_pixel(0, 0) && screen.get_pixel(7, 1));
+        assert!(!screen.get_pixel(1, 0));
+    }
+
+    #[test]
+    fn test_fill_and_invert_only_selected_planes() {
+        let mut screen = Chip8Screen::new();
+        screen.select_planes(0b10);
+
+        screen.fill(true);
+        assert_eq!(screen.get_pixel_planes(3, 3), 0b10);
+        screen.select_planes(0b11);
+        screen.invert();
+        assert_eq!(screen.get_pixel_planes(3, 3), 0b01);
+    }
+
+    /// Draw a sprite overlapping the bottom right corner of a `W`x`H` screen
+    fn check_draw_sprite_corner<const W: usize, const H: usize>() {
+        let mut screen = Chip8Screen::<W, H>::default();
+        let x = W as u8 - 2;
+        let y = H as u8 - 1;
+
+        let result = screen.draw_sprite_detailed(x, y, &[0b1110_0000, 0b1110_0000]);
+
+        assert_eq!(
+            result,
+            DrawResult {
+                collision: false,
+                collided_rows: 0,
+                clipped_rows: 1,
+            }
+        );
+        assert!(screen.get_pixel(x, y));
+        assert!(screen.get_pixel(x + 1, y));
+        // clipped rather than wrapped
+        assert!(!screen.get_pixel(0, y));
+        assert!(!screen.get_pixel(x, 0));
+    }
+
+    /// Draw `sprite` on `pixels` pixel by pixel like the screen originally
+    /// did, returning the collided rows bitmask and the number of clipped rows
+    fn reference_draw<const W: usize, const H: usize>(
+        pixels: &mut [[bool; W]; H],
+        x: u8,
+        y: u8,
+        sprite: &[u8],
+    ) -> (u64, u8) {
+        let mut collided_rows = 0;
+        let x = x as usize % W;
+        let y = y as usize % H;
+        let area_width = if x + 8 > W { 8 - ((x + 8) % W) } else { 8 };
+        let area_height = if y + sprite.len() > H {
+            sprite.len() - ((y + sprite.len()) % H)
+        } else {
+            sprite.len()
+        };
+        for ix in 0..area_width {
+            for iy in 0..area_height {
+                let pixel = pixels[y + iy][x + ix];
+                let sprite_pixel = (sprite[iy] & (0b1000_0000 >> ix)) != 0;
+                if pixel && sprite_pixel {
+                    collided_rows |= 1 << iy;
+                }
+                pixels[y + iy][x + ix] = pixel ^ sprite_pixel;
+            }
+        }
+        (collided_rows, (sprite.len() - area_height) as u8)
+    }
+
+    fn check_matches_reference<const W: usize, const H: usize>() {
+        let mut rng = Rng::new(W as u64);
+        let mut screen = Chip8Screen::<W, H>::default();
+        let mut pixels = [[false; W]; H];
+        for _ in 0..2000 {
+            let (x, y) = (rng.next_u8(), rng.next_u8());
+            let sprite: [u8; 15] = core::array::from_fn(|_| rng.next_u8());
+            // the original didn't support sprites taller than the screen
+            let sprite = &sprite[..rng.next_u8() as usize % 16 % (H + 1)];
+
+            let result = screen.draw_sprite_detailed(x, y, sprite);
+            let (collided_rows, clipped_rows) = reference_draw(&mut pixels, x, y, sprite);
+
+            let expected = DrawResult {
+                collision: collided_rows != 0,
+                collided_rows: collided_rows.count_ones() as u8,
+                clipped_rows,
+            };
+            assert_eq!(result, expected, "{x}, {y}, {sprite:?}");
+            for (py, row) in pixels.iter().enumerate() {
+                for (px, pixel) in row.iter().enumerate() {
+                    assert_eq!(screen.get_pixel(px as u8, py as u8), *pixel, "{px}, {py}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_draw_sprite_matches_reference() {
+        check_matches_reference::<64, 32>();
+        check_matches_reference::<128, 64>();
+        // sizes which aren't a whole number of sprites wide
+        check_matches_reference::<60, 30>();
+        check_matches_reference::<8, 8>();
+    }
+
+    #[test]
+    fn test_draw_sprite_corner_both_sizes() {
+        check_draw_sprite_corner::<64, 32>();
+        check_draw_sprite_corner::<128, 64>();
+    }
+
+    #[test]
+    fn test_high_res_screen() {
+        let mut screen = HighResScreen::default();
+        assert_eq!((screen.width(), screen.height()), (128, 64));
+
+        // past the edges of the low-res screen
+        _ = screen.draw_sprite(100, 50, &[0b1000_0000]);
+        assert!(screen.get_pixel(100, 50));
+
+        // coordinates wrap at the high-res size
+        _ = screen.draw_sprite(130, 70, &[0b1000_0000]);
+        assert!(screen.get_pixel(2, 6));
+
+        let previous = HighResScreen::default();
+        let mut changed = 0;
+        screen.for_each_changed(&previous, |_, _, _| changed += 1);
+        assert_eq!(changed, 2);
+
+        screen.clear();
+        assert!(!screen.get_pixel(100, 50));
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel coordinate is outside screen boundary")]
+    fn test_high_res_bounds_checks() {
+        _ = HighResScreen::default().get_pixel(128, 0);
+    }
 }
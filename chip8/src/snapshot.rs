@@ -0,0 +1,428 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    cpu::{AUDIO_PATTERN_SIZE_BYTES, STACK_SIZE},
+    quirks::Quirks,
+    screen::{HighResScreen, LowResScreen, PLANE_COUNT},
+};
+
+/// The first bytes of a snapshot from `Snapshot::to_bytes()`
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"C8SS";
+/// The version of the format written by `Snapshot::to_bytes()`, which is
+/// increased whenever the layout changes
+pub const SNAPSHOT_VERSION: u8 = 6;
+
+/// A copy of the complete state of a `Chip8`, which can be restored later
+/// with `Chip8::restore_snapshot()`.
+///
+/// Only the addressable memory is stored and the screen is packed, so a
+/// snapshot of a CHIP-8 without extended memory is under 5KB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub memory: Vec<u8>,
+    /// Whether the screen is in the SUPER-CHIP high-res mode
+    pub high_res: bool,
+    /// Each plane packed as by `Chip8Screen::plane_to_packed_vec()`
+    pub screen_planes: [Vec<u8>; PLANE_COUNT],
+    pub selected_planes: u8,
+    pub v_reg: [u8; 16],
+    pub i_reg: u16,
+    pub stack: [u16; STACK_SIZE],
+    pub stack_ptr: u8,
+    pub pc: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub audio_pattern: [u8; AUDIO_PATTERN_SIZE_BYTES],
+    pub pitch: u8,
+    /// The state of the random number generator, see `Rng::state()`
+    pub rng_state: u64,
+    pub quirks: Quirks,
+    /// See `Chip8::frame_count()`
+    pub frame_count: u64,
+    /// See `Chip8::is_waiting_for_key()`
+    pub waiting_for_key: bool,
+    /// The keys held when `FX0A` started waiting which haven't been released
+    /// since, with bit N set for key N
+    pub waiting_initial_keys: u16,
+    /// See `Chip8::is_halted()`
+    pub halted: bool,
+    /// Whether a sprite has been drawn since the timers last ticked, which
+    /// makes the next `DXYN` wait with the `display_wait` quirk
+    pub drew_since_vblank: bool,
+}
+
+impl Snapshot {
+    /// Encode the snapshot in a compact binary format, which can be decoded
+    /// with `from_bytes()`. All numbers are little-endian, and the layout is:
+    ///
+    /// - `SNAPSHOT_MAGIC`
+    /// - `SNAPSHOT_VERSION`
+    /// - the quirks as `u16` bit flags, in the order they're declared in
+    ///   `Quirks`
+    /// - `v_reg`, `i_reg`, `stack`, `stack_ptr` and `pc`
+    /// - `delay_timer`, `sound_timer`, `audio_pattern` and `pitch`
+    /// - `rng_state` and `frame_count`
+    /// - `waiting_for_key`, `halted` and `drew_since_vblank` as bit flags, in
+    ///   that order, then `waiting_initial_keys`
+    /// - `selected_planes`, `high_res` and `screen_planes`
+    /// - the length of `memory` as a `u32`, followed by `memory`
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // everything but the memory fits in 1KB
+        let mut bytes = Vec::with_capacity(self.memory.len() + 1024);
+        bytes.extend_from_slice(&SNAPSHOT_MAGIC);
+        bytes.push(SNAPSHOT_VERSION);
+        bytes.extend_from_slice(&quirks_to_flags(&self.quirks).to_le_bytes());
+        bytes.extend_from_slice(&self.v_reg);
+        bytes.extend_from_slice(&self.i_reg.to_le_bytes());
+        for address in self.stack {
+            bytes.extend_from_slice(&address.to_le_bytes());
+        }
+        bytes.push(self.stack_ptr);
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        bytes.push(self.delay_timer);
+        bytes.push(self.sound_timer);
+        bytes.extend_from_slice(&self.audio_pattern);
+        bytes.push(self.pitch);
+        bytes.extend_from_slice(&self.rng_state.to_le_bytes());
+        bytes.extend_from_slice(&self.frame_count.to_le_bytes());
+        bytes.push(
+            self.waiting_for_key as u8
+                | (self.halted as u8) << 1
+                | (self.drew_since_vblank as u8) << 2,
+        );
+        bytes.extend_from_slice(&self.waiting_initial_keys.to_le_bytes());
+        bytes.push(self.selected_planes);
+        bytes.push(self.high_res as u8);
+        for plane in &self.screen_planes {
+            bytes.extend_from_slice(plane);
+        }
+        bytes.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.memory);
+        bytes
+    }
+
+    /// Decode a snapshot written by `to_bytes()`. The snapshot's contents
+    /// aren't checked, that's done when it's restored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut reader = Reader { bytes };
+        if reader.array()? != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        let version = reader.u8()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion { version });
+        }
+        let quirks = flags_to_quirks(reader.u16()?);
+        let v_reg = reader.array()?;
+        let i_reg = reader.u16()?;
+        let mut stack = [0; STACK_SIZE];
+        for address in &mut stack {
+            *address = reader.u16()?;
+        }
+        let stack_ptr = reader.u8()?;
+        let pc = reader.u16()?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let audio_pattern = reader.array()?;
+        let pitch = reader.u8()?;
+        let rng_state = u64::from_le_bytes(reader.array()?);
+        let frame_count = u64::from_le_bytes(reader.array()?);
+        let flags = reader.u8()?;
+        let waiting_initial_keys = reader.u16()?;
+        let selected_planes = reader.u8()?;
+        let high_res = reader.u8()? != 0;
+        let plane_len = if high_res {
+            HighResScreen::PACKED_SIZE_BYTES
+        } else {
+            LowResScreen::PACKED_SIZE_BYTES
+        };
+        let mut screen_planes: [Vec<u8>; PLANE_COUNT] = Default::default();
+        for plane in &mut screen_planes {
+            *plane = reader.take(plane_len)?.to_vec();
+        }
+        let memory_len = u32::from_le_bytes(reader.array()?) as usize;
+        let memory = reader.take(memory_len)?.to_vec();
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::TrailingBytes {
+                len: reader.bytes.len(),
+            });
+        }
+        Ok(Snapshot {
+            memory,
+            high_res,
+            screen_planes,
+            selected_planes,
+            v_reg,
+            i_reg,
+            stack,
+            stack_ptr,
+            pc,
+            delay_timer,
+            sound_timer,
+            audio_pattern,
+            pitch,
+            rng_state,
+            quirks,
+            frame_count,
+            waiting_for_key: flags & 1 != 0,
+            waiting_initial_keys,
+            halted: flags & 1 << 1 != 0,
+            drew_since_vblank: flags & 1 << 2 != 0,
+        })
+    }
+}
+
+fn quirks_to_flags(quirks: &Quirks) -> u16 {
+    quirks.wait_key_on_release as u16
+        | (quirks.extended_memory as u16) << 1
+        | (quirks.add_to_i_sets_vf as u16) << 2
+        | (quirks.protect_reserved_memory as u16) << 3
+        | (quirks.shift_uses_vy as u16) << 4
+        | (quirks.load_store_increments_i as u16) << 5
+        | (quirks.jump_with_vx as u16) << 6
+        | (quirks.logic_resets_vf as u16) << 7
+        | (quirks.display_wait as u16) << 8
+}
+
+fn flags_to_quirks(flags: u16) -> Quirks {
+    Quirks {
+        wait_key_on_release: flags & 1 != 0,
+        extended_memory: flags & 1 << 1 != 0,
+        add_to_i_sets_vf: flags & 1 << 2 != 0,
+        protect_reserved_memory: flags & 1 << 3 != 0,
+        shift_uses_vy: flags & 1 << 4 != 0,
+        load_store_increments_i: flags & 1 << 5 != 0,
+        jump_with_vx: flags & 1 << 6 != 0,
+        logic_resets_vf: flags & 1 << 7 != 0,
+        display_wait: flags & 1 << 8 != 0,
+    }
+}
+
+/// Reads the fields of a snapshot from the front of `bytes`
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if len > self.bytes.len() {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+}
+
+/// Error type for restoring a `Snapshot`.
+///
+/// Note that this only implements the `Error` trait with the `std` feature,
+/// for reasons specified in the readme section titled "Why not implement the
+/// `Error` trait on error types?".
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The memory isn't the size expected for the snapshot's quirks
+    InvalidMemorySize { len: usize, expected_len: usize },
+    /// The stack pointer is past the end of the stack
+    InvalidStackPointer { stack_ptr: u8 },
+    /// A screen plane isn't the packed size for the snapshot's resolution
+    InvalidScreenSize { len: usize, expected_len: usize },
+    /// The bytes don't start with `SNAPSHOT_MAGIC`, so they aren't a snapshot
+    InvalidMagic,
+    /// The snapshot was written in a format this version can't read
+    UnsupportedVersion { version: u8 },
+    /// The bytes end partway through the snapshot
+    Truncated,
+    /// There are `len` bytes after the end of the snapshot
+    TrailingBytes { len: usize },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::InvalidMemorySize { len, expected_len } => {
+                write!(
+                    f,
+                    "snapshot memory is {len} bytes but should be {expected_len} bytes"
+                )
+            }
+            SnapshotError::InvalidStackPointer { stack_ptr } => {
+                write!(
+                    f,
+                    "snapshot stack pointer {stack_ptr} is greater than the stack size {STACK_SIZE}"
+                )
+            }
+            SnapshotError::InvalidScreenSize { len, expected_len } => {
+                write!(
+                    f,
+                    "snapshot screen plane is {len} bytes but should be {expected_len} bytes"
+                )
+            }
+            SnapshotError::InvalidMagic => write!(f, "the data isn't a snapshot"),
+            SnapshotError::UnsupportedVersion { version } => {
+                write!(
+                    f,
+                    "snapshot version {version} isn't supported, only version {SNAPSHOT_VERSION} is"
+                )
+            }
+            SnapshotError::Truncated => write!(f, "the snapshot is incomplete"),
+            SnapshotError::TrailingBytes { len } => {
+                write!(f, "there are {len} unexpected bytes after the snapshot")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod test {
+    use super::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+    use crate::{cpu::Chip8, quirks::Quirks};
+
+    const NO_KEYS: [bool; 16] = [false; 16];
+
+    fn running_chip8(quirks: Quirks) -> Chip8 {
+        // 6A07: VA = 7
+        // A20A: I = 0x20a
+        // DAA5: draw 5 rows at VA, VA
+        // 2208: call 0x208
+        // F029: I = the sprite for V0
+        let program = [0x6a, 0x07, 0xa2, 0x0a, 0xda, 0xa5, 0x22, 0x08, 0xf0, 0x29];
+        let mut chip8 = Chip8::new_with_quirks(&program, quirks);
+        for _ in 0..5 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+        chip8.update_timers();
+        chip8
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for quirks in [Quirks::vip(), Quirks::schip(), Quirks::xochip()] {
+            let snapshot = running_chip8(quirks).to_snapshot();
+
+            let bytes = snapshot.to_bytes();
+
+            assert_eq!(Snapshot::from_bytes(&bytes).unwrap(), snapshot);
+        }
+    }
+
+    #[test]
+    fn test_waiting_and_halted_round_trip() {
+        // F50A: wait for a key
+        let mut waiting = Chip8::new(&[0xf5, 0x0a]);
+        let mut held = NO_KEYS;
+        held[0x3] = true;
+        waiting.cycle(&held, &held).unwrap();
+        // 00FD: exit
+        let mut halted = Chip8::new_with_quirks(&[0x00, 0xfd], Quirks::xochip());
+        halted.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+
+        for chip8 in [waiting, halted] {
+            let restored = Chip8::from_bytes(&chip8.to_bytes()).unwrap();
+
+            assert_eq!(restored.is_waiting_for_key(), chip8.is_waiting_for_key());
+            assert_eq!(restored.is_halted(), chip8.is_halted());
+            assert_eq!(restored.frame_count(), chip8.frame_count());
+            assert_eq!(restored.to_snapshot(), chip8.to_snapshot());
+        }
+    }
+
+    #[test]
+    fn test_high_res_round_trip() {
+        let mut chip8 = running_chip8(Quirks::xochip());
+        chip8.set_high_res(true);
+        chip8.get_screen_mut().set_pixel(127, 63, true);
+
+        let restored = Chip8::from_bytes(&chip8.to_bytes()).unwrap();
+
+        assert!(restored.get_screen() == chip8.get_screen());
+        assert!(restored.get_screen().is_high_res());
+        assert_eq!(restored.to_snapshot(), chip8.to_snapshot());
+    }
+
+    #[test]
+    fn test_chip8_bytes_round_trip() {
+        let chip8 = running_chip8(Quirks::default());
+
+        let restored = Chip8::from_bytes(&chip8.to_bytes()).unwrap();
+
+        assert_eq!(restored.to_snapshot(), chip8.to_snapshot());
+    }
+
+    #[test]
+    fn test_bytes_are_compact() {
+        let bytes = running_chip8(Quirks::default()).to_bytes();
+
+        // memory plus under 1KB for everything else
+        assert!(bytes.len() < 4096 + 1024, "{}", bytes.len());
+    }
+
+    #[test]
+    fn test_truncated_bytes() {
+        let bytes = running_chip8(Quirks::default()).to_bytes();
+
+        for len in [0, 3, 5, 40, 600, bytes.len() - 1] {
+            assert!(
+                matches!(
+                    Snapshot::from_bytes(&bytes[..len]),
+                    Err(SnapshotError::Truncated)
+                ),
+                "{len}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupt_bytes() {
+        let bytes = running_chip8(Quirks::default()).to_bytes();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(matches!(
+            Snapshot::from_bytes(&wrong_magic),
+            Err(SnapshotError::InvalidMagic)
+        ));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = SNAPSHOT_VERSION + 1;
+        assert!(matches!(
+            Snapshot::from_bytes(&wrong_version),
+            Err(SnapshotError::UnsupportedVersion { .. })
+        ));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            Snapshot::from_bytes(&trailing),
+            Err(SnapshotError::TrailingBytes { len: 1 })
+        ));
+
+        // the memory length doesn't match the quirks
+        let mut wrong_memory = bytes[..bytes.len() - 4096 - 4].to_vec();
+        wrong_memory.extend_from_slice(&2u32.to_le_bytes());
+        wrong_memory.extend_from_slice(&[0, 0]);
+        assert!(matches!(
+            Chip8::from_bytes(&wrong_memory),
+            Err(SnapshotError::InvalidMemorySize { len: 2, .. })
+        ));
+    }
+}
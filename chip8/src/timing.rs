@@ -0,0 +1,191 @@
+use crate::instruction::Inst;
+
+/// The time in a 60Hz frame, in microseconds, for budgeting frames by
+/// `CycleCosts`
+pub const FRAME_TIME_MICROS: u32 = 16_667;
+
+/// How long each kind of instruction takes to run, in microseconds. See
+/// `Chip8::last_cycle_cost()`.
+///
+/// The defaults are the approximate average times on the COSMAC VIP, whose
+/// interpreter took far longer for some instructions than others. Some ROMs
+/// depend on these, e.g. by drawing as fast as the VIP could rather than
+/// waiting for the delay timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleCosts {
+    /// `00E0`
+    pub clear: u32,
+    /// `00EE`
+    pub return_from_subroutine: u32,
+    /// `1NNN` and `BNNN`
+    pub jump: u32,
+    /// `2NNN`
+    pub call: u32,
+    /// `3XNN` and `4XNN`
+    pub skip_value: u32,
+    /// `5XY0` and `9XY0`
+    pub skip_register: u32,
+    /// `EX9E` and `EXA1`
+    pub skip_key: u32,
+    /// `6XNN`
+    pub load_value: u32,
+    /// `7XNN`
+    pub add_value: u32,
+    /// `8XYN`
+    pub arithmetic: u32,
+    /// `ANNN` and `F000 NNNN`
+    pub load_i: u32,
+    /// `CXNN`
+    pub random: u32,
+    /// `DXYN`
+    pub draw_sprite: u32,
+    /// `FX07`, `FX0A`, `FX15` and `FX18`
+    pub timers: u32,
+    /// `FX1E`
+    pub add_to_i: u32,
+    /// `FX29`
+    pub digit_sprite: u32,
+    /// `FX33`
+    pub bcd: u32,
+    /// `FX55` and `FX65`
+    pub store_load_registers: u32,
+    /// Instructions the COSMAC VIP doesn't have, such as the XO-CHIP ones
+    pub other: u32,
+}
+
+impl CycleCosts {
+    /// Average times on the COSMAC VIP
+    #[must_use]
+    pub fn vip() -> CycleCosts {
+        CycleCosts {
+            clear: 109,
+            return_from_subroutine: 105,
+            jump: 105,
+            call: 105,
+            skip_value: 55,
+            skip_register: 73,
+            skip_key: 73,
+            load_value: 27,
+            add_value: 45,
+            arithmetic: 200,
+            load_i: 55,
+            random: 164,
+            draw_sprite: 22_734,
+            timers: 45,
+            add_to_i: 86,
+            digit_sprite: 91,
+            bcd: 927,
+            store_load_registers: 605,
+            other: 55,
+        }
+    }
+
+    /// The time `inst` takes to run
+    #[must_use]
+    pub fn cost(&self, inst: &Inst) -> u32 {
+        match inst {
+            Inst::Clear => self.clear,
+            Inst::Return => self.return_from_subroutine,
+            Inst::Jump { .. } | Inst::JumpAdd { .. } => self.jump,
+            Inst::Call { .. } => self.call,
+            Inst::SkipEqualValue { .. } | Inst::SkipNotEqualValue { .. } => self.skip_value,
+            Inst::SkipEqualRegister { .. } | Inst::SkipNotEqualRegister { .. } => {
+                self.skip_register
+            }
+            Inst::SkipIfKey { .. } | Inst::SkipIfNotKey { .. } => self.skip_key,
+            Inst::LoadValue { .. } => self.load_value,
+            Inst::AddValue { .. } => self.add_value,
+            Inst::LoadRegister { .. }
+            | Inst::Or { .. }
+            | Inst::And { .. }
+            | Inst::Xor { .. }
+            | Inst::AddRegister { .. }
+            | Inst::SubRegisterXY { .. }
+            | Inst::ShiftRight { .. }
+            | Inst::SubRegisterYX { .. }
+            | Inst::ShiftLeft { .. } => self.arithmetic,
+            Inst::LoadIntoI { .. } | Inst::LoadLongIntoI { .. } => self.load_i,
+            Inst::LoadRandom { .. } => self.random,
+            Inst::DrawSprite { .. } => self.draw_sprite,
+            Inst::LoadDelay { .. }
+            | Inst::WaitForKey { .. }
+            | Inst::SetDelay { .. }
+            | Inst::SetSound { .. } => self.timers,
+            Inst::AddToI { .. } => self.add_to_i,
+            Inst::LoadDigitSpriteAddrIntoI { .. } => self.digit_sprite,
+            Inst::StoreBCD { .. } => self.bcd,
+            Inst::StoreRegisters { .. } | Inst::LoadRegisters { .. } => self.store_load_registers,
+            Inst::Exe { .. }
+            | Inst::Exit
+            | Inst::SetPlane { .. }
+            | Inst::LoadAudioPattern
+            | Inst::SetPitch { .. } => self.other,
+        }
+    }
+}
+
+impl Default for CycleCosts {
+    fn default() -> Self {
+        Self::vip()
+    }
+}
+
+/// How many cycles to run in a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameBudget {
+    /// A fixed number of cycles
+    Cycles(u32),
+    /// Cycles until their total cost is at least this many microseconds
+    Micros(u32),
+}
+
+impl FrameBudget {
+    /// Whether a frame is over after running `cycles` cycles which cost
+    /// `micros` microseconds in total
+    #[must_use]
+    pub fn is_spent(&self, cycles: u32, micros: u32) -> bool {
+        match *self {
+            FrameBudget::Cycles(budget) => cycles >= budget,
+            FrameBudget::Micros(budget) => micros >= budget,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CycleCosts, FrameBudget};
+    use crate::instruction::decode;
+
+    #[test]
+    fn test_vip_costs() {
+        let costs = CycleCosts::vip();
+        let cost = |inst| costs.cost(&decode(inst).unwrap());
+
+        assert_eq!(cost(0x00e0), 109);
+        assert_eq!(cost(0x6a12), 27);
+        assert_eq!(cost(0x8124), 200);
+        assert_eq!(cost(0x8126), 200);
+        assert_eq!(cost(0xd125), 22_734);
+        assert_eq!(cost(0xf233), 927);
+        assert_eq!(cost(0xb200), cost(0x1200));
+    }
+
+    #[test]
+    fn test_overridden_costs() {
+        let costs = CycleCosts {
+            draw_sprite: 1,
+            ..CycleCosts::vip()
+        };
+
+        assert_eq!(costs.cost(&decode(0xd125).unwrap()), 1);
+        assert_eq!(costs.cost(&decode(0x00e0).unwrap()), 109);
+    }
+
+    #[test]
+    fn test_frame_budget() {
+        assert!(!FrameBudget::Cycles(30).is_spent(29, 100_000));
+        assert!(FrameBudget::Cycles(30).is_spent(30, 0));
+        assert!(!FrameBudget::Micros(1000).is_spent(100, 999));
+        assert!(FrameBudget::Micros(1000).is_spent(1, 1000));
+    }
+}
@@ -0,0 +1,81 @@
+/// How many of the most recently run instructions `Chip8::recent_trace()`
+/// remembers
+pub const TRACE_CAPACITY: usize = 256;
+
+/// The address and opcode of each of the last `TRACE_CAPACITY` instructions
+/// run, oldest first once it's full. This needs no allocator, so the history
+/// is available for working out how a program crashed even in embedded builds.
+#[derive(Clone)]
+pub(crate) struct TraceBuffer {
+    entries: [(u16, u16); TRACE_CAPACITY],
+    /// The index the next entry is written to, which is the oldest entry once
+    /// the buffer is full
+    next: usize,
+    len: usize,
+}
+
+impl TraceBuffer {
+    pub(crate) fn push(&mut self, pc: u16, opcode: u16) {
+        self.entries[self.next] = (pc, opcode);
+        self.next = (self.next + 1) % TRACE_CAPACITY;
+        self.len = (self.len + 1).min(TRACE_CAPACITY);
+    }
+
+    /// The entries from oldest to newest
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        let start = (self.next + TRACE_CAPACITY - self.len) % TRACE_CAPACITY;
+        (0..self.len).map(move |i| self.entries[(start + i) % TRACE_CAPACITY])
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+impl Default for TraceBuffer {
+    fn default() -> Self {
+        TraceBuffer {
+            entries: [(0, 0); TRACE_CAPACITY],
+            next: 0,
+            len: 0,
+        }
+    }
+}
+
+/// The history isn't machine state, so machines are equal whatever they've
+/// run to get where they are
+impl PartialEq for TraceBuffer {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for TraceBuffer {}
+
+#[cfg(test)]
+mod test {
+    use super::{TraceBuffer, TRACE_CAPACITY};
+
+    #[test]
+    fn test_trace_buffer() {
+        let mut trace = TraceBuffer::default();
+        assert_eq!(trace.iter().count(), 0);
+
+        trace.push(0x200, 0x00e0);
+        trace.push(0x202, 0x1202);
+        assert!(trace.iter().eq([(0x200, 0x00e0), (0x202, 0x1202)]));
+
+        for i in 0..TRACE_CAPACITY as u16 + 10 {
+            trace.push(i, i);
+        }
+        assert_eq!(trace.iter().count(), TRACE_CAPACITY);
+        assert!(trace
+            .iter()
+            .map(|(pc, _)| pc)
+            .eq(10..TRACE_CAPACITY as u16 + 10));
+
+        trace.clear();
+        assert_eq!(trace.iter().count(), 0);
+    }
+}
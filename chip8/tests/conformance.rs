@@ -0,0 +1,88 @@
+//! Runs small hand-assembled programs end-to-end and compares the resulting
+//! screen to a golden image in `tests/golden/`.
+//!
+//! Golden images are 32 lines of 64 characters, where `#` is an on pixel and
+//! `.` is an off pixel.
+
+use chip8::{
+    cpu::Chip8,
+    screen::{Screen, PACKED_SCREEN_SIZE_BYTES, SCREEN_WIDTH_PIXELS},
+};
+
+const FRAMES: usize = 10;
+const CYCLES_PER_FRAME: usize = 30;
+
+fn run(program: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new(program);
+    chip8
+        .run_frames(FRAMES, CYCLES_PER_FRAME, &[false; 16])
+        .unwrap();
+    chip8
+}
+
+fn packed_screen_to_text(packed: &[u8; PACKED_SCREEN_SIZE_BYTES]) -> String {
+    let mut text = String::new();
+    for row in packed.chunks(SCREEN_WIDTH_PIXELS / 8) {
+        for byte in row {
+            for bit in 0..8 {
+                text.push(if byte & (0b1000_0000 >> bit) != 0 {
+                    '#'
+                } else {
+                    '.'
+                });
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn assert_screen_matches(chip8: &Chip8, golden: &str) {
+    let Screen::LowRes(screen) = chip8.get_screen() else {
+        panic!("golden images are of the low-res screen");
+    };
+    let actual = packed_screen_to_text(&screen.to_packed_bytes());
+    assert_eq!(actual, golden.replace("\r\n", "\n"));
+}
+
+#[test]
+fn test_bcd_draw_skip() {
+    let program = [
+        0x60, 0x89, // 200: V0 = 137
+        0xa3, 0x00, // 202: I = 0x300
+        0xf0, 0x33, // 204: store BCD of V0 at I: 1, 3, 7
+        0x61, 0x08, // 206: V1 = 8
+        0x62, 0x04, // 208: V2 = 4
+        0xd1, 0x23, // 20A: draw the 3 BCD bytes as a sprite at (8, 4)
+        0xa3, 0x00, // 20C: I = 0x300
+        0xf2, 0x65, // 20E: V0, V1, V2 = 1, 3, 7
+        0x31, 0x03, // 210: skip the next instruction as V1 == 3
+        0xd0, 0x01, // 212: (skipped) draw at (1, 3)
+        0x42, 0x07, // 214: don't skip as V2 == 7
+        0x6a, 0x3f, // 216: VA = 63
+        0x12, 0x18, // 218: loop forever
+    ];
+
+    let chip8 = run(&program);
+
+    assert_screen_matches(&chip8, include_str!("golden/bcd_draw_skip.txt"));
+}
+
+#[test]
+fn test_diagonal_loop() {
+    let program = [
+        0xa2, 0x10, // 200: I = 0x210
+        0x60, 0x00, // 202: V0 = 0
+        0xd0, 0x01, // 204: draw a single pixel at (V0, V0)
+        0x70, 0x01, // 206: V0 += 1
+        0x30, 0x20, // 208: skip the next instruction if V0 == 32
+        0x12, 0x04, // 20A: jump to 204
+        0x12, 0x0c, // 20C: loop forever
+        0x00, 0x00, // 20E: padding
+        0x80, 0x00, // 210: sprite data
+    ];
+
+    let chip8 = run(&program);
+
+    assert_screen_matches(&chip8, include_str!("golden/diagonal_loop.txt"));
+}
@@ -0,0 +1,513 @@
+//! Runs tiny hand-assembled programs which each exercise the edge cases of one
+//! instruction, and checks the registers and memory after a fixed number of
+//! cycles. These use the default quirks, which are the COSMAC VIP's.
+//!
+//! Each program's listing says what it does, and the assertions after it say
+//! what it should leave behind.
+
+use chip8::cpu::Chip8;
+
+const NO_KEYS: [bool; 16] = [false; 16];
+
+/// Run the first `cycles` instructions of `program` with no keys held
+fn run(program: &[u8], cycles: usize) -> Chip8 {
+    run_with_keys(program, cycles, &NO_KEYS)
+}
+
+/// Run the first `cycles` instructions of `program` with `keys` held
+fn run_with_keys(program: &[u8], cycles: usize, keys: &[bool; 16]) -> Chip8 {
+    let mut chip8 = Chip8::new(program);
+    for _ in 0..cycles {
+        chip8.cycle(keys, keys).unwrap();
+    }
+    chip8
+}
+
+mod arithmetic {
+    use super::run;
+
+    #[test]
+    fn test_add_value_wraps_without_carry() {
+        let program = [
+            0x6f, 0x05, // 200: VF = 5
+            0x60, 0xff, // 202: V0 = 255
+            0x70, 0x02, // 204: V0 += 2, which wraps to 1 and leaves VF alone
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0x01);
+        assert_eq!(chip8.v_registers()[0xf], 0x05);
+    }
+
+    #[test]
+    fn test_add_register_carry() {
+        let program = [
+            0x60, 0xff, // 200: V0 = 255
+            0x61, 0x01, // 202: V1 = 1
+            0x80, 0x14, // 204: V0 += V1, which wraps to 0 and sets VF to 1
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0x00);
+        assert_eq!(chip8.v_registers()[0x1], 0x01);
+        assert_eq!(chip8.v_registers()[0xf], 1);
+    }
+
+    #[test]
+    fn test_add_register_no_carry() {
+        let program = [
+            0x6f, 0x01, // 200: VF = 1
+            0x60, 0x10, // 202: V0 = 0x10
+            0x61, 0xef, // 204: V1 = 0xEF
+            0x80, 0x14, // 206: V0 += V1, which is 255 exactly so VF is 0
+        ];
+
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.v_registers()[0x0], 0xff);
+        assert_eq!(chip8.v_registers()[0xf], 0);
+    }
+
+    #[test]
+    fn test_sub_borrow() {
+        let program = [
+            0x60, 0x05, // 200: V0 = 5
+            0x61, 0x07, // 202: V1 = 7
+            0x80, 0x15, // 204: V0 -= V1, which borrows so VF is 0
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0xfe);
+        assert_eq!(chip8.v_registers()[0xf], 0);
+    }
+
+    #[test]
+    fn test_sub_equal_doesnt_borrow() {
+        let program = [
+            0x60, 0x07, // 200: V0 = 7
+            0x61, 0x07, // 202: V1 = 7
+            0x80, 0x15, // 204: V0 -= V1, which doesn't borrow so VF is 1
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0x00);
+        assert_eq!(chip8.v_registers()[0xf], 1);
+    }
+
+    #[test]
+    fn test_subn() {
+        let program = [
+            0x60, 0x05, // 200: V0 = 5
+            0x61, 0x07, // 202: V1 = 7
+            0x80, 0x17, // 204: V0 = V1 - V0 = 2, which doesn't borrow so VF is 1
+            0x62, 0x07, // 206: V2 = 7
+            0x63, 0x05, // 208: V3 = 5
+            0x82, 0x37, // 20A: V2 = V3 - V2 = 254, which borrows so VF is 0
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0x02);
+        assert_eq!(chip8.v_registers()[0xf], 1);
+
+        let chip8 = run(&program, 6);
+
+        assert_eq!(chip8.v_registers()[0x2], 0xfe);
+        assert_eq!(chip8.v_registers()[0xf], 0);
+    }
+
+    #[test]
+    fn test_logic() {
+        let program = [
+            0x60, 0xcc, // 200: V0 = 0b1100_1100
+            0x61, 0xaa, // 202: V1 = 0b1010_1010
+            0x82, 0x00, // 204: V2 = V0
+            0x82, 0x11, // 206: V2 |= V1
+            0x83, 0x00, // 208: V3 = V0
+            0x83, 0x12, // 20A: V3 &= V1
+            0x84, 0x00, // 20C: V4 = V0
+            0x84, 0x13, // 20E: V4 ^= V1
+        ];
+
+        let chip8 = run(&program, 8);
+
+        assert_eq!(chip8.v_registers()[0x2], 0b1110_1110);
+        assert_eq!(chip8.v_registers()[0x3], 0b1000_1000);
+        assert_eq!(chip8.v_registers()[0x4], 0b0110_0110);
+        // the operands are unchanged
+        assert_eq!(chip8.v_registers()[0x0], 0xcc);
+        assert_eq!(chip8.v_registers()[0x1], 0xaa);
+    }
+}
+
+mod shifts {
+    use super::run;
+
+    #[test]
+    fn test_shift_right_uses_vy() {
+        let program = [
+            0x60, 0xff, // 200: V0 = 0xFF
+            0x61, 0x05, // 202: V1 = 0b0000_0101
+            0x80, 0x16, // 204: V0 = V1 >> 1, and VF = the bit shifted out, 1
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0b0000_0010);
+        assert_eq!(chip8.v_registers()[0x1], 0b0000_0101);
+        assert_eq!(chip8.v_registers()[0xf], 1);
+    }
+
+    #[test]
+    fn test_shift_right_flag_clear() {
+        let program = [
+            0x6f, 0x01, // 200: VF = 1
+            0x61, 0x04, // 202: V1 = 0b0000_0100
+            0x80, 0x16, // 204: V0 = V1 >> 1, and VF = the bit shifted out, 0
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x0], 0b0000_0010);
+        assert_eq!(chip8.v_registers()[0xf], 0);
+    }
+
+    #[test]
+    fn test_shift_left_uses_vy() {
+        let program = [
+            0x61, 0x81, // 200: V1 = 0b1000_0001
+            0x80, 0x1e, // 202: V0 = V1 << 1, and VF = the bit shifted out, 1
+            0x62, 0x40, // 204: V2 = 0b0100_0000
+            0x83, 0x2e, // 206: V3 = V2 << 1, and VF = the bit shifted out, 0
+        ];
+
+        let chip8 = run(&program, 2);
+
+        assert_eq!(chip8.v_registers()[0x0], 0b0000_0010);
+        assert_eq!(chip8.v_registers()[0x1], 0b1000_0001);
+        assert_eq!(chip8.v_registers()[0xf], 1);
+
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.v_registers()[0x3], 0b1000_0000);
+        assert_eq!(chip8.v_registers()[0xf], 0);
+    }
+}
+
+mod skips {
+    use chip8::{cpu::Chip8, quirks::Quirks};
+
+    use super::{run, run_with_keys, NO_KEYS};
+
+    #[test]
+    fn test_skip_equal_value() {
+        let program = [
+            0x60, 0x2a, // 200: V0 = 42
+            0x30, 0x2a, // 202: skip the next instruction as V0 == 42
+            0x61, 0x01, // 204: (skipped) V1 = 1
+            0x30, 0x2b, // 206: don't skip as V0 != 43
+            0x62, 0x01, // 208: V2 = 1
+        ];
+
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.v_registers()[0x1], 0);
+        assert_eq!(chip8.v_registers()[0x2], 1);
+        assert_eq!(chip8.pc(), 0x20a);
+    }
+
+    #[test]
+    fn test_skip_not_equal_value() {
+        let program = [
+            0x60, 0x2a, // 200: V0 = 42
+            0x40, 0x2a, // 202: don't skip as V0 == 42
+            0x61, 0x01, // 204: V1 = 1
+            0x40, 0x2b, // 206: skip the next instruction as V0 != 43
+            0x62, 0x01, // 208: (skipped) V2 = 1
+        ];
+
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.v_registers()[0x1], 1);
+        assert_eq!(chip8.v_registers()[0x2], 0);
+        assert_eq!(chip8.pc(), 0x20a);
+    }
+
+    #[test]
+    fn test_skip_registers() {
+        let program = [
+            0x60, 0x07, // 200: V0 = 7
+            0x61, 0x07, // 202: V1 = 7
+            0x50, 0x10, // 204: skip the next instruction as V0 == V1
+            0x62, 0x01, // 206: (skipped) V2 = 1
+            0x90, 0x10, // 208: don't skip as V0 == V1
+            0x63, 0x01, // 20A: V3 = 1
+            0x90, 0x20, // 20C: skip the next instruction as V0 != V2
+            0x64, 0x01, // 20E: (skipped) V4 = 1
+        ];
+
+        let chip8 = run(&program, 6);
+
+        assert_eq!(chip8.v_registers()[0x2], 0);
+        assert_eq!(chip8.v_registers()[0x3], 1);
+        assert_eq!(chip8.v_registers()[0x4], 0);
+        assert_eq!(chip8.pc(), 0x210);
+    }
+
+    #[test]
+    fn test_skip_over_long_instruction() {
+        let program = [
+            0x30, 0x00, // 200: skip the next instruction as V0 == 0
+            0xf0, 0x00, // 202: (skipped) I = the next word
+            0x03, 0x00, // 204: (skipped) 0x0300, part of the instruction
+            0x61, 0x01, // 206: V1 = 1
+        ];
+
+        // F000 NNNN is only an instruction on XO-CHIP
+        let mut chip8 = Chip8::new_with_quirks(&program, Quirks::xochip());
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.i_register(), 0);
+        assert_eq!(chip8.v_registers()[0x1], 1);
+        assert_eq!(chip8.pc(), 0x208);
+    }
+
+    #[test]
+    fn test_skip_keys() {
+        let program = [
+            0x60, 0x0b, // 200: V0 = 0xB
+            0xe0, 0x9e, // 202: skip the next instruction if key V0 is held
+            0x61, 0x01, // 204: V1 = 1
+            0xe0, 0xa1, // 206: skip the next instruction if key V0 isn't held
+            0x62, 0x01, // 208: V2 = 1
+        ];
+        let mut keys = NO_KEYS;
+        keys[0xb] = true;
+
+        // with the key held only the first skip happens
+        let chip8 = run_with_keys(&program, 4, &keys);
+
+        assert_eq!(chip8.v_registers()[0x1], 0);
+        assert_eq!(chip8.v_registers()[0x2], 1);
+
+        // without it only the second one does
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.v_registers()[0x1], 1);
+        assert_eq!(chip8.v_registers()[0x2], 0);
+        assert_eq!(chip8.pc(), 0x20a);
+    }
+}
+
+mod memory {
+    use chip8::cpu::Chip8;
+
+    use super::{run, NO_KEYS};
+
+    #[test]
+    fn test_bcd_of_255() {
+        let program = [
+            0x60, 0xff, // 200: V0 = 255
+            0xa3, 0x00, // 202: I = 0x300
+            0xf0, 0x33, // 204: store the BCD of V0 at I: 2, 5, 5
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.dump_memory()[0x300..0x303], [2, 5, 5]);
+        // I isn't changed
+        assert_eq!(chip8.i_register(), 0x300);
+    }
+
+    #[test]
+    fn test_bcd_of_0() {
+        let program = [
+            0xa3, 0x00, // 200: I = 0x300
+            0xf0, 0x33, // 202: store the BCD of V0 at I: 0, 0, 0
+        ];
+        let mut chip8 = Chip8::new(&program);
+        for address in 0x300..0x303 {
+            chip8.write_memory(address, 0xff);
+        }
+
+        for _ in 0..2 {
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(chip8.dump_memory()[0x300..0x303], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_store_registers_increments_i() {
+        let program = [
+            0x60, 0x11, // 200: V0 = 0x11
+            0x61, 0x22, // 202: V1 = 0x22
+            0x62, 0x33, // 204: V2 = 0x33
+            0xa3, 0x00, // 206: I = 0x300
+            0xf1, 0x55, // 208: store V0 and V1 at I, then I += 2
+        ];
+
+        let chip8 = run(&program, 5);
+
+        // V2 isn't stored
+        assert_eq!(chip8.dump_memory()[0x300..0x303], [0x11, 0x22, 0x00]);
+        assert_eq!(chip8.i_register(), 0x302);
+    }
+
+    #[test]
+    fn test_load_registers_increments_i() {
+        let program = [
+            0xa2, 0x08, // 200: I = 0x208
+            0xf2, 0x65, // 202: load V0, V1 and V2 from I, then I += 3
+            0x12, 0x04, // 204: loop forever
+            0x00, 0x00, // 206: padding
+            0xab, 0xcd, // 208: data
+            0xef, 0x99, // 20A: data
+        ];
+
+        let chip8 = run(&program, 2);
+
+        assert_eq!(chip8.v_registers()[..4], [0xab, 0xcd, 0xef, 0x00]);
+        assert_eq!(chip8.i_register(), 0x20b);
+    }
+
+    #[test]
+    fn test_add_to_i_leaves_vf() {
+        let program = [
+            0x6f, 0x05, // 200: VF = 5
+            0x60, 0x10, // 202: V0 = 0x10
+            0xaf, 0xf8, // 204: I = 0xFF8
+            0xf0, 0x1e, // 206: I += V0, past 0xFFF without changing VF
+        ];
+
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.i_register(), 0x1008);
+        assert_eq!(chip8.v_registers()[0xf], 5);
+    }
+
+    #[test]
+    fn test_digit_sprite_address() {
+        let program = [
+            0x60, 0x1a, // 200: V0 = 0x1A
+            0xf0, 0x29, // 202: I = the address of the sprite for the low nibble, A
+        ];
+
+        let chip8 = run(&program, 2);
+
+        assert_eq!(chip8.i_register(), 0xa * 5);
+        let sprite = &chip8.dump_memory()[0xa * 5..0xa * 5 + 5];
+        assert_eq!(sprite, [0xf0, 0x90, 0xf0, 0x90, 0x90]);
+    }
+}
+
+mod flow {
+    use super::run;
+
+    #[test]
+    fn test_call_and_return() {
+        let program = [
+            0x22, 0x06, // 200: call 0x206
+            0x61, 0x01, // 202: V1 = 1, after returning
+            0x12, 0x04, // 204: loop forever
+            0x60, 0x01, // 206: V0 = 1
+            0x00, 0xee, // 208: return to 0x202
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(chip8.v_registers()[0x0], 1);
+
+        let chip8 = run(&program, 4);
+
+        assert_eq!(chip8.v_registers()[0x1], 1);
+        assert_eq!(chip8.pc(), 0x204);
+    }
+
+    #[test]
+    fn test_jump_plus_v0() {
+        let program = [
+            0x60, 0x04, // 200: V0 = 4
+            0xb2, 0x04, // 202: jump to 0x204 + V0 = 0x208
+            0x61, 0x01, // 204: (jumped over) V1 = 1
+            0x62, 0x01, // 206: (jumped over) V2 = 1
+            0x63, 0x01, // 208: V3 = 1
+        ];
+
+        let chip8 = run(&program, 3);
+
+        assert_eq!(chip8.v_registers()[0x1..0x4], [0, 0, 1]);
+        assert_eq!(chip8.pc(), 0x20a);
+    }
+
+    #[test]
+    fn test_timers() {
+        let program = [
+            0x60, 0x2a, // 200: V0 = 42
+            0xf0, 0x15, // 202: delay timer = V0
+            0xf0, 0x18, // 204: sound timer = V0
+            0xf1, 0x07, // 206: V1 = delay timer
+        ];
+
+        let chip8 = run(&program, 4);
+
+        // the timers only count down in update_timers()
+        assert_eq!(chip8.delay_timer(), 42);
+        assert_eq!(chip8.sound_timer(), 42);
+        assert_eq!(chip8.v_registers()[0x1], 42);
+    }
+}
+
+mod drawing {
+    use chip8::screen::Screen;
+
+    use super::run;
+
+    #[test]
+    fn test_draw_start_wraps() {
+        let chip8 = run(
+            &[
+                0x60, 0x46, // 200: V0 = 70, past the right edge
+                0x61, 0x28, // 202: V1 = 40, past the bottom edge
+                0x62, 0x00, // 204: V2 = 0
+                0xf2, 0x29, // 206: I = the sprite for the digit in V2
+                0xd0, 0x15, // 208: draw 5 rows at (V0, V1), which wraps to (6, 8)
+            ],
+            5,
+        );
+        let screen = chip8.get_screen();
+        // the top row of the 0 is 4 pixels wide
+        assert!((6..10).all(|x| screen.get_pixel(x, 8)));
+        assert!(!screen.get_pixel(5, 8) && !screen.get_pixel(10, 8));
+        assert_eq!(chip8.v_registers()[0xf], 0);
+    }
+
+    #[test]
+    fn test_draw_clips_after_wrapping() {
+        let chip8 = run(
+            &[
+                0x60, 0xfe, // 200: V0 = 254, which wraps to 62
+                0x61, 0xfe, // 202: V1 = 254, which wraps to 30
+                0x62, 0x00, // 204: V2 = 0
+                0xf2, 0x29, // 206: I = the sprite for the digit in V2
+                0xd0, 0x15, // 208: draw 5 rows at (V0, V1), clipping all but 2x2
+            ],
+            5,
+        );
+        let Screen::LowRes(screen) = chip8.get_screen() else {
+            panic!("the screen should be low-res");
+        };
+        assert!(screen.get_pixel(62, 30) && screen.get_pixel(63, 30));
+        assert!(screen.get_pixel(62, 31));
+        // nothing is drawn on the other side of the screen
+        assert!(!screen.get_pixel(0, 30) && !screen.get_pixel(62, 0));
+        assert_eq!(screen.rows().map(u128::count_ones).sum::<u32>(), 3);
+    }
+}
@@ -0,0 +1,171 @@
+//! Run CHIP-8 programs without a GUI, for scripts and CI. See `USAGE` for the
+//! command line arguments.
+
+use std::{fmt, io, path::PathBuf};
+
+use chip8::{
+    cpu::{Chip8, CycleError},
+    memory::LoadError,
+    quirks::Quirks,
+    screen::Screen,
+};
+
+/// How many CHIP-8 cycles are run per 60Hz frame unless `--cycles-per-frame`
+/// is given, the same as the GUI
+pub const DEFAULT_CYCLES_PER_FRAME: usize = 30;
+
+pub const USAGE: &str = "\
+Usage: chip8-cli <ROM> <FRAMES> [OPTIONS]
+
+Run the ROM for FRAMES 60Hz frames with no keys pressed, then print the screen
+with '#' for pixels which are on and '.' for pixels which are off.
+
+Options:
+    --cycles-per-frame <N>  Cycles to run per frame [default: 30]
+    --seed <N>              Seed for the random numbers of CXNN
+    --quirks <PRESET>       vip, chip48, schip or xochip [default: vip]
+    --png <PATH>            Write the screen to a PNG instead of printing it";
+
+/// The command line arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub rom: PathBuf,
+    pub frames: usize,
+    pub cycles_per_frame: usize,
+    /// The random number seed, or `None` to use the default one
+    pub seed: Option<u64>,
+    pub quirks: Quirks,
+    /// Where to write the screen as a PNG, or `None` to print it
+    pub png: Option<PathBuf>,
+}
+
+/// Parse the command line arguments, not including the program name
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, CliError> {
+    let mut positional = vec![];
+    let mut cycles_per_frame = DEFAULT_CYCLES_PER_FRAME;
+    let mut seed = None;
+    let mut quirks = Quirks::vip();
+    let mut png = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| CliError::Usage(format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "--cycles-per-frame" => cycles_per_frame = parse_number(&arg, &value()?)?,
+            "--seed" => seed = Some(parse_number(&arg, &value()?)?),
+            "--quirks" => {
+                let name = value()?;
+                quirks = quirks_from_name(&name)
+                    .ok_or_else(|| CliError::Usage(format!("unknown quirks preset {name}")))?;
+            }
+            "--png" => png = Some(PathBuf::from(value()?)),
+            _ if arg.starts_with("--") => {
+                return Err(CliError::Usage(format!("unknown option {arg}")))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let [rom, frames] = <[String; 2]>::try_from(positional)
+        .map_err(|_| CliError::Usage("expected a ROM and a number of frames".to_owned()))?;
+    Ok(Options {
+        rom: PathBuf::from(rom),
+        frames: parse_number("FRAMES", &frames)?,
+        cycles_per_frame,
+        seed,
+        quirks,
+        png,
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("{name} must be a number, not {value}")))
+}
+
+/// The quirks preset with the name given to `--quirks`
+pub fn quirks_from_name(name: &str) -> Option<Quirks> {
+    match name {
+        "vip" => Some(Quirks::vip()),
+        "chip48" => Some(Quirks::chip48()),
+        "schip" => Some(Quirks::schip()),
+        "xochip" => Some(Quirks::xochip()),
+        _ => None,
+    }
+}
+
+/// Run `program` as described by `options`, ignoring `options.rom`, and
+/// return the CHIP-8 as it was at the end
+pub fn run(program: &[u8], options: &Options) -> Result<Chip8, CliError> {
+    let mut chip8 = Chip8::try_new_with_quirks(program, options.quirks).map_err(CliError::Load)?;
+    if let Some(seed) = options.seed {
+        chip8.seed_rng(seed);
+    }
+    chip8
+        .run_frames(options.frames, options.cycles_per_frame, &[false; 16])
+        .map_err(|err| CliError::Cycle {
+            err,
+            pc: chip8.pc(),
+        })?;
+    Ok(chip8)
+}
+
+/// Write `screen` to `writer` as a black and white PNG with one pixel per
+/// CHIP-8 pixel
+pub fn write_png(screen: &Screen, writer: impl io::Write) -> Result<(), png::EncodingError> {
+    let (width, height) = (screen.width(), screen.height());
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let on = screen.get_pixel_planes(x as u8, y as u8) != 0;
+            data.push(if on { 0xff } else { 0x00 });
+        }
+    }
+    encoder.write_header()?.write_image_data(&data)
+}
+
+/// Error type for the CLI
+#[derive(Debug)]
+pub enum CliError {
+    /// The arguments were invalid, so `USAGE` should be shown
+    Usage(String),
+    Read {
+        path: PathBuf,
+        err: io::Error,
+    },
+    Write {
+        path: PathBuf,
+        err: io::Error,
+    },
+    Load(LoadError),
+    /// The program failed at `pc`
+    Cycle {
+        err: CycleError,
+        pc: u16,
+    },
+    Png(png::EncodingError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{message}"),
+            CliError::Read { path, err } => write!(f, "failed to read {}: {err}", path.display()),
+            CliError::Write { path, err } => {
+                write!(f, "failed to write {}: {err}", path.display())
+            }
+            CliError::Load(err) => write!(f, "failed to load the ROM: {err}"),
+            CliError::Cycle { err, pc } => write!(f, "the program failed at 0x{pc:03x}: {err}"),
+            CliError::Png(err) => write!(f, "failed to write the PNG: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
@@ -0,0 +1,39 @@
+use std::{fs, io, process::ExitCode};
+
+use chip8_cli::{parse_args, run, write_png, CliError, USAGE};
+
+fn main() -> ExitCode {
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err @ CliError::Usage(_)) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn try_main() -> Result<(), CliError> {
+    let options = parse_args(std::env::args().skip(1))?;
+    let program = fs::read(&options.rom).map_err(|err| CliError::Read {
+        path: options.rom.clone(),
+        err,
+    })?;
+    let chip8 = run(&program, &options)?;
+    match &options.png {
+        Some(path) => {
+            let file = fs::File::create(path).map_err(|err| CliError::Write {
+                path: path.clone(),
+                err,
+            })?;
+            write_png(chip8.get_screen(), io::BufWriter::new(file)).map_err(CliError::Png)
+        }
+        None => {
+            print!("{}", chip8.get_screen().to_ascii());
+            Ok(())
+        }
+    }
+}
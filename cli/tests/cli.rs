@@ -0,0 +1,89 @@
+//! Runs the CLI's logic on an embedded ROM, as `chip8-cli` would with the same
+//! arguments.
+
+use chip8_cli::{parse_args, run, write_png, CliError, Options};
+
+/// Draws a diagonal line from the top left, one pixel per loop
+const DIAGONAL_LOOP: [u8; 18] = [
+    0xa2, 0x10, // 200: I = 0x210
+    0x60, 0x00, // 202: V0 = 0
+    0xd0, 0x01, // 204: draw a single pixel at (V0, V0)
+    0x70, 0x01, // 206: V0 += 1
+    0x30, 0x20, // 208: skip the next instruction if V0 == 32
+    0x12, 0x04, // 20A: jump to 204
+    0x12, 0x0c, // 20C: loop forever
+    0x00, 0x00, // 20E: padding
+    0x80, 0x00, // 210: sprite data
+];
+
+fn args(args: &[&str]) -> Result<Options, CliError> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_run_prints_screen() {
+    let options = args(&["diagonal.ch8", "10", "--seed", "1", "--quirks", "chip48"]).unwrap();
+
+    let chip8 = run(&DIAGONAL_LOOP, &options).unwrap();
+    let text = chip8.get_screen().to_ascii();
+
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows.len(), 32);
+    for (y, row) in rows.iter().enumerate() {
+        assert_eq!(row.len(), 64);
+        assert_eq!(row.find('#'), Some(y), "row {y}: {row}");
+        assert_eq!(row.matches('#').count(), 1, "row {y}: {row}");
+    }
+}
+
+#[test]
+fn test_too_few_cycles_to_finish() {
+    let options = args(&["diagonal.ch8", "1", "--cycles-per-frame", "6"]).unwrap();
+
+    let chip8 = run(&DIAGONAL_LOOP, &options).unwrap();
+    let text = chip8.get_screen().to_ascii();
+
+    // 2 setup cycles, then 4 cycles per pixel
+    assert_eq!(text.matches('#').count(), 1);
+}
+
+#[test]
+fn test_write_png() {
+    let options = args(&["diagonal.ch8", "10"]).unwrap();
+    let chip8 = run(&DIAGONAL_LOOP, &options).unwrap();
+    let mut png = vec![];
+
+    write_png(chip8.get_screen(), &mut png).unwrap();
+
+    assert_eq!(
+        png[..8],
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+    );
+}
+
+#[test]
+fn test_invalid_args() {
+    for invalid in [
+        &["diagonal.ch8"][..],
+        &["diagonal.ch8", "ten"],
+        &["diagonal.ch8", "10", "extra"],
+        &["diagonal.ch8", "10", "--quirks", "nope"],
+        &["diagonal.ch8", "10", "--seed"],
+        &["diagonal.ch8", "10", "--verbose"],
+    ] {
+        assert!(
+            matches!(args(invalid), Err(CliError::Usage(_))),
+            "{invalid:?}"
+        );
+    }
+}
+
+#[test]
+fn test_program_error() {
+    // 00EE: return with an empty stack
+    let options = args(&["bad.ch8", "1"]).unwrap();
+
+    let result = run(&[0x00, 0xee], &options);
+
+    assert!(matches!(result, Err(CliError::Cycle { pc: 0x200, .. })));
+}
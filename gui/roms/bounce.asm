@@ -0,0 +1,31 @@
+; A ball bouncing off the edges of the screen
+    LD V0, 10       ; x
+    LD V1, 5        ; y
+    LD V2, 1        ; x speed
+    LD V3, 1        ; y speed
+    LD I, ball
+    DRW V0, V1, 4
+loop:
+    ; move every other frame
+    LD V4, 2
+    LD DT, V4
+wait:
+    LD V4, DT
+    SE V4, 0
+    JP wait
+    ; erase, move, and draw again
+    DRW V0, V1, 4
+    ADD V0, V2
+    ADD V1, V3
+    SNE V0, 0
+    LD V2, 1
+    SNE V0, 60
+    LD V2, 0xff     ; -1
+    SNE V1, 0
+    LD V3, 1
+    SNE V1, 28
+    LD V3, 0xff     ; -1
+    DRW V0, V1, 4
+    JP loop
+ball:
+    DB 0x60, 0xf0, 0xf0, 0x60
@@ -0,0 +1,19 @@
+; Draw the 16 hex digits from the font in two rows
+    LD V0, 0        ; the digit
+    LD V1, 4        ; x
+    LD V2, 6        ; y
+next:
+    LD F, V0
+    DRW V1, V2, 5
+    ADD V0, 1
+    ADD V1, 7
+    SE V0, 8
+    JP check_end
+    ; start the second row
+    LD V1, 4
+    LD V2, 20
+check_end:
+    SE V0, 16
+    JP next
+end:
+    JP end
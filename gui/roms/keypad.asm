@@ -0,0 +1,13 @@
+; Show the hex digit of the last key pressed in the middle of the screen
+    LD V0, 0
+    LD V1, 30       ; x
+    LD V2, 13       ; y
+    LD F, V0
+    DRW V1, V2, 5
+loop:
+    LD V0, K
+    ; I still points at the old digit, so drawing it again erases it
+    DRW V1, V2, 5
+    LD F, V0
+    DRW V1, V2, 5
+    JP loop
@@ -1,85 +1,1320 @@
-use std::{fs, io};
+use std::{fs, io, time::Duration};
 
-use chip8::cpu::Chip8;
-use egui::DroppedFile;
+use chip8::{
+    cpu::{Chip8, CycleError, CycleOutcome, StepOverError, TraceHook},
+    instruction::Inst,
+    recording::Recording,
+    timing::{FrameBudget, FRAME_TIME_MICROS},
+};
 
-use crate::{keyboard::get_key_state, screen_ui::draw_chip8_screen};
+#[cfg(target_arch = "wasm32")]
+use crate::url_loader::{query_rom_url, UrlLoader};
+use crate::{
+    bug_report::{looks_like_bug_report, BugReport},
+    crossfade::Crossfade,
+    dropped_files::{drop_candidates, DropCandidate, DropTracker},
+    examples::EXAMPLES,
+    frame_clock::{FrameClock, FRAME_TIME},
+    keyboard::{get_key_state, merge_key_states},
+    keypad::keypad_ui,
+    memory_viewer::MemoryViewer,
+    profile::profile_ui,
+    rate_counter::RateCounter,
+    rewind::{RewindBuffer, StepHistory},
+    rom_check::{check_rom, looks_byte_swapped, swap_bytes, unknown_opcodes_warning},
+    rom_overrides::{rom_hash, rom_override_ui},
+    runtime_error::{error_message, error_report},
+    save_slots::{SaveSlot, SlotStatus, SLOT_COUNT},
+    save_state::SaveState,
+    screen_fade::{FlickerMode, ScreenFade},
+    screen_ui::{draw_chip8_screen, pixel_at, screen_pixel_scale},
+    settings::{settings_menu, Settings},
+    storage,
+    timer_meters::timer_meters_ui,
+};
+
+/// The window title when no ROM is loaded
+const APP_TITLE: &str = "improved-broccoli";
+
+/// The window title with the name of the loaded ROM, if any
+fn window_title(loaded_name: Option<&str>) -> String {
+    match loaded_name {
+        Some(name) => format!("{name} - {APP_TITLE}"),
+        None => APP_TITLE.to_owned(),
+    }
+}
+
+/// The file name at the end of a path or URL, to show as the name of the ROM
+/// loaded from it
+fn display_name(path: &str) -> &str {
+    let path = match path.contains("://") {
+        true => path.split(['?', '#']).next().unwrap_or(path),
+        false => path,
+    };
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// How many CHIP-8 cycles are run per 60Hz frame at the default speed
+pub const CYCLES_PER_FRAME: u32 = 30;
+
+/// The default speed in instructions per second, which is `CYCLES_PER_FRAME`
+pub const DEFAULT_INSTRUCTIONS_PER_SECOND: u32 = CYCLES_PER_FRAME * 60;
+/// The slowest speed which can be set, in instructions per second
+pub const MIN_INSTRUCTIONS_PER_SECOND: u32 = 60;
+/// The fastest speed which can be set, in instructions per second
+pub const MAX_INSTRUCTIONS_PER_SECOND: u32 = 12000;
+
+/// How many cycles to run per 60Hz frame for a speed of
+/// `instructions_per_second`, rounded to the nearest whole cycle and at
+/// least one
+fn cycles_per_frame_at(instructions_per_second: u32) -> u32 {
+    ((instructions_per_second + 30) / 60).max(1)
+}
+
+/// How many cycles to run in a frame, where `base` is the normal number, which
+/// is multiplied while turbo is held. The timers are still updated once per
+/// frame, so they stay in real time.
+fn cycles_per_frame(base: u32, turbo: bool, turbo_multiplier: u32) -> u32 {
+    if turbo {
+        base.saturating_mul(turbo_multiplier)
+    } else {
+        base
+    }
+}
+
+/// How much of the COSMAC VIP's time to run per frame with the VIP timing
+/// setting, where `turbo` multiplies it as for `cycles_per_frame()`
+fn vip_frame_budget(turbo: bool, turbo_multiplier: u32) -> FrameBudget {
+    FrameBudget::Micros(cycles_per_frame(FRAME_TIME_MICROS, turbo, turbo_multiplier))
+}
+
+/// Slow `budget` down to `speed` times normal speed, where `frame` counts
+/// the frames run so far. Below one cycle per frame, a single cycle is run
+/// every few frames. The timers are still updated every frame, since on real
+/// hardware they don't depend on how fast the CPU is.
+fn slow_motion_budget(budget: FrameBudget, speed: f32, frame: u64) -> FrameBudget {
+    match budget {
+        FrameBudget::Cycles(cycles) => FrameBudget::Cycles(slow_motion(cycles, speed, frame)),
+        FrameBudget::Micros(micros) => FrameBudget::Micros(slow_motion(micros, speed, frame)),
+    }
+}
+
+fn slow_motion(amount: u32, speed: f32, frame: u64) -> u32 {
+    if speed >= 1.0 || amount == 0 {
+        return amount;
+    }
+    let per_frame = amount as f32 * speed.max(0.0);
+    if per_frame >= 1.0 {
+        per_frame.round() as u32
+    } else {
+        let interval = (1.0 / per_frame).round() as u64;
+        u32::from(frame % interval == 0)
+    }
+}
+
+/// How long to wait between repaints while the program runs without changing
+/// the screen. Input repaints immediately, and the frame clock catches up on
+/// the frames in between, so this only delays the next change to the screen.
+const IDLE_REPAINT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait between repaints while the program runs without changing
+/// the screen and the window isn't focused
+const BACKGROUND_REPAINT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// When the GUI should next be repainted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repaint {
+    /// As soon as possible, for smooth animation
+    Continuously,
+    /// After a delay, to keep the CHIP-8 running while using little CPU
+    After(Duration),
+    /// Only when there's input, which egui repaints for anyway
+    OnInput,
+}
+
+/// How to repaint, where `running` is whether the CHIP-8 is advancing and
+/// `dirty` is whether anything on screen changed or is animating this frame
+fn repaint_strategy(running: bool, dirty: bool, focused: bool) -> Repaint {
+    match (running, dirty, focused) {
+        (false, _, _) => Repaint::OnInput,
+        (true, true, _) => Repaint::Continuously,
+        (true, false, true) => Repaint::After(IDLE_REPAINT_INTERVAL),
+        (true, false, false) => Repaint::After(BACKGROUND_REPAINT_INTERVAL),
+    }
+}
+
+/// Log an instruction about to run, for capturing an execution log with
+/// `RUST_LOG=trace`
+fn trace_instruction(pc: u16, opcode: u16, inst: &Inst) {
+    tracing::trace!(
+        pc = %format_args!("{pc:#05x}"),
+        opcode = %format_args!("{opcode:#06x}"),
+        "{inst}"
+    );
+}
+
+/// The name of the file bug reports are saved to
+#[cfg(not(target_arch = "wasm32"))]
+const BUG_REPORT_FILE_NAME: &str = "improved-broccoli-bug-report.txt";
+
+/// How many cycles "Step over" runs before giving up on the subroutine
+/// returning, which is a minute of CHIP-8 time at normal speed
+const STEP_OVER_MAX_CYCLES: usize = CYCLES_PER_FRAME as usize * 60 * 60;
+
+/// How many cycles "Next draw" runs before giving up on the screen changing,
+/// which is a minute of CHIP-8 time at normal speed
+const NEXT_DRAW_MAX_CYCLES: usize = CYCLES_PER_FRAME as usize * 60 * 60;
+
+/// An error which stopped the program, which is left as it was when the
+/// error happened so it can be inspected
+struct Fault {
+    error: CycleError,
+    /// The program counter when the error happened, which is the address of
+    /// the instruction which failed
+    pc: u16,
+}
+
+/// The most cycles "Run N" runs at once, which is a minute of CHIP-8 time at
+/// normal speed
+const MAX_RUN_COUNT: usize = CYCLES_PER_FRAME as usize * 60 * 60;
+
+/// The number of cycles typed into the "Run N" box, clamped to between 1 and
+/// `MAX_RUN_COUNT`, or `None` if it isn't a number
+fn parse_run_count(text: &str) -> Option<usize> {
+    let count: u64 = text.trim().parse().ok()?;
+    Some(count.clamp(1, MAX_RUN_COUNT as u64) as usize)
+}
+
+/// A recording being played back in place of the keyboard
+struct Replay {
+    recording: Recording,
+    /// The index of the next frame to play
+    next_frame: usize,
+}
+
+impl Replay {
+    /// The keys held in the next frame, or `None` if the replay has finished
+    fn next_keys(&mut self) -> Option<[bool; 16]> {
+        let keys = self.recording.frames.get(self.next_frame).copied();
+        self.next_frame += 1;
+        keys
+    }
+}
 
 #[derive(Default)]
 pub struct App {
     chip8: Option<Chip8>,
+    /// The currently loaded program, kept so the CHIP-8 can be reset
+    program: Vec<u8>,
+    /// The `rom_hash()` of `program`, for finding its settings overrides
+    rom_hash: u64,
+    /// The seed of the CHIP-8's random number generator when `program` was
+    /// loaded
+    seed: u64,
+    /// The keys held in each frame since recording started. Stepping,
+    /// rewinding or stopping at a breakpoint while recording makes replays of
+    /// it diverge.
+    recording: Option<Recording>,
+    replay: Option<Replay>,
+    paused: bool,
     previous_keyboard_state: [bool; 16],
-    delta_accumulator: f32,
+    /// Keys held on the on-screen keypad during the last frame
+    keypad_state: [bool; 16],
+    clock: FrameClock,
+    /// A snapshot from the start of each recent frame
+    rewind: RewindBuffer,
+    /// Whether the rewind button was held during the last frame
+    rewinding: bool,
+    /// The CHIP-8 from before each single step since it was paused, for
+    /// undoing steps. Cleared whenever it's changed any other way.
+    step_history: StepHistory,
+    /// Pixel brightnesses for `FlickerMode::Smoothed`, updated every frame
+    /// whichever mode is selected so it can be switched to at any time
+    screen_fade: ScreenFade,
+    /// Fading from the previous program's screen after loading a program
+    crossfade: Option<Crossfade>,
+    /// CHIP-8 cycles run per second
+    ips_counter: RateCounter,
+    /// GUI frames drawn per second
+    fps_counter: RateCounter,
+    /// Frames run since the program was loaded, for slow motion
+    frame_number: u64,
 
     filename: String,
+    /// The name of the loaded ROM, such as its file name, for the window title
+    loaded_name: Option<String>,
+    /// The window title which was last set
+    window_title: String,
+
+    settings: Settings,
+    show_settings: bool,
+
+    memory_viewer: MemoryViewer,
+    show_memory_viewer: bool,
+    show_timers: bool,
+    /// Count the instructions run while the profile is shown
+    show_profile: bool,
+    /// Clicking a pixel on the screen toggles it, for testing drawing and
+    /// collisions
+    edit_pixels: bool,
+    /// Log every instruction with `trace_instruction()`, which is slow
+    trace_instructions: bool,
+
+    /// An error to show to the user until they dismiss it
+    error: Option<String>,
+    /// A message to show to the user until they dismiss it, such as where a
+    /// file was saved
+    notice: Option<String>,
+    /// The error which stopped the program, until it's reset or resumed
+    fault: Option<Fault>,
+    /// The number of cycles for "Run N", see `parse_run_count()`
+    run_count_text: String,
+    /// How many cycles the last "Run N" ran, which is fewer than asked for if
+    /// it stopped early
+    last_run_count: Option<usize>,
+    /// A warning about the loaded ROM, such as it using unknown opcodes, to
+    /// show until the user dismisses it
+    rom_warning: Option<String>,
+    /// Whether the loaded ROM looks like it was saved with the wrong byte
+    /// order, see `looks_byte_swapped()`
+    byte_swap_suggested: bool,
+    /// What's in each save state slot, or `None` if they need to be checked
+    /// again because they've changed
+    slot_statuses: Option<[SlotStatus; SLOT_COUNT]>,
+    /// Files dropped at the same time, which the user is choosing between
+    drop_candidates: Vec<DropCandidate>,
+    /// Stops a drop which is reported for several frames loading every frame
+    drop_tracker: DropTracker,
+    /// Fetches ROMs from URLs, since the web has no file picker
+    #[cfg(target_arch = "wasm32")]
+    url_loader: UrlLoader,
 }
 
 impl App {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Default::default()
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let settings = storage::load_settings(
+            storage::FileStorage::new()
+                .as_ref()
+                .map(|storage| storage as &dyn eframe::Storage),
+        );
+        #[cfg(target_arch = "wasm32")]
+        let settings = storage::load_settings(cc.storage);
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = cc;
+
+        // Load the ROM linked with `?rom=<url>`, if any
+        #[cfg(target_arch = "wasm32")]
+        let mut url_loader = UrlLoader::default();
+        #[cfg(target_arch = "wasm32")]
+        if let Some(url) = query_rom_url() {
+            url_loader.start(&cc.egui_ctx, url);
+        }
+
+        Self {
+            settings,
+            #[cfg(target_arch = "wasm32")]
+            url_loader,
+            ..Default::default()
+        }
     }
 }
 
-impl App {}
+impl App {
+    /// Whether a ROM is loaded. Controls which act on the running CHIP-8
+    /// should be disabled when this is false.
+    fn rom_loaded(&self) -> bool {
+        self.chip8.is_some()
+    }
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Check for dropped files to load
-        let dropped_file: Option<DroppedFile> = ctx.input(|i| i.raw.dropped_files.get(0).cloned());
-        if let Some(dropped_file) = dropped_file {
-            let program: Option<Vec<u8>> = if let Some(dropped_file_bytes) = dropped_file.bytes {
-                // If the dropped file comes with the file bytes (e.g. web), just
-                // use them
-                Some(dropped_file_bytes.to_vec())
-            } else if let Some(dropped_file_path) = dropped_file.path {
-                // Otherwise, try to get the filename and load the file
-
-                // Can't get local file on wasm
-                #[cfg(not(target_arch = "wasm32"))]
-                {
-                    let mut file = fs::File::open(std::path::Path::new(&dropped_file_path)).unwrap();
-                    let mut program: Vec<u8> = vec![];
-                    io::Read::read_to_end(&mut file, &mut program).unwrap();
-                    Some(program)
+    /// How many cycles run per frame at normal speed, from this ROM's
+    /// override or the global setting
+    fn base_cycles_per_frame(&self) -> u32 {
+        cycles_per_frame_at(
+            self.settings
+                .rom_overrides
+                .get(&self.rom_hash)
+                .map_or(self.settings.instructions_per_second, |rom_override| {
+                    rom_override.instructions_per_second
+                }),
+        )
+    }
+
+    /// Load and start running `program`, whose name for the window title is
+    /// `name`
+    fn load_program(&mut self, ctx: &egui::Context, name: Option<String>, program: Vec<u8>) {
+        let quirks = self.settings.quirks_for_rom(rom_hash(&program));
+        let mut chip8 = match Chip8::try_new_with_quirks(&program, quirks) {
+            Ok(chip8) => chip8,
+            Err(err) => {
+                self.error = Some(format!("Failed to load the program: {err}"));
+                return;
+            }
+        };
+        // different every time, so random numbers differ between runs
+        self.seed = ctx.input(|i| i.time).to_bits() ^ rom_hash(&program);
+        chip8.seed_rng(self.seed);
+        self.crossfade = match &self.chip8 {
+            Some(previous) if self.settings.crossfade => {
+                Some(Crossfade::new(previous.get_screen().clone()))
+            }
+            _ => None,
+        };
+        self.chip8 = Some(chip8);
+        self.loaded_name = name;
+        self.recording = None;
+        self.replay = None;
+        self.rom_hash = rom_hash(&program);
+        self.rom_warning = unknown_opcodes_warning(&program);
+        self.byte_swap_suggested = looks_byte_swapped(&program);
+        self.program = program;
+        self.paused = false;
+        self.fault = None;
+        self.last_run_count = None;
+        self.screen_fade.clear();
+        self.clock.reset();
+        self.frame_number = 0;
+        self.rewind.clear();
+        self.step_history.clear();
+        ctx.request_repaint();
+    }
+
+    fn load_dropped_file(&mut self, ctx: &egui::Context, candidate: &DropCandidate) {
+        match candidate.read() {
+            Ok(bytes) if looks_like_bug_report(&bytes) => {
+                let report = String::from_utf8_lossy(&bytes);
+                match BugReport::from_storage_string(&report) {
+                    Ok(report) => self.open_bug_report(ctx, report),
+                    Err(err) => {
+                        self.error = Some(format!("Failed to load {}: {err}", candidate.name))
+                    }
+                }
+            }
+            Ok(program) => match check_rom(&program) {
+                Ok(()) => self.load_program(ctx, Some(candidate.name.clone()), program),
+                Err(err) => self.error = Some(format!("Failed to load {}: {err}", candidate.name)),
+            },
+            Err(err) => self.error = Some(format!("Failed to read {}: {err}", candidate.name)),
+        }
+    }
+
+    /// Let the user choose which of several dropped files to load
+    fn drop_picker(&mut self, ctx: &egui::Context) {
+        let mut chosen = None;
+        let mut open = true;
+        egui::Window::new("Choose a file to load")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                for (i, candidate) in self.drop_candidates.iter().enumerate() {
+                    if ui.button(&candidate.name).clicked() {
+                        chosen = Some(i);
+                    }
                 }
+            });
+        if let Some(i) = chosen {
+            let candidate = self.drop_candidates.swap_remove(i);
+            self.load_dropped_file(ctx, &candidate);
+            self.drop_candidates.clear();
+        } else if !open {
+            self.drop_candidates.clear();
+        }
+    }
 
-                #[cfg(target_arch = "wasm32")]
-                None
-            } else {
-                // Otherwise, nothing useful from the dropped file
-                None
+    /// Whether the CHIP-8 should currently be advancing
+    fn is_running(&self, focused: bool) -> bool {
+        self.rom_loaded()
+            && !self.paused
+            && !self.rewinding
+            && (focused || !self.settings.pause_when_unfocused)
+    }
+
+    /// Advance the clock by `delta_time` seconds and return the number of
+    /// CHIP-8 frames to run. Time spent not running is discarded, so the
+    /// CHIP-8 resumes cleanly rather than trying to catch up.
+    fn advance_clock(&mut self, delta_time: f32, focused: bool) -> u32 {
+        if self.is_running(focused) {
+            self.clock.advance(delta_time)
+        } else {
+            self.clock.reset();
+            0
+        }
+    }
+
+    fn reset(&mut self, ctx: &egui::Context) {
+        if self.rom_loaded() {
+            // cloned rather than taken, as the program may no longer fit if
+            // the quirks have changed
+            self.load_program(ctx, self.loaded_name.clone(), self.program.clone());
+        }
+    }
+
+    /// Restore the CHIP-8 to the start of the most recent frame in the rewind
+    /// buffer. Returns false if there's nothing to rewind to.
+    fn step_back(&mut self) -> bool {
+        if let Some(chip8) = &mut self.chip8 {
+            if let Some(snapshot) = self.rewind.pop() {
+                chip8
+                    .restore_snapshot(&snapshot)
+                    .expect("snapshots of the running CHIP-8 should be valid");
+                self.fault = None;
+                self.step_history.clear();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Restore the CHIP-8 to how it was before the most recent single step.
+    /// Returns false if there's no step to undo.
+    fn undo_step(&mut self) -> bool {
+        match self.step_history.pop() {
+            Some(chip8) if self.chip8.is_some() => {
+                self.chip8 = Some(chip8);
+                self.fault = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Save the CHIP-8's state and program so the user can return to it later
+    fn save_state(&mut self, frame: &mut eframe::Frame) {
+        if let Some(chip8) = &self.chip8 {
+            let state = SaveState {
+                program: self.program.clone(),
+                snapshot: chip8.to_snapshot(),
+            };
+            if storage::with_storage(frame, |storage| storage::save_state(storage, &state))
+                .is_none()
+            {
+                self.error = Some("There's nowhere to save the state on this platform".to_owned());
+            }
+        }
+    }
+
+    /// Everything needed to reproduce what's happening now, for the user to
+    /// attach to an issue
+    fn bug_report(&self) -> BugReport {
+        BugReport {
+            app_version: BugReport::APP_VERSION.to_owned(),
+            rom_name: self.loaded_name.clone(),
+            rom_hash: self.rom_hash,
+            settings: self.settings.clone(),
+            state: self.chip8.as_ref().map(|chip8| SaveState {
+                program: self.program.clone(),
+                snapshot: chip8.to_snapshot(),
+            }),
+            error: self
+                .fault
+                .as_ref()
+                .map(|fault| error_report(&fault.error, fault.pc)),
+        }
+    }
+
+    /// Save a bug report to the downloads folder, or copy it to the clipboard
+    /// on the web where there are no files
+    #[cfg_attr(not(target_arch = "wasm32"), allow(unused_variables))]
+    fn save_bug_report(&mut self, ctx: &egui::Context) {
+        let report = self.bug_report().to_storage_string();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Some(dir) = dirs::download_dir().or_else(dirs::home_dir) else {
+                self.error = Some("There's nowhere to save the bug report".to_owned());
+                return;
             };
+            let path = dir.join(BUG_REPORT_FILE_NAME);
+            match fs::write(&path, report) {
+                Ok(()) => {
+                    self.notice = Some(format!(
+                        "Saved a bug report to {}. Please attach it to your issue.",
+                        path.display()
+                    ));
+                }
+                Err(err) => {
+                    self.error = Some(format!(
+                        "Failed to write the bug report to {}: {err}",
+                        path.display()
+                    ));
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            ctx.output_mut(|o| o.copied_text = report);
+            self.notice = Some(
+                "Copied a bug report to the clipboard. Please paste it into your issue.".to_owned(),
+            );
+        }
+    }
 
-            // If we got a program from the dropped file, load it
-            if let Some(program) = program {
-                self.chip8 = Some(Chip8::new(&program));
-                self.delta_accumulator = 0.0;
+    /// Load the program and state from a bug report, paused so it can be
+    /// inspected. The user's own settings are kept.
+    fn open_bug_report(&mut self, ctx: &egui::Context, report: BugReport) {
+        let Some(state) = report.state else {
+            self.error = Some("The bug report was made without a ROM loaded".to_owned());
+            return;
+        };
+        match state.to_chip8() {
+            Ok(chip8) => {
+                self.chip8 = Some(chip8);
+                self.loaded_name = report.rom_name;
+                self.rom_hash = rom_hash(&state.program);
+                self.program = state.program;
+                self.paused = true;
+                self.fault = None;
+                self.screen_fade.clear();
+                self.clock.reset();
+                self.rewind.clear();
+                self.step_history.clear();
+                self.notice = report
+                    .error
+                    .map(|error| format!("The reported program stopped with: {error}"));
                 ctx.request_repaint();
             }
+            Err(err) => self.error = Some(format!("Failed to load the bug report: {err}")),
         }
+    }
 
-        // Handle CHIP-8 simulation
+    /// Replace the CHIP-8 and its program with the saved state
+    fn load_state(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let result = storage::with_storage(frame, |storage| storage::load_state(storage))
+            .flatten()
+            .map(|state| state.and_then(|state| Ok((state.to_chip8()?, state.program))));
+        match result {
+            Some(Ok((chip8, program))) => self.restore_state(ctx, chip8, program),
+            Some(Err(err)) => self.error = Some(format!("Failed to load the state: {err}")),
+            None => self.error = Some("No state has been saved".to_owned()),
+        }
+    }
+
+    /// Replace the CHIP-8 and its program with ones restored from a save
+    /// state. The ROM's name is kept if it's the same program.
+    fn restore_state(&mut self, ctx: &egui::Context, chip8: Chip8, program: Vec<u8>) {
+        self.chip8 = Some(chip8);
+        if rom_hash(&program) != self.rom_hash {
+            self.loaded_name = None;
+        }
+        self.rom_hash = rom_hash(&program);
+        self.program = program;
+        self.screen_fade.clear();
+        self.clock.reset();
+        self.rewind.clear();
+        self.step_history.clear();
+        ctx.request_repaint();
+    }
+
+    /// Save the CHIP-8's state and program to a slot, replacing what was there
+    fn save_slot(&mut self, frame: &mut eframe::Frame, slot: usize) {
+        if let Some(chip8) = &self.chip8 {
+            let save_slot = SaveSlot {
+                name: self.loaded_name.clone(),
+                state: SaveState {
+                    program: self.program.clone(),
+                    snapshot: chip8.to_snapshot(),
+                },
+            };
+            let saved = storage::with_storage(frame, |storage| {
+                storage::save_slot(storage, slot, &save_slot)
+            });
+            if saved.is_none() {
+                self.error = Some("There's nowhere to save the state on this platform".to_owned());
+            }
+            self.slot_statuses = None;
+        }
+    }
+
+    /// Replace the CHIP-8 and its program with the state in a slot
+    fn load_slot(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame, slot: usize) {
+        let result = storage::with_storage(frame, |storage| storage::load_slot(storage, slot))
+            .flatten()
+            .map(|save_slot| {
+                save_slot.and_then(|save_slot| {
+                    let chip8 = save_slot.state.to_chip8()?;
+                    Ok((chip8, save_slot.state.program, save_slot.name))
+                })
+            });
+        match result {
+            Some(Ok((chip8, program, name))) => {
+                self.restore_state(ctx, chip8, program);
+                self.loaded_name = name;
+            }
+            Some(Err(err)) => self.error = Some(format!("Failed to load slot {}: {err}", slot + 1)),
+            None => self.error = Some(format!("Slot {} is empty", slot + 1)),
+        }
+    }
+
+    fn delete_slot(&mut self, frame: &mut eframe::Frame, slot: usize) {
+        storage::with_storage(frame, |storage| storage::delete_slot(storage, slot));
+        self.slot_statuses = None;
+    }
+
+    /// Buttons to save, load and delete each save state slot
+    fn slot_controls(&mut self, ui: &mut egui::Ui, frame: &mut eframe::Frame) {
+        if self.slot_statuses.is_none() {
+            self.slot_statuses =
+                storage::with_storage(frame, |storage| storage::slot_statuses(storage));
+        }
+        let Some(statuses) = self.slot_statuses.clone() else {
+            ui.label("There's nowhere to save states on this platform");
+            return;
+        };
+        egui::Grid::new("save_slots").show(ui, |ui| {
+            for (slot, status) in statuses.iter().enumerate() {
+                ui.label(format!("Slot {}", slot + 1));
+                match status {
+                    SlotStatus::Empty => ui.weak("Empty"),
+                    SlotStatus::Occupied { name } => ui.label(name.as_deref().unwrap_or("Unnamed")),
+                    SlotStatus::Invalid(err) => ui
+                        .colored_label(ui.visuals().error_fg_color, "Unreadable")
+                        .on_hover_text(err),
+                };
+                if ui
+                    .add_enabled(self.rom_loaded(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    self.save_slot(frame, slot);
+                }
+                let occupied = matches!(status, SlotStatus::Occupied { .. });
+                if ui
+                    .add_enabled(occupied, egui::Button::new("Load"))
+                    .clicked()
+                {
+                    self.load_slot(ui.ctx(), frame, slot);
+                    ui.close_menu();
+                }
+                let empty = *status == SlotStatus::Empty;
+                if ui
+                    .add_enabled(!empty, egui::Button::new("Delete"))
+                    .clicked()
+                {
+                    self.delete_slot(frame, slot);
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Pause at `error`, leaving the CHIP-8 as it was when it happened so the
+    /// user can inspect it, and report it
+    fn stop_at_fault(&mut self, error: CycleError) {
+        let pc = self.chip8.as_ref().map_or(0, Chip8::pc);
+        self.paused = true;
+        self.error = Some(error_report(&error, pc));
+        self.fault = Some(Fault { error, pc });
+    }
+
+    /// Run a single CHIP-8 cycle, for stepping while paused
+    fn step(&mut self, keyboard_state: &[bool; 16]) {
         if let Some(chip8) = &mut self.chip8 {
-            let delta_time = ctx.input(|i| i.unstable_dt);
-            self.delta_accumulator += delta_time;
-            let frametime = 1.0 / 60.0; // CHIP-8 runs at 60hz
+            self.step_history.push(chip8);
+            let result = chip8.cycle(keyboard_state, &self.previous_keyboard_state);
+            self.previous_keyboard_state = *keyboard_state;
+            if let Err(err) = result {
+                self.stop_at_fault(err);
+            }
+        }
+    }
 
-            let mut keyboard_state: [bool; 16] = Default::default();
-            ctx.input(|i| keyboard_state = get_key_state(i));
+    /// Run until the instruction at the program counter has completed,
+    /// including the whole subroutine if it's a call, for stepping while paused
+    fn step_over(&mut self, keyboard_state: &[bool; 16]) {
+        self.step_history.clear();
+        let cycles_per_frame = self.base_cycles_per_frame();
+        if let Some(chip8) = &mut self.chip8 {
+            let result = chip8.step_over(
+                keyboard_state,
+                &self.previous_keyboard_state,
+                cycles_per_frame as usize,
+                STEP_OVER_MAX_CYCLES,
+            );
+            self.previous_keyboard_state = *keyboard_state;
+            match result {
+                Ok(_) => {}
+                Err(StepOverError::CycleError(err)) => self.stop_at_fault(err),
+                Err(err @ StepOverError::CycleLimitReached { .. }) => {
+                    self.error = Some(format!("Stopped stepping over: {err}"));
+                }
+            }
+        }
+    }
+
+    /// Run `count` cycles, stopping early at a breakpoint or error, for
+    /// stepping quickly while paused
+    fn run_cycles(&mut self, count: usize, keyboard_state: &[bool; 16]) {
+        self.step_history.clear();
+        if let Some(chip8) = &mut self.chip8 {
+            let (ran, result) = chip8.cycle_n(count, keyboard_state, &self.previous_keyboard_state);
+            self.previous_keyboard_state = *keyboard_state;
+            self.last_run_count = Some(ran);
+            if let Err(err) = result {
+                self.stop_at_fault(err);
+            }
+        }
+    }
 
-            while self.delta_accumulator > frametime {
-                // TODO un-hardcode cycles per frame
-                for _ in 0..30 {
-                    chip8
-                        .cycle(&keyboard_state, &self.previous_keyboard_state)
-                        .unwrap();
+    /// Run until the screen is drawn to, for stepping through animations
+    /// while paused
+    fn run_to_next_draw(&mut self, keyboard_state: &[bool; 16]) {
+        self.step_history.clear();
+        let cycles_per_frame = self.base_cycles_per_frame();
+        if let Some(chip8) = &mut self.chip8 {
+            let result = chip8.run_to_next_draw(
+                keyboard_state,
+                &self.previous_keyboard_state,
+                cycles_per_frame as usize,
+                NEXT_DRAW_MAX_CYCLES,
+            );
+            self.previous_keyboard_state = *keyboard_state;
+            if let Err(err) = result {
+                self.stop_at_fault(err);
+            }
+        }
+    }
+
+    /// Restart the program and record the keys held in each frame from now on
+    fn start_recording(&mut self, ctx: &egui::Context, cycles_per_frame: u32) {
+        self.reset(ctx);
+        if self.rom_loaded() {
+            self.recording = Some(Recording::new(self.rom_hash, self.seed, cycles_per_frame));
+            self.previous_keyboard_state = [false; 16];
+        }
+    }
+
+    fn stop_recording(&mut self, frame: &mut eframe::Frame) {
+        if let Some(recording) = self.recording.take() {
+            if storage::with_storage(frame, |storage| {
+                storage::save_recording(storage, &recording)
+            })
+            .is_none()
+            {
+                self.error =
+                    Some("There's nowhere to save the recording on this platform".to_owned());
+            }
+        }
+    }
+
+    /// Restart the program and play back the saved recording in place of the
+    /// keyboard
+    fn start_replay(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let result =
+            storage::with_storage(frame, |storage| storage::load_recording(storage)).flatten();
+        match result {
+            Some(Ok(recording)) if recording.rom_hash != self.rom_hash => {
+                self.error = Some("The recording is of a different ROM".to_owned());
+            }
+            Some(Ok(recording)) => {
+                self.reset(ctx);
+                if let Some(chip8) = &mut self.chip8 {
+                    chip8.seed_rng(recording.seed);
+                    self.previous_keyboard_state = [false; 16];
+                    self.replay = Some(Replay {
+                        recording,
+                        next_frame: 0,
+                    });
+                }
+            }
+            Some(Err(err)) => self.error = Some(format!("Failed to load the recording: {err}")),
+            None => self.error = Some("Nothing has been recorded".to_owned()),
+        }
+    }
+
+    fn recording_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        frame: &mut eframe::Frame,
+        cycles_per_frame: u32,
+    ) {
+        if let Some(recording) = &self.recording {
+            ui.label(format!("Recorded {} frames", recording.frames.len()));
+            if ui
+                .button("Stop and save recording")
+                .on_hover_text("Replaces the previously saved recording")
+                .clicked()
+            {
+                self.stop_recording(frame);
+                ui.close_menu();
+            }
+        } else if let Some(replay) = &self.replay {
+            ui.label(format!(
+                "Replaying frame {} of {}",
+                replay.next_frame.min(replay.recording.frames.len()),
+                replay.recording.frames.len()
+            ));
+            if ui.button("Stop replay").clicked() {
+                self.replay = None;
+                ui.close_menu();
+            }
+        } else {
+            ui.add_enabled_ui(self.rom_loaded(), |ui| {
+                if ui
+                    .button("Start recording")
+                    .on_hover_text("Restart the ROM and record the keys pressed")
+                    .clicked()
+                {
+                    self.start_recording(ui.ctx(), cycles_per_frame);
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Replay recording")
+                    .on_hover_text("Restart the ROM and play back the saved recording")
+                    .clicked()
+                {
+                    self.start_replay(ui.ctx(), frame);
+                    ui.close_menu();
+                }
+            });
+        }
+    }
+
+    fn run_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        frame: &mut eframe::Frame,
+        keyboard_state: &[bool; 16],
+    ) {
+        ui.add_enabled_ui(self.rom_loaded(), |ui| {
+            let pause_text = if self.paused { "Resume" } else { "Pause" };
+            if ui.button(pause_text).clicked() {
+                self.paused = !self.paused;
+                // resuming runs the failed instruction again
+                self.fault = None;
+                self.step_history.clear();
+            }
+            if ui
+                .add_enabled(self.paused, egui::Button::new("Step"))
+                .clicked()
+            {
+                self.step(keyboard_state);
+            }
+            if ui
+                .add_enabled(
+                    self.paused && !self.step_history.is_empty(),
+                    egui::Button::new("Undo step"),
+                )
+                .on_hover_text(format!(
+                    "Go back to before the last Step, {} can be undone",
+                    self.step_history.len()
+                ))
+                .clicked()
+            {
+                self.undo_step();
+            }
+            if ui
+                .add_enabled(self.paused, egui::Button::new("Step over"))
+                .on_hover_text("Step, running the whole subroutine if this is a call")
+                .clicked()
+            {
+                self.step_over(keyboard_state);
+            }
+            if ui
+                .add_enabled(self.paused, egui::Button::new("Next draw"))
+                .on_hover_text("Run until the screen is drawn to or cleared")
+                .clicked()
+            {
+                self.run_to_next_draw(keyboard_state);
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.run_count_text)
+                    .hint_text("N")
+                    .desired_width(50.0),
+            );
+            let run_count = parse_run_count(&self.run_count_text);
+            if ui
+                .add_enabled(
+                    self.paused && run_count.is_some(),
+                    egui::Button::new("Run N"),
+                )
+                .on_hover_text(format!(
+                    "Run N instructions, up to {MAX_RUN_COUNT}, stopping at breakpoints"
+                ))
+                .clicked()
+            {
+                if let Some(count) = run_count {
+                    self.run_cycles(count, keyboard_state);
+                }
+            }
+            if let Some(ran) = self.last_run_count {
+                ui.label(format!("Ran {ran}"));
+            }
+            if ui
+                .add_enabled(
+                    self.paused && !self.rewind.is_empty(),
+                    egui::Button::new("Step back"),
+                )
+                .on_hover_text("Go back to the start of the previous frame")
+                .clicked()
+            {
+                self.step_back();
+            }
+            self.rewinding = ui
+                .add_enabled(!self.rewind.is_empty(), egui::Button::new("Rewind"))
+                .on_hover_text(format!(
+                    "Hold to rewind, up to {:.1} seconds",
+                    self.rewind.len() as f32 * FRAME_TIME
+                ))
+                .is_pointer_button_down_on();
+            if ui.button("Reset").clicked() {
+                self.reset(ui.ctx());
+            }
+            if ui.button("Save state").clicked() {
+                self.save_state(frame);
+            }
+            if ui
+                .button("Copy screen")
+                .on_hover_text("Copy the screen as text art, for sharing")
+                .clicked()
+            {
+                if let Some(chip8) = &self.chip8 {
+                    let text = chip8.get_screen().to_block_art();
+                    ui.output_mut(|o| o.copied_text = text);
+                }
+            }
+        });
+        if ui.button("Load state").clicked() {
+            self.load_state(ui.ctx(), frame);
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage::save_settings(storage, &self.settings);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // eframe doesn't call `save()` on native without its persistence
+        // feature, so save to our own storage instead
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(mut storage) = storage::FileStorage::new() {
+            storage::save_settings(&mut storage, &self.settings);
+            eframe::Storage::flush(&mut storage);
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Check for dropped files to load, asking which to load if there are
+        // several
+        let candidates = ctx.input(|i| match self.drop_tracker.is_new(&i.raw.dropped_files) {
+            true => drop_candidates(&i.raw.dropped_files),
+            false => Vec::new(),
+        });
+        match candidates.len() {
+            0 => {}
+            1 => self.load_dropped_file(ctx, &candidates[0]),
+            _ => self.drop_candidates = candidates,
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        if let Some(result) = self.url_loader.poll() {
+            match result.map(|program| check_rom(&program).map(|()| program)) {
+                Ok(Ok(program)) => {
+                    let name = display_name(self.url_loader.url()).to_owned();
+                    self.load_program(ctx, Some(name), program);
+                }
+                Ok(Err(err)) => {
+                    self.url_loader.set_error(err.to_string());
+                    self.error = Some(format!("Failed to load the ROM: {err}"));
+                }
+                Err(err) => self.error = Some(format!("Failed to fetch the ROM: {err}")),
+            }
+        }
+
+        // the browser tab keeps the page's title
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let title = window_title(self.loaded_name.as_deref());
+            if title != self.window_title {
+                frame.set_window_title(&title);
+                self.window_title = title;
+            }
+        }
+
+        let mut keyboard_state: [bool; 16] = Default::default();
+        ctx.input(|i| {
+            // there's no gamepad backend yet, so no buttons are ever held
+            keyboard_state =
+                get_key_state(i, &self.settings.key_map, &[], &self.settings.gamepad_map)
+        });
+        let keyboard_state = merge_key_states(&keyboard_state, &self.keypad_state);
+
+        // Handle CHIP-8 simulation
+        let focused = ctx.input(|i| i.raw.has_focus);
+        let delta_time = ctx.input(|i| i.unstable_dt);
+        let frames = self.advance_clock(delta_time, focused);
+        let turbo = ctx.input(|i| i.key_down(self.settings.turbo_key));
+        let base_cycles = self.base_cycles_per_frame();
+        let budget = if let Some(replay) = &self.replay {
+            FrameBudget::Cycles(replay.recording.cycles_per_frame)
+        } else if self.recording.is_some() {
+            // turbo isn't recorded, and recordings are replayed by cycle count
+            FrameBudget::Cycles(base_cycles)
+        } else if self.settings.vip_timing {
+            vip_frame_budget(turbo, self.settings.turbo_multiplier)
+        } else {
+            FrameBudget::Cycles(cycles_per_frame(
+                base_cycles,
+                turbo,
+                self.settings.turbo_multiplier,
+            ))
+        };
+        let speed = if self.replay.is_some() || self.recording.is_some() || turbo {
+            1.0
+        } else {
+            self.settings.speed
+        };
+        if self.rewinding {
+            self.step_back();
+            ctx.request_repaint();
+        }
+        let mut cycles_run = 0;
+        let mut fault = None;
+        let screen_before = self.chip8.as_ref().map(|chip8| chip8.get_screen().clone());
+        if let Some(chip8) = &mut self.chip8 {
+            let trace_hook = self
+                .trace_instructions
+                .then_some(trace_instruction as TraceHook);
+            chip8.set_trace_hook(trace_hook);
+            chip8.set_count_instructions(self.show_profile);
+            'frames: for _ in 0..frames {
+                let keyboard_state = match &mut self.replay {
+                    Some(replay) => match replay.next_keys() {
+                        Some(keys) => keys,
+                        None => {
+                            self.replay = None;
+                            self.paused = true;
+                            break 'frames;
+                        }
+                    },
+                    None => keyboard_state,
+                };
+                let budget = slow_motion_budget(budget, speed, self.frame_number);
+                self.frame_number += 1;
+                // in slow motion, frames where nothing runs aren't worth
+                // rewinding to
+                if !budget.is_spent(0, 0) {
+                    self.rewind.push(chip8.to_snapshot());
+                }
+                let (mut frame_cycles, mut frame_micros) = (0, 0);
+                while !budget.is_spent(frame_cycles, frame_micros) {
+                    frame_cycles += 1;
+                    cycles_run += 1;
+                    match chip8.cycle(&keyboard_state, &self.previous_keyboard_state) {
+                        Ok(CycleOutcome::BreakpointHit) => {
+                            self.paused = true;
+                            self.previous_keyboard_state = keyboard_state;
+                            break 'frames;
+                        }
+                        // nothing more will run, but the timers still tick
+                        Ok(CycleOutcome::Halted) => break,
+                        Ok(_) => frame_micros += chip8.last_cycle_cost(),
+                        Err(err) => {
+                            // stop rather than keep hitting the same error
+                            fault = Some(err);
+                            break 'frames;
+                        }
+                    }
                 }
                 chip8.update_timers();
+                self.screen_fade
+                    .update(chip8.get_screen(), self.settings.fade_decay);
+                if let Some(recording) = &mut self.recording {
+                    recording.push_frame(keyboard_state);
+                }
                 self.previous_keyboard_state = keyboard_state;
-                self.delta_accumulator -= frametime;
             }
+        }
+        if let Some(err) = fault {
+            self.stop_at_fault(err);
+        }
+        if let Some(crossfade) = &mut self.crossfade {
+            if !crossfade.advance(frames) {
+                self.crossfade = None;
+            }
+        }
+        let screen_changed = match (&self.chip8, &screen_before) {
+            (Some(chip8), Some(before)) => chip8.get_screen() != before,
+            _ => false,
+        };
+        let fading =
+            self.settings.flicker_mode == FlickerMode::Smoothed && self.screen_fade.is_fading();
+        let dirty =
+            !self.settings.power_saving || screen_changed || fading || self.crossfade.is_some();
+        match repaint_strategy(self.is_running(focused), dirty, focused) {
+            Repaint::Continuously => ctx.request_repaint(),
+            Repaint::After(delay) => ctx.request_repaint_after(delay),
+            Repaint::OnInput => {}
+        }
+        let now = ctx.input(|i| i.time);
+        self.ips_counter.record(now, cycles_run);
+        self.fps_counter.record(now, 1);
 
-            ctx.request_repaint();
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.toggle_value(&mut self.show_settings, "Settings");
+                ui.toggle_value(&mut self.show_memory_viewer, "Memory");
+                ui.toggle_value(&mut self.show_timers, "Timers");
+                ui.toggle_value(&mut self.show_profile, "Profile")
+                    .on_hover_text("Count which instructions run most often");
+                ui.toggle_value(&mut self.edit_pixels, "Edit pixels")
+                    .on_hover_text("Click pixels on the screen to toggle them");
+                ui.toggle_value(&mut self.trace_instructions, "Trace")
+                    .on_hover_text("Log every instruction run, with RUST_LOG=trace");
+                ui.menu_button("Examples", |ui| {
+                    for example in &EXAMPLES {
+                        if ui
+                            .button(example.name)
+                            .on_hover_text(example.description)
+                            .clicked()
+                        {
+                            let name = Some(example.name.to_owned());
+                            self.load_program(ui.ctx(), name, example.rom.to_vec());
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("Recording", |ui| {
+                    self.recording_controls(ui, frame, base_cycles)
+                });
+                ui.menu_button("Slots", |ui| self.slot_controls(ui, frame));
+                ui.menu_button("Help", |ui| {
+                    if ui
+                        .button("Report a bug")
+                        .on_hover_text(
+                            "Save the ROM, settings and state to a file to attach to an issue",
+                        )
+                        .clicked()
+                    {
+                        self.save_bug_report(ui.ctx());
+                        ui.close_menu();
+                    }
+                });
+                #[cfg(target_arch = "wasm32")]
+                ui.menu_button("Open URL", |ui| self.url_loader.ui(ui));
+                ui.separator();
+                self.run_controls(ui, frame, &keyboard_state);
+            });
+        });
+
+        let quirks = self.settings.quirks_for_rom(self.rom_hash);
+        let rom_loaded = self.rom_loaded();
+        let rom_hash = self.rom_hash;
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .show(ctx, |ui| {
+                settings_menu(ui, &mut self.settings);
+                if rom_loaded {
+                    ui.separator();
+                    rom_override_ui(
+                        ui,
+                        &mut self.settings.rom_overrides,
+                        rom_hash,
+                        self.settings.quirks,
+                        self.settings.instructions_per_second,
+                    );
+                }
+            });
+        let new_quirks = self.settings.quirks_for_rom(self.rom_hash);
+        if new_quirks != quirks {
+            if let Some(chip8) = &mut self.chip8 {
+                chip8.set_quirks(new_quirks);
+                self.step_history.clear();
+            }
+        }
+
+        if !self.drop_candidates.is_empty() {
+            self.drop_picker(ctx);
+        }
+
+        if let Some(warning) = &self.rom_warning {
+            let mut dismissed = false;
+            egui::Window::new("Warning")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(warning);
+                    dismissed = ui.button("OK").clicked();
+                });
+            if dismissed {
+                self.rom_warning = None;
+            }
+        }
+
+        if self.byte_swap_suggested {
+            let (mut swap, mut dismissed) = (false, false);
+            egui::Window::new("Byte order")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(
+                        "This ROM makes more sense with the bytes of each instruction swapped, \
+                         so it may have been saved in the wrong byte order.",
+                    );
+                    ui.horizontal(|ui| {
+                        swap = ui.button("Swap bytes and reload").clicked();
+                        dismissed = ui.button("Keep as it is").clicked();
+                    });
+                });
+            if swap {
+                let program = swap_bytes(&self.program);
+                self.load_program(ctx, self.loaded_name.clone(), program);
+            } else if dismissed {
+                self.byte_swap_suggested = false;
+            }
+        }
+
+        if let Some(error) = &self.error {
+            let mut dismissed = false;
+            egui::Window::new("Error")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(error);
+                    dismissed = ui.button("OK").clicked();
+                });
+            if dismissed {
+                self.error = None;
+            }
+        }
+
+        if let Some(notice) = &self.notice {
+            let mut dismissed = false;
+            egui::Window::new("Notice")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(notice);
+                    dismissed = ui.button("OK").clicked();
+                });
+            if dismissed {
+                self.notice = None;
+            }
+        }
+
+        if let Some(chip8) = &mut self.chip8 {
+            let memory_viewer = &mut self.memory_viewer;
+            let edited = egui::Window::new("Memory")
+                .open(&mut self.show_memory_viewer)
+                .show(ctx, |ui| memory_viewer.ui(ui, chip8))
+                .and_then(|response| response.inner)
+                .unwrap_or(false);
+            if edited {
+                self.step_history.clear();
+            }
+            egui::Window::new("Timers")
+                .open(&mut self.show_timers)
+                .resizable(false)
+                .show(ctx, |ui| timer_meters_ui(ui, chip8));
+            egui::Window::new("Profile")
+                .open(&mut self.show_profile)
+                .show(ctx, |ui| profile_ui(ui, chip8));
+        }
+
+        self.keypad_state = [false; 16];
+        if self.settings.show_keypad {
+            egui::TopBottomPanel::bottom("keypad").show(ctx, |ui| {
+                ui.vertical_centered(|ui| self.keypad_state = keypad_ui(ui));
+            });
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -98,17 +1333,418 @@ impl eframe::App for App {
                             let mut program: Vec<u8> = vec![];
                             io::Read::read_to_end(&mut file, &mut program).unwrap();
 
-                            self.chip8 = Some(Chip8::new(&program));
-                            self.delta_accumulator = 0.0;
-                            ctx.request_repaint();
+                            let name = display_name(&self.filename).to_owned();
+                            self.load_program(ctx, Some(name), program);
                         }
                     });
                 }
 
-                if let Some(chip8) = &self.chip8 {
-                    draw_chip8_screen(ui, 10, chip8.get_screen());
+                let mut reset = false;
+                if let Some(chip8) = &mut self.chip8 {
+                    let screen = chip8.get_screen();
+                    let (width, height) = (screen.width(), screen.height());
+                    let pixel_scale = screen_pixel_scale(
+                        width,
+                        height,
+                        self.settings.pixel_scale,
+                        self.settings.fit_to_window,
+                        ui.available_size(),
+                    );
+                    let crossfade = self
+                        .crossfade
+                        .as_ref()
+                        .map(|crossfade| (crossfade.previous_screen(), crossfade.progress()));
+                    let response = draw_chip8_screen(
+                        ui,
+                        pixel_scale,
+                        chip8.get_screen(),
+                        match self.settings.flicker_mode {
+                            FlickerMode::Authentic => None,
+                            FlickerMode::Smoothed => Some(&self.screen_fade),
+                        },
+                        crossfade,
+                        &self.settings.screen_render_options(),
+                    );
+                    if self.edit_pixels && response.clicked() {
+                        let pixel = response.interact_pointer_pos().and_then(|pos| {
+                            pixel_at(pos, response.rect, pixel_scale, width, height)
+                        });
+                        if let Some((x, y)) = pixel {
+                            let screen = chip8.get_screen_mut();
+                            let value = !screen.get_pixel(x, y);
+                            screen.set_pixel(x, y, value);
+                            self.step_history.clear();
+                        }
+                    }
+                    if chip8.is_waiting_for_key() && !self.paused {
+                        ui.label("The program is waiting for a key press");
+                    }
+                    if chip8.is_halted() {
+                        ui.horizontal(|ui| {
+                            ui.label("The program has exited");
+                            reset = ui.button("Reset").clicked();
+                        });
+                    }
+                    if let Some(fault) = &self.fault {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Stopped at 0x{:03x}: {}",
+                                fault.pc,
+                                error_message(&fault.error)
+                            ));
+                            reset = ui.button("Reset").clicked();
+                        });
+                    }
+                    if self.settings.show_performance {
+                        let text = format!(
+                            "{} IPS\n{} FPS",
+                            self.ips_counter.rate(now),
+                            self.fps_counter.rate(now)
+                        );
+                        ui.painter().text(
+                            ui.max_rect().right_top() + egui::vec2(-4.0, 4.0),
+                            egui::Align2::RIGHT_TOP,
+                            text,
+                            egui::FontId::monospace(12.0),
+                            ui.visuals().strong_text_color(),
+                        );
+                    }
+                } else {
+                    ui.centered_and_justified(|ui| {
+                        ui.heading("No ROM loaded");
+                    });
+                }
+                if reset {
+                    self.reset(ctx);
                 }
             })
         });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chip8::{
+        cpu::{Chip8, CycleError},
+        instruction::DecodeError,
+        timing::{FrameBudget, FRAME_TIME_MICROS},
+    };
+
+    use super::{
+        cycles_per_frame, cycles_per_frame_at, display_name, parse_run_count, repaint_strategy,
+        slow_motion_budget, vip_frame_budget, window_title, App, Repaint,
+        BACKGROUND_REPAINT_INTERVAL, CYCLES_PER_FRAME, DEFAULT_INSTRUCTIONS_PER_SECOND,
+        IDLE_REPAINT_INTERVAL, MAX_RUN_COUNT,
+    };
+    use crate::{
+        dropped_files::drop_candidates, frame_clock::FRAME_TIME, rom_check::MAX_ROM_SIZE_BYTES,
+    };
+
+    #[test]
+    fn test_controls_disabled_without_rom() {
+        let app = App::default();
+        assert!(!app.rom_loaded());
+    }
+
+    #[test]
+    fn test_refocus_doesnt_catch_up() {
+        let mut app = App {
+            chip8: Some(Chip8::new(&[])),
+            ..Default::default()
+        };
+        app.settings.pause_when_unfocused = true;
+
+        assert_eq!(app.advance_clock(FRAME_TIME * 0.9, true), 0);
+        // a long time passes while unfocused
+        assert_eq!(app.advance_clock(10.0, false), 0);
+        // only the time since refocusing counts
+        assert_eq!(app.advance_clock(FRAME_TIME * 0.9, true), 0);
+        assert_eq!(app.advance_clock(FRAME_TIME * 0.2, true), 1);
+    }
+
+    #[test]
+    fn test_keeps_running_unfocused_when_disabled() {
+        let mut app = App {
+            chip8: Some(Chip8::new(&[])),
+            ..Default::default()
+        };
+        app.settings.pause_when_unfocused = false;
+
+        assert_eq!(app.advance_clock(FRAME_TIME * 2.5, false), 2);
+    }
+
+    #[test]
+    fn test_rewinding_stops_running() {
+        let mut app = App {
+            chip8: Some(Chip8::new(&[])),
+            rewinding: true,
+            ..Default::default()
+        };
+
+        assert!(!app.is_running(true));
+        assert_eq!(app.advance_clock(FRAME_TIME * 2.5, true), 0);
+    }
+
+    #[test]
+    fn test_step_back() {
+        // 7001: V0 += 1
+        // 1200: jump back to the start
+        let mut app = App {
+            chip8: Some(Chip8::new(&[0x70, 0x01, 0x12, 0x00])),
+            paused: true,
+            ..Default::default()
+        };
+        let chip8 = app.chip8.as_mut().unwrap();
+        let before = chip8.to_snapshot();
+        app.rewind.push(before.clone());
+        chip8.run_frames(1, 4, &[false; 16]).unwrap();
+
+        assert!(app.step_back());
+        assert_eq!(app.chip8.as_ref().unwrap().to_snapshot(), before);
+        assert!(!app.step_back());
+    }
+
+    #[test]
+    fn test_undo_step() {
+        // 7001: V0 += 1
+        // 1200: jump back to the start
+        let mut app = App {
+            chip8: Some(Chip8::new(&[0x70, 0x01, 0x12, 0x00])),
+            paused: true,
+            ..Default::default()
+        };
+        let start = app.chip8.clone().unwrap();
+        app.step(&[false; 16]);
+        let after_first = app.chip8.clone().unwrap();
+        app.step(&[false; 16]);
+
+        assert!(app.undo_step());
+        assert!(app.chip8.as_ref().unwrap() == &after_first);
+        assert!(app.undo_step());
+        assert!(app.chip8.as_ref().unwrap() == &start);
+        assert!(!app.undo_step());
+    }
+
+    #[test]
+    fn test_cycles_per_frame_at() {
+        assert_eq!(
+            cycles_per_frame_at(DEFAULT_INSTRUCTIONS_PER_SECOND),
+            CYCLES_PER_FRAME
+        );
+        assert_eq!(cycles_per_frame_at(600), 10);
+        // rounded to the nearest cycle
+        assert_eq!(cycles_per_frame_at(500), 8);
+        assert_eq!(cycles_per_frame_at(700), 12);
+        assert_eq!(cycles_per_frame_at(1000), 17);
+        // always at least one
+        assert_eq!(cycles_per_frame_at(0), 1);
+    }
+
+    #[test]
+    fn test_turbo_cycles_per_frame() {
+        assert_eq!(
+            cycles_per_frame(CYCLES_PER_FRAME, false, 8),
+            CYCLES_PER_FRAME
+        );
+        assert_eq!(
+            cycles_per_frame(CYCLES_PER_FRAME, true, 8),
+            CYCLES_PER_FRAME * 8
+        );
+        assert_eq!(cycles_per_frame(u32::MAX / 2, true, 8), u32::MAX);
+    }
+
+    #[test]
+    fn test_vip_frame_budget() {
+        assert_eq!(
+            vip_frame_budget(false, 8),
+            FrameBudget::Micros(FRAME_TIME_MICROS)
+        );
+        assert_eq!(
+            vip_frame_budget(true, 8),
+            FrameBudget::Micros(FRAME_TIME_MICROS * 8)
+        );
+
+        // drawing a sprite takes longer than a frame on the VIP, so sprite
+        // heavy programs slow down like they did on it
+        let mut chip8 = Chip8::new(&[0xd0, 0x01, 0x12, 0x00]);
+        let budget = vip_frame_budget(false, 8);
+        let (mut cycles, mut micros) = (0, 0);
+        while !budget.is_spent(cycles, micros) {
+            chip8.cycle(&[false; 16], &[false; 16]).unwrap();
+            cycles += 1;
+            micros += chip8.last_cycle_cost();
+        }
+        assert_eq!(cycles, 1);
+    }
+
+    #[test]
+    fn test_slow_motion_budget() {
+        let cycles = |speed, frame| slow_motion_budget(FrameBudget::Cycles(30), speed, frame);
+        assert_eq!(cycles(1.0, 0), FrameBudget::Cycles(30));
+        assert_eq!(cycles(0.5, 0), FrameBudget::Cycles(15));
+        assert_eq!(cycles(0.1, 7), FrameBudget::Cycles(3));
+        assert_eq!(
+            slow_motion_budget(FrameBudget::Micros(FRAME_TIME_MICROS), 0.5, 0),
+            FrameBudget::Micros(8334)
+        );
+
+        // below one cycle per frame, one cycle is run every few frames
+        let ran: Vec<_> = (0..10)
+            .map(|frame| slow_motion_budget(FrameBudget::Cycles(2), 0.1, frame))
+            .collect();
+        assert_eq!(
+            ran.iter().filter(|b| **b == FrameBudget::Cycles(1)).count(),
+            2
+        );
+        assert_eq!(ran[0], FrameBudget::Cycles(1));
+        assert_eq!(ran[5], FrameBudget::Cycles(1));
+        assert_eq!(ran[1], FrameBudget::Cycles(0));
+        assert_eq!(
+            slow_motion_budget(FrameBudget::Cycles(0), 0.5, 0),
+            FrameBudget::Cycles(0)
+        );
+    }
+
+    #[test]
+    fn test_error_pauses_at_fault() {
+        // 6005: V0 = 5
+        // 5121: unknown
+        let mut app = App {
+            chip8: Some(Chip8::new(&[0x60, 0x05, 0x51, 0x21])),
+            paused: true,
+            ..Default::default()
+        };
+
+        app.step(&[false; 16]);
+        assert!(app.fault.is_none());
+        app.step(&[false; 16]);
+
+        assert!(app.paused);
+        assert!(app.error.is_some());
+        let fault = app.fault.as_ref().unwrap();
+        assert_eq!(fault.pc, 0x202);
+        assert!(matches!(
+            fault.error,
+            CycleError::DecodeError(DecodeError::UnknownInstruction { inst: 0x5121 })
+        ));
+        // the machine is left as it was for inspecting
+        let chip8 = app.chip8.as_ref().unwrap();
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(chip8.to_snapshot().v_reg[0], 5);
+    }
+
+    #[test]
+    fn test_parse_run_count() {
+        assert_eq!(parse_run_count("10"), Some(10));
+        assert_eq!(parse_run_count(" 250 "), Some(250));
+        assert_eq!(parse_run_count("0"), Some(1));
+        assert_eq!(parse_run_count("99999999999"), Some(MAX_RUN_COUNT));
+        assert_eq!(parse_run_count(""), None);
+        assert_eq!(parse_run_count("-5"), None);
+        assert_eq!(parse_run_count("ten"), None);
+    }
+
+    #[test]
+    fn test_run_cycles_stops_at_breakpoint() {
+        // 7001: V0 += 1
+        // 1200: jump back to the start
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x12, 0x00]);
+        assert!(chip8.add_breakpoint(0x202));
+        let mut app = App {
+            chip8: Some(chip8),
+            paused: true,
+            ..Default::default()
+        };
+
+        app.run_cycles(100, &[false; 16]);
+
+        assert_eq!(app.last_run_count, Some(1));
+        assert_eq!(app.chip8.as_ref().unwrap().pc(), 0x202);
+    }
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(window_title(None), "improved-broccoli");
+        assert_eq!(
+            window_title(Some("pong.ch8")),
+            "pong.ch8 - improved-broccoli"
+        );
+    }
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!(display_name("pong.ch8"), "pong.ch8");
+        assert_eq!(display_name("/home/user/roms/pong.ch8"), "pong.ch8");
+        assert_eq!(display_name(r"C:\roms\pong.ch8"), "pong.ch8");
+        assert_eq!(
+            display_name("https://example.com/roms/pong.ch8?raw=1"),
+            "pong.ch8"
+        );
+        assert_eq!(display_name("roms/#1.ch8"), "#1.ch8");
+    }
+
+    #[test]
+    fn test_repaint_strategy() {
+        // (running, dirty, focused)
+        let cases = [
+            ((false, false, false), Repaint::OnInput),
+            ((false, false, true), Repaint::OnInput),
+            ((false, true, false), Repaint::OnInput),
+            ((false, true, true), Repaint::OnInput),
+            (
+                (true, false, false),
+                Repaint::After(BACKGROUND_REPAINT_INTERVAL),
+            ),
+            ((true, false, true), Repaint::After(IDLE_REPAINT_INTERVAL)),
+            ((true, true, false), Repaint::Continuously),
+            ((true, true, true), Repaint::Continuously),
+        ];
+        for ((running, dirty, focused), expected) in cases {
+            assert_eq!(
+                repaint_strategy(running, dirty, focused),
+                expected,
+                "running: {running}, dirty: {dirty}, focused: {focused}"
+            );
+        }
+        assert!(IDLE_REPAINT_INTERVAL < BACKGROUND_REPAINT_INTERVAL);
+    }
+
+    #[test]
+    fn test_dropped_rom_too_large() {
+        let ctx = egui::Context::default();
+        let mut app = App::default();
+        // bigger than any memory, which would panic if it was loaded
+        let files = [egui::DroppedFile {
+            name: "huge.ch8".to_owned(),
+            bytes: Some(vec![0; MAX_ROM_SIZE_BYTES + 1].into()),
+            ..Default::default()
+        }];
+        let candidates = drop_candidates(&files);
+
+        app.load_dropped_file(&ctx, &candidates[0]);
+
+        assert!(app.chip8.is_none());
+        assert!(app
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Failed to load huge.ch8"));
+
+        // fits in XO-CHIP memory, but not with the default quirks
+        let files = [egui::DroppedFile {
+            name: "big.ch8".to_owned(),
+            bytes: Some(vec![0; 8192].into()),
+            ..Default::default()
+        }];
+        let candidates = drop_candidates(&files);
+
+        app.load_dropped_file(&ctx, &candidates[0]);
+
+        assert!(app.chip8.is_none());
+        assert!(app
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Failed to load the program"));
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+
+use chip8::snapshot::Snapshot;
+
+use crate::{
+    save_slots::{base64_decode, base64_encode},
+    save_state::{SaveState, SaveStateError},
+    settings::Settings,
+    storage::{parse_entries, push_entry},
+};
+
+/// Increased whenever the format changes in a way older versions can't load
+const BUG_REPORT_VERSION: u32 = 2;
+
+/// The prefix of the keys of the settings' entries in a bug report
+const SETTINGS_PREFIX: &str = "settings.";
+
+/// Everything needed to reproduce a problem, which the user can save to a file
+/// and attach to an issue
+#[derive(Debug, Clone, PartialEq)]
+pub struct BugReport {
+    /// The version of the emulator which made the report
+    pub app_version: String,
+    /// The name of the loaded ROM, if it has one
+    pub rom_name: Option<String>,
+    /// The `rom_hash()` of the loaded ROM
+    pub rom_hash: u64,
+    /// The settings, including the quirks and any ROM overrides
+    pub settings: Settings,
+    /// The program and the CHIP-8's state, if a ROM is loaded
+    pub state: Option<SaveState>,
+    /// The error which stopped the program, if any
+    pub error: Option<String>,
+}
+
+impl BugReport {
+    /// The version of this build, for `app_version`
+    pub const APP_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    /// Serialize the report to a string with one `key=value` pair per line,
+    /// where the settings are included in their own storage format with their
+    /// keys prefixed. The program and the binary snapshot from
+    /// `Snapshot::to_bytes()` are stored in base64, as for save slots.
+    pub fn to_storage_string(&self) -> String {
+        let mut s = String::new();
+        push_entry(&mut s, "version", BUG_REPORT_VERSION);
+        push_entry(&mut s, "app_version", &self.app_version);
+        if let Some(rom_name) = &self.rom_name {
+            push_entry(&mut s, "rom_name", single_line(rom_name));
+        }
+        push_entry(&mut s, "rom_hash", format!("{:016x}", self.rom_hash));
+        if let Some(error) = &self.error {
+            push_entry(&mut s, "error", single_line(error));
+        }
+        push_prefixed(&mut s, SETTINGS_PREFIX, &self.settings.to_storage_string());
+        if let Some(state) = &self.state {
+            push_entry(&mut s, "program", base64_encode(&state.program));
+            push_entry(
+                &mut s,
+                "snapshot",
+                base64_encode(&state.snapshot.to_bytes()),
+            );
+        }
+        s
+    }
+
+    /// Deserialize a report from a string created with `to_storage_string()`
+    pub fn from_storage_string(s: &str) -> Result<BugReport, BugReportError> {
+        let entries = parse_entries(s);
+        let get = |key: &str| {
+            entries
+                .get(key)
+                .copied()
+                .ok_or_else(|| BugReportError::MissingEntry {
+                    key: key.to_owned(),
+                })
+        };
+        let invalid = |key: &str| BugReportError::InvalidEntry {
+            key: key.to_owned(),
+        };
+        let version: u32 = get("version")?.parse().map_err(|_| invalid("version"))?;
+        if version != BUG_REPORT_VERSION {
+            return Err(BugReportError::UnsupportedVersion { version });
+        }
+        let rom_hash =
+            u64::from_str_radix(get("rom_hash")?, 16).map_err(|_| invalid("rom_hash"))?;
+        let state = match entries.get("snapshot") {
+            None => None,
+            Some(snapshot) => {
+                let program = base64_decode(get("program")?).ok_or_else(|| invalid("program"))?;
+                let snapshot = base64_decode(snapshot).ok_or_else(|| invalid("snapshot"))?;
+                let snapshot = Snapshot::from_bytes(&snapshot)
+                    .map_err(|err| BugReportError::State(SaveStateError::InvalidSnapshot(err)))?;
+                Some(SaveState { program, snapshot })
+            }
+        };
+        Ok(BugReport {
+            app_version: get("app_version")?.to_owned(),
+            rom_name: entries.get("rom_name").map(|name| (*name).to_owned()),
+            rom_hash,
+            settings: Settings::from_storage_string(&strip_prefixed(s, SETTINGS_PREFIX)),
+            state,
+            error: entries.get("error").map(|error| (*error).to_owned()),
+        })
+    }
+}
+
+/// Whether a dropped file is a bug report rather than a ROM, which is checked
+/// before loading it so that it can be opened for debugging
+pub fn looks_like_bug_report(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).map_or(false, |s| {
+        s.starts_with("version=") && s.contains("\napp_version=")
+    })
+}
+
+/// Replace line breaks, which would end the entry early
+fn single_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// Append each line of `entries` to `s` with `prefix` before its key
+fn push_prefixed(s: &mut String, prefix: &str, entries: &str) {
+    for line in entries.lines() {
+        s.push_str(prefix);
+        s.push_str(line);
+        s.push('\n');
+    }
+}
+
+/// The lines of `s` which start with `prefix`, without it
+fn strip_prefixed(s: &str, prefix: &str) -> String {
+    let mut entries = String::new();
+    for line in s.lines().filter_map(|line| line.strip_prefix(prefix)) {
+        entries.push_str(line);
+        entries.push('\n');
+    }
+    entries
+}
+
+/// Error type for loading a `BugReport`
+#[derive(Debug)]
+pub enum BugReportError {
+    /// The report was made by a version with an incompatible format
+    UnsupportedVersion {
+        version: u32,
+    },
+    MissingEntry {
+        key: String,
+    },
+    InvalidEntry {
+        key: String,
+    },
+    State(SaveStateError),
+}
+
+impl fmt::Display for BugReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BugReportError::UnsupportedVersion { version } => write!(
+                f,
+                "the report is in format version {version}, but only version {BUG_REPORT_VERSION} is supported"
+            ),
+            BugReportError::MissingEntry { key } => write!(f, "the report is missing {key}"),
+            BugReportError::InvalidEntry { key } => write!(f, "the report has an invalid {key}"),
+            BugReportError::State(err) => write!(f, "the report's state is invalid: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BugReportError {}
+
+#[cfg(test)]
+mod test {
+    use chip8::cpu::Chip8;
+
+    use super::{looks_like_bug_report, BugReport, BugReportError};
+    use crate::{
+        rom_overrides::rom_hash,
+        save_state::{SaveState, SaveStateError},
+        settings::Settings,
+    };
+
+    #[test]
+    fn test_storage_string_round_trip() {
+        let program = vec![0x60, 0x05, 0x12, 0x02];
+        let mut chip8 = Chip8::new(&program);
+        chip8.cycle(&[false; 16], &[false; 16]).unwrap();
+        let report = BugReport {
+            app_version: BugReport::APP_VERSION.to_owned(),
+            rom_name: Some("test.ch8".to_owned()),
+            rom_hash: rom_hash(&program),
+            settings: Settings {
+                pixel_scale: 3,
+                ..Default::default()
+            },
+            state: Some(SaveState {
+                program,
+                snapshot: chip8.to_snapshot(),
+            }),
+            error: Some("Unknown instruction:\n5121".to_owned()),
+        };
+
+        let loaded = BugReport::from_storage_string(&report.to_storage_string()).unwrap();
+
+        assert_eq!(
+            loaded,
+            BugReport {
+                error: Some("Unknown instruction: 5121".to_owned()),
+                ..report
+            }
+        );
+    }
+
+    #[test]
+    fn test_storage_string_round_trip_without_rom() {
+        let report = BugReport {
+            app_version: BugReport::APP_VERSION.to_owned(),
+            rom_name: None,
+            rom_hash: 0,
+            settings: Settings::default(),
+            state: None,
+            error: None,
+        };
+
+        let loaded = BugReport::from_storage_string(&report.to_storage_string()).unwrap();
+
+        assert_eq!(loaded, report);
+    }
+
+    #[test]
+    fn test_invalid_snapshot() {
+        let report = "version=2\napp_version=0.1.0\nrom_hash=0\nprogram=AOA=\nsnapshot=AAAA\n";
+
+        assert!(matches!(
+            BugReport::from_storage_string(report),
+            Err(BugReportError::State(SaveStateError::InvalidSnapshot(..)))
+        ));
+        assert!(matches!(
+            BugReport::from_storage_string(&report.replace("AAAA", "!")),
+            Err(BugReportError::InvalidEntry { .. })
+        ));
+    }
+
+    #[test]
+    fn test_looks_like_bug_report() {
+        let report = BugReport {
+            app_version: BugReport::APP_VERSION.to_owned(),
+            rom_name: None,
+            rom_hash: 0,
+            settings: Settings::default(),
+            state: None,
+            error: None,
+        };
+        assert!(looks_like_bug_report(report.to_storage_string().as_bytes()));
+        // save states have a version but no app version
+        assert!(!looks_like_bug_report(b"version=1\nprogram=00e0\n"));
+        assert!(!looks_like_bug_report(&[0x00, 0xe0, 0x12, 0x00]));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        assert!(matches!(
+            BugReport::from_storage_string("version=1\n"),
+            Err(BugReportError::UnsupportedVersion { version: 1 })
+        ));
+        assert!(matches!(
+            BugReport::from_storage_string("version=2\n"),
+            Err(BugReportError::MissingEntry { .. })
+        ));
+    }
+}
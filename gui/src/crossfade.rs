@@ -0,0 +1,68 @@
+use chip8::screen::Screen;
+
+/// How many 60Hz frames a crossfade lasts
+pub const CROSSFADE_FRAMES: u32 = 16;
+
+/// A fade from the screen of the previous program to the new program's
+/// screen, so loading a ROM doesn't snap straight to a blank screen
+#[derive(Clone)]
+pub struct Crossfade {
+    /// The previous program's screen
+    previous: Screen,
+    frames_left: u32,
+}
+
+impl Crossfade {
+    pub fn new(previous: Screen) -> Self {
+        Self {
+            previous,
+            frames_left: CROSSFADE_FRAMES,
+        }
+    }
+
+    /// Advance by `frames` 60Hz frames, and return whether the crossfade is
+    /// still going
+    pub fn advance(&mut self, frames: u32) -> bool {
+        self.frames_left = self.frames_left.saturating_sub(frames);
+        self.frames_left > 0
+    }
+
+    /// How far through the crossfade it is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        1.0 - self.frames_left as f32 / CROSSFADE_FRAMES as f32
+    }
+
+    /// The screen being faded from
+    pub fn previous_screen(&self) -> &Screen {
+        &self.previous
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::screen::{LowResScreen, Screen};
+
+    use super::{Crossfade, CROSSFADE_FRAMES};
+
+    #[test]
+    fn test_crossfade_midpoint() {
+        let mut crossfade = Crossfade::new(Screen::default());
+        assert_eq!(crossfade.progress(), 0.0);
+
+        assert!(crossfade.advance(CROSSFADE_FRAMES / 2));
+
+        assert_eq!(crossfade.progress(), 0.5);
+    }
+
+    #[test]
+    fn test_crossfade_ends() {
+        let mut screen = LowResScreen::default();
+        screen.set_plane_pixel(1, 5, 6, true);
+        let mut crossfade = Crossfade::new(Screen::LowRes(screen));
+
+        // every plane of the previous screen is kept
+        assert_eq!(crossfade.previous_screen().get_pixel_planes(5, 6), 0b10);
+        assert!(!crossfade.advance(CROSSFADE_FRAMES + 1));
+        assert_eq!(crossfade.progress(), 1.0);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io;
+
+use egui::DroppedFile;
+
+/// A dropped file which a program can be loaded from
+#[derive(Debug, Clone)]
+pub struct DropCandidate {
+    /// The name to show the user when choosing between files
+    pub name: String,
+    file: DroppedFile,
+}
+
+impl DropCandidate {
+    /// Read the program from the file
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        if let Some(bytes) = &self.file.bytes {
+            // If the dropped file comes with the file bytes (e.g. web), just
+            // use them
+            return Ok(bytes.to_vec());
+        }
+        // Can't get local file on wasm
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.file.path {
+            return std::fs::read(path);
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the dropped file has no data",
+        ))
+    }
+}
+
+/// Remembers the last drop which was handled, since some backends report the
+/// same drop for several frames in a row, which would reload the program
+/// every frame
+#[derive(Debug, Default)]
+pub struct DropTracker {
+    last: Vec<DroppedFile>,
+}
+
+impl DropTracker {
+    /// Whether `files`, the files dropped this frame, are a new drop rather
+    /// than the same one as last frame. A frame with nothing dropped ends the
+    /// drop, so dropping the same file again later counts as a new drop.
+    pub fn is_new(&mut self, files: &[DroppedFile]) -> bool {
+        if files == self.last.as_slice() {
+            return false;
+        }
+        self.last = files.to_vec();
+        !files.is_empty()
+    }
+}
+
+/// The dropped files which a program can be loaded from, in the order they
+/// were dropped
+pub fn drop_candidates(files: &[DroppedFile]) -> Vec<DropCandidate> {
+    files
+        .iter()
+        .filter(|file| file.bytes.is_some() || (can_read_paths() && file.path.is_some()))
+        .map(|file| DropCandidate {
+            name: file_name(file),
+            file: file.clone(),
+        })
+        .collect()
+}
+
+fn can_read_paths() -> bool {
+    cfg!(not(target_arch = "wasm32"))
+}
+
+/// The name of the file, which is only set by the web backend, otherwise the
+/// last component of the path
+fn file_name(file: &DroppedFile) -> String {
+    if !file.name.is_empty() {
+        return file.name.clone();
+    }
+    file.path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Unnamed file".to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use egui::DroppedFile;
+
+    use super::{drop_candidates, DropTracker};
+
+    #[test]
+    fn test_drop_candidates() {
+        let files = [
+            DroppedFile {
+                name: "web.ch8".to_owned(),
+                bytes: Some(vec![0x00, 0xe0].into()),
+                ..Default::default()
+            },
+            DroppedFile {
+                path: Some(PathBuf::from("roms").join("native.ch8")),
+                ..Default::default()
+            },
+            // nothing to load from
+            DroppedFile::default(),
+        ];
+
+        let candidates = drop_candidates(&files);
+
+        let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["web.ch8", "native.ch8"]);
+        assert_eq!(candidates[0].read().unwrap(), [0x00, 0xe0]);
+    }
+
+    #[test]
+    fn test_drop_tracker() {
+        let rom = DroppedFile {
+            name: "rom.ch8".to_owned(),
+            bytes: Some(vec![0x00, 0xe0].into()),
+            ..Default::default()
+        };
+        let other = DroppedFile {
+            name: "other.ch8".to_owned(),
+            ..rom.clone()
+        };
+        let both = [rom.clone(), other.clone()];
+        let (rom, other) = ([rom], [other]);
+        let mut tracker = DropTracker::default();
+
+        assert!(!tracker.is_new(&[]));
+        // the same drop reported for several frames is only new once
+        assert!(tracker.is_new(&rom));
+        assert!(!tracker.is_new(&rom));
+        assert!(!tracker.is_new(&rom));
+        // a different drop straight after is new
+        assert!(tracker.is_new(&other));
+        assert!(tracker.is_new(&both));
+        // dropping the same file again after the drop ended is new
+        assert!(!tracker.is_new(&[]));
+        assert!(tracker.is_new(&rom));
+    }
+}
@@ -0,0 +1,53 @@
+/// A ROM built into the GUI, so it can be tried without finding a ROM first
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rom: &'static [u8],
+}
+
+/// The examples in the "Examples" menu. Their source is next to them in
+/// `gui/roms/`, and can be assembled with `chip8::assembler::assemble()`.
+pub const EXAMPLES: [Example; 3] = [
+    Example {
+        name: "Hex digits",
+        description: "Draws the 16 hex digits from the font",
+        rom: include_bytes!("../roms/hex_digits.ch8"),
+    },
+    Example {
+        name: "Keypad",
+        description: "Shows the last key pressed",
+        rom: include_bytes!("../roms/keypad.ch8"),
+    },
+    Example {
+        name: "Bounce",
+        description: "A ball bouncing around the screen",
+        rom: include_bytes!("../roms/bounce.ch8"),
+    },
+];
+
+#[cfg(test)]
+mod test {
+    use chip8::{assembler::assemble, instruction::decode};
+
+    use super::EXAMPLES;
+
+    #[test]
+    fn test_examples_decode() {
+        for example in EXAMPLES {
+            let first = u16::from_be_bytes([example.rom[0], example.rom[1]]);
+            assert!(decode(first).is_ok(), "{}", example.name);
+        }
+    }
+
+    #[test]
+    fn test_examples_match_source() {
+        let sources = [
+            include_str!("../roms/hex_digits.asm"),
+            include_str!("../roms/keypad.asm"),
+            include_str!("../roms/bounce.asm"),
+        ];
+        for (example, source) in EXAMPLES.iter().zip(sources) {
+            assert_eq!(assemble(source).unwrap(), example.rom, "{}", example.name);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/// The time between CHIP-8 frames in seconds, as the CHIP-8 runs at 60Hz
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// Converts the variable time between GUI frames into whole 60Hz CHIP-8
+/// frames
+#[derive(Debug, Default)]
+pub struct FrameClock {
+    /// Time which hasn't been used for a frame yet, in seconds
+    accumulator: f32,
+}
+
+impl FrameClock {
+    /// Add `delta_time` seconds to the clock and return the number of
+    /// CHIP-8 frames which should be run
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulator += delta_time;
+        let mut frames = 0;
+        while self.accumulator > FRAME_TIME {
+            self.accumulator -= FRAME_TIME;
+            frames += 1;
+        }
+        frames
+    }
+
+    /// Discard any accumulated time, so the CHIP-8 doesn't try to catch up
+    /// on time it wasn't running for
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FrameClock, FRAME_TIME};
+
+    #[test]
+    fn test_advance() {
+        let mut clock = FrameClock::default();
+        assert_eq!(clock.advance(FRAME_TIME / 2.0), 0);
+        assert_eq!(clock.advance(FRAME_TIME), 1);
+        assert_eq!(clock.advance(FRAME_TIME * 3.0), 3);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut clock = FrameClock::default();
+        assert_eq!(clock.advance(FRAME_TIME * 0.9), 0);
+        clock.reset();
+        assert_eq!(clock.advance(FRAME_TIME * 0.9), 0);
+    }
+}
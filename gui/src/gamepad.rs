@@ -0,0 +1,139 @@
+/// A button on a game controller which can be bound to a CHIP-8 key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    A,
+    B,
+    X,
+    Y,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 12] = [
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+        GamepadButton::A,
+        GamepadButton::B,
+        GamepadButton::X,
+        GamepadButton::Y,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::Select,
+        GamepadButton::Start,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GamepadButton::DPadUp => "Up",
+            GamepadButton::DPadDown => "Down",
+            GamepadButton::DPadLeft => "Left",
+            GamepadButton::DPadRight => "Right",
+            GamepadButton::A => "A",
+            GamepadButton::B => "B",
+            GamepadButton::X => "X",
+            GamepadButton::Y => "Y",
+            GamepadButton::LeftShoulder => "L",
+            GamepadButton::RightShoulder => "R",
+            GamepadButton::Select => "Select",
+            GamepadButton::Start => "Start",
+        }
+    }
+
+    /// Find the button with the given name, as returned by `name()`
+    pub fn from_name(name: &str) -> Option<GamepadButton> {
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+}
+
+/// The default controller button for each CHIP-8 key, if any. The d-pad is
+/// on the keys `KEY_MAP` puts under W, A, S and D, which many programs use
+/// for movement.
+pub const GAMEPAD_MAP: [Option<GamepadButton>; 16] = [
+    None,                               // 0
+    None,                               // 1
+    None,                               // 2
+    None,                               // 3
+    Some(GamepadButton::B),             // 4
+    Some(GamepadButton::DPadUp),        // 5
+    Some(GamepadButton::A),             // 6
+    Some(GamepadButton::DPadLeft),      // 7
+    Some(GamepadButton::DPadDown),      // 8
+    Some(GamepadButton::DPadRight),     // 9
+    Some(GamepadButton::Y),             // A
+    Some(GamepadButton::X),             // B
+    Some(GamepadButton::Select),        // C
+    Some(GamepadButton::LeftShoulder),  // D
+    Some(GamepadButton::RightShoulder), // E
+    Some(GamepadButton::Start),         // F
+];
+
+/// Combine the keyboard's key states with the controller buttons in
+/// `buttons_down`, where a CHIP-8 key is down if it's down on the keyboard or
+/// its button in `gamepad_map` is held
+pub fn merge_gamepad_state(
+    keyboard_state: &[bool; 16],
+    buttons_down: &[GamepadButton],
+    gamepad_map: &[Option<GamepadButton>; 16],
+) -> [bool; 16] {
+    let mut state = *keyboard_state;
+    for (i, button) in gamepad_map.iter().enumerate() {
+        if let Some(button) = button {
+            state[i] |= buttons_down.contains(button);
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use super::{merge_gamepad_state, GamepadButton, GAMEPAD_MAP};
+
+    #[test]
+    fn test_merge_gamepad_state() {
+        let mut keyboard_state = [false; 16];
+        keyboard_state[0x1] = true;
+        keyboard_state[0x5] = true;
+        let mut gamepad_map = [None; 16];
+        gamepad_map[0x5] = Some(GamepadButton::DPadUp);
+        gamepad_map[0x6] = Some(GamepadButton::A);
+        gamepad_map[0x7] = Some(GamepadButton::B);
+
+        let merged = merge_gamepad_state(
+            &keyboard_state,
+            &[
+                GamepadButton::DPadUp,
+                GamepadButton::A,
+                GamepadButton::Start,
+            ],
+            &gamepad_map,
+        );
+
+        let mut expected = [false; 16];
+        expected[0x1] = true;
+        expected[0x5] = true;
+        expected[0x6] = true;
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_button_names() {
+        for button in GamepadButton::ALL {
+            assert_eq!(GamepadButton::from_name(button.name()), Some(button));
+        }
+        assert_eq!(GamepadButton::from_name("Z"), None);
+        // every button has one key by default
+        for button in GamepadButton::ALL {
+            let keys = GAMEPAD_MAP.iter().filter(|b| **b == Some(button)).count();
+            assert_eq!(keys, 1, "{button:?}");
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Translations of user facing strings.
+//!
+//! Locale files contain one `key=value` pair per line, where values may
+//! contain `{name}` placeholders to be filled in by the caller.
+
+const EN: &str = include_str!("../locales/en.txt");
+
+/// Look up the translation of `key`, falling back to the key itself if there
+/// isn't one
+pub fn t(key: &str) -> &str {
+    lookup(EN, key).unwrap_or(key)
+}
+
+fn lookup<'a>(locale: &'a str, key: &str) -> Option<&'a str> {
+    locale
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(line_key, _)| line_key.trim() == key)
+        .map(|(_, value)| value.trim())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lookup, t};
+
+    #[test]
+    fn test_lookup() {
+        let locale = "a.b=first\na.c = second \n";
+        assert_eq!(lookup(locale, "a.b"), Some("first"));
+        assert_eq!(lookup(locale, "a.c"), Some("second"));
+        assert_eq!(lookup(locale, "a"), None);
+    }
+
+    #[test]
+    fn test_missing_key_falls_back_to_key() {
+        assert_eq!(t("missing.key"), "missing.key");
+    }
+}
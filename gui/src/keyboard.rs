@@ -1,6 +1,10 @@
+use crate::gamepad::{merge_gamepad_state, GamepadButton};
+
 // see here for layout information:
 // http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#2.3
-const KEY_MAP: [egui::Key; 16] = [
+/// The default keyboard key for each CHIP-8 key, laid out like the left side
+/// of a QWERTY keyboard
+pub const KEY_MAP: [egui::Key; 16] = [
     egui::Key::X, // 0
     egui::Key::Num1, // 1
     egui::Key::Num2, // 2
@@ -19,10 +23,171 @@ const KEY_MAP: [egui::Key; 16] = [
     egui::Key::V, // F
 ];
 
-pub fn get_key_state(input_state: &egui::InputState) -> [bool; 16] {
+/// Keys which can be bound to the turbo button, which are all unused by
+/// `KEY_MAP`
+pub const TURBO_KEYS: [egui::Key; 8] = [
+    egui::Key::Space,
+    egui::Key::Tab,
+    egui::Key::Backspace,
+    egui::Key::Enter,
+    egui::Key::G,
+    egui::Key::T,
+    egui::Key::Y,
+    egui::Key::Num0,
+];
+
+/// Find the turbo key with the given name, as returned by `egui::Key::name()`
+pub fn turbo_key_from_name(name: &str) -> Option<egui::Key> {
+    TURBO_KEYS.iter().copied().find(|key| key.name() == name)
+}
+
+/// Keys which can be bound to a CHIP-8 key
+pub const BINDABLE_KEYS: [egui::Key; 40] = [
+    egui::Key::Num0,
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+    egui::Key::A,
+    egui::Key::B,
+    egui::Key::C,
+    egui::Key::D,
+    egui::Key::E,
+    egui::Key::F,
+    egui::Key::G,
+    egui::Key::H,
+    egui::Key::I,
+    egui::Key::J,
+    egui::Key::K,
+    egui::Key::L,
+    egui::Key::M,
+    egui::Key::N,
+    egui::Key::O,
+    egui::Key::P,
+    egui::Key::Q,
+    egui::Key::R,
+    egui::Key::S,
+    egui::Key::T,
+    egui::Key::U,
+    egui::Key::V,
+    egui::Key::W,
+    egui::Key::X,
+    egui::Key::Y,
+    egui::Key::Z,
+    egui::Key::ArrowUp,
+    egui::Key::ArrowDown,
+    egui::Key::ArrowLeft,
+    egui::Key::ArrowRight,
+];
+
+/// Find the bindable key with the given name, as returned by
+/// `egui::Key::name()`
+pub fn bindable_key_from_name(name: &str) -> Option<egui::Key> {
+    BINDABLE_KEYS.iter().copied().find(|key| key.name() == name)
+}
+
+/// Which CHIP-8 keys share their keyboard key with another CHIP-8 key or with
+/// the turbo key, so pressing it would be ambiguous
+pub fn key_map_conflicts(key_map: &[egui::Key; 16], turbo_key: egui::Key) -> [bool; 16] {
+    let mut conflicts = [false; 16];
+    for (i, key) in key_map.iter().enumerate() {
+        conflicts[i] = *key == turbo_key
+            || key_map
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && other == key);
+    }
+    conflicts
+}
+
+/// Which CHIP-8 keys are down, from the keyboard keys in `key_map` and the
+/// controller buttons in `buttons_down` bound in `gamepad_map`
+pub fn get_key_state(
+    input_state: &egui::InputState,
+    key_map: &[egui::Key; 16],
+    buttons_down: &[GamepadButton],
+    gamepad_map: &[Option<GamepadButton>; 16],
+) -> [bool; 16] {
     let mut state = [false; 16];
-    for (i, key) in KEY_MAP.iter().enumerate() {
+    for (i, key) in key_map.iter().enumerate() {
         state[i] = input_state.key_down(*key);
     }
+    merge_gamepad_state(&state, buttons_down, gamepad_map)
+}
+
+/// Combine the key states from two input sources, where a key is down if it's
+/// down in either
+pub fn merge_key_states(a: &[bool; 16], b: &[bool; 16]) -> [bool; 16] {
+    let mut state = [false; 16];
+    for i in 0..16 {
+        state[i] = a[i] || b[i];
+    }
     state
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        bindable_key_from_name, key_map_conflicts, merge_key_states, turbo_key_from_name,
+        BINDABLE_KEYS, KEY_MAP, TURBO_KEYS,
+    };
+
+    #[test]
+    fn test_merge_key_states() {
+        let mut a = [false; 16];
+        let mut b = [false; 16];
+        a[0x1] = true;
+        a[0x5] = true;
+        b[0x5] = true;
+        b[0xf] = true;
+
+        let merged = merge_key_states(&a, &b);
+
+        let mut expected = [false; 16];
+        expected[0x1] = true;
+        expected[0x5] = true;
+        expected[0xf] = true;
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_turbo_keys() {
+        for key in TURBO_KEYS {
+            assert!(!KEY_MAP.contains(&key), "{key:?} is used by the keypad");
+            assert_eq!(turbo_key_from_name(key.name()), Some(key));
+        }
+        assert_eq!(turbo_key_from_name("X"), None);
+    }
+
+    #[test]
+    fn test_key_map_conflicts() {
+        assert_eq!(key_map_conflicts(&KEY_MAP, egui::Key::Space), [false; 16]);
+
+        let mut key_map = KEY_MAP;
+        key_map[0x5] = egui::Key::Q;
+        let mut expected = [false; 16];
+        expected[0x4] = true;
+        expected[0x5] = true;
+        assert_eq!(key_map_conflicts(&key_map, egui::Key::Space), expected);
+
+        let mut expected = [false; 16];
+        expected[0x0] = true;
+        assert_eq!(key_map_conflicts(&KEY_MAP, egui::Key::X), expected);
+    }
+
+    #[test]
+    fn test_bindable_keys() {
+        for key in KEY_MAP {
+            assert!(BINDABLE_KEYS.contains(&key), "{key:?} can't be bound");
+        }
+        for key in BINDABLE_KEYS {
+            assert_eq!(bindable_key_from_name(key.name()), Some(key));
+        }
+        assert_eq!(bindable_key_from_name("Space"), None);
+    }
+}
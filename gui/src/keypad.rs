@@ -0,0 +1,52 @@
+// see here for layout information:
+// http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#2.3
+/// The CHIP-8 keys in the order they're laid out on the hex keypad
+const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Draw an on-screen hex keypad, returning which keys are held down
+pub fn keypad_ui(ui: &mut egui::Ui) -> [bool; 16] {
+    let mut state = [false; 16];
+    let button_size = egui::vec2(40.0, 40.0);
+    egui::Grid::new("virtual_keypad")
+        .spacing(egui::vec2(4.0, 4.0))
+        .show(ui, |ui| {
+            for row in KEYPAD_LAYOUT {
+                for key in row {
+                    let button = egui::Button::new(format!("{key:X}")).min_size(button_size);
+                    let response = ui.add(button);
+                    state[key as usize] = response.is_pointer_button_down_on();
+                }
+                ui.end_row();
+            }
+        });
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use super::KEYPAD_LAYOUT;
+
+    #[test]
+    fn test_keypad_layout() {
+        assert_eq!(KEYPAD_LAYOUT[0][0], 0x1);
+        assert_eq!(KEYPAD_LAYOUT[0][3], 0xC);
+        assert_eq!(KEYPAD_LAYOUT[3][1], 0x0);
+        assert_eq!(KEYPAD_LAYOUT[3][3], 0xF);
+    }
+
+    #[test]
+    fn test_keypad_has_every_key_once() {
+        let mut seen = [0; 16];
+        for row in KEYPAD_LAYOUT {
+            for key in row {
+                seen[key as usize] += 1;
+            }
+        }
+        assert_eq!(seen, [1; 16]);
+    }
+}
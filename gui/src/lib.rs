@@ -1,7 +1,31 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
-mod screen_ui;
+mod bug_report;
+mod crossfade;
+mod dropped_files;
+mod examples;
+mod frame_clock;
+mod gamepad;
+mod i18n;
 mod keyboard;
+mod keypad;
+mod memory_viewer;
+mod profile;
+mod rate_counter;
+mod recording;
+mod rewind;
+mod rom_check;
+mod rom_overrides;
+mod runtime_error;
+mod save_slots;
+mod save_state;
+mod screen_fade;
+mod screen_ui;
+mod settings;
+mod storage;
+mod timer_meters;
+#[cfg(target_arch = "wasm32")]
+mod url_loader;
 
 mod app;
 pub use app::App;
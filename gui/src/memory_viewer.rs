@@ -0,0 +1,245 @@
+use std::fmt::Write;
+
+use chip8::{
+    assembler::assemble,
+    cpu::Chip8,
+    instruction::find_opcode,
+    memory::{MemoryRegion, RegionKind},
+};
+use egui::{Color32, RichText};
+
+/// How many bytes are shown on each row
+pub const BYTES_PER_ROW: usize = 16;
+
+const PC_COLOR: Color32 = Color32::from_rgb(0x30, 0x60, 0xa0);
+const I_COLOR: Color32 = Color32::from_rgb(0x80, 0x50, 0x20);
+const FOUND_COLOR: Color32 = Color32::from_rgb(0x20, 0x70, 0x50);
+
+/// The color of the bytes in each region of `Chip8::memory_map()`, or `None`
+/// to use the normal text color
+fn region_color(region: &MemoryRegion) -> Option<Color32> {
+    match region.kind {
+        RegionKind::Font => Some(Color32::from_rgb(0xc0, 0x80, 0xe0)),
+        RegionKind::Reserved => Some(Color32::GRAY),
+        RegionKind::Program => Some(Color32::from_rgb(0x70, 0xc0, 0x70)),
+        RegionKind::Free => None,
+    }
+}
+
+/// Format `memory` as rows of `BYTES_PER_ROW` hex bytes, each starting with
+/// the address of its first byte, where `memory` starts at `start_address`
+pub fn format_memory_rows(memory: &[u8], start_address: usize) -> Vec<String> {
+    memory
+        .chunks(BYTES_PER_ROW)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let mut line = format!("{:04x}:", start_address + row * BYTES_PER_ROW);
+            for byte in bytes {
+                write!(line, " {byte:02x}").unwrap();
+            }
+            line
+        })
+        .collect()
+}
+
+/// Parse an instruction to search for into an opcode and the mask of its bits
+/// which have to match for `find_opcode()`. This is either four hex digits
+/// where any other character matches any digit, like `DXYN` or `F?33`, or an
+/// instruction for `assemble()`, like `CLS`.
+pub fn parse_search(text: &str) -> Option<(u16, u16)> {
+    let text = text.trim();
+    let is_pattern = text.len() == 4
+        && text
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || "XYNxyn?_".contains(c));
+    if is_pattern {
+        let (mut opcode, mut mask) = (0, 0);
+        for c in text.chars() {
+            let digit = c.to_digit(16);
+            opcode = opcode << 4 | digit.unwrap_or(0) as u16;
+            mask = mask << 4 | if digit.is_some() { 0xf } else { 0 };
+        }
+        return Some((opcode, mask));
+    }
+    match assemble(text).ok()?.as_slice() {
+        [high, low, ..] => Some((u16::from_be_bytes([*high, *low]), 0xffff)),
+        _ => None,
+    }
+}
+
+/// A hex view of the CHIP-8's memory, where a byte can be clicked to edit it
+#[derive(Debug, Default)]
+pub struct MemoryViewer {
+    /// The address of the byte being edited
+    selected: Option<usize>,
+    /// The new value of the selected byte as typed by the user
+    edit_text: String,
+    /// The instruction being searched for, see `parse_search()`
+    search_text: String,
+    /// The addresses of the instructions found by the last search
+    found: Vec<usize>,
+    /// The index in `found` of the instruction which was last scrolled to
+    found_index: usize,
+    /// A row to scroll to in the next frame
+    scroll_to_row: Option<usize>,
+}
+
+impl MemoryViewer {
+    /// Show the memory, and return whether a byte was edited
+    pub fn ui(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8) -> bool {
+        let pc = chip8.pc() as usize;
+        let i = chip8.i_register() as usize;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("PC").background_color(PC_COLOR));
+            ui.label(RichText::new("I").background_color(I_COLOR));
+            for region in chip8.memory_map() {
+                if let Some(color) = region_color(&region) {
+                    ui.label(RichText::new(region.kind.name()).color(color));
+                }
+            }
+            let next = match chip8.peek_instruction() {
+                Ok(inst) => format!("Next: {:04X} {inst}", chip8.peek_opcode()),
+                Err(err) => format!("Next: {err}"),
+            };
+            ui.label(next);
+            if ui.button("Copy").clicked() {
+                let text = format_memory_rows(chip8.dump_memory(), 0).join("\n");
+                ui.output_mut(|o| o.copied_text = text);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Find:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.search_text)
+                    .hint_text("DXYN or CLS")
+                    .desired_width(100.0),
+            );
+            let search = parse_search(&self.search_text);
+            if response.changed() {
+                self.found = search.map_or_else(Vec::new, |(opcode, mask)| {
+                    find_opcode(chip8.dump_memory(), opcode, mask)
+                });
+                self.found_index = 0;
+                self.scroll_to_row = self.found.first().map(|address| address / BYTES_PER_ROW);
+            }
+            let next = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui
+                .add_enabled(self.found.len() > 1, egui::Button::new("Next"))
+                .clicked()
+                || next && !self.found.is_empty()
+            {
+                self.found_index = (self.found_index + 1) % self.found.len();
+                self.scroll_to_row = Some(self.found[self.found_index] / BYTES_PER_ROW);
+            }
+            if !self.search_text.trim().is_empty() {
+                let status = match self.found.get(self.found_index) {
+                    _ if search.is_none() => "Not an instruction".to_owned(),
+                    None => "Not found".to_owned(),
+                    Some(address) => format!(
+                        "{} of {} at {address:04x}",
+                        self.found_index + 1,
+                        self.found.len()
+                    ),
+                };
+                ui.label(status);
+            }
+        });
+        let mut edited = false;
+        if let Some(address) = self.selected {
+            ui.horizontal(|ui| {
+                ui.label(format!("{address:04x}:"));
+                let response =
+                    ui.add(egui::TextEdit::singleline(&mut self.edit_text).desired_width(30.0));
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    if let Ok(value) = u8::from_str_radix(self.edit_text.trim(), 16) {
+                        chip8.write_memory(address, value);
+                        edited = true;
+                    }
+                    self.selected = None;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.selected = None;
+                }
+            });
+        }
+        ui.separator();
+
+        let memory_map = chip8.memory_map();
+        let memory = chip8.dump_memory();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let row_count = (memory.len() + BYTES_PER_ROW - 1) / BYTES_PER_ROW;
+        let mut scroll_area = egui::ScrollArea::vertical();
+        if let Some(row) = self.scroll_to_row.take() {
+            let spacing = ui.spacing().item_spacing.y;
+            scroll_area = scroll_area.vertical_scroll_offset(row as f32 * (row_height + spacing));
+        }
+        let found = &self.found;
+        scroll_area.show_rows(ui, row_height, row_count, |ui, rows| {
+            for row in rows {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    let start = row * BYTES_PER_ROW;
+                    ui.label(RichText::new(format!("{start:04x}:")).monospace().weak());
+                    for (address, byte) in memory.iter().enumerate().skip(start).take(BYTES_PER_ROW)
+                    {
+                        let mut text = RichText::new(format!("{byte:02x}")).monospace();
+                        if let Some(color) = memory_map
+                            .iter()
+                            .find(|region| region.contains(address))
+                            .and_then(region_color)
+                        {
+                            text = text.color(color);
+                        }
+                        if address == pc || address == pc + 1 {
+                            text = text.background_color(PC_COLOR);
+                        } else if address == i {
+                            text = text.background_color(I_COLOR);
+                        } else if found.binary_search(&(address & !1)).is_ok() {
+                            text = text.background_color(FOUND_COLOR);
+                        }
+                        if ui
+                            .selectable_label(self.selected == Some(address), text)
+                            .clicked()
+                        {
+                            self.selected = Some(address);
+                            self.edit_text = format!("{byte:02x}");
+                        }
+                    }
+                });
+            }
+        });
+        edited
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_memory_rows, parse_search};
+
+    #[test]
+    fn test_format_memory_rows() {
+        let memory: Vec<u8> = (0..20).collect();
+
+        let rows = format_memory_rows(&memory, 0x200);
+
+        assert_eq!(
+            rows,
+            vec![
+                "0200: 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f",
+                "0210: 10 11 12 13",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_search() {
+        assert_eq!(parse_search("DXYN"), Some((0xd000, 0xf000)));
+        assert_eq!(parse_search(" f?33 "), Some((0xf033, 0xf0ff)));
+        assert_eq!(parse_search("00E0"), Some((0x00e0, 0xffff)));
+        assert_eq!(parse_search("CLS"), Some((0x00e0, 0xffff)));
+        assert_eq!(parse_search("LD V1, 5"), Some((0x6105, 0xffff)));
+        assert_eq!(parse_search("not an instruction"), None);
+        assert_eq!(parse_search(""), None);
+    }
+}
@@ -0,0 +1,68 @@
+use chip8::{
+    cpu::Chip8,
+    instruction::{INST_KIND_COUNT, INST_KIND_PATTERNS},
+};
+
+/// How many kinds of instruction the profile lists
+const TOP_COUNT: usize = 10;
+
+/// The `n` kinds of instruction which ran most often, as their opcode pattern
+/// and count, with the most first. Kinds which never ran aren't included.
+pub fn top_instructions(histogram: &[u64; INST_KIND_COUNT], n: usize) -> Vec<(&'static str, u64)> {
+    let mut top: Vec<_> = INST_KIND_PATTERNS
+        .into_iter()
+        .zip(histogram.iter().copied())
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    // stable, so ties stay in opcode order
+    top.sort_by(|(_, a), (_, b)| b.cmp(a));
+    top.truncate(n);
+    top
+}
+
+/// List the instructions which ran most often, which are only counted while
+/// this is shown
+pub fn profile_ui(ui: &mut egui::Ui, chip8: &mut Chip8) {
+    let Some(histogram) = chip8.instruction_histogram() else {
+        return;
+    };
+    let total: u64 = histogram.iter().sum();
+    let top = top_instructions(histogram, TOP_COUNT);
+    if top.is_empty() {
+        ui.label("No instructions have run yet");
+    }
+    egui::Grid::new("profile").striped(true).show(ui, |ui| {
+        for (pattern, count) in top {
+            ui.monospace(pattern);
+            ui.label(count.to_string());
+            ui.label(format!("{:.1}%", 100.0 * count as f64 / total as f64));
+            ui.end_row();
+        }
+    });
+    if ui.button("Reset").clicked() {
+        chip8.reset_instruction_histogram();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::instruction::{Inst, INST_KIND_COUNT};
+
+    use super::top_instructions;
+
+    #[test]
+    fn test_top_instructions() {
+        let mut histogram = [0; INST_KIND_COUNT];
+        histogram[Inst::Clear.kind()] = 1;
+        histogram[Inst::Jump { nnn: 0 }.kind()] = 10;
+        histogram[Inst::AddValue { vx: 0, nn: 0 }.kind()] = 10;
+        histogram[Inst::Exit.kind()] = 5;
+
+        assert_eq!(
+            top_instructions(&histogram, 3),
+            [("1NNN", 10), ("7XNN", 10), ("00FD", 5)]
+        );
+        assert_eq!(top_instructions(&histogram, 10).len(), 4);
+        assert!(top_instructions(&[0; INST_KIND_COUNT], 10).is_empty());
+    }
+}
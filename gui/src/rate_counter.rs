@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+/// Measures how often something happens per second, averaged over a rolling
+/// one second window
+#[derive(Debug, Default)]
+pub struct RateCounter {
+    /// When each count was recorded in seconds, and how many happened then
+    samples: VecDeque<(f64, u64)>,
+    /// The sum of the counts in `samples`
+    total: u64,
+}
+
+impl RateCounter {
+    /// The length of the window in seconds
+    const WINDOW: f64 = 1.0;
+
+    /// Record that `count` things happened at `now` seconds
+    pub fn record(&mut self, now: f64, count: u64) {
+        self.samples.push_back((now, count));
+        self.total += count;
+        self.remove_old(now);
+    }
+
+    /// The number of things recorded in the window ending at `now` seconds
+    pub fn rate(&mut self, now: f64) -> u64 {
+        self.remove_old(now);
+        self.total
+    }
+
+    fn remove_old(&mut self, now: f64) {
+        while let Some((time, count)) = self.samples.front() {
+            if now - time < Self::WINDOW {
+                break;
+            }
+            self.total -= count;
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RateCounter;
+
+    #[test]
+    fn test_rate() {
+        let mut counter = RateCounter::default();
+        for i in 0..60 {
+            counter.record(i as f64 / 60.0, 30);
+        }
+
+        assert_eq!(counter.rate(59.0 / 60.0), 60 * 30);
+    }
+
+    #[test]
+    fn test_old_samples_are_removed() {
+        let mut counter = RateCounter::default();
+        counter.record(0.0, 100);
+        counter.record(0.5, 10);
+
+        assert_eq!(counter.rate(0.9), 110);
+        assert_eq!(counter.rate(1.2), 10);
+        assert_eq!(counter.rate(10.0), 0);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{collections::HashMap, fmt};
+
+use chip8::recording::Recording;
+
+use crate::storage::{parse_entries, push_entry};
+
+/// Increased whenever the format changes in a way older versions can't load
+const RECORDING_VERSION: u32 = 1;
+
+/// Serialize `recording` to a string with one `key=value` pair per line. The
+/// frames are stored in hex, as a big-endian `u16` per frame with a bit set
+/// for each key held.
+pub fn recording_to_storage_string(recording: &Recording) -> String {
+    let mut s = String::new();
+    push_entry(&mut s, "version", RECORDING_VERSION);
+    push_entry(&mut s, "rom_hash", format!("{:016x}", recording.rom_hash));
+    push_entry(&mut s, "seed", recording.seed);
+    push_entry(&mut s, "cycles_per_frame", recording.cycles_per_frame);
+    let frames: Vec<u8> = recording
+        .frames
+        .iter()
+        .flat_map(|keys| keys_to_bits(keys).to_be_bytes())
+        .collect();
+    push_entry(&mut s, "frames", hex::encode(frames));
+    s
+}
+
+/// Deserialize a recording from a string created with
+/// `recording_to_storage_string()`
+pub fn recording_from_storage_string(s: &str) -> Result<Recording, RecordingError> {
+    let entries = parse_entries(s);
+    let version: u32 = get_entry(&entries, "version")?
+        .parse()
+        .map_err(|_| invalid_entry("version"))?;
+    if version != RECORDING_VERSION {
+        return Err(RecordingError::UnsupportedVersion { version });
+    }
+    let rom_hash = u64::from_str_radix(get_entry(&entries, "rom_hash")?, 16)
+        .map_err(|_| invalid_entry("rom_hash"))?;
+    let seed = get_entry(&entries, "seed")?
+        .parse()
+        .map_err(|_| invalid_entry("seed"))?;
+    let cycles_per_frame = get_entry(&entries, "cycles_per_frame")?
+        .parse()
+        .map_err(|_| invalid_entry("cycles_per_frame"))?;
+    let frame_bytes =
+        hex::decode(get_entry(&entries, "frames")?).map_err(|_| invalid_entry("frames"))?;
+    if frame_bytes.len() % 2 != 0 {
+        return Err(invalid_entry("frames"));
+    }
+    let frames = frame_bytes
+        .chunks(2)
+        .map(|bytes| bits_to_keys(u16::from_be_bytes([bytes[0], bytes[1]])))
+        .collect();
+    Ok(Recording {
+        rom_hash,
+        seed,
+        cycles_per_frame,
+        frames,
+    })
+}
+
+fn keys_to_bits(keys: &[bool; 16]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .fold(0, |bits, (key, &held)| bits | (held as u16) << key)
+}
+
+fn bits_to_keys(bits: u16) -> [bool; 16] {
+    std::array::from_fn(|key| bits & (1 << key) != 0)
+}
+
+fn get_entry<'a>(entries: &HashMap<&str, &'a str>, key: &str) -> Result<&'a str, RecordingError> {
+    entries
+        .get(key)
+        .copied()
+        .ok_or_else(|| RecordingError::MissingEntry {
+            key: key.to_owned(),
+        })
+}
+
+fn invalid_entry(key: &str) -> RecordingError {
+    RecordingError::InvalidEntry {
+        key: key.to_owned(),
+    }
+}
+
+/// Error type for loading a `Recording`
+#[derive(Debug)]
+pub enum RecordingError {
+    /// The recording was saved by a version with an incompatible format
+    UnsupportedVersion {
+        version: u32,
+    },
+    MissingEntry {
+        key: String,
+    },
+    InvalidEntry {
+        key: String,
+    },
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::UnsupportedVersion { version } => write!(
+                f,
+                "the recording was saved in format version {version}, but only version {RECORDING_VERSION} is supported"
+            ),
+            RecordingError::MissingEntry { key } => {
+                write!(f, "the recording is corrupt: {key} is missing")
+            }
+            RecordingError::InvalidEntry { key } => {
+                write!(f, "the recording is corrupt: {key} is invalid")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::recording::Recording;
+
+    use super::{recording_from_storage_string, recording_to_storage_string, RecordingError};
+
+    #[test]
+    fn test_storage_round_trip() {
+        let mut recording = Recording::new(0x0123456789abcdef, 42, 30);
+        recording.push_frame([false; 16]);
+        recording.push_frame(std::array::from_fn(|key| key % 3 == 0));
+        recording.push_frame([true; 16]);
+
+        let s = recording_to_storage_string(&recording);
+
+        assert_eq!(recording_from_storage_string(&s).unwrap(), recording);
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let s = "version=1\nrom_hash=00000000000000ff\nseed=1\ncycles_per_frame=30\nframes=abc\n";
+
+        assert!(matches!(
+            recording_from_storage_string(s),
+            Err(RecordingError::InvalidEntry { key }) if key == "frames"
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+
+use chip8::{cpu::Chip8, snapshot::Snapshot};
+
+/// How many snapshots are kept, which is 10 seconds of CHIP-8 frames
+pub const REWIND_CAPACITY: usize = 600;
+
+/// How many single steps can be undone
+pub const STEP_HISTORY_CAPACITY: usize = 64;
+
+/// Snapshots of the CHIP-8 taken every frame, so it can be stepped back in
+/// time. Once full, the oldest snapshot is dropped for each new one.
+#[derive(Debug)]
+pub struct RewindBuffer {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        Self::with_capacity(REWIND_CAPACITY)
+    }
+}
+
+impl RewindBuffer {
+    pub fn with_capacity(capacity: usize) -> RewindBuffer {
+        RewindBuffer {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Remove and return the most recent snapshot
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.snapshots.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+/// Copies of the CHIP-8 from before each single step while paused, so steps
+/// can be undone one instruction at a time. Once full, the oldest copy is
+/// dropped for each new one.
+#[derive(Default)]
+pub struct StepHistory {
+    states: VecDeque<Chip8>,
+}
+
+impl StepHistory {
+    /// Remember `chip8` as it is before a step
+    pub fn push(&mut self, chip8: &Chip8) {
+        if self.states.len() == STEP_HISTORY_CAPACITY {
+            self.states.pop_front();
+        }
+        self.states.push_back(chip8.clone());
+    }
+
+    /// Remove and return the CHIP-8 from before the most recent step
+    pub fn pop(&mut self) -> Option<Chip8> {
+        self.states.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::cpu::Chip8;
+
+    use super::{RewindBuffer, StepHistory, STEP_HISTORY_CAPACITY};
+
+    const NO_KEYS: [bool; 16] = [false; 16];
+
+    #[test]
+    fn test_restores_earlier_snapshot() {
+        // 7001: V0 += 1
+        // 1200: jump back to the start
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x12, 0x00]);
+        let mut rewind = RewindBuffer::default();
+        let mut snapshots = vec![];
+        for _ in 0..3 {
+            let snapshot = chip8.to_snapshot();
+            rewind.push(snapshot.clone());
+            snapshots.push(snapshot);
+            chip8.run_frames(1, 5, &NO_KEYS).unwrap();
+        }
+
+        rewind.pop();
+        let snapshot = rewind.pop().unwrap();
+        chip8.restore_snapshot(&snapshot).unwrap();
+
+        assert_eq!(chip8.to_snapshot(), snapshots[1]);
+        assert_eq!(rewind.len(), 1);
+    }
+
+    #[test]
+    fn test_drops_oldest_when_full() {
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x12, 0x00]);
+        let mut rewind = RewindBuffer::with_capacity(2);
+        let first = chip8.to_snapshot();
+        rewind.push(first.clone());
+        for _ in 0..2 {
+            chip8.run_frames(1, 1, &NO_KEYS).unwrap();
+            rewind.push(chip8.to_snapshot());
+        }
+
+        assert_eq!(rewind.len(), 2);
+        while let Some(snapshot) = rewind.pop() {
+            assert_ne!(snapshot, first);
+        }
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn test_step_history_is_bounded() {
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x12, 0x00]);
+        let mut history = StepHistory::default();
+        let first = chip8.clone();
+        for _ in 0..STEP_HISTORY_CAPACITY + 1 {
+            history.push(&chip8);
+            chip8.cycle(&NO_KEYS, &NO_KEYS).unwrap();
+        }
+
+        assert_eq!(history.len(), STEP_HISTORY_CAPACITY);
+        while let Some(state) = history.pop() {
+            assert!(state != first);
+        }
+    }
+}
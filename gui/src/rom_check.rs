@@ -0,0 +1,161 @@
+use std::fmt;
+
+use chip8::{
+    instruction::{decode, validate_rom, Inst},
+    memory::{PROGRAM_OFFSET_BYTES, XO_CHIP_MEMORY_SIZE_BYTES},
+};
+
+/// The largest ROM which fits in memory with any quirks
+pub const MAX_ROM_SIZE_BYTES: usize = XO_CHIP_MEMORY_SIZE_BYTES - PROGRAM_OFFSET_BYTES;
+
+/// Check that `bytes`, from a dropped file or a URL, could be a ROM before
+/// loading it
+pub fn check_rom(bytes: &[u8]) -> Result<(), RomError> {
+    if bytes.is_empty() {
+        return Err(RomError::Empty);
+    }
+    if bytes.len() > MAX_ROM_SIZE_BYTES {
+        return Err(RomError::TooLarge {
+            len: bytes.len(),
+            max_len: MAX_ROM_SIZE_BYTES,
+        });
+    }
+    Ok(())
+}
+
+/// How many addresses `unknown_opcodes_warning()` lists before summarising
+/// the rest
+const MAX_LISTED_ADDRESSES: usize = 4;
+
+/// A warning to show when `program` contains words which don't decode, see
+/// `validate_rom()`, or `None` if they all do. The ROM is still loaded, since
+/// they may be data which is never run.
+pub fn unknown_opcodes_warning(program: &[u8]) -> Option<String> {
+    let errors = validate_rom(program);
+    if errors.is_empty() {
+        return None;
+    }
+    let mut listed: Vec<String> = errors
+        .iter()
+        .take(MAX_LISTED_ADDRESSES)
+        .map(|(addr, _)| format!("0x{addr:03x}"))
+        .collect();
+    if errors.len() > MAX_LISTED_ADDRESSES {
+        listed.push(format!("{} more", errors.len() - MAX_LISTED_ADDRESSES));
+    }
+    Some(format!(
+        "This ROM uses unknown opcodes at {}, so it may not run correctly. \
+         They may just be data which is never run.",
+        listed.join(", ")
+    ))
+}
+
+/// How many words from the start of a ROM `looks_byte_swapped()` decodes
+const BYTE_ORDER_CHECK_WORDS: usize = 64;
+
+/// Whether `program` decodes much more cleanly with the bytes of each word
+/// swapped, which suggests it was saved little-endian by mistake. Words which
+/// don't decode and `0NNN` machine code calls, which hardly any programs use
+/// but most swapped `6XNN` and `7XNN` become, count against each byte order.
+/// This is only a guess, since data decodes too.
+pub fn looks_byte_swapped(program: &[u8]) -> bool {
+    let implausible_count = |swap: bool| {
+        program
+            .chunks_exact(2)
+            .take(BYTE_ORDER_CHECK_WORDS)
+            .map(|word| match swap {
+                false => u16::from_be_bytes([word[0], word[1]]),
+                true => u16::from_le_bytes([word[0], word[1]]),
+            })
+            .filter(|&word| matches!(decode(word), Err(_) | Ok(Inst::Exe { .. })))
+            .count()
+    };
+    let implausible = implausible_count(false);
+    let implausible_swapped = implausible_count(true);
+    implausible >= 2 && implausible_swapped * 4 <= implausible
+}
+
+/// `program` with the bytes of each word swapped. A trailing odd byte is kept
+/// as it is.
+pub fn swap_bytes(program: &[u8]) -> Vec<u8> {
+    let mut swapped = program.to_vec();
+    for word in swapped.chunks_exact_mut(2) {
+        word.swap(0, 1);
+    }
+    swapped
+}
+
+/// Error type for `check_rom()`
+#[derive(Debug, PartialEq, Eq)]
+pub enum RomError {
+    Empty,
+    TooLarge { len: usize, max_len: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Empty => write!(f, "the ROM is empty"),
+            RomError::TooLarge { len, max_len } => write!(
+                f,
+                "the ROM is {len} bytes, but the largest which fits in memory is {max_len} bytes"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_rom, looks_byte_swapped, swap_bytes, unknown_opcodes_warning, RomError,
+        MAX_ROM_SIZE_BYTES,
+    };
+
+    #[test]
+    fn test_check_rom() {
+        assert_eq!(check_rom(&[0x00, 0xe0]), Ok(()));
+        assert_eq!(check_rom(&vec![0; MAX_ROM_SIZE_BYTES]), Ok(()));
+        assert_eq!(check_rom(&[]), Err(RomError::Empty));
+        assert_eq!(
+            check_rom(&vec![0; MAX_ROM_SIZE_BYTES + 1]),
+            Err(RomError::TooLarge {
+                len: MAX_ROM_SIZE_BYTES + 1,
+                max_len: MAX_ROM_SIZE_BYTES
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_opcodes_warning() {
+        assert_eq!(unknown_opcodes_warning(&[0x00, 0xe0, 0x12, 0x00]), None);
+
+        let warning = unknown_opcodes_warning(&[0x00, 0xe0, 0x51, 0x21]).unwrap();
+        assert!(warning.contains("0x202"), "{warning}");
+
+        let warning = unknown_opcodes_warning(&[0xff; 12]).unwrap();
+        assert!(warning.contains("0x206, 2 more"), "{warning}");
+    }
+
+    #[test]
+    fn test_swap_bytes() {
+        assert_eq!(
+            swap_bytes(&[0x00, 0xe0, 0x12, 0x00, 0xff]),
+            [0xe0, 0x00, 0x00, 0x12, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_looks_byte_swapped() {
+        for rom in [
+            &include_bytes!("../roms/hex_digits.ch8")[..],
+            include_bytes!("../roms/keypad.ch8"),
+            include_bytes!("../roms/bounce.ch8"),
+        ] {
+            assert!(!looks_byte_swapped(rom));
+            assert!(looks_byte_swapped(&swap_bytes(rom)));
+        }
+        // too little to go on
+        assert!(!looks_byte_swapped(&[0xe0, 0x00]));
+        assert!(!looks_byte_swapped(&[]));
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chip8::quirks::Quirks;
+
+use crate::{
+    app::{MAX_INSTRUCTIONS_PER_SECOND, MIN_INSTRUCTIONS_PER_SECOND},
+    storage::push_entry,
+};
+
+/// Hash a program with 64-bit FNV-1a, to recognise it when it's loaded again
+pub fn rom_hash(program: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    program.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// Settings used instead of the global ones for a particular ROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomOverride {
+    pub quirks: Quirks,
+    /// How many CHIP-8 instructions are run per second at normal speed
+    pub instructions_per_second: u32,
+}
+
+/// The prefix of the storage keys for overrides, which are followed by the
+/// ROM's hash and the field name, e.g.
+/// `rom_override.0123456789abcdef.instructions_per_second`
+const KEY_PREFIX: &str = "rom_override.";
+
+/// Append the overrides to `s` as `key=value` lines, see `push_entry()`
+pub fn push_rom_overrides(s: &mut String, overrides: &BTreeMap<u64, RomOverride>) {
+    for (hash, rom_override) in overrides {
+        let mut push = |field: &str, value: &dyn std::fmt::Display| {
+            push_entry(s, &format!("{KEY_PREFIX}{hash:016x}.{field}"), value);
+        };
+        let quirks = &rom_override.quirks;
+        push(
+            "instructions_per_second",
+            &rom_override.instructions_per_second,
+        );
+        push("wait_key_on_release", &quirks.wait_key_on_release);
+        push("extended_memory", &quirks.extended_memory);
+        push("add_to_i_sets_vf", &quirks.add_to_i_sets_vf);
+        push("protect_reserved_memory", &quirks.protect_reserved_memory);
+        push("shift_uses_vy", &quirks.shift_uses_vy);
+        push("load_store_increments_i", &quirks.load_store_increments_i);
+        push("jump_with_vx", &quirks.jump_with_vx);
+        push("logic_resets_vf", &quirks.logic_resets_vf);
+        push("display_wait", &quirks.display_wait);
+    }
+}
+
+/// Read the overrides written by `push_rom_overrides()`. Missing fields are
+/// taken from `defaults`, invalid entries are ignored, and speeds are clamped
+/// to the range the UI allows.
+pub fn read_rom_overrides(
+    entries: &HashMap<&str, &str>,
+    defaults: RomOverride,
+) -> BTreeMap<u64, RomOverride> {
+    let mut overrides = BTreeMap::new();
+    for (key, value) in entries {
+        let Some((hash, field)) = key
+            .strip_prefix(KEY_PREFIX)
+            .and_then(|rest| rest.split_once('.'))
+        else {
+            continue;
+        };
+        let Ok(hash) = u64::from_str_radix(hash, 16) else {
+            continue;
+        };
+        let rom_override: &mut RomOverride = overrides.entry(hash).or_insert(defaults);
+        let quirks = &mut rom_override.quirks;
+        match field {
+            "instructions_per_second" => {
+                parse_into(value, &mut rom_override.instructions_per_second)
+            }
+            // older versions stored the speed per 60Hz frame, which is only
+            // used if there's no entry in the new format
+            "cycles_per_frame" => {
+                let new_key = key.replace(field, "instructions_per_second");
+                if let (false, Ok(cycles_per_frame)) =
+                    (entries.contains_key(new_key.as_str()), value.parse::<u32>())
+                {
+                    rom_override.instructions_per_second = cycles_per_frame.saturating_mul(60);
+                }
+            }
+            "wait_key_on_release" => parse_into(value, &mut quirks.wait_key_on_release),
+            "extended_memory" => parse_into(value, &mut quirks.extended_memory),
+            "add_to_i_sets_vf" => parse_into(value, &mut quirks.add_to_i_sets_vf),
+            "protect_reserved_memory" => parse_into(value, &mut quirks.protect_reserved_memory),
+            "shift_uses_vy" => parse_into(value, &mut quirks.shift_uses_vy),
+            "load_store_increments_i" => parse_into(value, &mut quirks.load_store_increments_i),
+            "jump_with_vx" => parse_into(value, &mut quirks.jump_with_vx),
+            "logic_resets_vf" => parse_into(value, &mut quirks.logic_resets_vf),
+            "display_wait" => parse_into(value, &mut quirks.display_wait),
+            _ => {}
+        }
+    }
+    for rom_override in overrides.values_mut() {
+        rom_override.instructions_per_second = rom_override
+            .instructions_per_second
+            .clamp(MIN_INSTRUCTIONS_PER_SECOND, MAX_INSTRUCTIONS_PER_SECOND);
+    }
+    overrides
+}
+
+fn parse_into<T: std::str::FromStr>(s: &str, value: &mut T) {
+    if let Ok(parsed) = s.parse() {
+        *value = parsed;
+    }
+}
+
+/// Draw the UI for saving or removing the override for the ROM with `hash`,
+/// where a new override uses `quirks` and `instructions_per_second`
+pub fn rom_override_ui(
+    ui: &mut egui::Ui,
+    overrides: &mut BTreeMap<u64, RomOverride>,
+    hash: u64,
+    quirks: Quirks,
+    instructions_per_second: u32,
+) {
+    ui.label(format!("This ROM ({hash:016x}):"));
+    let mut remove = false;
+    match overrides.get_mut(&hash) {
+        Some(rom_override) => {
+            ui.label("Using this ROM's own quirks and speed");
+            ui.add(
+                egui::Slider::new(
+                    &mut rom_override.instructions_per_second,
+                    MIN_INSTRUCTIONS_PER_SECOND..=MAX_INSTRUCTIONS_PER_SECOND,
+                )
+                .logarithmic(true)
+                .text("Instructions per second"),
+            );
+            ui.horizontal(|ui| {
+                if ui.button("Save current quirks for this ROM").clicked() {
+                    rom_override.quirks = quirks;
+                }
+                remove = ui.button("Use the global settings").clicked();
+            });
+        }
+        None => {
+            if ui.button("Save current quirks for this ROM").clicked() {
+                overrides.insert(
+                    hash,
+                    RomOverride {
+                        quirks,
+                        instructions_per_second,
+                    },
+                );
+            }
+        }
+    }
+    if remove {
+        overrides.remove(&hash);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use chip8::quirks::Quirks;
+
+    use super::{push_rom_overrides, read_rom_overrides, rom_hash, RomOverride};
+    use crate::storage::parse_entries;
+
+    #[test]
+    fn test_rom_hash() {
+        // known FNV-1a values
+        assert_eq!(rom_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(rom_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(rom_hash(b"foobar"), 0x85944171f73967e8);
+        assert_ne!(rom_hash(&[0x00, 0xe0]), rom_hash(&[0xe0, 0x00]));
+    }
+
+    #[test]
+    fn test_storage_round_trip() {
+        let defaults = RomOverride {
+            quirks: Quirks::vip(),
+            instructions_per_second: 1800,
+        };
+        let mut overrides = BTreeMap::new();
+        overrides.insert(
+            rom_hash(&[0x12, 0x00]),
+            RomOverride {
+                quirks: Quirks::xochip(),
+                instructions_per_second: 1000,
+            },
+        );
+        overrides.insert(0, defaults);
+        let mut s = String::new();
+        push_rom_overrides(&mut s, &overrides);
+
+        let loaded = read_rom_overrides(&parse_entries(&s), defaults);
+
+        assert_eq!(loaded, overrides);
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let defaults = RomOverride {
+            quirks: Quirks::vip(),
+            instructions_per_second: 1800,
+        };
+        let s = "rom_override.00000000000000ff.instructions_per_second=500\n\
+                 rom_override.nothex.instructions_per_second=500\n\
+                 instructions_per_second=1\n";
+
+        let loaded = read_rom_overrides(&parse_entries(s), defaults);
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(
+            loaded[&0xff],
+            RomOverride {
+                instructions_per_second: 500,
+                ..defaults
+            }
+        );
+    }
+
+    #[test]
+    fn test_cycles_per_frame_converted() {
+        let defaults = RomOverride {
+            quirks: Quirks::vip(),
+            instructions_per_second: 1800,
+        };
+        let s = "rom_override.00000000000000ff.cycles_per_frame=5\n\
+                 rom_override.0000000000000100.cycles_per_frame=5\n\
+                 rom_override.0000000000000100.instructions_per_second=700\n";
+
+        let loaded = read_rom_overrides(&parse_entries(s), defaults);
+
+        assert_eq!(loaded[&0xff].instructions_per_second, 300);
+        // the new entry is used if there are both
+        assert_eq!(loaded[&0x100].instructions_per_second, 700);
+    }
+
+    #[test]
+    fn test_instructions_per_second_clamped() {
+        let defaults = RomOverride {
+            quirks: Quirks::vip(),
+            instructions_per_second: 1800,
+        };
+        let s = "rom_override.00000000000000ff.instructions_per_second=4294967295\n\
+                 rom_override.0000000000000100.instructions_per_second=0\n\
+                 rom_override.0000000000000101.cycles_per_frame=4294967295\n";
+
+        let loaded = read_rom_overrides(&parse_entries(s), defaults);
+
+        assert_eq!(loaded[&0xff].instructions_per_second, 12000);
+        assert_eq!(loaded[&0x100].instructions_per_second, 60);
+        assert_eq!(loaded[&0x101].instructions_per_second, 12000);
+    }
+}
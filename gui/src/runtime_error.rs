@@ -0,0 +1,132 @@
+use chip8::{
+    cpu::{CycleError, ExecuteError},
+    instruction::DecodeError,
+};
+
+use crate::i18n::t;
+
+/// The translation key for the message describing `error`
+pub fn error_key(error: &CycleError) -> &'static str {
+    match error {
+        CycleError::DecodeError(DecodeError::UnknownInstruction { .. }) => {
+            "runtime_error.unknown_instruction"
+        }
+        CycleError::DecodeError(DecodeError::MissingImmediate { .. }) => {
+            "runtime_error.missing_immediate"
+        }
+        CycleError::ExecuteError(error) => match error {
+            ExecuteError::UnimplementedInstruction { .. } => {
+                "runtime_error.unimplemented_instruction"
+            }
+            ExecuteError::UnknownMachineSubroutine { .. } => {
+                "runtime_error.unknown_machine_subroutine"
+            }
+            ExecuteError::EmptyStackReturn => "runtime_error.empty_stack_return",
+            ExecuteError::StackOverflow { .. } => "runtime_error.stack_overflow",
+            ExecuteError::SpriteMemoryOverflow { .. } => "runtime_error.sprite_memory_overflow",
+            ExecuteError::ProtectedMemoryWrite { .. } => "runtime_error.protected_memory_write",
+            ExecuteError::AudioPatternMemoryOverflow { .. } => {
+                "runtime_error.audio_pattern_memory_overflow"
+            }
+            ExecuteError::RegisterMemoryOverflow { .. } => "runtime_error.register_memory_overflow",
+            ExecuteError::PcOutOfBounds { .. } => "runtime_error.pc_out_of_bounds",
+        },
+    }
+}
+
+/// A localized message describing `error`, to show to the user instead of
+/// the core crate's English `Display` output
+pub fn error_message(error: &CycleError) -> String {
+    let message = t(error_key(error));
+    match error {
+        CycleError::DecodeError(
+            DecodeError::UnknownInstruction { inst } | DecodeError::MissingImmediate { inst },
+        ) => message.replace("{inst}", &format!("0x{inst:04x}")),
+        CycleError::ExecuteError(error) => match error {
+            ExecuteError::UnimplementedInstruction { inst } => {
+                message.replace("{inst}", &format!("{inst:?}"))
+            }
+            ExecuteError::UnknownMachineSubroutine { nnn } => {
+                message.replace("{nnn}", &format!("0x{nnn:03x}"))
+            }
+            ExecuteError::EmptyStackReturn => message.to_owned(),
+            ExecuteError::StackOverflow { pc } => message.replace("{pc}", &format!("0x{pc:03x}")),
+            ExecuteError::SpriteMemoryOverflow { index, len } => message
+                .replace("{index}", &format!("0x{index:03x}"))
+                .replace("{len}", &len.to_string()),
+            ExecuteError::ProtectedMemoryWrite { addr } => {
+                message.replace("{addr}", &format!("0x{addr:03x}"))
+            }
+            ExecuteError::AudioPatternMemoryOverflow { index } => {
+                message.replace("{index}", &format!("0x{index:03x}"))
+            }
+            ExecuteError::RegisterMemoryOverflow { index, len } => message
+                .replace("{index}", &format!("0x{index:03x}"))
+                .replace("{len}", &len.to_string()),
+            ExecuteError::PcOutOfBounds { pc } => message.replace("{pc}", &format!("0x{pc:03x}")),
+        },
+    }
+}
+
+/// A localized message describing `error`, which happened when running the
+/// instruction at `pc`
+pub fn error_report(error: &CycleError, pc: u16) -> String {
+    t("runtime_error.stopped_at")
+        .replace("{pc}", &format!("0x{pc:03x}"))
+        .replace("{message}", &error_message(error))
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::{
+        cpu::{CycleError, ExecuteError},
+        instruction::{DecodeError, Inst},
+    };
+
+    use super::{error_key, error_message, error_report};
+
+    fn all_errors() -> Vec<CycleError> {
+        vec![
+            CycleError::DecodeError(DecodeError::UnknownInstruction { inst: 0x5001 }),
+            CycleError::DecodeError(DecodeError::MissingImmediate { inst: 0xf000 }),
+            CycleError::ExecuteError(ExecuteError::UnimplementedInstruction { inst: Inst::Clear }),
+            CycleError::ExecuteError(ExecuteError::UnknownMachineSubroutine { nnn: 0x123 }),
+            CycleError::ExecuteError(ExecuteError::EmptyStackReturn),
+            CycleError::ExecuteError(ExecuteError::SpriteMemoryOverflow {
+                index: 0xfff,
+                len: 5,
+            }),
+            CycleError::ExecuteError(ExecuteError::ProtectedMemoryWrite { addr: 0x1ff }),
+            CycleError::ExecuteError(ExecuteError::AudioPatternMemoryOverflow { index: 0xff8 }),
+            CycleError::ExecuteError(ExecuteError::RegisterMemoryOverflow {
+                index: 0xff8,
+                len: 16,
+            }),
+            CycleError::ExecuteError(ExecuteError::PcOutOfBounds { pc: 0xfff }),
+        ]
+    }
+
+    #[test]
+    fn test_every_error_has_a_translation() {
+        for error in all_errors() {
+            let key = error_key(&error);
+            assert!(key.starts_with("runtime_error."));
+            let message = error_message(&error);
+            assert!(!message.is_empty());
+            assert_ne!(message, key, "missing translation for {key}");
+            assert!(!message.contains('{'), "unfilled placeholder in {message}");
+        }
+    }
+
+    #[test]
+    fn test_error_report() {
+        let error = CycleError::DecodeError(DecodeError::UnknownInstruction { inst: 0x5001 });
+
+        let report = error_report(&error, 0x2a4);
+
+        assert_eq!(
+            report,
+            "The CHIP-8 stopped at 0x2a4: Unknown instruction: 0x5001"
+        );
+    }
+}
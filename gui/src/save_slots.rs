@@ -0,0 +1,224 @@
+use chip8::snapshot::Snapshot;
+
+use crate::{
+    save_state::{SaveState, SaveStateError},
+    storage::{parse_entries, push_entry},
+};
+
+/// How many save state slots there are
+pub const SLOT_COUNT: usize = 4;
+
+/// Increased whenever the format changes in a way older versions can't load
+const SAVE_SLOT_VERSION: u32 = 1;
+
+/// The storage key of a slot, numbered from 0
+pub fn slot_key(slot: usize) -> String {
+    format!("save_slot_{slot}")
+}
+
+/// A save state in one of the slots, with the name of the ROM it was running
+/// to tell the slots apart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveSlot {
+    pub name: Option<String>,
+    pub state: SaveState,
+}
+
+impl SaveSlot {
+    /// Serialize the slot to a string with one `key=value` pair per line. The
+    /// program and the binary snapshot from `Snapshot::to_bytes()` are stored
+    /// in base64, which takes less space than `SaveState`'s hex, as browsers
+    /// limit how much can be stored.
+    pub fn to_storage_string(&self) -> String {
+        let mut s = String::new();
+        push_entry(&mut s, "version", SAVE_SLOT_VERSION);
+        if let Some(name) = &self.name {
+            push_entry(&mut s, "name", name.replace(['\r', '\n'], " "));
+        }
+        push_entry(&mut s, "program", base64_encode(&self.state.program));
+        push_entry(
+            &mut s,
+            "snapshot",
+            base64_encode(&self.state.snapshot.to_bytes()),
+        );
+        s
+    }
+
+    /// Deserialize a slot from a string created with `to_storage_string()`
+    pub fn from_storage_string(s: &str) -> Result<SaveSlot, SaveStateError> {
+        let entries = parse_entries(s);
+        let get = |key: &str| {
+            entries
+                .get(key)
+                .copied()
+                .ok_or_else(|| SaveStateError::MissingEntry {
+                    key: key.to_owned(),
+                })
+        };
+        let invalid = |key: &str| SaveStateError::InvalidEntry {
+            key: key.to_owned(),
+        };
+        let version: u32 = get("version")?.parse().map_err(|_| invalid("version"))?;
+        if version != SAVE_SLOT_VERSION {
+            return Err(SaveStateError::UnsupportedVersion { version });
+        }
+        let program = base64_decode(get("program")?).ok_or_else(|| invalid("program"))?;
+        let snapshot = base64_decode(get("snapshot")?).ok_or_else(|| invalid("snapshot"))?;
+        Ok(SaveSlot {
+            name: entries.get("name").map(|name| (*name).to_owned()),
+            state: SaveState {
+                program,
+                snapshot: Snapshot::from_bytes(&snapshot)
+                    .map_err(SaveStateError::InvalidSnapshot)?,
+            },
+        })
+    }
+}
+
+/// What's in a slot, for showing the user without loading it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotStatus {
+    Empty,
+    /// Holds a state for the named ROM, if it has a name
+    Occupied {
+        name: Option<String>,
+    },
+    /// Holds something which can't be loaded, with the reason why
+    Invalid(String),
+}
+
+impl SlotStatus {
+    /// The status of a slot whose storage string is `s`, or `None` if it has
+    /// never been saved to. Deleted slots are stored as an empty string.
+    pub fn from_storage_string(s: Option<&str>) -> SlotStatus {
+        match s {
+            None | Some("") => SlotStatus::Empty,
+            Some(s) => match SaveSlot::from_storage_string(s) {
+                Ok(slot) => SlotStatus::Occupied { name: slot.name },
+                Err(err) => SlotStatus::Invalid(err.to_string()),
+            },
+        }
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `bytes` in standard base64 with padding
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                s.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+/// Decode a string encoded by `base64_encode()`, or `None` if it isn't valid
+/// base64
+pub fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 4 != 0 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(s.len() / 4 * 3);
+    let chunks: Vec<&[u8]> = s.as_bytes().chunks(4).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        // padding is only allowed at the end
+        if padding > 2 || (padding > 0 && i + 1 != chunks.len()) {
+            return None;
+        }
+        let mut group = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)?;
+            group = group << 6 | value as u32;
+        }
+        group <<= 6 * padding;
+        bytes.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::cpu::Chip8;
+
+    use super::{base64_decode, base64_encode, slot_key, SaveSlot, SlotStatus, SLOT_COUNT};
+    use crate::save_state::SaveState;
+
+    #[test]
+    fn test_slot_key() {
+        assert_eq!(slot_key(0), "save_slot_0");
+        assert_eq!(slot_key(3), "save_slot_3");
+        let keys: Vec<_> = (0..SLOT_COUNT).map(slot_key).collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key));
+            // distinct from the single save state's key
+            assert_ne!(key, "save_state");
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "+/8=");
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..bytes.len() {
+            let encoded = base64_encode(&bytes[..len]);
+            assert_eq!(base64_decode(&encoded).as_deref(), Some(&bytes[..len]));
+        }
+
+        assert_eq!(base64_decode("Zg="), None);
+        assert_eq!(base64_decode("Z==="), None);
+        assert_eq!(base64_decode("Zg==Zg=="), None);
+        assert_eq!(base64_decode("Zm9*"), None);
+    }
+
+    #[test]
+    fn test_slot_round_trip() {
+        let program = vec![0x70, 0x01, 0xd0, 0x01, 0x22, 0x00];
+        let mut chip8 = Chip8::new(&program);
+        chip8.run_frames(1, 8, &[false; 16]).unwrap();
+        let slot = SaveSlot {
+            name: Some("test.ch8".to_owned()),
+            state: SaveState {
+                program,
+                snapshot: chip8.to_snapshot(),
+            },
+        };
+
+        let s = slot.to_storage_string();
+
+        assert_eq!(SaveSlot::from_storage_string(&s).unwrap(), slot);
+        assert_eq!(
+            SlotStatus::from_storage_string(Some(&s)),
+            SlotStatus::Occupied {
+                name: Some("test.ch8".to_owned())
+            }
+        );
+    }
+
+    #[test]
+    fn test_slot_status() {
+        assert_eq!(SlotStatus::from_storage_string(None), SlotStatus::Empty);
+        assert_eq!(SlotStatus::from_storage_string(Some("")), SlotStatus::Empty);
+        assert!(matches!(
+            SlotStatus::from_storage_string(Some("version=1\nprogram=AAAA\nsnapshot=!\n")),
+            SlotStatus::Invalid(_)
+        ));
+    }
+}
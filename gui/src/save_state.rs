@@ -0,0 +1,312 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use chip8::{
+    cpu::{Chip8, DEFAULT_PITCH, STACK_SIZE},
+    memory::LoadError,
+    quirks::Quirks,
+    rng::Rng,
+    screen::PLANE_COUNT,
+    snapshot::{Snapshot, SnapshotError},
+};
+
+use crate::storage::{parse_entries, push_entry};
+
+/// Increased whenever the format changes in a way older versions can't load
+const SAVE_STATE_VERSION: u32 = 1;
+
+/// A checkpoint of the CHIP-8 which the user can go back to, along with the
+/// program it was running so that resetting afterwards restarts that program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveState {
+    pub program: Vec<u8>,
+    pub snapshot: Snapshot,
+}
+
+impl SaveState {
+    /// Create a CHIP-8 in the saved state
+    pub fn to_chip8(&self) -> Result<Chip8, SaveStateError> {
+        let mut chip8 = Chip8::try_new_with_quirks(&self.program, self.snapshot.quirks)
+            .map_err(SaveStateError::InvalidProgram)?;
+        chip8
+            .restore_snapshot(&self.snapshot)
+            .map_err(SaveStateError::InvalidSnapshot)?;
+        Ok(chip8)
+    }
+
+    /// Serialize the state to a string with one `key=value` pair per line,
+    /// with bytes in hex
+    pub fn to_storage_string(&self) -> String {
+        let snapshot = &self.snapshot;
+        let mut s = String::new();
+        push_entry(&mut s, "version", SAVE_STATE_VERSION);
+        push_entry(&mut s, "program", hex::encode(&self.program));
+        push_entry(&mut s, "memory", hex::encode(&snapshot.memory));
+        push_entry(&mut s, "high_res", snapshot.high_res);
+        for (plane, bytes) in snapshot.screen_planes.iter().enumerate() {
+            push_entry(&mut s, &format!("screen_plane_{plane}"), hex::encode(bytes));
+        }
+        push_entry(&mut s, "selected_planes", snapshot.selected_planes);
+        push_entry(&mut s, "v_reg", hex::encode(snapshot.v_reg));
+        push_entry(&mut s, "i_reg", snapshot.i_reg);
+        let stack: Vec<u8> = snapshot
+            .stack
+            .iter()
+            .flat_map(|addr| addr.to_be_bytes())
+            .collect();
+        push_entry(&mut s, "stack", hex::encode(stack));
+        push_entry(&mut s, "stack_ptr", snapshot.stack_ptr);
+        push_entry(&mut s, "pc", snapshot.pc);
+        push_entry(&mut s, "delay_timer", snapshot.delay_timer);
+        push_entry(&mut s, "sound_timer", snapshot.sound_timer);
+        push_entry(&mut s, "audio_pattern", hex::encode(snapshot.audio_pattern));
+        push_entry(&mut s, "pitch", snapshot.pitch);
+        push_entry(&mut s, "rng_state", snapshot.rng_state);
+        push_entry(&mut s, "frame_count", snapshot.frame_count);
+        push_entry(&mut s, "waiting_for_key", snapshot.waiting_for_key);
+        push_entry(
+            &mut s,
+            "waiting_initial_keys",
+            snapshot.waiting_initial_keys,
+        );
+        push_entry(&mut s, "halted", snapshot.halted);
+        push_entry(&mut s, "drew_since_vblank", snapshot.drew_since_vblank);
+        push_entry(
+            &mut s,
+            "wait_key_on_release",
+            snapshot.quirks.wait_key_on_release,
+        );
+        push_entry(&mut s, "extended_memory", snapshot.quirks.extended_memory);
+        push_entry(&mut s, "add_to_i_sets_vf", snapshot.quirks.add_to_i_sets_vf);
+        push_entry(
+            &mut s,
+            "protect_reserved_memory",
+            snapshot.quirks.protect_reserved_memory,
+        );
+        push_entry(&mut s, "shift_uses_vy", snapshot.quirks.shift_uses_vy);
+        push_entry(
+            &mut s,
+            "load_store_increments_i",
+            snapshot.quirks.load_store_increments_i,
+        );
+        push_entry(&mut s, "jump_with_vx", snapshot.quirks.jump_with_vx);
+        push_entry(&mut s, "logic_resets_vf", snapshot.quirks.logic_resets_vf);
+        push_entry(&mut s, "display_wait", snapshot.quirks.display_wait);
+        s
+    }
+
+    /// Deserialize a state from a string created with `to_storage_string()`
+    pub fn from_storage_string(s: &str) -> Result<SaveState, SaveStateError> {
+        let entries = parse_entries(s);
+        let version: u32 = read_entry(&entries, "version")?;
+        if version != SAVE_STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion { version });
+        }
+
+        // the planes' sizes are checked against `high_res` when restoring
+        let mut screen_planes: [Vec<u8>; PLANE_COUNT] = Default::default();
+        for (plane, bytes) in screen_planes.iter_mut().enumerate() {
+            *bytes = read_hex(&entries, &format!("screen_plane_{plane}"))?;
+        }
+        let stack_bytes: [u8; STACK_SIZE * 2] = read_hex_array(&entries, "stack")?;
+        let mut stack = [0; STACK_SIZE];
+        for (addr, bytes) in stack.iter_mut().zip(stack_bytes.chunks(2)) {
+            *addr = u16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+        let quirks = Quirks {
+            wait_key_on_release: read_entry(&entries, "wait_key_on_release")?,
+            extended_memory: read_entry(&entries, "extended_memory")?,
+            // quirks added since the first version are off in older states,
+            // as they didn't exist yet
+            add_to_i_sets_vf: read_entry_or(&entries, "add_to_i_sets_vf", false)?,
+            protect_reserved_memory: read_entry_or(&entries, "protect_reserved_memory", false)?,
+            // these were always the VIP's behavior before they were quirks
+            shift_uses_vy: read_entry_or(&entries, "shift_uses_vy", true)?,
+            load_store_increments_i: read_entry_or(&entries, "load_store_increments_i", true)?,
+            jump_with_vx: read_entry_or(&entries, "jump_with_vx", false)?,
+            logic_resets_vf: read_entry_or(&entries, "logic_resets_vf", false)?,
+            display_wait: read_entry_or(&entries, "display_wait", false)?,
+        };
+
+        Ok(SaveState {
+            program: read_hex(&entries, "program")?,
+            snapshot: Snapshot {
+                memory: read_hex(&entries, "memory")?,
+                // older states were always low-res
+                high_res: read_entry_or(&entries, "high_res", false)?,
+                screen_planes,
+                selected_planes: read_entry(&entries, "selected_planes")?,
+                v_reg: read_hex_array(&entries, "v_reg")?,
+                i_reg: read_entry(&entries, "i_reg")?,
+                stack,
+                stack_ptr: read_entry(&entries, "stack_ptr")?,
+                pc: read_entry(&entries, "pc")?,
+                delay_timer: read_entry(&entries, "delay_timer")?,
+                sound_timer: read_entry(&entries, "sound_timer")?,
+                // the audio pattern and pitch didn't exist in older states
+                audio_pattern: if entries.contains_key("audio_pattern") {
+                    read_hex_array(&entries, "audio_pattern")?
+                } else {
+                    Default::default()
+                },
+                pitch: read_entry_or(&entries, "pitch", DEFAULT_PITCH)?,
+                rng_state: read_entry_or(&entries, "rng_state", Rng::DEFAULT_SEED)?,
+                quirks,
+                // older states didn't store these, and were resumed as if the
+                // program was running
+                frame_count: read_entry_or(&entries, "frame_count", 0)?,
+                waiting_for_key: read_entry_or(&entries, "waiting_for_key", false)?,
+                waiting_initial_keys: read_entry_or(&entries, "waiting_initial_keys", 0)?,
+                halted: read_entry_or(&entries, "halted", false)?,
+                drew_since_vblank: read_entry_or(&entries, "drew_since_vblank", false)?,
+            },
+        })
+    }
+}
+
+fn get_entry<'a>(entries: &HashMap<&str, &'a str>, key: &str) -> Result<&'a str, SaveStateError> {
+    entries
+        .get(key)
+        .copied()
+        .ok_or_else(|| SaveStateError::MissingEntry {
+            key: key.to_owned(),
+        })
+}
+
+fn invalid_entry(key: &str) -> SaveStateError {
+    SaveStateError::InvalidEntry {
+        key: key.to_owned(),
+    }
+}
+
+fn read_entry<T: FromStr>(entries: &HashMap<&str, &str>, key: &str) -> Result<T, SaveStateError> {
+    get_entry(entries, key)?
+        .parse()
+        .map_err(|_| invalid_entry(key))
+}
+
+/// Like `read_entry()`, but `default` is returned if the entry is missing
+fn read_entry_or<T: FromStr>(
+    entries: &HashMap<&str, &str>,
+    key: &str,
+    default: T,
+) -> Result<T, SaveStateError> {
+    if entries.contains_key(key) {
+        read_entry(entries, key)
+    } else {
+        Ok(default)
+    }
+}
+
+fn read_hex(entries: &HashMap<&str, &str>, key: &str) -> Result<Vec<u8>, SaveStateError> {
+    hex::decode(get_entry(entries, key)?).map_err(|_| invalid_entry(key))
+}
+
+fn read_hex_array<const N: usize>(
+    entries: &HashMap<&str, &str>,
+    key: &str,
+) -> Result<[u8; N], SaveStateError> {
+    read_hex(entries, key)?
+        .try_into()
+        .map_err(|_| invalid_entry(key))
+}
+
+/// Error type for loading a `SaveState`
+#[derive(Debug)]
+pub enum SaveStateError {
+    /// The state was saved by a version with an incompatible format
+    UnsupportedVersion {
+        version: u32,
+    },
+    MissingEntry {
+        key: String,
+    },
+    InvalidEntry {
+        key: String,
+    },
+    InvalidSnapshot(SnapshotError),
+    InvalidProgram(LoadError),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStateError::UnsupportedVersion { version } => write!(
+                f,
+                "the state was saved in format version {version}, but only version {SAVE_STATE_VERSION} is supported"
+            ),
+            SaveStateError::MissingEntry { key } => {
+                write!(f, "the saved state is corrupt: {key} is missing")
+            }
+            SaveStateError::InvalidEntry { key } => {
+                write!(f, "the saved state is corrupt: {key} is invalid")
+            }
+            SaveStateError::InvalidSnapshot(inner) => {
+                write!(f, "the saved state is corrupt: {inner}")
+            }
+            SaveStateError::InvalidProgram(inner) => {
+                write!(f, "the saved state is corrupt: {inner}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::cpu::Chip8;
+
+    use super::{SaveState, SaveStateError};
+
+    fn save_state() -> SaveState {
+        // 7001: V0 += 1
+        // D001: draw the 1 byte sprite at I (0x000) at V0, V0
+        // 2200: call the start
+        let program = vec![0x70, 0x01, 0xd0, 0x01, 0x22, 0x00];
+        let mut chip8 = Chip8::new(&program);
+        chip8.run_frames(1, 8, &[false; 16]).unwrap();
+        SaveState {
+            program,
+            snapshot: chip8.to_snapshot(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = save_state();
+
+        let loaded = SaveState::from_storage_string(&state.to_storage_string()).unwrap();
+
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.to_chip8().unwrap().to_snapshot(), state.snapshot);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let s = save_state()
+            .to_storage_string()
+            .replace("version=1", "version=99");
+
+        let result = SaveState::from_storage_string(&s);
+
+        assert!(matches!(
+            result,
+            Err(SaveStateError::UnsupportedVersion { version: 99 })
+        ));
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let s = save_state().to_storage_string();
+
+        let missing = s.replace("pc=", "nothing=");
+        assert!(matches!(
+            SaveState::from_storage_string(&missing),
+            Err(SaveStateError::MissingEntry { key }) if key == "pc"
+        ));
+        let invalid = s.replace("v_reg=", "v_reg=zz");
+        assert!(matches!(
+            SaveState::from_storage_string(&invalid),
+            Err(SaveStateError::InvalidEntry { key }) if key == "v_reg"
+        ));
+        assert!(SaveState::from_storage_string("").is_err());
+    }
+}
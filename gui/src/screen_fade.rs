@@ -0,0 +1,225 @@
+use chip8::screen::{Screen, SCREEN_HEIGHT_PIXELS, SCREEN_WIDTH_PIXELS};
+use egui::Color32;
+
+/// Pixels dimmer than this are treated as fully off
+const MIN_BRIGHTNESS: f32 = 0.02;
+
+/// The brightness of a pixel after a frame, where `brightness` is its
+/// brightness from 0 to 1 in the previous frame. Pixels which are on are fully
+/// bright, and pixels which are off lose `decay` of their brightness each
+/// frame.
+pub fn fade(brightness: f32, on: bool, decay: f32) -> f32 {
+    if on {
+        return 1.0;
+    }
+    let brightness = brightness * (1.0 - decay);
+    if brightness < MIN_BRIGHTNESS {
+        0.0
+    } else {
+        brightness
+    }
+}
+
+/// Blend from `from` to `to` by `t`, where `t` is from 0 to 1
+pub fn blend(from: Color32, to: Color32, t: f32) -> Color32 {
+    let t = t.clamp(0.0, 1.0);
+    let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+    Color32::from_rgba_premultiplied(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+        channel(from.a(), to.a()),
+    )
+}
+
+/// How the screen is drawn when programs erase and redraw their sprites,
+/// which makes them flicker on the original hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlickerMode {
+    /// Draw exactly what's in the framebuffer at the end of each frame
+    Authentic,
+    /// Fade pixels out with a `ScreenFade` to hide the flicker
+    Smoothed,
+}
+
+impl FlickerMode {
+    pub const ALL: [FlickerMode; 2] = [FlickerMode::Authentic, FlickerMode::Smoothed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FlickerMode::Authentic => "Authentic",
+            FlickerMode::Smoothed => "Smoothed",
+        }
+    }
+
+    pub fn storage_name(self) -> &'static str {
+        match self {
+            FlickerMode::Authentic => "authentic",
+            FlickerMode::Smoothed => "smoothed",
+        }
+    }
+
+    pub fn from_storage_name(name: &str) -> Option<FlickerMode> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.storage_name() == name)
+    }
+}
+
+/// The brightness of each pixel, where pixels fade out over a few frames after
+/// turning off instead of immediately, like a phosphor screen. This hides the
+/// flicker of programs which erase and redraw their sprites every frame.
+///
+/// It starts at the low-res size, and takes on the size of the screen it's
+/// updated with.
+#[derive(Debug, Clone)]
+pub struct ScreenFade {
+    width: usize,
+    brightness: Vec<f32>,
+    /// The planes each pixel was in when it was last on, so it keeps its
+    /// color while fading out
+    planes: Vec<u8>,
+}
+
+impl Default for ScreenFade {
+    fn default() -> Self {
+        Self {
+            width: SCREEN_WIDTH_PIXELS,
+            brightness: vec![0.0; SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS],
+            planes: vec![0; SCREEN_WIDTH_PIXELS * SCREEN_HEIGHT_PIXELS],
+        }
+    }
+}
+
+impl ScreenFade {
+    /// Advance by one 60Hz frame, where `screen` is the screen at the end of
+    /// the frame. Every pixel is turned off if the screen's size has changed.
+    pub fn update(&mut self, screen: &Screen, decay: f32) {
+        let (width, height) = (screen.width(), screen.height());
+        if self.width != width || self.brightness.len() != width * height {
+            self.width = width;
+            self.brightness = vec![0.0; width * height];
+            self.planes = vec![0; width * height];
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let planes = screen.get_pixel_planes(x as u8, y as u8);
+                let brightness = &mut self.brightness[y * width + x];
+                *brightness = fade(*brightness, planes != 0, decay);
+                if planes != 0 {
+                    self.planes[y * width + x] = planes;
+                }
+            }
+        }
+    }
+
+    /// The brightness of the pixel at `x`, `y` from 0 to 1
+    pub fn brightness(&self, x: u8, y: u8) -> f32 {
+        self.brightness[y as usize * self.width + x as usize]
+    }
+
+    /// The planes the pixel at `x`, `y` was in when it was last on, as
+    /// returned by `Chip8Screen::get_pixel_planes()`
+    pub fn planes(&self, x: u8, y: u8) -> u8 {
+        self.planes[y as usize * self.width + x as usize]
+    }
+
+    /// Whether any pixel is part way through fading out
+    pub fn is_fading(&self) -> bool {
+        self.brightness
+            .iter()
+            .any(|&brightness| brightness > 0.0 && brightness < 1.0)
+    }
+
+    /// Turn every pixel off immediately
+    pub fn clear(&mut self) {
+        self.brightness.fill(0.0);
+        self.planes.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::screen::Screen;
+    use egui::Color32;
+
+    use super::{blend, fade, FlickerMode, ScreenFade};
+
+    #[test]
+    fn test_fade_curve() {
+        let mut brightness = fade(0.0, true, 0.5);
+        assert_eq!(brightness, 1.0);
+
+        let mut curve = vec![];
+        for _ in 0..6 {
+            brightness = fade(brightness, false, 0.5);
+            curve.push(brightness);
+        }
+
+        // halves each frame until it's too dim to see
+        assert_eq!(curve, [0.5, 0.25, 0.125, 0.0625, 0.03125, 0.0]);
+        // turning back on is immediate
+        assert_eq!(fade(0.25, true, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_no_decay_keeps_brightness() {
+        assert_eq!(fade(0.7, false, 0.0), 0.7);
+        assert_eq!(fade(0.7, false, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_blend() {
+        let from = Color32::from_rgb(0, 100, 200);
+        let to = Color32::from_rgb(200, 100, 0);
+
+        assert_eq!(blend(from, to, 0.0), from);
+        assert_eq!(blend(from, to, 1.0), to);
+        assert_eq!(blend(from, to, 0.5), Color32::from_rgb(100, 100, 100));
+        assert_eq!(blend(from, to, 2.0), to);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut screen = Screen::default();
+        screen.set_pixel(3, 4, true);
+        let mut fade = ScreenFade::default();
+
+        fade.update(&screen, 0.5);
+        assert_eq!(fade.brightness(3, 4), 1.0);
+        assert_eq!(fade.brightness(4, 4), 0.0);
+        assert!(!fade.is_fading());
+
+        screen.set_pixel(3, 4, false);
+        fade.update(&screen, 0.5);
+        assert_eq!(fade.brightness(3, 4), 0.5);
+        assert!(fade.is_fading());
+
+        fade.clear();
+        assert_eq!(fade.brightness(3, 4), 0.0);
+        assert!(!fade.is_fading());
+    }
+
+    #[test]
+    fn test_update_high_res() {
+        let mut screen = Screen::new(true);
+        screen.set_pixel(100, 50, true);
+        let mut fade = ScreenFade::default();
+
+        fade.update(&screen, 0.5);
+
+        assert_eq!(fade.brightness(100, 50), 1.0);
+        assert_eq!(fade.brightness(99, 50), 0.0);
+    }
+
+    #[test]
+    fn test_flicker_mode_storage_names() {
+        for mode in FlickerMode::ALL {
+            assert_eq!(
+                FlickerMode::from_storage_name(mode.storage_name()),
+                Some(mode)
+            );
+        }
+        assert_eq!(FlickerMode::from_storage_name("Authentic"), None);
+    }
+}
@@ -1,27 +1,109 @@
+use chip8::screen::{Screen, SCREEN_WIDTH_PIXELS};
+
+use crate::screen_fade::{blend, ScreenFade};
+
+/// Options for how the CHIP-8 screen is drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenRenderOptions {
+    /// The color of pixels which are on, in only the first plane for XO-CHIP
+    /// programs
+    pub foreground_color: egui::Color32,
+    /// The color of pixels which are off
+    pub background_color: egui::Color32,
+    /// The color of XO-CHIP pixels which are on in only the second plane
+    pub plane2_color: egui::Color32,
+    /// The color of XO-CHIP pixels which are on in both planes
+    pub both_planes_color: egui::Color32,
+    /// Draw 1px lines between pixels
+    pub grid: bool,
+    pub grid_color: egui::Color32,
+    /// How much every other row is darkened, where 0 doesn't darken at all
+    pub scanline_alpha: u8,
+}
+
+impl Default for ScreenRenderOptions {
+    fn default() -> Self {
+        Self {
+            foreground_color: egui::Color32::WHITE,
+            background_color: egui::Color32::BLACK,
+            // like Octo's, which most XO-CHIP programs are designed with
+            plane2_color: egui::Color32::from_rgb(0xff, 0x66, 0x00),
+            both_planes_color: egui::Color32::from_rgb(0x99, 0x66, 0x00),
+            grid: false,
+            grid_color: egui::Color32::from_gray(40),
+            scanline_alpha: 0,
+        }
+    }
+}
+
+/// The color of a pixel whose planes are `planes`, as returned by
+/// `Chip8Screen::get_pixel_planes()`, where the least significant bit is the
+/// first plane
+pub fn plane_color(planes: u8, options: &ScreenRenderOptions) -> egui::Color32 {
+    match planes & 0b11 {
+        0b00 => options.background_color,
+        0b01 => options.foreground_color,
+        0b10 => options.plane2_color,
+        _ => options.both_planes_color,
+    }
+}
+
+/// The color of the pixel at `x`, `y` of `screen`. Without a `fade` this is
+/// exactly the framebuffer's pixel colored by `plane_color()`, and with one,
+/// pixels which are off are drawn in the color they were last on in, dimmed
+/// to its brightness.
+pub fn pixel_color(
+    screen: &Screen,
+    fade: Option<&ScreenFade>,
+    x: u8,
+    y: u8,
+    options: &ScreenRenderOptions,
+) -> egui::Color32 {
+    let planes = screen.get_pixel_planes(x, y);
+    match fade {
+        Some(fade) if planes == 0 => blend(
+            options.background_color,
+            plane_color(fade.planes(x, y), options),
+            fade.brightness(x, y),
+        ),
+        _ => plane_color(planes, options),
+    }
+}
+
+/// Draw `screen`, which can be either size, coloring each pixel with
+/// `pixel_color()`. If `crossfade` is given, each pixel is blended from its
+/// color on that screen by `blend()` with that progress, where a
+/// screen of the other size is stretched to fit. The response senses clicks,
+/// which `pixel_at()` turns into pixel coordinates.
 pub fn draw_chip8_screen(
     ui: &mut egui::Ui,
     pixel_scale: u32,
-    screen: &chip8::screen::Chip8Screen
+    screen: &Screen,
+    fade: Option<&ScreenFade>,
+    crossfade: Option<(&Screen, f32)>,
+    options: &ScreenRenderOptions,
 ) -> egui::Response {
+    let (width, height) = (screen.width(), screen.height());
     let pixel_scale = pixel_scale as f32;
-    let desired_size = pixel_scale * egui::vec2(64.0, 32.0);
-    let (rect, response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
+    let desired_size = pixel_scale * egui::vec2(width as f32, height as f32);
+    let (rect, response) = ui.allocate_exact_size(desired_size, egui::Sense::click());
 
-    let on_color = egui::Rgba::WHITE;
-    let off_color = egui::Rgba::BLACK;
     let pixel_vec = pixel_scale * egui::vec2(1., 1.);
 
     if ui.is_rect_visible(rect) {
-        for y in 0..chip8::screen::SCREEN_HEIGHT_PIXELS as u8 {
-            for x in 0..chip8::screen::SCREEN_WIDTH_PIXELS as u8{
+        for y in 0..height {
+            for x in 0..width {
                 let min = pixel_scale * egui::vec2(x as f32, y as f32) + rect.min.to_vec2();
                 let max = min + pixel_vec;
-                let color = {
-                    if screen.get_pixel(x, y) {
-                        on_color
-                    } else {
-                        off_color
+                let color = pixel_color(screen, fade, x as u8, y as u8, options);
+                let color = match crossfade {
+                    Some((previous, progress)) => {
+                        let previous_x = x * previous.width() / width;
+                        let previous_y = y * previous.height() / height;
+                        let planes = previous.get_pixel_planes(previous_x as u8, previous_y as u8);
+                        blend(plane_color(planes, options), color, progress)
                     }
+                    None => color,
                 };
                 ui.painter().rect_filled(
                     egui::Rect {
@@ -33,7 +115,202 @@ pub fn draw_chip8_screen(
                 );
             }
         }
+
+        if options.scanline_alpha > 0 {
+            let scanline_color = egui::Color32::from_black_alpha(options.scanline_alpha);
+            for y in (1..height).step_by(2) {
+                let min = rect.min + egui::vec2(0.0, pixel_scale * y as f32);
+                let max = egui::pos2(rect.max.x, min.y + pixel_scale);
+                ui.painter().rect_filled(
+                    egui::Rect { min, max },
+                    egui::Rounding::none(),
+                    scanline_color,
+                );
+            }
+        }
+
+        if options.grid {
+            let stroke = egui::Stroke::new(1.0, options.grid_color);
+            for x in 1..width {
+                let x = rect.min.x + pixel_scale * x as f32;
+                ui.painter().line_segment(
+                    [egui::pos2(x, rect.min.y), egui::pos2(x, rect.max.y)],
+                    stroke,
+                );
+            }
+            for y in 1..height {
+                let y = rect.min.y + pixel_scale * y as f32;
+                ui.painter().line_segment(
+                    [egui::pos2(rect.min.x, y), egui::pos2(rect.max.x, y)],
+                    stroke,
+                );
+            }
+        }
     }
 
     response
 }
+
+/// The coordinates of the CHIP-8 pixel at `pos` on a `width` by `height`
+/// screen drawn in `rect` at `pixel_scale`, or `None` if `pos` is outside it
+pub fn pixel_at(
+    pos: egui::Pos2,
+    rect: egui::Rect,
+    pixel_scale: u32,
+    width: usize,
+    height: usize,
+) -> Option<(u8, u8)> {
+    let offset = (pos - rect.min) / pixel_scale as f32;
+    if offset.x < 0.0 || offset.y < 0.0 {
+        return None;
+    }
+    let (x, y) = (offset.x as usize, offset.y as usize);
+    (x < width && y < height).then_some((x as u8, y as u8))
+}
+
+/// The largest integer pixel scale at which a `width` by `height` screen fits
+/// in `available_size`. Integer scales keep every CHIP-8 pixel the same size.
+pub fn fit_pixel_scale(width: usize, height: usize, available_size: egui::Vec2) -> u32 {
+    let scale_x = available_size.x / width as f32;
+    let scale_y = available_size.y / height as f32;
+    (scale_x.min(scale_y).floor() as u32).max(1)
+}
+
+/// The pixel scale to draw a `width` by `height` screen at. The `pixel_scale`
+/// setting is for the 64x32 screen, so it's reduced for wider screens to keep
+/// them about the same size, e.g. halved for the 128x64 high-res screen. If
+/// `fit_to_window` is set, the largest scale which fits is used instead.
+pub fn screen_pixel_scale(
+    width: usize,
+    height: usize,
+    pixel_scale: u32,
+    fit_to_window: bool,
+    available_size: egui::Vec2,
+) -> u32 {
+    if fit_to_window {
+        fit_pixel_scale(width, height, available_size)
+    } else {
+        (pixel_scale * SCREEN_WIDTH_PIXELS as u32 / width as u32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::screen::{LowResScreen, Screen};
+
+    use super::{
+        fit_pixel_scale, pixel_at, pixel_color, plane_color, screen_pixel_scale,
+        ScreenRenderOptions,
+    };
+    use crate::screen_fade::{blend, ScreenFade};
+
+    #[test]
+    fn test_render_options_default_draws_plain_screen() {
+        let options = ScreenRenderOptions::default();
+        assert!(!options.grid);
+        assert_eq!(options.scanline_alpha, 0);
+    }
+
+    #[test]
+    fn test_plane_color() {
+        let options = ScreenRenderOptions::default();
+
+        assert_eq!(plane_color(0b00, &options), options.background_color);
+        assert_eq!(plane_color(0b01, &options), options.foreground_color);
+        assert_eq!(plane_color(0b10, &options), options.plane2_color);
+        assert_eq!(plane_color(0b11, &options), options.both_planes_color);
+        let colors = [0b00, 0b01, 0b10, 0b11].map(|planes| plane_color(planes, &options));
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[i + 1..].contains(color), "{colors:?}");
+        }
+    }
+
+    #[test]
+    fn test_fit_pixel_scale() {
+        assert_eq!(fit_pixel_scale(64, 32, egui::vec2(640.0, 320.0)), 10);
+        assert_eq!(fit_pixel_scale(64, 32, egui::vec2(700.0, 330.0)), 10);
+        // limited by height
+        assert_eq!(fit_pixel_scale(64, 32, egui::vec2(1000.0, 100.0)), 3);
+        // never smaller than 1
+        assert_eq!(fit_pixel_scale(64, 32, egui::vec2(10.0, 10.0)), 1);
+        assert_eq!(fit_pixel_scale(128, 64, egui::vec2(640.0, 320.0)), 5);
+    }
+
+    #[test]
+    fn test_screen_pixel_scale() {
+        let panel = egui::vec2(700.0, 400.0);
+        assert_eq!(screen_pixel_scale(64, 32, 10, false, panel), 10);
+        // high-res is drawn the same size as low-res
+        assert_eq!(screen_pixel_scale(128, 64, 10, false, panel), 5);
+        assert_eq!(screen_pixel_scale(128, 64, 7, false, panel), 3);
+        assert_eq!(screen_pixel_scale(128, 64, 1, false, panel), 1);
+        // fitting ignores the setting
+        assert_eq!(screen_pixel_scale(64, 32, 3, true, panel), 10);
+        assert_eq!(screen_pixel_scale(128, 64, 3, true, panel), 5);
+    }
+
+    #[test]
+    fn test_pixel_at() {
+        let rect = egui::Rect::from_min_size(egui::pos2(10.0, 20.0), egui::vec2(640.0, 320.0));
+        assert_eq!(
+            pixel_at(egui::pos2(10.0, 20.0), rect, 10, 64, 32),
+            Some((0, 0))
+        );
+        assert_eq!(
+            pixel_at(egui::pos2(29.9, 39.9), rect, 10, 64, 32),
+            Some((1, 1))
+        );
+        assert_eq!(
+            pixel_at(egui::pos2(649.0, 339.0), rect, 10, 64, 32),
+            Some((63, 31))
+        );
+        assert_eq!(pixel_at(egui::pos2(650.0, 30.0), rect, 10, 64, 32), None);
+        assert_eq!(pixel_at(egui::pos2(9.0, 30.0), rect, 10, 64, 32), None);
+        assert_eq!(pixel_at(egui::pos2(15.0, 19.5), rect, 10, 64, 32), None);
+        assert_eq!(
+            pixel_at(egui::pos2(649.0, 339.0), rect, 5, 128, 64),
+            Some((127, 63))
+        );
+    }
+
+    #[test]
+    fn test_pixel_color_without_fade_is_raw_framebuffer() {
+        let options = ScreenRenderOptions::default();
+        let mut screen = Screen::default();
+        let mut fade = ScreenFade::default();
+        screen.set_pixel(1, 2, true);
+        fade.update(&screen, 0.5);
+        // the pixel turns off, but is still fading out
+        screen.set_pixel(1, 2, false);
+        screen.set_pixel(3, 4, true);
+
+        for (x, y) in [(0, 0), (1, 2), (3, 4)] {
+            let raw = plane_color(screen.get_pixel_planes(x, y), &options);
+            assert_eq!(pixel_color(&screen, None, x, y, &options), raw);
+        }
+        assert_ne!(
+            pixel_color(&screen, Some(&fade), 1, 2, &options),
+            options.background_color
+        );
+        assert_eq!(
+            pixel_color(&screen, Some(&fade), 3, 4, &options),
+            options.foreground_color
+        );
+    }
+
+    #[test]
+    fn test_pixel_color_fades_in_plane_color() {
+        let options = ScreenRenderOptions::default();
+        let mut screen = LowResScreen::default();
+        screen.set_plane_pixel(1, 1, 2, true);
+        let mut fade = ScreenFade::default();
+        fade.update(&Screen::LowRes(screen), 0.5);
+        let blank = Screen::default();
+        fade.update(&blank, 0.5);
+
+        assert_eq!(
+            pixel_color(&blank, Some(&fade), 1, 2, &options),
+            blend(options.background_color, options.plane2_color, 0.5)
+        );
+    }
+}
@@ -0,0 +1,1126 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chip8::quirks::Quirks;
+use egui::Color32;
+
+use crate::{
+    app::{
+        DEFAULT_INSTRUCTIONS_PER_SECOND, MAX_INSTRUCTIONS_PER_SECOND, MIN_INSTRUCTIONS_PER_SECOND,
+    },
+    gamepad::{GamepadButton, GAMEPAD_MAP},
+    i18n::t,
+    keyboard::{
+        bindable_key_from_name, key_map_conflicts, turbo_key_from_name, BINDABLE_KEYS, KEY_MAP,
+        TURBO_KEYS,
+    },
+    rom_overrides::{push_rom_overrides, read_rom_overrides, RomOverride},
+    screen_fade::FlickerMode,
+    screen_ui::ScreenRenderOptions,
+    storage::{parse_entries, push_entry},
+};
+
+/// User configurable settings for the GUI
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// How many physical pixels wide and tall each CHIP-8 pixel is drawn
+    pub pixel_scale: u32,
+    /// Ignore `pixel_scale` and use the largest integer scale which fits in
+    /// the window
+    pub fit_to_window: bool,
+    /// The color of pixels which are on
+    pub foreground_color: Color32,
+    /// The color of pixels which are off
+    pub background_color: Color32,
+    /// The colors of XO-CHIP pixels which are on in only the second plane, and
+    /// in both planes. Pixels in only the first plane use `foreground_color`.
+    pub plane2_color: Color32,
+    pub both_planes_color: Color32,
+    /// Draw lines between the CHIP-8 pixels
+    pub grid: bool,
+    pub grid_color: Color32,
+    /// How much every other row is darkened for a scanline effect
+    pub scanline_alpha: u8,
+    /// Whether to draw the raw framebuffer, flicker and all, or fade pixels
+    /// out over a few frames instead of turning them off immediately
+    pub flicker_mode: FlickerMode,
+    /// How much of their brightness fading pixels lose each frame, from 0 to 1,
+    /// when `flicker_mode` is `FlickerMode::Smoothed`
+    pub fade_decay: f32,
+    /// Fade from the previous program's screen when loading a program
+    pub crossfade: bool,
+    /// Stop running the CHIP-8 while the window doesn't have focus
+    pub pause_when_unfocused: bool,
+    /// Repaint less often while the screen isn't changing, to save battery
+    pub power_saving: bool,
+    /// Show an on-screen hex keypad, for devices without a keyboard
+    pub show_keypad: bool,
+    /// Show the instructions and frames per second over the screen
+    pub show_performance: bool,
+    /// The keyboard key for each CHIP-8 key. A map where two CHIP-8 keys share
+    /// a keyboard key, or one shares the turbo key, isn't saved.
+    pub key_map: [egui::Key; 16],
+    /// The controller button bound to each CHIP-8 key, if any, which works
+    /// alongside `key_map`
+    pub gamepad_map: [Option<GamepadButton>; 16],
+    /// While this key is held, the CHIP-8 runs `turbo_multiplier` times as
+    /// many cycles per frame
+    pub turbo_key: egui::Key,
+    pub turbo_multiplier: u32,
+    /// How many CHIP-8 instructions are run per second at normal speed,
+    /// spread over the 60Hz frames
+    pub instructions_per_second: u32,
+    /// Run this fraction of the normal cycles per frame, from 0.1 to 1.0, for
+    /// watching programs in slow motion. Turbo runs at full speed.
+    pub speed: f32,
+    /// Run as many cycles per frame as the COSMAC VIP would have time for,
+    /// using `CycleCosts::vip()`, instead of a fixed number
+    pub vip_timing: bool,
+    /// The quirks programs are run with, which can be set from a
+    /// `QuirksPreset` or individually
+    pub quirks: Quirks,
+    /// Settings for particular ROMs, keyed by `rom_hash()`, which are used
+    /// instead of the ones above when that ROM is loaded
+    pub rom_overrides: BTreeMap<u64, RomOverride>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let render_options = ScreenRenderOptions::default();
+        Self {
+            pixel_scale: 10,
+            fit_to_window: false,
+            foreground_color: render_options.foreground_color,
+            background_color: render_options.background_color,
+            plane2_color: render_options.plane2_color,
+            both_planes_color: render_options.both_planes_color,
+            grid: render_options.grid,
+            grid_color: render_options.grid_color,
+            scanline_alpha: render_options.scanline_alpha,
+            // match the hardware unless asked otherwise
+            flicker_mode: FlickerMode::Authentic,
+            fade_decay: 0.5,
+            crossfade: false,
+            pause_when_unfocused: false,
+            power_saving: true,
+            // there's likely no keyboard on the web, e.g. on phones
+            show_keypad: cfg!(target_arch = "wasm32"),
+            show_performance: false,
+            key_map: KEY_MAP,
+            gamepad_map: GAMEPAD_MAP,
+            turbo_key: egui::Key::Space,
+            turbo_multiplier: 8,
+            instructions_per_second: DEFAULT_INSTRUCTIONS_PER_SECOND,
+            speed: 1.0,
+            vip_timing: false,
+            quirks: QuirksPreset::Vip.quirks(),
+            rom_overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn screen_render_options(&self) -> ScreenRenderOptions {
+        ScreenRenderOptions {
+            foreground_color: self.foreground_color,
+            background_color: self.background_color,
+            plane2_color: self.plane2_color,
+            both_planes_color: self.both_planes_color,
+            grid: self.grid,
+            grid_color: self.grid_color,
+            scanline_alpha: self.scanline_alpha,
+        }
+    }
+
+    /// The quirks to run the ROM with `rom_hash()` of `hash` with
+    pub fn quirks_for_rom(&self, hash: u64) -> Quirks {
+        self.rom_overrides
+            .get(&hash)
+            .map_or(self.quirks, |rom_override| rom_override.quirks)
+    }
+
+    /// Whether two CHIP-8 keys are bound to the same keyboard key, or one is
+    /// bound to the turbo key
+    pub fn has_key_conflicts(&self) -> bool {
+        key_map_conflicts(&self.key_map, self.turbo_key).contains(&true)
+    }
+
+    /// Serialize the settings to a string with one `key=value` pair per line
+    pub fn to_storage_string(&self) -> String {
+        let mut s = String::new();
+        push_entry(&mut s, "pixel_scale", self.pixel_scale);
+        push_entry(&mut s, "fit_to_window", self.fit_to_window);
+        push_entry(
+            &mut s,
+            "foreground_color",
+            color_to_string(self.foreground_color),
+        );
+        push_entry(
+            &mut s,
+            "background_color",
+            color_to_string(self.background_color),
+        );
+        push_entry(&mut s, "plane2_color", color_to_string(self.plane2_color));
+        push_entry(
+            &mut s,
+            "both_planes_color",
+            color_to_string(self.both_planes_color),
+        );
+        push_entry(&mut s, "grid", self.grid);
+        push_entry(&mut s, "grid_color", color_to_string(self.grid_color));
+        push_entry(&mut s, "scanline_alpha", self.scanline_alpha);
+        push_entry(&mut s, "flicker_mode", self.flicker_mode.storage_name());
+        push_entry(&mut s, "crossfade", self.crossfade);
+        push_entry(&mut s, "fade_decay", self.fade_decay);
+        push_entry(&mut s, "pause_when_unfocused", self.pause_when_unfocused);
+        push_entry(&mut s, "power_saving", self.power_saving);
+        push_entry(&mut s, "show_keypad", self.show_keypad);
+        push_entry(&mut s, "show_performance", self.show_performance);
+        if !self.has_key_conflicts() {
+            push_entry(&mut s, "key_map", key_map_to_string(&self.key_map));
+        }
+        push_entry(
+            &mut s,
+            "gamepad_map",
+            gamepad_map_to_string(&self.gamepad_map),
+        );
+        push_entry(&mut s, "turbo_key", self.turbo_key.name());
+        push_entry(&mut s, "turbo_multiplier", self.turbo_multiplier);
+        push_entry(
+            &mut s,
+            "instructions_per_second",
+            self.instructions_per_second,
+        );
+        push_entry(&mut s, "speed", self.speed);
+        push_entry(&mut s, "vip_timing", self.vip_timing);
+        push_entry(
+            &mut s,
+            "wait_key_on_release",
+            self.quirks.wait_key_on_release,
+        );
+        push_entry(&mut s, "extended_memory", self.quirks.extended_memory);
+        push_entry(&mut s, "add_to_i_sets_vf", self.quirks.add_to_i_sets_vf);
+        push_entry(
+            &mut s,
+            "protect_reserved_memory",
+            self.quirks.protect_reserved_memory,
+        );
+        push_entry(&mut s, "shift_uses_vy", self.quirks.shift_uses_vy);
+        push_entry(
+            &mut s,
+            "load_store_increments_i",
+            self.quirks.load_store_increments_i,
+        );
+        push_entry(&mut s, "jump_with_vx", self.quirks.jump_with_vx);
+        push_entry(&mut s, "logic_resets_vf", self.quirks.logic_resets_vf);
+        push_entry(&mut s, "display_wait", self.quirks.display_wait);
+        push_rom_overrides(&mut s, &self.rom_overrides);
+        s
+    }
+
+    /// Deserialize settings from a string created with `to_storage_string()`.
+    ///
+    /// Missing or invalid entries are left as their default values, so that
+    /// settings saved by older versions can still be loaded.
+    pub fn from_storage_string(s: &str) -> Settings {
+        let entries = parse_entries(s);
+        let mut settings = Settings::default();
+        read_entry_with(
+            &entries,
+            "pixel_scale",
+            &mut settings.pixel_scale,
+            |entry| Some(entry.parse::<u32>().ok()?.clamp(1, 20)),
+        );
+        read_entry(&entries, "fit_to_window", &mut settings.fit_to_window);
+        read_entry_with(
+            &entries,
+            "foreground_color",
+            &mut settings.foreground_color,
+            color_from_str,
+        );
+        read_entry_with(
+            &entries,
+            "background_color",
+            &mut settings.background_color,
+            color_from_str,
+        );
+        read_entry_with(
+            &entries,
+            "plane2_color",
+            &mut settings.plane2_color,
+            color_from_str,
+        );
+        read_entry_with(
+            &entries,
+            "both_planes_color",
+            &mut settings.both_planes_color,
+            color_from_str,
+        );
+        read_entry(&entries, "grid", &mut settings.grid);
+        read_entry_with(
+            &entries,
+            "grid_color",
+            &mut settings.grid_color,
+            color_from_str,
+        );
+        read_entry(&entries, "scanline_alpha", &mut settings.scanline_alpha);
+        // older versions stored whether flicker reduction was on
+        read_entry_with(
+            &entries,
+            "flicker_reduction",
+            &mut settings.flicker_mode,
+            |entry| match entry.parse().ok()? {
+                true => Some(FlickerMode::Smoothed),
+                false => Some(FlickerMode::Authentic),
+            },
+        );
+        read_entry_with(
+            &entries,
+            "flicker_mode",
+            &mut settings.flicker_mode,
+            FlickerMode::from_storage_name,
+        );
+        read_entry(&entries, "crossfade", &mut settings.crossfade);
+        read_entry_with(&entries, "fade_decay", &mut settings.fade_decay, |entry| {
+            let fade_decay: f32 = entry.parse().ok()?;
+            fade_decay.is_finite().then(|| fade_decay.clamp(0.1, 0.9))
+        });
+        read_entry(
+            &entries,
+            "pause_when_unfocused",
+            &mut settings.pause_when_unfocused,
+        );
+        read_entry(&entries, "power_saving", &mut settings.power_saving);
+        read_entry(&entries, "show_keypad", &mut settings.show_keypad);
+        read_entry(&entries, "show_performance", &mut settings.show_performance);
+        read_entry_with(
+            &entries,
+            "turbo_key",
+            &mut settings.turbo_key,
+            turbo_key_from_name,
+        );
+        let turbo_key = settings.turbo_key;
+        read_entry_with(&entries, "key_map", &mut settings.key_map, |entry| {
+            key_map_from_str(entry)
+                .filter(|key_map| !key_map_conflicts(key_map, turbo_key).contains(&true))
+        });
+        read_entry_with(
+            &entries,
+            "gamepad_map",
+            &mut settings.gamepad_map,
+            gamepad_map_from_str,
+        );
+        read_entry_with(
+            &entries,
+            "turbo_multiplier",
+            &mut settings.turbo_multiplier,
+            |entry| Some(entry.parse::<u32>().ok()?.clamp(2, 32)),
+        );
+        read_entry_with(
+            &entries,
+            "instructions_per_second",
+            &mut settings.instructions_per_second,
+            |entry| {
+                let ips: u32 = entry.parse().ok()?;
+                Some(ips.clamp(MIN_INSTRUCTIONS_PER_SECOND, MAX_INSTRUCTIONS_PER_SECOND))
+            },
+        );
+        read_entry_with(&entries, "speed", &mut settings.speed, |entry| {
+            let speed: f32 = entry.parse().ok()?;
+            speed.is_finite().then(|| speed.clamp(0.1, 1.0))
+        });
+        read_entry(&entries, "vip_timing", &mut settings.vip_timing);
+        // older versions only stored a preset, which individual quirks
+        // override
+        read_entry_with(&entries, "quirks_preset", &mut settings.quirks, |name| {
+            QuirksPreset::from_storage_name(name).map(QuirksPreset::quirks)
+        });
+        let quirks = &mut settings.quirks;
+        read_entry(
+            &entries,
+            "wait_key_on_release",
+            &mut quirks.wait_key_on_release,
+        );
+        read_entry(&entries, "extended_memory", &mut quirks.extended_memory);
+        read_entry(&entries, "add_to_i_sets_vf", &mut quirks.add_to_i_sets_vf);
+        read_entry(
+            &entries,
+            "protect_reserved_memory",
+            &mut quirks.protect_reserved_memory,
+        );
+        read_entry(&entries, "shift_uses_vy", &mut quirks.shift_uses_vy);
+        read_entry(
+            &entries,
+            "load_store_increments_i",
+            &mut quirks.load_store_increments_i,
+        );
+        read_entry(&entries, "jump_with_vx", &mut quirks.jump_with_vx);
+        read_entry(&entries, "logic_resets_vf", &mut quirks.logic_resets_vf);
+        read_entry(&entries, "display_wait", &mut quirks.display_wait);
+        settings.rom_overrides = read_rom_overrides(
+            &entries,
+            RomOverride {
+                quirks: settings.quirks,
+                instructions_per_second: settings.instructions_per_second,
+            },
+        );
+        settings
+    }
+}
+
+fn read_entry<T: std::str::FromStr>(entries: &HashMap<&str, &str>, key: &str, value: &mut T) {
+    read_entry_with(entries, key, value, |entry| entry.parse().ok());
+}
+
+fn read_entry_with<T>(
+    entries: &HashMap<&str, &str>,
+    key: &str,
+    value: &mut T,
+    parse: impl Fn(&str) -> Option<T>,
+) {
+    if let Some(parsed) = entries.get(key).and_then(|entry| parse(entry)) {
+        *value = parsed;
+    }
+}
+
+/// A platform with a well-known combination of quirks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuirksPreset {
+    Vip,
+    Chip48,
+    Schip,
+    XoChip,
+}
+
+impl QuirksPreset {
+    pub const ALL: [QuirksPreset; 4] = [
+        QuirksPreset::Vip,
+        QuirksPreset::Chip48,
+        QuirksPreset::Schip,
+        QuirksPreset::XoChip,
+    ];
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            QuirksPreset::Vip => Quirks::vip(),
+            QuirksPreset::Chip48 => Quirks::chip48(),
+            QuirksPreset::Schip => Quirks::schip(),
+            QuirksPreset::XoChip => Quirks::xochip(),
+        }
+    }
+
+    /// The preset with exactly these quirks, if any
+    pub fn matching(quirks: Quirks) -> Option<QuirksPreset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.quirks() == quirks)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuirksPreset::Vip => "COSMAC VIP",
+            QuirksPreset::Chip48 => "CHIP-48",
+            QuirksPreset::Schip => "SUPER-CHIP",
+            QuirksPreset::XoChip => "XO-CHIP",
+        }
+    }
+
+    fn storage_name(self) -> &'static str {
+        match self {
+            QuirksPreset::Vip => "vip",
+            QuirksPreset::Chip48 => "chip48",
+            QuirksPreset::Schip => "schip",
+            QuirksPreset::XoChip => "xochip",
+        }
+    }
+
+    fn from_storage_name(name: &str) -> Option<QuirksPreset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.storage_name() == name)
+    }
+}
+
+/// A bundle of the settings which trade accuracy to the original hardware for
+/// compatibility with modern programs, so they can be set with one choice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccuracyMode {
+    /// Behave like the COSMAC VIP, including how long its instructions took
+    Accurate,
+    /// Run a fixed number of cycles per frame with the quirks most modern
+    /// programs are written for
+    Fast,
+}
+
+impl AccuracyMode {
+    pub const ALL: [AccuracyMode; 2] = [AccuracyMode::Accurate, AccuracyMode::Fast];
+
+    /// Change the settings in this mode's bundle, leaving the rest alone
+    pub fn apply(self, settings: &mut Settings) {
+        match self {
+            AccuracyMode::Accurate => {
+                settings.quirks = QuirksPreset::Vip.quirks();
+                settings.vip_timing = true;
+            }
+            AccuracyMode::Fast => {
+                settings.quirks = QuirksPreset::Schip.quirks();
+                settings.vip_timing = false;
+            }
+        }
+    }
+
+    /// The mode whose bundle the settings match, if any. This is how the mode
+    /// is saved, so changing one of its settings individually can't leave a
+    /// saved mode which disagrees with them.
+    pub fn matching(settings: &Settings) -> Option<AccuracyMode> {
+        Self::ALL.into_iter().find(|mode| {
+            let mut applied = settings.clone();
+            mode.apply(&mut applied);
+            applied == *settings
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AccuracyMode::Accurate => "Accurate (COSMAC VIP)",
+            AccuracyMode::Fast => "Fast (compatible)",
+        }
+    }
+}
+
+/// Named foreground and background color pairs the user can pick from
+pub fn presets() -> &'static [(&'static str, Color32, Color32)] {
+    PRESETS
+}
+
+const PRESETS: &[(&str, Color32, Color32)] = &[
+    ("White on black", Color32::WHITE, Color32::BLACK),
+    ("Black on white", Color32::BLACK, Color32::WHITE),
+    (
+        "Green phosphor",
+        Color32::from_rgb(0x33, 0xff, 0x66),
+        Color32::from_rgb(0x0a, 0x1a, 0x0f),
+    ),
+    (
+        "Amber",
+        Color32::from_rgb(0xff, 0xb0, 0x00),
+        Color32::from_rgb(0x1e, 0x12, 0x00),
+    ),
+    (
+        "Game Boy",
+        Color32::from_rgb(0x0f, 0x38, 0x0f),
+        Color32::from_rgb(0x9b, 0xbc, 0x0f),
+    ),
+];
+
+/// Format a color as `#rrggbbaa`
+fn color_to_string(color: Color32) -> String {
+    let [r, g, b, a] = color.to_array();
+    format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+}
+
+/// Parse a color formatted by `color_to_string()`
+fn color_from_str(s: &str) -> Option<Color32> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 8 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok();
+    Some(Color32::from_rgba_premultiplied(
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        channel(3)?,
+    ))
+}
+
+/// Format a key map as the names of its keys separated by commas, in order
+/// from CHIP-8 key 0 to F
+fn key_map_to_string(key_map: &[egui::Key; 16]) -> String {
+    key_map.map(|key| key.name()).join(",")
+}
+
+/// Parse a key map formatted by `key_map_to_string()`
+fn key_map_from_str(s: &str) -> Option<[egui::Key; 16]> {
+    let keys = s
+        .split(',')
+        .map(bindable_key_from_name)
+        .collect::<Option<Vec<_>>>()?;
+    keys.try_into().ok()
+}
+
+/// Format a gamepad map as the names of its buttons separated by commas, in
+/// order from CHIP-8 key 0 to F, with `None` for unbound keys
+fn gamepad_map_to_string(gamepad_map: &[Option<GamepadButton>; 16]) -> String {
+    gamepad_map
+        .map(|button| button.map_or("None", GamepadButton::name))
+        .join(",")
+}
+
+/// Parse a gamepad map formatted by `gamepad_map_to_string()`
+fn gamepad_map_from_str(s: &str) -> Option<[Option<GamepadButton>; 16]> {
+    let buttons = s
+        .split(',')
+        .map(|name| match name {
+            "None" => Some(None),
+            _ => GamepadButton::from_name(name).map(Some),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    buttons.try_into().ok()
+}
+
+/// Common speeds for `Settings::instructions_per_second`, from roughly the
+/// COSMAC VIP's to what most modern games expect
+const INSTRUCTIONS_PER_SECOND_PRESETS: [u32; 4] = [500, 700, 1000, 1800];
+
+/// The CHIP-8 keys in the order they're laid out on the COSMAC VIP's keypad
+const KEYPAD_LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xc],
+    [0x4, 0x5, 0x6, 0xd],
+    [0x7, 0x8, 0x9, 0xe],
+    [0xa, 0x0, 0xb, 0xf],
+];
+
+/// Draw a grid for binding each CHIP-8 key to a keyboard key, with
+/// conflicting bindings highlighted
+fn key_map_menu(ui: &mut egui::Ui, settings: &mut Settings) {
+    let conflicts = key_map_conflicts(&settings.key_map, settings.turbo_key);
+    let error_color = ui.visuals().error_fg_color;
+    egui::Grid::new("key_map").show(ui, |ui| {
+        for row in KEYPAD_LAYOUT {
+            for chip8_key in row {
+                ui.label(format!("{chip8_key:X}:"));
+                let mut text = egui::RichText::new(settings.key_map[chip8_key].name());
+                if conflicts[chip8_key] {
+                    text = text.color(error_color);
+                }
+                egui::ComboBox::from_id_source(("key_map", chip8_key))
+                    .selected_text(text)
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        for key in BINDABLE_KEYS {
+                            ui.selectable_value(&mut settings.key_map[chip8_key], key, key.name());
+                        }
+                    });
+            }
+            ui.end_row();
+        }
+    });
+    if conflicts.contains(&true) {
+        ui.colored_label(
+            error_color,
+            "Some keys are bound more than once, so the key map won't be saved",
+        );
+    }
+    if ui.button("Reset keys").clicked() {
+        settings.key_map = KEY_MAP;
+    }
+}
+
+/// Draw a grid for binding each CHIP-8 key to a controller button
+fn gamepad_map_menu(ui: &mut egui::Ui, settings: &mut Settings) {
+    egui::Grid::new("gamepad_map").show(ui, |ui| {
+        for row in KEYPAD_LAYOUT {
+            for chip8_key in row {
+                ui.label(format!("{chip8_key:X}:"));
+                let binding = &mut settings.gamepad_map[chip8_key];
+                egui::ComboBox::from_id_source(("gamepad_map", chip8_key))
+                    .selected_text(binding.map_or("None", GamepadButton::name))
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(binding, None, "None");
+                        for button in GamepadButton::ALL {
+                            ui.selectable_value(binding, Some(button), button.name());
+                        }
+                    });
+            }
+            ui.end_row();
+        }
+    });
+    if ui.button("Reset buttons").clicked() {
+        settings.gamepad_map = GAMEPAD_MAP;
+    }
+}
+
+/// Draw the settings UI for editing `settings`
+pub fn settings_menu(ui: &mut egui::Ui, settings: &mut Settings) {
+    ui.checkbox(
+        &mut settings.fit_to_window,
+        "Fit to window using largest integer scale",
+    );
+    ui.add_enabled(
+        !settings.fit_to_window,
+        egui::Slider::new(&mut settings.pixel_scale, 1..=20).text("Pixel scale"),
+    );
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Foreground:");
+        ui.color_edit_button_srgba(&mut settings.foreground_color);
+        ui.label("Background:");
+        ui.color_edit_button_srgba(&mut settings.background_color);
+    });
+    ui.horizontal(|ui| {
+        ui.label("XO-CHIP plane 2:");
+        ui.color_edit_button_srgba(&mut settings.plane2_color);
+        ui.label("Both planes:");
+        ui.color_edit_button_srgba(&mut settings.both_planes_color);
+    });
+    ui.horizontal(|ui| {
+        if ui.button("Reset colors").clicked() {
+            let defaults = Settings::default();
+            settings.foreground_color = defaults.foreground_color;
+            settings.background_color = defaults.background_color;
+            settings.plane2_color = defaults.plane2_color;
+            settings.both_planes_color = defaults.both_planes_color;
+        }
+        if ui.button("Swap colors").clicked() {
+            std::mem::swap(
+                &mut settings.foreground_color,
+                &mut settings.background_color,
+            );
+        }
+        egui::ComboBox::from_id_source("color_presets")
+            .selected_text("Presets")
+            .show_ui(ui, |ui| {
+                for (name, foreground, background) in presets() {
+                    if ui.selectable_label(false, *name).clicked() {
+                        settings.foreground_color = *foreground;
+                        settings.background_color = *background;
+                    }
+                }
+            });
+    });
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.grid, "Grid lines");
+        ui.add_enabled_ui(settings.grid, |ui| {
+            ui.color_edit_button_srgba(&mut settings.grid_color);
+        });
+    });
+    ui.add(egui::Slider::new(&mut settings.scanline_alpha, 0..=255).text("Scanlines"));
+    ui.horizontal(|ui| {
+        ui.label("Flicker:")
+            .on_hover_text(t("settings.flicker_mode_tooltip"));
+        for mode in FlickerMode::ALL {
+            ui.radio_value(&mut settings.flicker_mode, mode, mode.name())
+                .on_hover_text(t("settings.flicker_mode_tooltip"));
+        }
+        ui.add_enabled(
+            settings.flicker_mode == FlickerMode::Smoothed,
+            egui::Slider::new(&mut settings.fade_decay, 0.1..=0.9).text("Fade speed"),
+        );
+    });
+    ui.checkbox(
+        &mut settings.crossfade,
+        "Fade between programs when loading",
+    );
+
+    ui.separator();
+    ui.checkbox(
+        &mut settings.pause_when_unfocused,
+        "Pause when the window loses focus",
+    );
+    ui.checkbox(
+        &mut settings.power_saving,
+        "Repaint less often while the screen isn't changing",
+    );
+    ui.checkbox(&mut settings.show_keypad, "Show on-screen keypad");
+    ui.checkbox(
+        &mut settings.show_performance,
+        "Show instructions and frames per second",
+    );
+
+    ui.separator();
+    ui.label("Keys:");
+    key_map_menu(ui, settings);
+    ui.label("Controller buttons:");
+    gamepad_map_menu(ui, settings);
+    ui.horizontal(|ui| {
+        ui.label("Turbo key:");
+        egui::ComboBox::from_id_source("turbo_key")
+            .selected_text(settings.turbo_key.name())
+            .show_ui(ui, |ui| {
+                for key in TURBO_KEYS {
+                    ui.selectable_value(&mut settings.turbo_key, key, key.name());
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        ui.label("Accuracy:");
+        let mode = AccuracyMode::matching(settings);
+        egui::ComboBox::from_id_source("accuracy_mode")
+            .selected_text(mode.map_or("Custom", AccuracyMode::name))
+            .show_ui(ui, |ui| {
+                for option in AccuracyMode::ALL {
+                    if ui
+                        .selectable_label(mode == Some(option), option.name())
+                        .clicked()
+                    {
+                        option.apply(settings);
+                    }
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        ui.label("Instructions per second:");
+        for preset in INSTRUCTIONS_PER_SECOND_PRESETS {
+            ui.selectable_value(
+                &mut settings.instructions_per_second,
+                preset,
+                preset.to_string(),
+            );
+        }
+        ui.add(
+            egui::DragValue::new(&mut settings.instructions_per_second)
+                .clamp_range(MIN_INSTRUCTIONS_PER_SECOND..=MAX_INSTRUCTIONS_PER_SECOND)
+                .suffix(" Hz"),
+        )
+        .on_hover_text("A custom speed");
+    });
+    ui.add(egui::Slider::new(&mut settings.turbo_multiplier, 2..=32).text("Turbo speed"));
+    ui.add(
+        egui::Slider::new(&mut settings.speed, 0.1..=1.0)
+            .text("Speed")
+            .custom_formatter(|speed, _| format!("{speed:.2}x")),
+    );
+    ui.checkbox(
+        &mut settings.vip_timing,
+        "Run at COSMAC VIP speed instead of a fixed number of cycles",
+    );
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Quirks:");
+        let preset = QuirksPreset::matching(settings.quirks);
+        egui::ComboBox::from_id_source("quirks_preset")
+            .selected_text(preset.map_or("Custom", QuirksPreset::name))
+            .show_ui(ui, |ui| {
+                for option in QuirksPreset::ALL {
+                    if ui
+                        .selectable_label(preset == Some(option), option.name())
+                        .clicked()
+                    {
+                        settings.quirks = option.quirks();
+                    }
+                }
+            });
+    });
+    let quirks = &mut settings.quirks;
+    ui.checkbox(
+        &mut quirks.wait_key_on_release,
+        "FX0A waits for the key to be released",
+    );
+    ui.checkbox(&mut quirks.extended_memory, "64KB of memory (XO-CHIP)");
+    ui.checkbox(
+        &mut quirks.add_to_i_sets_vf,
+        "FX1E sets VF when I goes past 0xFFF",
+    );
+    ui.checkbox(
+        &mut quirks.protect_reserved_memory,
+        "Stop on writes below 0x200",
+    );
+    ui.checkbox(&mut quirks.shift_uses_vy, "8XY6/8XYE shift VY into VX");
+    ui.checkbox(
+        &mut quirks.load_store_increments_i,
+        "FX55/FX65 move I past the registers",
+    );
+    ui.checkbox(&mut quirks.jump_with_vx, "BNNN jumps to XNN + VX");
+    ui.checkbox(&mut quirks.logic_resets_vf, "8XY1/8XY2/8XY3 reset VF");
+    ui.checkbox(
+        &mut quirks.display_wait,
+        "DXYN waits for the next frame after drawing",
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use chip8::quirks::Quirks;
+
+    use super::{presets, AccuracyMode, QuirksPreset, Settings};
+    use crate::gamepad::{GamepadButton, GAMEPAD_MAP};
+    use crate::rom_overrides::{rom_hash, RomOverride};
+    use crate::screen_fade::FlickerMode;
+
+    #[test]
+    fn test_storage_string_round_trip() {
+        let settings = Settings {
+            pixel_scale: 7,
+            fit_to_window: true,
+            pause_when_unfocused: true,
+            power_saving: false,
+            show_keypad: true,
+            show_performance: true,
+            flicker_mode: FlickerMode::Smoothed,
+            crossfade: true,
+            fade_decay: 0.25,
+            key_map: {
+                let mut key_map = super::KEY_MAP;
+                key_map.reverse();
+                key_map
+            },
+            turbo_key: egui::Key::G,
+            turbo_multiplier: 3,
+            instructions_per_second: 700,
+            speed: 0.25,
+            vip_timing: true,
+            quirks: Quirks {
+                add_to_i_sets_vf: true,
+                ..QuirksPreset::XoChip.quirks()
+            },
+            rom_overrides: [(
+                rom_hash(&[0x12, 0x00]),
+                RomOverride {
+                    quirks: Quirks::schip(),
+                    instructions_per_second: 6000,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+
+        let loaded = Settings::from_storage_string(&settings.to_storage_string());
+
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_storage_string_missing_entries_use_defaults() {
+        let loaded = Settings::from_storage_string("pixel_scale=3\nunknown=1\n");
+
+        assert_eq!(loaded.pixel_scale, 3);
+        assert_eq!(loaded.fit_to_window, Settings::default().fit_to_window);
+    }
+
+    #[test]
+    fn test_render_options_round_trip() {
+        let settings = Settings {
+            grid: true,
+            foreground_color: egui::Color32::from_rgb(1, 2, 3),
+            background_color: egui::Color32::from_rgb(4, 5, 6),
+            plane2_color: egui::Color32::from_rgb(7, 8, 9),
+            both_planes_color: egui::Color32::from_rgb(10, 11, 12),
+            grid_color: egui::Color32::from_rgba_premultiplied(10, 20, 30, 40),
+            scanline_alpha: 128,
+            ..Default::default()
+        };
+
+        let loaded = Settings::from_storage_string(&settings.to_storage_string());
+
+        assert_eq!(
+            loaded.screen_render_options(),
+            settings.screen_render_options()
+        );
+    }
+
+    #[test]
+    fn test_presets_are_distinct_and_opaque() {
+        let presets = presets();
+        for (i, (name, foreground, background)) in presets.iter().enumerate() {
+            assert!(!name.is_empty());
+            assert_ne!(foreground, background, "preset {name} has identical colors");
+            assert!(foreground.is_opaque(), "preset {name} foreground");
+            assert!(background.is_opaque(), "preset {name} background");
+            for (other_name, other_foreground, other_background) in &presets[i + 1..] {
+                assert_ne!(name, other_name);
+                assert!((foreground, background) != (other_foreground, other_background));
+            }
+        }
+    }
+
+    #[test]
+    fn test_quirks_preset_storage_names() {
+        for preset in QuirksPreset::ALL {
+            assert_eq!(
+                QuirksPreset::from_storage_name(preset.storage_name()),
+                Some(preset)
+            );
+        }
+        assert_eq!(QuirksPreset::from_storage_name("chip8"), None);
+    }
+
+    #[test]
+    fn test_quirks_from_preset_entry() {
+        let loaded = Settings::from_storage_string("quirks_preset=xochip\n");
+        assert_eq!(loaded.quirks, Quirks::xochip());
+
+        let loaded = Settings::from_storage_string("quirks_preset=xochip\nextended_memory=false\n");
+        assert_eq!(
+            loaded.quirks,
+            Quirks {
+                extended_memory: false,
+                ..Quirks::xochip()
+            }
+        );
+    }
+
+    #[test]
+    fn test_pixel_scale_and_fade_decay_clamped() {
+        let load = |s| Settings::from_storage_string(s);
+        assert_eq!(load("pixel_scale=0\n").pixel_scale, 1);
+        assert_eq!(load("pixel_scale=1000\n").pixel_scale, 20);
+        assert_eq!(load("fade_decay=0\n").fade_decay, 0.1);
+        assert_eq!(load("fade_decay=1\n").fade_decay, 0.9);
+        assert_eq!(load("fade_decay=NaN\n").fade_decay, 0.5);
+    }
+
+    #[test]
+    fn test_speed_clamped() {
+        assert_eq!(Settings::from_storage_string("speed=0\n").speed, 0.1);
+        assert_eq!(Settings::from_storage_string("speed=4\n").speed, 1.0);
+        assert_eq!(Settings::from_storage_string("speed=NaN\n").speed, 1.0);
+        assert_eq!(Settings::from_storage_string("speed=inf\n").speed, 1.0);
+    }
+
+    #[test]
+    fn test_turbo_multiplier_clamped() {
+        let load = |s| Settings::from_storage_string(s).turbo_multiplier;
+        assert_eq!(load("turbo_multiplier=0\n"), 2);
+        assert_eq!(load("turbo_multiplier=4294967295\n"), 32);
+        assert_eq!(load("turbo_multiplier=16\n"), 16);
+    }
+
+    #[test]
+    fn test_instructions_per_second_clamped() {
+        let load = |s| Settings::from_storage_string(s).instructions_per_second;
+        assert_eq!(load("instructions_per_second=0\n"), 60);
+        assert_eq!(load("instructions_per_second=4294967295\n"), 12000);
+        assert_eq!(load("instructions_per_second=1000\n"), 1000);
+    }
+
+    #[test]
+    fn test_quirks_for_rom() {
+        let overridden = rom_hash(&[0x12, 0x00]);
+        let settings = Settings {
+            quirks: Quirks::vip(),
+            rom_overrides: [(
+                overridden,
+                RomOverride {
+                    quirks: Quirks::xochip(),
+                    instructions_per_second: 6000,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+
+        assert_eq!(settings.quirks_for_rom(overridden), Quirks::xochip());
+        // other ROMs use the global quirks
+        assert_eq!(
+            settings.quirks_for_rom(rom_hash(&[0x00, 0xe0])),
+            Quirks::vip()
+        );
+    }
+
+    #[test]
+    fn test_matching_preset() {
+        for preset in QuirksPreset::ALL {
+            assert_eq!(QuirksPreset::matching(preset.quirks()), Some(preset));
+        }
+        let custom = Quirks {
+            protect_reserved_memory: true,
+            ..Quirks::vip()
+        };
+        assert_eq!(QuirksPreset::matching(custom), None);
+    }
+
+    #[test]
+    fn test_accuracy_modes() {
+        let mut settings = Settings::default();
+        AccuracyMode::Accurate.apply(&mut settings);
+        assert_eq!(settings.quirks, Quirks::vip());
+        assert!(settings.quirks.display_wait);
+        assert!(settings.quirks.logic_resets_vf);
+        assert!(settings.vip_timing);
+        assert_eq!(
+            AccuracyMode::matching(&settings),
+            Some(AccuracyMode::Accurate)
+        );
+
+        AccuracyMode::Fast.apply(&mut settings);
+        assert_eq!(settings.quirks, Quirks::schip());
+        assert!(!settings.quirks.display_wait);
+        assert!(!settings.quirks.logic_resets_vf);
+        assert!(!settings.vip_timing);
+        assert_eq!(AccuracyMode::matching(&settings), Some(AccuracyMode::Fast));
+        // only the bundle's settings change
+        assert_eq!(
+            settings,
+            Settings {
+                quirks: Quirks::schip(),
+                ..Settings::default()
+            }
+        );
+
+        // saved through the settings in the bundle
+        let loaded = Settings::from_storage_string(&settings.to_storage_string());
+        assert_eq!(AccuracyMode::matching(&loaded), Some(AccuracyMode::Fast));
+
+        settings.vip_timing = true;
+        assert_eq!(AccuracyMode::matching(&settings), None);
+    }
+
+    #[test]
+    fn test_key_map_round_trip() {
+        let mut key_map = super::KEY_MAP;
+        key_map.swap(0x1, 0x2);
+        key_map[0xf] = egui::Key::ArrowUp;
+        let settings = Settings {
+            key_map,
+            ..Default::default()
+        };
+        assert!(!settings.has_key_conflicts());
+
+        let loaded = Settings::from_storage_string(&settings.to_storage_string());
+
+        assert_eq!(loaded.key_map, key_map);
+    }
+
+    #[test]
+    fn test_gamepad_map_round_trip() {
+        let mut gamepad_map = GAMEPAD_MAP;
+        gamepad_map.swap(0x5, 0x8);
+        gamepad_map[0x0] = Some(GamepadButton::Start);
+        gamepad_map[0xf] = None;
+        let settings = Settings {
+            gamepad_map,
+            ..Default::default()
+        };
+
+        let loaded = Settings::from_storage_string(&settings.to_storage_string());
+
+        assert_eq!(loaded.gamepad_map, gamepad_map);
+        let invalid = Settings::from_storage_string("gamepad_map=A,B\n");
+        assert_eq!(invalid.gamepad_map, GAMEPAD_MAP);
+    }
+
+    #[test]
+    fn test_conflicting_key_map_isnt_saved() {
+        let mut key_map = super::KEY_MAP;
+        key_map[0x1] = key_map[0x2];
+        let settings = Settings {
+            key_map,
+            ..Default::default()
+        };
+        assert!(settings.has_key_conflicts());
+
+        let loaded = Settings::from_storage_string(&settings.to_storage_string());
+
+        assert_eq!(loaded.key_map, super::KEY_MAP);
+        // a conflicting map written by hand is rejected when loading
+        let loaded = Settings::from_storage_string("key_map=X,X,2,3,Q,W,E,A,S,D,Z,C,4,R,F,V\n");
+        assert_eq!(loaded.key_map, super::KEY_MAP);
+        let loaded = Settings::from_storage_string("key_map=X,1,2\n");
+        assert_eq!(loaded.key_map, super::KEY_MAP);
+    }
+
+    #[test]
+    fn test_flicker_mode_defaults_to_authentic() {
+        assert_eq!(Settings::default().flicker_mode, FlickerMode::Authentic);
+        // older versions stored a flag instead
+        let loaded = Settings::from_storage_string("flicker_reduction=true\n");
+        assert_eq!(loaded.flicker_mode, FlickerMode::Smoothed);
+        let loaded = Settings::from_storage_string("flicker_reduction=false\n");
+        assert_eq!(loaded.flicker_mode, FlickerMode::Authentic);
+    }
+}
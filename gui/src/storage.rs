@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use chip8::recording::Recording;
+
+use crate::{
+    recording::{recording_from_storage_string, recording_to_storage_string, RecordingError},
+    save_slots::{slot_key, SaveSlot, SlotStatus, SLOT_COUNT},
+    save_state::{SaveState, SaveStateError},
+    settings::Settings,
+};
+
+/// The key the settings are stored under
+const SETTINGS_KEY: &str = "settings";
+/// The key the user's save state is stored under
+const SAVE_STATE_KEY: &str = "save_state";
+/// The key the user's input recording is stored under
+const RECORDING_KEY: &str = "recording";
+
+/// Load the settings from `storage`, or the defaults if there aren't any
+pub fn load_settings(storage: Option<&dyn eframe::Storage>) -> Settings {
+    storage
+        .and_then(|storage| storage.get_string(SETTINGS_KEY))
+        .map(|s| Settings::from_storage_string(&s))
+        .unwrap_or_default()
+}
+
+pub fn save_settings(storage: &mut dyn eframe::Storage, settings: &Settings) {
+    storage.set_string(SETTINGS_KEY, settings.to_storage_string());
+}
+
+pub fn save_state(storage: &mut dyn eframe::Storage, state: &SaveState) {
+    storage.set_string(SAVE_STATE_KEY, state.to_storage_string());
+}
+
+/// Load the save state from `storage`, or `None` if nothing has been saved
+pub fn load_state(storage: &dyn eframe::Storage) -> Option<Result<SaveState, SaveStateError>> {
+    storage
+        .get_string(SAVE_STATE_KEY)
+        .map(|s| SaveState::from_storage_string(&s))
+}
+
+pub fn save_slot(storage: &mut dyn eframe::Storage, slot: usize, save_slot: &SaveSlot) {
+    storage.set_string(&slot_key(slot), save_slot.to_storage_string());
+}
+
+/// Load the state in a slot, or `None` if the slot is empty
+pub fn load_slot(
+    storage: &dyn eframe::Storage,
+    slot: usize,
+) -> Option<Result<SaveSlot, SaveStateError>> {
+    storage
+        .get_string(&slot_key(slot))
+        .filter(|s| !s.is_empty())
+        .map(|s| SaveSlot::from_storage_string(&s))
+}
+
+/// Empty a slot. There's no way to remove a key from eframe's storage, so
+/// it's left empty.
+pub fn delete_slot(storage: &mut dyn eframe::Storage, slot: usize) {
+    storage.set_string(&slot_key(slot), String::new());
+}
+
+/// What's in each slot, where each is checked separately so one which can't
+/// be loaded doesn't affect the others
+pub fn slot_statuses(storage: &dyn eframe::Storage) -> [SlotStatus; SLOT_COUNT] {
+    std::array::from_fn(|slot| {
+        SlotStatus::from_storage_string(storage.get_string(&slot_key(slot)).as_deref())
+    })
+}
+
+/// Save the input recording in `storage` alongside the settings, replacing
+/// the one saved before. Only one recording is kept.
+pub fn save_recording(storage: &mut dyn eframe::Storage, recording: &Recording) {
+    storage.set_string(RECORDING_KEY, recording_to_storage_string(recording));
+}
+
+/// Load the input recording from `storage`, or `None` if nothing has been
+/// recorded
+pub fn load_recording(storage: &dyn eframe::Storage) -> Option<Result<Recording, RecordingError>> {
+    storage
+        .get_string(RECORDING_KEY)
+        .map(|s| recording_from_storage_string(&s))
+}
+
+/// Call `f` with the storage for this platform and then flush it, or return
+/// `None` if there's no storage available
+pub fn with_storage<R>(
+    frame: &mut eframe::Frame,
+    f: impl FnOnce(&mut dyn eframe::Storage) -> R,
+) -> Option<R> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = frame;
+        let mut storage = FileStorage::new()?;
+        let result = f(&mut storage);
+        eframe::Storage::flush(&mut storage);
+        Some(result)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let storage = frame.storage_mut()?;
+        let result = f(storage);
+        storage.flush();
+        Some(result)
+    }
+}
+
+/// Append a `key=value` line to `s`
+pub fn push_entry(s: &mut String, key: &str, value: impl std::fmt::Display) {
+    s.push_str(&format!("{key}={value}\n"));
+}
+
+/// Parse the `key=value` lines created with `push_entry()`
+pub fn parse_entries(s: &str) -> HashMap<&str, &str> {
+    s.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+/// Stores each key in its own file in the user's config directory.
+///
+/// eframe only provides storage on native with its `persistence` feature, so
+/// this is used instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    dir: std::path::PathBuf,
+    pending: std::collections::HashMap<String, String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    /// Returns `None` if there's no config directory on this platform
+    pub fn new() -> Option<FileStorage> {
+        Some(FileStorage {
+            dir: dirs::config_dir()?.join("improved-broccoli"),
+            pending: Default::default(),
+        })
+    }
+
+    fn path(&self, key: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{key}.txt"))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl eframe::Storage for FileStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.pending.get(key) {
+            return Some(value.clone());
+        }
+        std::fs::read_to_string(self.path(key)).ok()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        self.pending.insert(key.to_owned(), value);
+    }
+
+    fn flush(&mut self) {
+        if let Err(err) = std::fs::create_dir_all(&self.dir) {
+            tracing::error!("failed to create config directory: {err}");
+            return;
+        }
+        for (key, value) in std::mem::take(&mut self.pending) {
+            if let Err(err) = std::fs::write(self.path(&key), value) {
+                tracing::error!("failed to save {key}: {err}");
+            }
+        }
+    }
+}
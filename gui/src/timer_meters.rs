@@ -0,0 +1,46 @@
+use chip8::cpu::Chip8;
+
+/// The timer value which fills a meter, which is one second
+pub const FULL_TIMER: u8 = 60;
+
+/// How much of a meter is filled for a timer at `value`, from 0 to 1. Values
+/// over `FULL_TIMER` fill it completely.
+pub fn timer_fraction(value: u8) -> f32 {
+    value.min(FULL_TIMER) as f32 / FULL_TIMER as f32
+}
+
+/// Draw meters for the delay and sound timers, which empty as the timers
+/// count down
+pub fn timer_meters_ui(ui: &mut egui::Ui, chip8: &Chip8) {
+    egui::Grid::new("timer_meters").show(ui, |ui| {
+        for (name, value) in [
+            ("Delay", chip8.delay_timer()),
+            ("Sound", chip8.sound_timer()),
+        ] {
+            ui.label(name);
+            ui.add(
+                egui::ProgressBar::new(timer_fraction(value))
+                    .desired_width(120.0)
+                    .text(value.to_string()),
+            );
+            ui.end_row();
+        }
+    });
+    if chip8.is_sound_playing() {
+        ui.label("Beeping");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::timer_fraction;
+
+    #[test]
+    fn test_timer_fraction() {
+        assert_eq!(timer_fraction(0), 0.0);
+        assert_eq!(timer_fraction(30), 0.5);
+        // a second fills the meter
+        assert_eq!(timer_fraction(60), 1.0);
+        assert_eq!(timer_fraction(255), 1.0);
+    }
+}
@@ -0,0 +1,114 @@
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+/// The result of a fetch, shared with the future doing it
+type FetchResult = Rc<RefCell<Option<Result<Vec<u8>, String>>>>;
+
+/// Fetches ROMs over HTTP, for loading ROMs on the web without dropping a
+/// file
+#[derive(Default)]
+pub struct UrlLoader {
+    /// The URL being typed by the user
+    url: String,
+    /// The fetch in progress, if any
+    pending: Option<FetchResult>,
+    /// Why the last fetch failed
+    error: Option<String>,
+}
+
+impl UrlLoader {
+    /// Start fetching the ROM at `url`, replacing any fetch in progress. The
+    /// result is returned by `poll()` once it's done.
+    pub fn start(&mut self, ctx: &egui::Context, url: String) {
+        let result = FetchResult::default();
+        self.pending = Some(result.clone());
+        self.error = None;
+        self.url = url.clone();
+        let ctx = ctx.clone();
+        spawn_local(async move {
+            *result.borrow_mut() = Some(fetch_bytes(&url).await);
+            ctx.request_repaint();
+        });
+    }
+
+    /// The bytes of the fetched ROM or an error, if a fetch has finished since
+    /// the last call
+    pub fn poll(&mut self) -> Option<Result<Vec<u8>, String>> {
+        let result = self.pending.as_ref()?.borrow_mut().take()?;
+        self.pending = None;
+        if let Err(err) = &result {
+            self.error = Some(err.clone());
+        }
+        Some(result)
+    }
+
+    /// The URL being typed, or of the last fetch
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Show that a fetched ROM was invalid
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    /// Draw the URL input
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("URL:");
+            let response = ui.text_edit_singleline(&mut self.url);
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let button = ui.add_enabled(
+                self.pending.is_none() && !self.url.is_empty(),
+                egui::Button::new("Load"),
+            );
+            if submitted || button.clicked() {
+                self.start(ui.ctx(), self.url.trim().to_owned());
+            }
+        });
+        if self.pending.is_some() {
+            ui.label("Loading...");
+        } else if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+}
+
+/// The URL in the page's `?rom=<url>` query parameter, for linking to a ROM
+pub fn query_rom_url() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get("rom")
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>, String> {
+    let window = web_sys::window().ok_or("there's no window to fetch from")?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(js_error)?;
+    let response: web_sys::Response = response.dyn_into().map_err(js_error)?;
+    if !response.ok() {
+        return Err(format!(
+            "the server responded with {} {}",
+            response.status(),
+            response.status_text()
+        ));
+    }
+    // a common mistake is linking to a page about the ROM rather than the ROM
+    if let Ok(Some(content_type)) = response.headers().get("content-type") {
+        if content_type.starts_with("text/html") {
+            return Err("the URL is a web page, not a ROM".to_owned());
+        }
+    }
+    let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+        .await
+        .map_err(js_error)?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+fn js_error(value: JsValue) -> String {
+    value.as_string().unwrap_or_else(|| format!("{value:?}"))
+}